@@ -0,0 +1,200 @@
+//! Benchmark scenarios written once and run against every [`EditableText`] backend.
+//!
+//! A scenario is a function generic over [`Backend`], and [`for_each_backend!`] runs it for each
+//! of them, so adding a backend only takes implementing `Backend` for it and listing it in the
+//! macro. Each scenario makes a benchmark group named after the backend, e.g.
+//! `ptable_insert_small`, so results stay comparable with older runs.
+
+// Every bench includes this module, but none of them uses all of it
+#![allow(dead_code)]
+
+use std::ops::Range;
+
+use criterion::{Criterion, black_box};
+use piece_table::PieceTable;
+use piece_table::baseline::Baseline;
+use piece_table::gap_buffer::GapBuffer;
+use piece_table::interface::EditableText;
+use piece_table::line_buffer::LineBuffer;
+use piece_table::workload::Workload;
+use rand::random;
+use ropey::Rope;
+
+pub const TEXT_SMALL: &str = include_str!("../small.txt");
+pub const TEXT_MEDIUM: &str = include_str!("../medium.txt");
+pub const TEXT_LARGE: &str = include_str!("../large.txt");
+pub const TEXT_LF: &str = include_str!("../lf.txt");
+
+/// A text type which can be benchmarked.
+pub trait Backend: EditableText<'static> {
+    /// The prefix of the names of its benchmark groups.
+    const NAME: &'static str;
+
+    /// Returns the length of the text, in bytes.
+    fn len(&self) -> usize;
+}
+
+impl Backend for PieceTable<'static> {
+    const NAME: &'static str = "ptable";
+
+    fn len(&self) -> usize {
+        PieceTable::len(self)
+    }
+}
+
+impl Backend for Baseline {
+    const NAME: &'static str = "string";
+
+    fn len(&self) -> usize {
+        Baseline::len(self)
+    }
+}
+
+impl Backend for LineBuffer {
+    const NAME: &'static str = "line_buffer";
+
+    fn len(&self) -> usize {
+        LineBuffer::len(self)
+    }
+}
+
+impl Backend for GapBuffer {
+    const NAME: &'static str = "gap_buffer";
+
+    fn len(&self) -> usize {
+        GapBuffer::len(self)
+    }
+}
+
+/// A [`Rope`] edited at byte offsets, which are converted to the char indices it takes.
+pub struct RopeText(Rope);
+
+impl EditableText<'_> for RopeText {
+    fn new(string: &str) -> Self {
+        RopeText(Rope::from_str(string))
+    }
+
+    fn insert(&mut self, data: &str, offset: usize) {
+        let idx = self.0.byte_to_char(offset);
+        self.0.insert(idx, data);
+    }
+
+    fn delete(&mut self, range: Range<usize>) {
+        let start = self.0.byte_to_char(range.start);
+        let end = self.0.byte_to_char(range.end);
+        self.0.remove(start..end);
+    }
+}
+
+impl Backend for RopeText {
+    const NAME: &'static str = "rope";
+
+    fn len(&self) -> usize {
+        self.0.len_bytes()
+    }
+}
+
+/// Runs the scenario `bench_support::$scenario::<T>` for every backend `T`, with `c` and the
+/// arguments after it.
+#[macro_export]
+macro_rules! for_each_backend {
+    ($scenario:ident, $c:expr $(, $arg:expr)*) => {{
+        use $crate::bench_support::RopeText;
+        use piece_table::PieceTable;
+        use piece_table::baseline::Baseline;
+        use piece_table::gap_buffer::GapBuffer;
+        use piece_table::line_buffer::LineBuffer;
+
+        $crate::bench_support::$scenario::<RopeText>($c $(, $arg)*);
+        $crate::bench_support::$scenario::<Baseline>($c $(, $arg)*);
+        $crate::bench_support::$scenario::<PieceTable<'static>>($c $(, $arg)*);
+        $crate::bench_support::$scenario::<LineBuffer>($c $(, $arg)*);
+        $crate::bench_support::$scenario::<GapBuffer>($c $(, $arg)*);
+    }};
+}
+
+/// Returns `text` repeated `n` times, leaked so every backend can borrow it.
+pub fn repeated(text: &str, n: usize) -> &'static str {
+    text.repeat(n).leak()
+}
+
+/// Where in the document a scenario edits.
+const POSITIONS: [&str; 4] = ["random", "start", "middle", "end"];
+
+/// Returns the offset `position` stands for in a document of `len` bytes.
+fn offset_at(position: &str, len: usize) -> usize {
+    match position {
+        "random" => random::<u64>() as usize % (len + 1),
+        "start" => 0,
+        "middle" => len / 2,
+        _ => len,
+    }
+}
+
+/// Creates a `T` from each of the sample texts.
+pub fn from_str<T: Backend>(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("{}_from_str", T::NAME));
+    for (name, text) in [
+        ("small", TEXT_SMALL),
+        ("medium", TEXT_MEDIUM),
+        ("large", TEXT_LARGE),
+        ("linefeeds", TEXT_LF),
+    ] {
+        group.bench_function(name, |bench| {
+            bench.iter(|| T::new(black_box(text)));
+        });
+    }
+}
+
+/// Inserts `data` over and over into the large text, at each position.
+pub fn insert<T: Backend>(c: &mut Criterion, size: &str, data: &str) {
+    let mut group = c.benchmark_group(format!("{}_insert_{size}", T::NAME));
+    for position in POSITIONS {
+        group.bench_function(position, |bench| {
+            let mut doc = T::new(TEXT_LARGE);
+            bench.iter(|| {
+                let offset = offset_at(position, doc.len());
+                doc.insert(data, offset);
+            })
+        });
+    }
+}
+
+/// Deletes `count` bytes over and over from `text`, at each position, starting over once half
+/// of it is deleted.
+pub fn remove<T: Backend>(c: &mut Criterion, size: &str, text: &'static str, count: usize) {
+    let mut group = c.benchmark_group(format!("{}_remove_{size}", T::NAME));
+    for position in POSITIONS {
+        group.bench_function(position, |bench| {
+            let mut doc = T::new(text);
+            bench.iter(|| {
+                let len = doc.len();
+                let range = match position {
+                    "end" => len - count.min(len)..len,
+                    _ => {
+                        let start = offset_at(position, len);
+                        start..(start + count).min(len)
+                    }
+                };
+                doc.delete(range);
+
+                if doc.len() <= text.len() / 2 {
+                    doc = T::new(text);
+                }
+            })
+        });
+    }
+}
+
+/// Replays each of `workloads` on a new `T` made from `text`.
+pub fn replay<T: Backend>(c: &mut Criterion, text: &'static str, workloads: &[(&str, Workload)]) {
+    let mut group = c.benchmark_group(format!("{}_workloads", T::NAME));
+    for (name, workload) in workloads {
+        group.bench_function(*name, |bench| {
+            bench.iter(|| {
+                let mut doc = T::new(text);
+                workload.replay(&mut doc);
+            })
+        });
+    }
+}
@@ -1,115 +1,95 @@
 extern crate criterion;
 extern crate ropey;
 
+mod bench_support;
+
+use bench_support::TEXT_LARGE;
 use criterion::{Criterion, black_box, criterion_group, criterion_main};
 use piece_table::PieceTable;
 use ropey::Rope;
 
-const TEXT_SMALL: &str = include_str!("small.txt");
-const TEXT_MEDIUM: &str = include_str!("medium.txt");
-const TEXT_LARGE: &str = include_str!("large.txt");
-const TEXT_LF: &str = include_str!("lf.txt");
-
 //----
 
-fn rope_from_str(c: &mut Criterion) {
-    let mut group = c.benchmark_group("rope_from_str");
-
-    group.bench_function("small", |bench| {
-        bench.iter(|| {
-            Rope::from_str(black_box(TEXT_SMALL));
-        })
-    });
-
-    group.bench_function("medium", |bench| {
-        bench.iter(|| {
-            Rope::from_str(black_box(TEXT_MEDIUM));
-        })
-    });
-
-    group.bench_function("large", |bench| {
-        bench.iter(|| {
-            Rope::from_str(black_box(TEXT_LARGE));
-        })
-    });
-
-    group.bench_function("linefeeds", |bench| {
-        bench.iter(|| {
-            Rope::from_str(black_box(TEXT_LF));
-        })
-    });
+fn from_str(c: &mut Criterion) {
+    for_each_backend!(from_str, c);
 }
 
-fn string_from_str(c: &mut Criterion) {
-    let mut group = c.benchmark_group("string_from_str");
-
-    group.bench_function("small", |bench| {
+fn rope_clone(c: &mut Criterion) {
+    let rope = Rope::from_str(TEXT_LARGE);
+    c.bench_function("rope_clone", |bench| {
         bench.iter(|| {
-            let _ = String::from(black_box(TEXT_SMALL));
+            let _ = black_box(&rope).clone();
         })
     });
+}
 
-    group.bench_function("medium", |bench| {
+fn ptable_clone(c: &mut Criterion) {
+    let ptable = PieceTable::new(TEXT_LARGE);
+    c.bench_function("ptable_clone", |bench| {
         bench.iter(|| {
-            let _ = String::from(black_box(TEXT_MEDIUM));
+            let _ = black_box(&ptable).clone();
         })
     });
+}
 
-    group.bench_function("large", |bench| {
-        bench.iter(|| {
-            let _ = String::from(black_box(TEXT_LARGE));
-        })
-    });
+/// A `PieceTable` over `text` with an insertion every 64 bytes, so it has many pieces
+fn fragmented_ptable(text: &str) -> PieceTable<'_> {
+    let mut ptable = PieceTable::new(text);
+    let len = ptable.len();
+    for offset in (0..len).step_by(64).rev() {
+        if text.is_char_boundary(offset) {
+            ptable.insert("a", offset);
+        }
+    }
+    ptable
+}
 
-    group.bench_function("linefeeds", |bench| {
+fn rope_to_string(c: &mut Criterion) {
+    let rope = Rope::from_str(TEXT_LARGE);
+    c.bench_function("rope_to_string", |bench| {
         bench.iter(|| {
-            let _ = String::from(black_box(TEXT_LF));
+            let _ = black_box(&rope).to_string();
         })
     });
 }
 
-fn ptable_from_str(c: &mut Criterion) {
-    let mut group = c.benchmark_group("ptable_from_str");
-
-    group.bench_function("small", |bench| {
-        bench.iter(|| {
-            PieceTable::new(black_box(TEXT_SMALL));
-        })
-    });
+fn ptable_to_string(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ptable_to_string");
 
-    group.bench_function("medium", |bench| {
+    group.bench_function("display", |bench| {
+        let ptable = PieceTable::new(TEXT_LARGE);
         bench.iter(|| {
-            PieceTable::new(black_box(TEXT_MEDIUM));
+            let _ = black_box(&ptable).to_string();
         })
     });
 
-    group.bench_function("large", |bench| {
+    group.bench_function("as_string", |bench| {
+        let ptable = PieceTable::new(TEXT_LARGE);
         bench.iter(|| {
-            PieceTable::new(black_box(TEXT_LARGE));
+            let _ = black_box(&ptable).as_string();
         })
     });
 
-    group.bench_function("linefeeds", |bench| {
+    group.bench_function("display_fragmented", |bench| {
+        let ptable = fragmented_ptable(TEXT_LARGE);
         bench.iter(|| {
-            PieceTable::new(black_box(TEXT_LF));
+            let _ = black_box(&ptable).to_string();
         })
     });
-}
 
-fn rope_clone(c: &mut Criterion) {
-    let rope = Rope::from_str(TEXT_LARGE);
-    c.bench_function("rope_clone", |bench| {
+    group.bench_function("as_string_fragmented", |bench| {
+        let ptable = fragmented_ptable(TEXT_LARGE);
         bench.iter(|| {
-            let _ = black_box(&rope).clone();
+            let _ = black_box(&ptable).as_string();
         })
     });
-}
 
-fn ptable_clone(c: &mut Criterion) {
-    let ptable = PieceTable::new(TEXT_LARGE);
-    c.bench_function("ptable_clone", |bench| {
+    group.bench_function("copy_into_fragmented", |bench| {
+        let ptable = fragmented_ptable(TEXT_LARGE);
+        let mut buf = String::new();
         bench.iter(|| {
-            let _ = black_box(&ptable).clone();
+            buf.clear();
+            black_box(&ptable).copy_into(&mut buf);
         })
     });
 }
@@ -118,10 +98,10 @@ fn ptable_clone(c: &mut Criterion) {
 
 criterion_group!(
     benches,
-    rope_from_str,
-    string_from_str,
-    ptable_from_str,
+    from_str,
     rope_clone,
-    ptable_clone
+    ptable_clone,
+    rope_to_string,
+    ptable_to_string
 );
 criterion_main!(benches);
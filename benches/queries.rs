@@ -4,6 +4,9 @@ extern crate ropey;
 
 use criterion::{Criterion, criterion_group, criterion_main};
 use piece_table::PieceTable;
+use piece_table::interface::EditableText;
+use piece_table::range_set::Bias;
+use piece_table::search::IndexedPieceTable;
 use rand::random;
 use ropey::Rope;
 
@@ -125,7 +128,7 @@ fn ptable_slice(c: &mut Criterion) {
         let len = ptable.len();
         let slice = ptable.slice(1..len - 1);
         bench.iter(|| {
-            slice.slice(0..TEXT.len());
+            slice.slice(..);
         })
     });
 }
@@ -161,7 +164,7 @@ fn string_len(c: &mut Criterion) {
     group.bench_function("len", |bench| {
         let string = String::from(TEXT);
         bench.iter(|| {
-            string.len();
+            let _ = string.len();
         })
     });
 
@@ -191,6 +194,60 @@ fn ptable_len(c: &mut Criterion) {
     });
 }
 
+/// Returns a table of `TEXT` with `edits` small insertions at random offsets.
+fn fragmented(edits: usize) -> PieceTable<'static> {
+    let mut ptable = PieceTable::new(TEXT);
+    for _ in 0..edits {
+        let mut offset = random::<u64>() as usize % (ptable.len() + 1);
+        while ptable.byte(offset).is_some_and(|b| (b as i8) < -0x40) {
+            offset -= 1;
+        }
+        ptable.insert("ab", offset);
+    }
+    ptable
+}
+
+fn ptable_lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ptable_lookup");
+
+    group.bench_function("read_heavy", |bench| {
+        let ptable = fragmented(2000);
+        let len = ptable.len();
+        bench.iter(|| {
+            for _ in 0..100 {
+                ptable.byte(random::<u64>() as usize % len);
+            }
+        })
+    });
+
+    group.bench_function("write_heavy", |bench| {
+        let mut ptable = fragmented(2000);
+        bench.iter(|| {
+            let offset = ptable.len() / 2;
+            ptable.insert("ab", offset);
+            ptable.byte(offset);
+        })
+    });
+}
+
+/// Typing in the middle of an indexed document, which shifts every offset of the index after it,
+/// so it's slower the larger the document is.
+fn indexed_edit(c: &mut Criterion) {
+    let mut group = c.benchmark_group("indexed_edit");
+
+    for (name, text) in [("small", SMALL_TEXT), ("large", TEXT)] {
+        group.bench_function(name, |bench| {
+            let mut doc = IndexedPieceTable::new(text);
+            bench.iter(|| {
+                let offset = doc.table().len() / 2;
+                let offset = doc.table().clamp_to_char_boundary(offset, Bias::Left);
+                doc.insert("ab", offset);
+                doc.delete(offset..offset + 2);
+            })
+        });
+    }
+}
+
 //----
 
 criterion_group!(
@@ -199,6 +256,8 @@ criterion_group!(
     ptable_slice,
     rope_len,
     string_len,
-    ptable_len
+    ptable_len,
+    ptable_lookup,
+    indexed_edit
 );
 criterion_main!(benches);
@@ -0,0 +1,29 @@
+extern crate criterion;
+
+mod bench_support;
+
+use bench_support::TEXT_MEDIUM as TEXT;
+use criterion::{Criterion, criterion_group, criterion_main};
+use piece_table::workload::{Profile, Workload, generate};
+
+const MOSTLY_INSERTS: &str = include_str!("../workloads/mostly_inserts.txt");
+
+const EDITS: usize = 1000;
+const SEED: u64 = 0x5eed;
+
+fn workloads() -> Vec<(&'static str, Workload)> {
+    let mut workloads = vec![("mostly_inserts", Workload::parse(MOSTLY_INSERTS).unwrap())];
+    workloads
+        .extend(Profile::ALL.map(|profile| (profile.name(), generate(SEED, profile, TEXT, EDITS))));
+    workloads
+}
+
+fn replay_workloads(c: &mut Criterion) {
+    let workloads = workloads();
+    for_each_backend!(replay, c, TEXT, &workloads);
+}
+
+//----
+
+criterion_group!(benches, replay_workloads);
+criterion_main!(benches);
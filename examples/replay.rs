@@ -0,0 +1,90 @@
+//! Replays a workload file against `PieceTable` and the `String`-based baseline, timing both
+//! and checking that they agree after every edit.
+//!
+//! The workload is in the text format described in the `workload` module, as used by the
+//! benches or recorded by `snooper.lua`. It's validated before being replayed, so a malformed
+//! trace is reported with the offending line or edit instead of panicking halfway through.
+//!
+//! ```text
+//! cargo run --release --features test-utils --example replay -- <workload> [initial text]
+//! ```
+
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+use std::{env, fs};
+
+use piece_table::PieceTable;
+use piece_table::baseline::Baseline;
+use piece_table::interface::{EditableText, check_equivalence};
+use piece_table::workload::Workload;
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<(), String> {
+    let mut args = env::args().skip(1);
+    let (Some(workload_path), initial_path, None) = (args.next(), args.next(), args.next()) else {
+        return Err("usage: replay <workload> [initial text]".to_string());
+    };
+
+    let workload = read_text(&workload_path)?;
+    let workload = Workload::parse(&workload).map_err(|err| format!("{workload_path}: {err}"))?;
+    let initial = match &initial_path {
+        Some(path) => read_text(path)?,
+        None => String::new(),
+    };
+    workload
+        .validate(&initial)
+        .map_err(|err| format!("{workload_path}: {err}"))?;
+
+    let (piece_table, piece_table_time) = time_replay::<PieceTable>(&initial, &workload);
+    let (baseline, baseline_time) = time_replay::<Baseline>(&initial, &workload);
+    println!(
+        "{} edits, {} bytes initially, {} bytes after",
+        workload.edits.len(),
+        initial.len(),
+        piece_table.len()
+    );
+    println!("PieceTable: {piece_table_time:?}");
+    println!("Baseline:   {baseline_time:?}");
+
+    // Comparing after every edit is much slower than replaying, so it's only done to find
+    // where the final texts started to differ
+    if piece_table == baseline.to_string() {
+        println!("no divergence");
+        return Ok(());
+    }
+    match check_equivalence::<PieceTable, Baseline>(&initial, &workload) {
+        Ok(()) => Err("the final texts differ".to_string()),
+        Err(divergence) => Err(format!("divergence {divergence}")),
+    }
+}
+
+/// Reads the file at `path`, reporting where it isn't valid UTF-8.
+fn read_text(path: &str) -> Result<String, String> {
+    let bytes = fs::read(path).map_err(|err| format!("{path}: {err}"))?;
+    String::from_utf8(bytes).map_err(|err| {
+        let valid = &err.as_bytes()[..err.utf8_error().valid_up_to()];
+        let line = valid.iter().filter(|&&b| b == b'\n').count() + 1;
+        format!(
+            "{path}: invalid UTF-8 at byte {} (line {line})",
+            valid.len()
+        )
+    })
+}
+
+/// Replays `workload` against a new `T` made from `initial`, returning it and how long the
+/// replay took.
+fn time_replay<'a, T: EditableText<'a>>(initial: &'a str, workload: &Workload) -> (T, Duration) {
+    let mut doc = T::new(initial);
+    let start = Instant::now();
+    workload.replay(&mut doc);
+    (doc, start.elapsed())
+}
@@ -0,0 +1,63 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use piece_table::PieceTable;
+
+#[derive(Debug, Arbitrary)]
+enum Op {
+    Insert { offset: u16, text: String },
+    InsertChar { offset: u16, c: char },
+    Delete { start: u16, end: u16 },
+}
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    initial: String,
+    ops: Vec<Op>,
+}
+
+fn is_valid_offset(model: &str, offset: usize) -> bool {
+    model.is_char_boundary(offset)
+}
+
+fuzz_target!(|input: Input| {
+    let mut table = PieceTable::new(&input.initial);
+    let mut model = input.initial.clone();
+
+    for op in &input.ops {
+        match *op {
+            Op::Insert { offset, ref text } => {
+                let offset = offset as usize;
+                let result = table.try_insert(text, offset);
+                assert_eq!(is_valid_offset(&model, offset), result.is_ok());
+                if result.is_ok() {
+                    model.insert_str(offset, text);
+                }
+            }
+            Op::InsertChar { offset, c } => {
+                let offset = offset as usize;
+                let result = table.try_insert_char(offset, c);
+                assert_eq!(is_valid_offset(&model, offset), result.is_ok());
+                if result.is_ok() {
+                    model.insert(offset, c);
+                }
+            }
+            Op::Delete { start, end } => {
+                let (start, end) = (start as usize, end as usize);
+                let valid =
+                    start <= end && is_valid_offset(&model, start) && is_valid_offset(&model, end);
+                let result = table.try_delete(start..end);
+                assert_eq!(valid, result.is_ok());
+                if result.is_ok() {
+                    model.replace_range(start..end, "");
+                }
+            }
+        }
+
+        assert_eq!(model.len(), table.len());
+        assert_eq!(model.matches('\n').count() + 1, table.len_lines());
+        assert_eq!(model, table.to_string());
+        assert_eq!(model, table.slice(0..model.len()).to_string());
+    }
+});
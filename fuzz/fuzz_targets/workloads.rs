@@ -0,0 +1,34 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use piece_table::PieceTable;
+use piece_table::baseline::Baseline;
+use piece_table::interface::EditableText;
+use piece_table::workload::{Profile, generate};
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    initial: String,
+    seed: u64,
+    profile: u8,
+    edits: u8,
+}
+
+fuzz_target!(|input: Input| {
+    let profile = Profile::ALL[input.profile as usize % Profile::ALL.len()];
+    let workload = generate(input.seed, profile, &input.initial, input.edits as usize);
+
+    let mut table = PieceTable::new(&input.initial);
+    let mut model = Baseline::new(&input.initial);
+    for edit in &workload.edits {
+        edit.apply(&mut table);
+        edit.apply(&mut model);
+
+        let model = model.to_string();
+        assert_eq!(model.len(), table.len());
+        assert_eq!(model.matches('\n').count() + 1, table.len_lines());
+        assert_eq!(model, table.to_string());
+        assert_eq!(Ok(()), table.check_invariants());
+    }
+});
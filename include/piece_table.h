@@ -0,0 +1,47 @@
+/* C interface to the piece-table crate, built with its `ffi` feature, see src/ffi.rs for details. */
+#ifndef PIECE_TABLE_H
+#define PIECE_TABLE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define PTABLE_OK 0
+#define PTABLE_NULL_POINTER 1
+#define PTABLE_INVALID_UTF8 2
+#define PTABLE_OUT_OF_BOUNDS 3
+#define PTABLE_NOT_CHAR_BOUNDARY 4
+#define PTABLE_INVALID_RANGE 5
+#define PTABLE_BUFFER_TOO_SMALL 6
+#define PTABLE_LIMIT_EXCEEDED 7
+
+/* An opaque handle to a piece table which owns its text. */
+typedef struct PTable PTable;
+
+/* Returns NULL if `text` isn't valid UTF-8. Free the table with `ptable_free`. */
+PTable *ptable_new(const uint8_t *text, size_t len);
+void ptable_free(PTable *table);
+
+size_t ptable_len(const PTable *table);
+int32_t ptable_insert(PTable *table, size_t offset, const uint8_t *text, size_t len);
+int32_t ptable_delete(PTable *table, size_t start, size_t end);
+
+/* Copies the bytes in [start, end) into `buf`, without a NUL terminator, and writes their
+ * count to `written`. Pass a NULL `buf` and a `buf_len` of 0 to query the needed size. */
+int32_t ptable_slice_utf8(const PTable *table, size_t start, size_t end, uint8_t *buf,
+                          size_t buf_len, size_t *written);
+
+/* Calls `callback` with each contiguous run of the bytes in [start, end) and `user_data`,
+ * without copying them. The runs are only valid until `callback` returns. */
+int32_t ptable_for_each_run(const PTable *table, size_t start, size_t end,
+                            void (*callback)(const uint8_t *run, size_t len, void *user_data),
+                            void *user_data);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* PIECE_TABLE_H */
@@ -0,0 +1,347 @@
+//! The "added" buffer of a `PieceTable`, stored in segments which are never moved.
+//!
+//! A single growing `String` has to be reallocated, and all of it copied, every time it runs out
+//! of capacity, which causes long pauses once a session has added a lot of text. Instead, text is
+//! appended to a segment of fixed capacity, and a new segment is started once it's full.
+//!
+//! Segments are reference counted, so slices of a table can keep the segments they refer to
+//! alive without borrowing the table. A segment is only appended to while nothing else refers
+//! to it, so text which may be read elsewhere is never written to, and no `unsafe` is needed.
+//!
+//! Offsets into the buffer are contiguous across segments, but a piece never spans two segments,
+//! since each push goes entirely into one segment.
+//!
+//! A segment may also be text borrowed from the caller, for inserting huge strings without
+//! copying them. Such a segment is never appended to.
+
+use std::ops::{Deref, Index, Range};
+use std::sync::Arc;
+
+/// The default capacity of a segment, in bytes.
+pub(crate) const DEFAULT_SEGMENT_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone)]
+pub(crate) struct AddedBuffer<'a> {
+    /// The segments, along with the offset where each one starts
+    segments: Vec<(usize, Segment<'a>)>,
+    len: usize,
+    /// The number of bytes in borrowed segments
+    borrowed: usize,
+    segment_size: usize,
+}
+
+/// The text of a segment.
+#[derive(Debug, Clone)]
+enum Segment<'a> {
+    /// Text pushed to the buffer
+    Owned(Arc<String>),
+    /// Text owned by the caller
+    Borrowed(&'a str),
+}
+
+impl Deref for Segment<'_> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            Segment::Owned(text) => text,
+            Segment::Borrowed(text) => text,
+        }
+    }
+}
+
+impl Default for AddedBuffer<'_> {
+    fn default() -> Self {
+        AddedBuffer {
+            segments: Vec::new(),
+            len: 0,
+            borrowed: 0,
+            segment_size: DEFAULT_SEGMENT_SIZE,
+        }
+    }
+}
+
+impl<'a> AddedBuffer<'a> {
+    /// Returns the number of bytes pushed so far, including borrowed text.
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the number of bytes pushed so far which the buffer stores itself, i.e. excluding
+    /// borrowed text.
+    pub(crate) fn owned_len(&self) -> usize {
+        self.len - self.borrowed
+    }
+
+    /// Returns the number of segments.
+    #[cfg(feature = "instrument")]
+    pub(crate) fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Returns the capacity of the segments started from now on.
+    pub(crate) fn segment_size(&self) -> usize {
+        self.segment_size
+    }
+
+    /// Sets the capacity of the segments started from now on.
+    pub(crate) fn set_segment_size(&mut self, size: usize) {
+        self.segment_size = size;
+    }
+
+    /// Checks if anything else, e.g. a slice or a clone of the table, refers to any of the
+    /// segments.
+    pub(crate) fn is_shared(&self) -> bool {
+        self.segments.iter().any(|(_, segment)| match segment {
+            Segment::Owned(text) => Arc::strong_count(text) > 1,
+            Segment::Borrowed(_) => false,
+        })
+    }
+
+    /// Appends `data`, returning the range it was stored at.
+    ///
+    /// `data` goes into the last segment if it fits and nothing else refers to that segment, and
+    /// into a new one otherwise. Data larger than a segment gets a segment of its own.
+    pub(crate) fn push_str(&mut self, data: &str) -> Range<usize> {
+        self.push_strs(&[data])
+    }
+
+    /// Appends each of `data` right after the previous one, returning the range they were stored
+    /// at. They're stored in a single segment, which grows at most once.
+    pub(crate) fn push_strs(&mut self, data: &[&str]) -> Range<usize> {
+        let len = data.iter().map(|data| data.len()).sum();
+        self.push_with(len, |segment| {
+            for data in data {
+                segment.push_str(data);
+            }
+        })
+    }
+
+    /// Appends `count` copies of `c`, returning the range they were stored at. They're written
+    /// straight into a single segment, like [`AddedBuffer::push_strs`].
+    pub(crate) fn push_repeated(&mut self, c: char, count: usize) -> Range<usize> {
+        self.push_with(c.len_utf8() * count, |segment| {
+            segment.extend(std::iter::repeat_n(c, count));
+        })
+    }
+
+    /// Makes room for `len` bytes in a writable segment and lets `write` append exactly that
+    /// many to it.
+    fn push_with(&mut self, len: usize, write: impl FnOnce(&mut String)) -> Range<usize> {
+        let start = self.len;
+        if len == 0 {
+            return start..start;
+        }
+
+        let fits = self
+            .segments
+            .last_mut()
+            .and_then(|(_, segment)| segment.writable())
+            .is_some_and(|segment| segment.capacity() - segment.len() >= len);
+        if !fits {
+            let segment = String::with_capacity(self.segment_size.max(len));
+            self.segments
+                .push((start, Segment::Owned(Arc::new(segment))));
+        }
+        let (_, segment) = self.segments.last_mut().expect("a segment was just made");
+        let segment = segment.writable().expect("the last segment isn't shared");
+        write(segment);
+        self.len += len;
+        start..self.len
+    }
+
+    /// Appends `data` as a segment of its own without copying it, returning the range it was
+    /// stored at.
+    pub(crate) fn push_borrowed(&mut self, data: &'a str) -> Range<usize> {
+        let start = self.len;
+        if !data.is_empty() {
+            self.segments.push((start, Segment::Borrowed(data)));
+            self.len += data.len();
+            self.borrowed += data.len();
+        }
+        start..self.len
+    }
+
+    /// Appends all of `other`'s segments without copying them, returning the offset they now
+    /// start at.
+    pub(crate) fn append(&mut self, other: AddedBuffer<'a>) -> usize {
+        let offset = self.len;
+        self.segments.extend(
+            other
+                .segments
+                .into_iter()
+                .map(|(start, segment)| (start + offset, segment)),
+        );
+        self.len += other.len;
+        self.borrowed += other.borrowed;
+        offset
+    }
+
+    /// Checks if the bytes at `a` and `b` are stored in the same segment.
+    pub(crate) fn same_segment(&self, a: usize, b: usize) -> bool {
+        self.segment_of(a) == self.segment_of(b)
+    }
+
+    /// Returns the text in `range`, or `None` if it's out of bounds, spans several segments or
+    /// isn't on char boundaries.
+    pub(crate) fn get(&self, range: Range<usize>) -> Option<&str> {
+        if range.start > range.end || range.end > self.len {
+            return None;
+        }
+        if range.is_empty() {
+            return Some("");
+        }
+        let (start, segment) = &self.segments[self.segment_of(range.start)?];
+        segment.get(range.start - start..range.end - start)
+    }
+
+    /// Appends the text in `range` to `buf`, which may span several segments.
+    pub(crate) fn copy_range(&self, range: Range<usize>, buf: &mut String) {
+        let Some(first) = self.segment_of(range.start) else {
+            return;
+        };
+        for (start, segment) in &self.segments[first..] {
+            if *start >= range.end {
+                break;
+            }
+            let from = range.start.max(*start) - start;
+            let to = range.end.min(start + segment.len()) - start;
+            buf.push_str(&segment[from..to]);
+        }
+    }
+
+    /// Returns the index of the segment containing byte `offset`.
+    fn segment_of(&self, offset: usize) -> Option<usize> {
+        self.segments
+            .partition_point(|(start, _)| *start <= offset)
+            .checked_sub(1)
+    }
+}
+
+impl Segment<'_> {
+    /// Returns the text of the segment for appending to it, if it's owned and nothing else
+    /// refers to it.
+    fn writable(&mut self) -> Option<&mut String> {
+        match self {
+            Segment::Owned(text) => Arc::get_mut(text),
+            Segment::Borrowed(_) => None,
+        }
+    }
+}
+
+impl From<String> for AddedBuffer<'_> {
+    fn from(string: String) -> Self {
+        let mut buffer = AddedBuffer::default();
+        if !string.is_empty() {
+            buffer.len = string.len();
+            buffer.segments.push((0, Segment::Owned(Arc::new(string))));
+        }
+        buffer
+    }
+}
+
+impl Index<Range<usize>> for AddedBuffer<'_> {
+    type Output = str;
+
+    /// Returns the text in `range`, which must lie within a single segment.
+    fn index(&self, range: Range<usize>) -> &str {
+        match self.get(range.clone()) {
+            Some(text) => text,
+            None => panic!("range {range:?} isn't a valid range of a single segment"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(segment_size: usize) -> AddedBuffer<'static> {
+        let mut buffer = AddedBuffer::default();
+        buffer.set_segment_size(segment_size);
+        buffer
+    }
+
+    #[test]
+    fn pushes_never_move_text() {
+        let mut buffer = buffer(8);
+        buffer.push_str("abc");
+        let before = buffer[0..3].as_ptr();
+
+        for _ in 0..100 {
+            buffer.push_str("defg");
+        }
+
+        assert_eq!(before, buffer[0..3].as_ptr());
+        assert_eq!(403, buffer.len());
+    }
+
+    #[test]
+    fn push_starts_new_segment_when_full() {
+        let mut buffer = buffer(8);
+
+        assert_eq!(0..5, buffer.push_str("hello"));
+        assert_eq!(5..10, buffer.push_str("world"));
+        assert_eq!(10..30, buffer.push_str("a push bigger than 8"));
+
+        assert!(!buffer.same_segment(4, 5));
+        assert_eq!("world", &buffer[5..10]);
+        assert_eq!(None, buffer.get(3..7));
+
+        let mut copy = String::new();
+        buffer.copy_range(3..12, &mut copy);
+        assert_eq!("loworlda ", copy);
+    }
+
+    #[test]
+    fn push_repeated_writes_in_place() {
+        let mut buffer = buffer(8);
+        buffer.push_str("ab");
+
+        assert_eq!(2..8, buffer.push_repeated('é', 3));
+        assert_eq!(8..8, buffer.push_repeated('x', 0));
+        assert_eq!(8..18, buffer.push_repeated('-', 10));
+        assert!(buffer.same_segment(0, 7));
+        assert_eq!("abééé", &buffer[0..8]);
+        assert_eq!("-".repeat(10), &buffer[8..18]);
+    }
+
+    #[test]
+    fn shared_segment_is_not_written_to() {
+        let mut buffer = buffer(16);
+        buffer.push_str("abc");
+        let shared = buffer.clone();
+
+        assert_eq!(3..6, buffer.push_str("def"));
+        assert!(!buffer.same_segment(2, 3));
+        assert_eq!("abc", &shared[0..3]);
+        assert_eq!("def", &buffer[3..6]);
+    }
+
+    #[test]
+    fn append_shifts_segments() {
+        let mut first = buffer(4);
+        first.push_str("ab");
+        let mut second = buffer(4);
+        second.push_str("cd");
+        second.push_str("efgh");
+
+        assert_eq!(2, first.append(second));
+        assert_eq!("cd", &first[2..4]);
+        assert_eq!("efgh", &first[4..8]);
+        assert_eq!(8, first.len());
+    }
+
+    #[test]
+    fn borrowed_segments_are_not_written_to() {
+        let text = String::from("borrowed");
+        let mut buffer = buffer(16);
+        buffer.push_str("ab");
+
+        assert_eq!(2..10, buffer.push_borrowed(&text));
+        assert_eq!(10..12, buffer.push_str("cd"));
+        assert_eq!(text.as_ptr(), buffer[2..10].as_ptr());
+        assert!(!buffer.same_segment(9, 10));
+        assert_eq!(4, buffer.owned_len());
+    }
+}
@@ -0,0 +1,345 @@
+//! Hosting several documents which share a single "added" buffer.
+
+use std::ops::Range;
+
+use crate::added::AddedBuffer;
+use crate::{EditError, Node, NodeKind, PieceTable, VALIDATE};
+
+/// Identifies a document in a [`PieceTableArena`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DocumentId(usize);
+
+/// A set of documents which share one "added" buffer.
+///
+/// Every document keeps its own original text and its own list of pieces, but text inserted into
+/// any of them is appended to the same buffer. This amortizes allocations across all documents,
+/// and lets text be copied or moved between documents by copying pieces instead of the text they
+/// refer to. Only pieces of one document's original text which are copied into a document with a
+/// different original text have their text copied into the shared buffer.
+///
+/// # Examples
+///
+/// ```
+/// # use piece_table::arena::PieceTableArena;
+/// let mut arena = PieceTableArena::new();
+/// let a = arena.add_document("fn main() {}\n");
+/// let b = arena.add_document("mod util;\n");
+///
+/// arena.insert(a, "fn helper() {}\n", 0);
+/// arena.move_range(a, 0..15, b, 10);
+///
+/// assert_eq!(arena.text(a), "fn main() {}\n");
+/// assert_eq!(arena.text(b), "mod util;\nfn helper() {}\n");
+/// ```
+#[derive(Debug, Default)]
+pub struct PieceTableArena<'a> {
+    added: AddedBuffer<'a>,
+    /// The documents' own `added` buffers are always empty, except while the shared buffer is
+    /// swapped into one of them by `with_document`.
+    documents: Vec<PieceTable<'a>>,
+}
+
+impl<'a> PieceTableArena<'a> {
+    /// Creates an arena with no documents.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a new document with `original` as its original text.
+    pub fn add_document(&mut self, original: &'a str) -> DocumentId {
+        self.documents.push(PieceTable::new(original));
+        DocumentId(self.documents.len() - 1)
+    }
+
+    /// Returns the number of documents in the arena.
+    pub fn document_count(&self) -> usize {
+        self.documents.len()
+    }
+
+    /// Returns the length of the shared "added" buffer in bytes.
+    pub fn added_len(&self) -> usize {
+        self.added.len()
+    }
+
+    /// Returns the length of a document in bytes.
+    pub fn len(&self, doc: DocumentId) -> usize {
+        self.documents[doc.0].len
+    }
+
+    /// Checks if a document is empty.
+    pub fn is_empty(&self, doc: DocumentId) -> bool {
+        self.len(doc) == 0
+    }
+
+    /// Returns the number of lines in a document, see [`PieceTable::len_lines`].
+    pub fn len_lines(&self, doc: DocumentId) -> usize {
+        self.documents[doc.0].newlines + 1
+    }
+
+    /// Returns the contents of a document as a `String`.
+    pub fn text(&self, doc: DocumentId) -> String {
+        let document = &self.documents[doc.0];
+        let mut string = String::with_capacity(document.len);
+        for node in &document.nodes {
+            node.chunks(document.original, &self.added)
+                .for_each(|chunk| string.push_str(chunk));
+        }
+        string
+    }
+
+    /// Inserts `data` into a document, see [`PieceTable::insert`].
+    pub fn insert(&mut self, doc: DocumentId, data: &str, offset: usize) {
+        self.with_document(doc, |document| document.insert(data, offset));
+    }
+
+    /// Inserts `data` into a document, see [`PieceTable::try_insert`].
+    pub fn try_insert(
+        &mut self,
+        doc: DocumentId,
+        data: &str,
+        offset: usize,
+    ) -> Result<(), EditError> {
+        self.with_document(doc, |document| document.try_insert(data, offset))
+    }
+
+    /// Deletes a range of text from a document, see [`PieceTable::delete`].
+    pub fn delete(&mut self, doc: DocumentId, range: Range<usize>) {
+        self.with_document(doc, |document| document.delete(range));
+    }
+
+    /// Deletes a range of text from a document, see [`PieceTable::try_delete`].
+    pub fn try_delete(&mut self, doc: DocumentId, range: Range<usize>) -> Result<(), EditError> {
+        self.with_document(doc, |document| document.try_delete(range))
+    }
+
+    /// Copies the text in `range` of document `from` to `offset` in document `to`.
+    ///
+    /// `from` and `to` may be the same document.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds of `from`, or `offset` is out of bounds of `to`. In
+    /// debug builds, or when the `strict` feature is enabled, also panics if either isn't on a
+    /// char boundary.
+    pub fn copy_range(
+        &mut self,
+        from: DocumentId,
+        range: Range<usize>,
+        to: DocumentId,
+        offset: usize,
+    ) {
+        let nodes = self.pieces(from, range);
+        self.insert_pieces(from, nodes, to, offset);
+    }
+
+    /// Moves the text in `range` of document `from` to `offset` in document `to`.
+    ///
+    /// `offset` refers to `to` as it is before the move. When moving within a single document,
+    /// an `offset` inside `range` leaves the text where it is.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds of `from`, or `offset` is out of bounds of `to`. In
+    /// debug builds, or when the `strict` feature is enabled, also panics if either isn't on a
+    /// char boundary.
+    pub fn move_range(
+        &mut self,
+        from: DocumentId,
+        range: Range<usize>,
+        to: DocumentId,
+        offset: usize,
+    ) {
+        let nodes = self.pieces(from, range.clone());
+        let checked = self.with_document(to, |document| {
+            if VALIDATE {
+                document.check_offset(offset)
+            } else {
+                document.check_offset_bounds(offset)
+            }
+        });
+        if let Err(err) = checked {
+            panic!("invalid move: {err}");
+        }
+
+        self.with_document(from, |document| document.delete_unchecked(range.clone()));
+        let offset = if from != to || offset <= range.start {
+            offset
+        } else if offset >= range.end {
+            offset - range.len()
+        } else {
+            range.start
+        };
+        self.insert_pieces(from, nodes, to, offset);
+    }
+
+    /// Returns copies of the pieces covering `range` of a document.
+    fn pieces(&mut self, doc: DocumentId, range: Range<usize>) -> Vec<Node> {
+        self.with_document(doc, |document| {
+            let checked = if VALIDATE {
+                document.check_range(&range)
+            } else {
+                document.check_range_bounds(&range)
+            };
+            if let Err(err) = checked {
+                panic!("invalid range: {err}");
+            }
+
+            let first = document.split_at_offset(range.start);
+            let last = document.split_at_offset(range.end);
+            document.nodes.range(first..last).cloned().collect()
+        })
+    }
+
+    /// Inserts pieces taken from document `from` at `offset` in document `to`.
+    ///
+    /// Pieces of `from`'s original text are copied into the shared buffer, unless both documents
+    /// have the same original text.
+    fn insert_pieces(
+        &mut self,
+        from: DocumentId,
+        mut nodes: Vec<Node>,
+        to: DocumentId,
+        offset: usize,
+    ) {
+        let original = self.documents[from.0].original;
+        if !std::ptr::eq(original, self.documents[to.0].original) {
+            for node in &mut nodes {
+                if let NodeKind::Original = node.kind {
+                    node.range = self.added.push_str(&original[node.range.clone()]);
+                    node.kind = NodeKind::Added;
+                }
+            }
+        }
+
+        self.with_document(to, |document| {
+            let checked = if VALIDATE {
+                document.check_offset(offset)
+            } else {
+                document.check_offset_bounds(offset)
+            };
+            if let Err(err) = checked {
+                panic!("invalid offset: {err}");
+            }
+            document.insert_nodes(offset, nodes);
+        });
+    }
+
+    /// Runs `f` on a document, with the shared buffer temporarily swapped into it.
+    fn with_document<R>(&mut self, doc: DocumentId, f: impl FnOnce(&mut PieceTable<'a>) -> R) -> R {
+        let document = &mut self.documents[doc.0];
+        std::mem::swap(&mut self.added, &mut document.added);
+        let result = f(document);
+        std::mem::swap(&mut self.added, &mut document.added);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_into_separate_documents() {
+        let mut arena = PieceTableArena::new();
+        let a = arena.add_document("hello");
+        let b = arena.add_document("world");
+
+        arena.insert(a, ", there", 5);
+        arena.insert(b, "!\n", 5);
+        arena.delete(a, 0..1);
+
+        assert_eq!("ello, there", arena.text(a));
+        assert_eq!("world!\n", arena.text(b));
+        assert_eq!(2, arena.len_lines(b));
+        assert_eq!(9, arena.added_len());
+    }
+
+    #[test]
+    fn try_edits_report_errors() {
+        let mut arena = PieceTableArena::new();
+        let a = arena.add_document("é");
+
+        assert_eq!(
+            Err(EditError::NotCharBoundary { offset: 1 }),
+            arena.try_insert(a, "x", 1)
+        );
+        assert_eq!(
+            Err(EditError::OutOfBounds { offset: 3, len: 2 }),
+            arena.try_delete(a, 0..3)
+        );
+        assert_eq!("é", arena.text(a));
+    }
+
+    #[test]
+    fn copy_added_text_does_not_grow_buffer() {
+        let mut arena = PieceTableArena::new();
+        let a = arena.add_document("");
+        let b = arena.add_document("");
+        arena.insert(a, "shared text", 0);
+
+        arena.copy_range(a, 0..6, b, 0);
+
+        assert_eq!("shared text", arena.text(a));
+        assert_eq!("shared", arena.text(b));
+        assert_eq!(11, arena.added_len());
+    }
+
+    #[test]
+    fn copy_original_text_between_documents() {
+        let mut arena = PieceTableArena::new();
+        let original = "one\ntwo";
+        let a = arena.add_document(original);
+        let b = arena.add_document(original);
+        let c = arena.add_document("three");
+
+        arena.copy_range(a, 2..5, b, 0);
+        assert_eq!(0, arena.added_len());
+
+        arena.copy_range(a, 2..5, c, 5);
+        assert_eq!(3, arena.added_len());
+
+        assert_eq!("e\ntone\ntwo", arena.text(b));
+        assert_eq!("threee\nt", arena.text(c));
+        assert_eq!(2, arena.len_lines(c));
+    }
+
+    #[test]
+    fn move_between_documents() {
+        let mut arena = PieceTableArena::new();
+        let a = arena.add_document("abcdef");
+        let b = arena.add_document("xyz");
+
+        arena.move_range(a, 1..4, b, 1);
+
+        assert_eq!("aef", arena.text(a));
+        assert_eq!("xbcdyz", arena.text(b));
+        assert_eq!(3, arena.len(a));
+        assert_eq!(6, arena.len(b));
+    }
+
+    #[test]
+    fn move_within_document() {
+        let mut arena = PieceTableArena::new();
+        let a = arena.add_document("abcdef");
+
+        arena.move_range(a, 0..2, a, 6);
+        assert_eq!("cdefab", arena.text(a));
+
+        arena.move_range(a, 4..6, a, 0);
+        assert_eq!("abcdef", arena.text(a));
+
+        arena.move_range(a, 1..4, a, 2);
+        assert_eq!("abcdef", arena.text(a));
+        assert_eq!(0, arena.added_len());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid move: offset 9 is out of bounds of document of length 3")]
+    fn move_past_the_end() {
+        let mut arena = PieceTableArena::new();
+        let a = arena.add_document("abcdef");
+        let b = arena.add_document("xyz");
+
+        arena.move_range(a, 1..4, b, 9);
+    }
+}
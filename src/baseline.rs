@@ -6,6 +6,18 @@ pub struct Baseline {
     text: String,
 }
 
+impl Baseline {
+    /// Returns the length of the text, in bytes.
+    pub fn len(&self) -> usize {
+        self.text.len()
+    }
+
+    /// Checks if the text is empty.
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+}
+
 impl EditableText<'_> for Baseline {
     fn new(string: &str) -> Self {
         Baseline { text: string.to_string() }
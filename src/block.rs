@@ -0,0 +1,117 @@
+//! Rectangular (column) selections, spanning the same columns on several lines.
+
+use std::ops::Range;
+
+use crate::PieceTable;
+use crate::edit_builder::EditBuilder;
+use crate::point::ColumnUnit;
+
+impl PieceTable<'_> {
+    /// Inserts `text` at `column` on each line in `lines`, as when typing with a column
+    /// selection.
+    ///
+    /// Lines which are shorter than `column` are padded with spaces first, so the inserted text
+    /// lines up. Lines past the end of the document are skipped. All the insertions are applied
+    /// as a single batch, see [`EditBuilder`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// # use piece_table::point::ColumnUnit;
+    /// let mut pt = PieceTable::new("let a = 1;\nlet bb = 2;\nx\n");
+    /// pt.insert_block(0..3, 4, ColumnUnit::Chars, "mut ");
+    /// assert_eq!(pt.to_string(), "let mut a = 1;\nlet mut bb = 2;\nx   mut \n");
+    /// ```
+    pub fn insert_block(
+        &mut self,
+        lines: Range<usize>,
+        column: usize,
+        unit: ColumnUnit,
+        text: &str,
+    ) {
+        let mut builder = EditBuilder::new(self);
+        for line in lines {
+            let Some(line_start) = self.line_start(line) else {
+                break;
+            };
+            let (offset, found) = self.seek_column(line_start, column, unit);
+            let at_line_end = self.byte(offset).is_none_or(|b| b == b'\n');
+            if at_line_end && found < column {
+                builder.insert(offset, " ".repeat(column - found) + text);
+            } else {
+                builder.insert(offset, text);
+            }
+        }
+        builder
+            .apply(self)
+            .expect("block insertions are on separate lines");
+    }
+
+    /// Deletes the text between `columns.start` and `columns.end` on each line in `lines`, as
+    /// when deleting a column selection.
+    ///
+    /// Columns past the end of a line are clamped to it, and a column which falls inside a char
+    /// refers to the start of that char, like in [`PieceTable::point_to_offset`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// # use piece_table::point::ColumnUnit;
+    /// let mut pt = PieceTable::new("// one\n// two\n//\n");
+    /// pt.delete_block(0..3, 0..3, ColumnUnit::Chars);
+    /// assert_eq!(pt.to_string(), "one\ntwo\n\n");
+    /// ```
+    pub fn delete_block(&mut self, lines: Range<usize>, columns: Range<usize>, unit: ColumnUnit) {
+        let mut builder = EditBuilder::new(self);
+        for line in lines {
+            let Some(line_start) = self.line_start(line) else {
+                break;
+            };
+            let (start, _) = self.seek_column(line_start, columns.start, unit);
+            let (end, _) = self.seek_column(line_start, columns.end, unit);
+            builder.delete(start..end.max(start));
+        }
+        builder
+            .apply(self)
+            .expect("block deletions are on separate lines");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_block_pads_short_lines() {
+        let mut piece_table = PieceTable::new("abcdef\nab\n\nabcdef");
+        piece_table.insert_block(0..10, 3, ColumnUnit::Bytes, "|");
+
+        assert_eq!("abc|def\nab |\n   |\nabc|def", piece_table.to_string());
+    }
+
+    #[test]
+    #[cfg(feature = "unicode-width")]
+    fn block_in_display_cells() {
+        let mut piece_table = PieceTable::new("\tx = 1\n    y = 2\n世界 = 3");
+        let unit = ColumnUnit::Cells { tab_width: 4 };
+
+        piece_table.insert_block(0..3, 4, unit, "let ");
+        assert_eq!(
+            "\tlet x = 1\n    let y = 2\n世界let  = 3",
+            piece_table.to_string()
+        );
+
+        piece_table.delete_block(0..3, 4..8, unit);
+        assert_eq!("\tx = 1\n    y = 2\n世界 = 3", piece_table.to_string());
+    }
+
+    #[test]
+    fn delete_block_clamps_to_line_end() {
+        let mut piece_table = PieceTable::new("abcdef\nab\nabcd");
+        piece_table.delete_block(0..3, 1..4, ColumnUnit::Chars);
+
+        assert_eq!("aef\na\na", piece_table.to_string());
+    }
+}
@@ -0,0 +1,421 @@
+//! Rebuilding the pieces of a long-edited table in the background.
+//!
+//! Every edit in a new place adds pieces, and text which is deleted stays in the "added" buffer,
+//! so a table edited for hours gets slower to read and keeps growing. Rebuilding it copies the
+//! text added by edits into a new buffer, which takes time proportional to the document, so it's
+//! done by a [`CompactionJob`] on another thread from a slice of the table, while it keeps being
+//! edited. Pieces of the original text are kept, so what was changed is still known. The result is swapped in by [`PieceTable::apply_compaction`], which only replaces the
+//! list of pieces and the buffer, and [`BackgroundCompactor`] ties the two together.
+
+use std::sync::mpsc;
+
+use crate::added::AddedBuffer;
+use crate::cursor::next_generation;
+use crate::{Node, NodeKind, PTableSlice, PieceTable};
+
+/// When a table is worth compacting, see [`PieceTable::needs_compaction`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactionPolicy {
+    /// The most pieces the document can have.
+    pub max_pieces: usize,
+    /// The most bytes of the "added" buffer which no piece refers to.
+    pub max_garbage: usize,
+}
+
+impl Default for CompactionPolicy {
+    /// Allows 4096 pieces and 1 MiB of garbage.
+    fn default() -> Self {
+        CompactionPolicy {
+            max_pieces: 4096,
+            max_garbage: 1024 * 1024,
+        }
+    }
+}
+
+/// Rebuilding the pieces of a table from a slice of it, see [`PieceTable::start_compaction`].
+///
+/// The job doesn't borrow the table, and can be sent to any thread or executor to be run.
+#[derive(Debug)]
+pub struct CompactionJob<'a> {
+    slice: PTableSlice<'a>,
+    generation: u64,
+}
+
+/// The rebuilt pieces of a table, to be applied with [`PieceTable::apply_compaction`].
+#[derive(Debug)]
+pub struct Compacted {
+    nodes: Vec<Node>,
+    added: String,
+    generation: u64,
+}
+
+impl CompactionJob<'_> {
+    /// Rebuilds the pieces.
+    ///
+    /// Pieces of the original text, and runs of a repeated char, are kept, merging the ones
+    /// which follow each other, and the text between them is copied into a new buffer as a
+    /// single piece.
+    pub fn run(self) -> Compacted {
+        let (original, added) = (self.slice.original, &self.slice.added);
+        let mut compacted = Compacted {
+            nodes: Vec::new(),
+            added: String::new(),
+            generation: self.generation,
+        };
+        let mut run_start = 0;
+        for node in self
+            .slice
+            .nodes
+            .iter()
+            .filter(|node| !node.range.is_empty())
+        {
+            if let NodeKind::Added | NodeKind::Inline(_) = node.kind {
+                for chunk in node.chunks(original, added) {
+                    compacted.added.push_str(chunk);
+                }
+                continue;
+            }
+            if compacted.added.len() > run_start {
+                compacted.end_run(run_start);
+                run_start = compacted.added.len();
+            } else if let Some(last) = compacted.nodes.last_mut() {
+                // Lossy tables have many pieces of the same replacement char, which are only
+                // merged if they're next to each other in the buffer
+                let range = last.range.start..node.range.end;
+                match (last.kind, node.kind) {
+                    (NodeKind::Original, NodeKind::Original)
+                        if last.range.end == node.range.start =>
+                    {
+                        *last = Node::new(NodeKind::Original, range.clone(), &original[range]);
+                        continue;
+                    }
+                    (NodeKind::Repeat(byte), NodeKind::Repeat(next)) if byte == next => {
+                        *last = Node::repeat(byte, last.range.len() + node.range.len());
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+            compacted.nodes.push(node.clone());
+        }
+        compacted.end_run(run_start);
+        compacted
+    }
+}
+
+impl Compacted {
+    /// Adds a piece for the text copied since `run_start`, if there is any.
+    fn end_run(&mut self, run_start: usize) {
+        let range = run_start..self.added.len();
+        if !range.is_empty() {
+            let node = Node::new(NodeKind::Added, range.clone(), &self.added[range]);
+            self.nodes.push(node);
+        }
+    }
+}
+
+impl<'a> PieceTable<'a> {
+    /// Checks if the table has more pieces, or more deleted text in its "added" buffer, than
+    /// `policy` allows.
+    ///
+    /// Pieces of the original text are kept by a compaction, so a table with more of them than
+    /// `policy.max_pieces` still needs compacting after one.
+    pub fn needs_compaction(&self, policy: &CompactionPolicy) -> bool {
+        let referenced: usize = self
+            .nodes
+            .iter()
+            .filter(|node| node.kind == NodeKind::Added)
+            .map(|node| node.range.len())
+            .sum();
+        self.nodes.len() > policy.max_pieces
+            || self.added.owned_len().saturating_sub(referenced) > policy.max_garbage
+    }
+
+    /// Returns a job rebuilding the pieces of the current state, to be run on another thread
+    /// and applied with [`PieceTable::apply_compaction`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("");
+    /// for offset in 0..10 {
+    ///     pt.insert("ab", offset);
+    /// }
+    /// pt.delete(5..15);
+    /// assert_eq!(pt.pieces().count(), 10);
+    ///
+    /// let job = pt.start_compaction();
+    /// let compacted = std::thread::spawn(move || job.run()).join().unwrap();
+    /// assert!(pt.apply_compaction(compacted));
+    /// assert_eq!(pt.to_string(), "aaaaabbbbb");
+    /// assert_eq!(pt.pieces().count(), 1);
+    /// ```
+    pub fn start_compaction(&self) -> CompactionJob<'a> {
+        CompactionJob {
+            slice: self.create_slice(),
+            generation: self.generation,
+        }
+    }
+
+    /// Replaces the pieces and the "added" buffer with the ones rebuilt by a
+    /// [`CompactionJob`], returning `false` and dropping them if they can't be applied.
+    ///
+    /// They can't be applied if the table was edited since the job was started, or if anything
+    /// still refers to the old buffer by offset, i.e. if the table has checkpoints or attached
+    /// sources, or is being or was loaded by streaming. Slices and clones of the table keep the
+    /// old buffer alive, so they must be dropped first for its memory to be freed, and nothing
+    /// is applied while any exist. Readers created with [`PieceTable::reader`] are fine.
+    ///
+    /// [`InverseEdit`](crate::inverse::InverseEdit)s returned before a compaction is applied
+    /// refer to the old buffer, so applying them afterwards panics.
+    pub fn apply_compaction(&mut self, compacted: Compacted) -> bool {
+        if compacted.generation != self.generation || !self.can_apply_compaction() {
+            return false;
+        }
+        let mut added = AddedBuffer::from(compacted.added);
+        added.set_segment_size(self.added.segment_size());
+        self.added = added;
+        self.buffers = next_generation();
+        self.nodes = compacted.nodes.into();
+        self.pieces_changed();
+        self.publisher.reset_chunks();
+        self.publish();
+        true
+    }
+
+    /// Checks if a compaction could be applied now, if the table isn't edited before it is,
+    /// see [`PieceTable::apply_compaction`].
+    pub fn can_apply_compaction(&self) -> bool {
+        self.checkpoints.is_empty()
+            && self.sources.is_empty()
+            && !self.is_loading()
+            && self.stream.loaded_len() == 0
+            && !self.added.is_shared()
+    }
+}
+
+/// Runs compactions of a table on an executor whenever it crosses the thresholds of a
+/// [`CompactionPolicy`].
+///
+/// [`BackgroundCompactor::poll`] is meant to be called regularly, e.g. when the user stops
+/// typing. An edit made while a job runs makes its result useless, so starting jobs when the
+/// table is idle gives them the best chance of being applied.
+///
+/// # Examples
+///
+/// ```
+/// # use piece_table::PieceTable;
+/// use piece_table::compaction::{BackgroundCompactor, CompactionPolicy};
+///
+/// let mut pt = PieceTable::from(String::new());
+/// for i in 0..100 {
+///     pt.insert("ab", i);
+/// }
+/// let policy = CompactionPolicy {
+///     max_pieces: 10,
+///     ..CompactionPolicy::default()
+/// };
+/// let mut compactor = BackgroundCompactor::new(policy);
+/// let spawn = |job: Box<dyn FnOnce() + Send>| {
+///     std::thread::spawn(job);
+/// };
+///
+/// assert!(!compactor.poll(&mut pt, spawn));
+/// while compactor.is_pending() {
+///     if compactor.poll(&mut pt, spawn) {
+///         break;
+///     }
+/// }
+/// assert_eq!(pt.pieces().count(), 1);
+/// ```
+#[derive(Debug, Default)]
+pub struct BackgroundCompactor {
+    policy: CompactionPolicy,
+    pending: Option<mpsc::Receiver<Compacted>>,
+}
+
+impl BackgroundCompactor {
+    /// Creates a compactor which compacts tables crossing the thresholds of `policy`.
+    pub fn new(policy: CompactionPolicy) -> Self {
+        BackgroundCompactor {
+            policy,
+            pending: None,
+        }
+    }
+
+    /// Checks if a job was started and its result wasn't applied or dropped yet.
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Applies the result of the pending job if it's done, returning whether it was applied,
+    /// and starts a new job if `table` needs compacting and none is pending.
+    ///
+    /// Jobs are handed to `spawn` as closures, which it runs on a thread pool, a thread of its
+    /// own, or any other executor. If a job is dropped without being run, its result is never
+    /// applied, and a new job is started on a later call. No job is started while the result
+    /// couldn't be applied, see [`PieceTable::can_apply_compaction`].
+    pub fn poll<'a, S>(&mut self, table: &mut PieceTable<'a>, spawn: S) -> bool
+    where
+        S: FnOnce(Box<dyn FnOnce() + Send + 'a>),
+    {
+        if let Some(pending) = &self.pending {
+            match pending.try_recv() {
+                Ok(compacted) => {
+                    self.pending = None;
+                    if table.apply_compaction(compacted) {
+                        return true;
+                    }
+                }
+                Err(mpsc::TryRecvError::Empty) => return false,
+                Err(mpsc::TryRecvError::Disconnected) => self.pending = None,
+            }
+        }
+
+        if table.needs_compaction(&self.policy) && table.can_apply_compaction() {
+            let (sender, receiver) = mpsc::channel();
+            let job = table.start_compaction();
+            self.pending = Some(receiver);
+            spawn(Box::new(move || {
+                // The compactor may have been dropped in the meantime
+                let _ = sender.send(job.run());
+            }));
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_original_pieces_and_repeats() {
+        let original = "x".repeat(1000);
+        let mut piece_table = PieceTable::new(&original);
+        piece_table.insert("ab", 500);
+        piece_table.insert("cd", 100);
+        piece_table.delete(1..3);
+        piece_table.insert_repeated(1002, ' ', 10);
+        piece_table.insert("yz", 1012);
+        let expected = piece_table.to_string();
+
+        let compacted = piece_table.start_compaction().run();
+        assert!(piece_table.apply_compaction(compacted));
+        assert_eq!(expected, piece_table.to_string());
+        assert_eq!(Ok(()), piece_table.check_invariants());
+        let kinds: Vec<_> = piece_table.nodes.iter().map(|node| node.kind).collect();
+        assert_eq!(
+            vec![
+                NodeKind::Original,
+                NodeKind::Original,
+                NodeKind::Added,
+                NodeKind::Original,
+                NodeKind::Added,
+                NodeKind::Original,
+                NodeKind::Repeat(b' '),
+                NodeKind::Added,
+            ],
+            kinds
+        );
+        assert_eq!(2 + 2 + 2, piece_table.added.len());
+    }
+
+    #[test]
+    fn changes_are_still_known() {
+        let mut piece_table = PieceTable::new("hello world, this is the original");
+        piece_table.insert("X", 5);
+        piece_table.split_at_offset(20);
+        piece_table.insert_repeated(0, '-', 2);
+        piece_table.insert_repeated(2, '-', 3);
+        let changed = piece_table.changed_ranges();
+
+        let compacted = piece_table.start_compaction().run();
+        assert!(piece_table.apply_compaction(compacted));
+        assert_eq!(changed, piece_table.changed_ranges());
+        // Pieces which follow each other in the original text, or repeat the same char, are
+        // merged
+        assert_eq!(4, piece_table.nodes.len());
+
+        let mut piece_table = PieceTable::from_bytes_lossy(b"ab\xFFcd\xFE\xFFe");
+        piece_table.insert("X", 0);
+        let replacements = piece_table.replacements();
+        let compacted = piece_table.start_compaction().run();
+        assert!(piece_table.apply_compaction(compacted));
+        assert_eq!(replacements, piece_table.replacements());
+        assert_eq!(3, replacements.len());
+    }
+
+    #[test]
+    fn stale_or_unsafe_results_are_dropped() {
+        let mut piece_table = PieceTable::new("abc");
+        piece_table.insert("d", 1);
+
+        let job = piece_table.start_compaction();
+        piece_table.insert("e", 0);
+        assert!(!piece_table.apply_compaction(job.run()));
+
+        let job = piece_table.start_compaction();
+        let slice = piece_table.create_slice();
+        assert!(!piece_table.apply_compaction(job.run()));
+        drop(slice);
+
+        piece_table.checkpoint("saved");
+        assert!(!piece_table.can_apply_compaction());
+        assert!(!piece_table.apply_compaction(piece_table.start_compaction().run()));
+        assert_eq!("eadbc", piece_table.to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid apply_inverse: the edit refers to buffers")]
+    fn inverse_edits_from_before_are_rejected() {
+        let mut piece_table = PieceTable::new("");
+        piece_table.insert("abc", 0);
+        piece_table.insert("XYZ", 3);
+        let undo = piece_table.delete_with_inverse(0..3);
+
+        let compacted = piece_table.start_compaction().run();
+        assert!(piece_table.apply_compaction(compacted));
+        assert_eq!("XYZ", piece_table.to_string());
+        piece_table.apply_inverse(undo);
+    }
+
+    #[test]
+    fn readers_see_the_compacted_table() {
+        let mut piece_table = PieceTable::new("hello");
+        let reader = piece_table.reader();
+        piece_table.insert(" world", 5);
+        piece_table.delete(0..1);
+        piece_table.insert("J", 0);
+
+        let compacted = piece_table.start_compaction().run();
+        assert!(piece_table.apply_compaction(compacted));
+        piece_table.insert("!", 11);
+        assert_eq!("Jello world!", reader.snapshot().to_string());
+    }
+
+    #[test]
+    fn compactor_waits_for_the_job() {
+        let mut piece_table = PieceTable::new("");
+        let mut compactor = BackgroundCompactor::new(CompactionPolicy {
+            max_pieces: 2,
+            max_garbage: usize::MAX,
+        });
+        let mut jobs: Vec<Box<dyn FnOnce() + Send>> = Vec::new();
+
+        assert!(!compactor.poll(&mut piece_table, |job| jobs.push(job)));
+        assert!(jobs.is_empty());
+        for offset in [0, 0, 0] {
+            piece_table.insert("ab", offset);
+        }
+        assert!(!compactor.poll(&mut piece_table, |job| jobs.push(job)));
+        assert_eq!(1, jobs.len());
+        assert!(!compactor.poll(&mut piece_table, |_| unreachable!()));
+
+        jobs.pop().unwrap()();
+        assert!(compactor.poll(&mut piece_table, |_| unreachable!()));
+        assert!(!compactor.is_pending());
+        assert_eq!(1, piece_table.nodes.len());
+    }
+}
@@ -0,0 +1,159 @@
+//! How much work each operation does, in terms of the number of pieces.
+//!
+//! The text of a piece is only read where an edit or a read touches it, so the cost which grows
+//! with the history of a document is finding the pieces. With `n` pieces, and `k` the length of
+//! the text inserted, deleted or read:
+//!
+//! | Operation                                        | Pieces visited |
+//! |--------------------------------------------------|----------------|
+//! | Inserting or deleting at the start or end        | O(1)           |
+//! | Typing or backspacing at the same place          | O(1) per char  |
+//! | Inserting or deleting anywhere else              | O(n)           |
+//! | The first two lookups by offset after an edit    | O(n)           |
+//! | Later lookups by offset, until the next edit     | O(log n)       |
+//! | Converting between lines and offsets             | O(n)           |
+//! | Reading `k` bytes from a known offset            | O(k)           |
+//!
+//! Lookups by offset, e.g. [`PieceTable::byte`](crate::PieceTable::byte), build the offsets of
+//! the pieces on the second one after an edit, and binary search them until the next edit.
+//!
+//! With the `perf-tests` feature, `cargo test --features perf-tests` checks the table above
+//! against the counters of the `instrument` feature, by doing each operation on documents of
+//! 1 000 and 10 000 pieces. A change which makes an operation visit more pieces than its row
+//! allows fails those tests, whatever the machine they run on.
+
+#[cfg(all(test, feature = "perf-tests"))]
+mod tests {
+    use crate::{CoalescePolicy, PieceTable};
+
+    const SMALL: usize = 1_000;
+    const LARGE: usize = 10 * SMALL;
+
+    /// An operation to measure, with its name for failures.
+    type Op = (&'static str, fn(&mut PieceTable));
+
+    /// Returns a document of `pieces` pieces of 2 bytes, each one a line.
+    fn fragmented(pieces: usize) -> PieceTable<'static> {
+        let mut piece_table = PieceTable::from(String::new());
+        piece_table.set_coalesce_policy(CoalescePolicy::Off);
+        for i in 0..pieces {
+            piece_table.insert("a\n", 2 * i);
+        }
+        piece_table.set_coalesce_policy(CoalescePolicy::Always);
+        piece_table
+    }
+
+    /// Returns how many pieces `op` visits on a document of `pieces` pieces.
+    fn visited(pieces: usize, op: impl Fn(&mut PieceTable)) -> usize {
+        let mut piece_table = fragmented(pieces);
+        assert_eq!(pieces, piece_table.nodes.len());
+        piece_table.reset_metrics();
+        op(&mut piece_table);
+        piece_table.metrics().nodes_visited
+    }
+
+    #[test]
+    fn edits_at_the_ends_are_constant() {
+        let ops: [Op; 5] = [
+            ("insert at the start", |pt| pt.insert("x", 0)),
+            ("insert at the end", |pt| pt.insert("x", pt.len())),
+            ("delete at the start", |pt| pt.delete(0..3)),
+            ("delete at the end", |pt| {
+                let len = pt.len();
+                pt.delete(len - 3..len);
+            }),
+            ("insert before the last char", |pt| {
+                pt.insert("x", pt.len() - 1)
+            }),
+        ];
+        for (name, op) in ops {
+            let small = visited(SMALL, op);
+            assert_eq!(small, visited(LARGE, op), "{name}");
+            assert!(small <= 10, "{name} visited {small} pieces");
+        }
+    }
+
+    #[test]
+    fn typing_is_constant_per_char() {
+        let ops: [Op; 3] = [
+            ("typing near the start", |pt| {
+                for i in 0..100 {
+                    pt.insert_char(1 + i, 'x');
+                }
+            }),
+            ("typing near the end", |pt| {
+                let len = pt.len();
+                for i in 0..100 {
+                    pt.insert_char(len - 1 + i, 'x');
+                }
+            }),
+            ("backspacing near the end", |pt| {
+                let len = pt.len();
+                for i in 0..100 {
+                    pt.delete(len - 2 - i..len - 1 - i);
+                }
+            }),
+        ];
+        for (name, op) in ops {
+            let small = visited(SMALL, op);
+            assert_eq!(small, visited(LARGE, op), "{name}");
+            assert!(small <= 10 * 100, "{name} visited {small} pieces");
+        }
+    }
+
+    #[test]
+    fn lookups_are_logarithmic_once_offsets_are_built() {
+        let lookups = |pt: &mut PieceTable| {
+            let len = pt.len();
+            for i in 0..100 {
+                pt.byte(len * i / 100);
+            }
+        };
+        let warm = |pieces| {
+            visited(pieces, |pt| {
+                pt.byte(0);
+                pt.byte(0);
+                pt.reset_metrics();
+                lookups(pt);
+            })
+        };
+
+        // Ten times the pieces is a few more steps of binary search
+        let (small, large) = (warm(SMALL), warm(LARGE));
+        assert!(small <= 100 * 12, "{small} pieces visited");
+        assert!(
+            large - small <= 100 * 4,
+            "{small} then {large} pieces visited"
+        );
+
+        // Cold lookups build the offsets once, rather than scanning every time
+        let cold = visited(LARGE, lookups);
+        assert!(cold <= LARGE + large, "{cold} pieces visited");
+    }
+
+    #[test]
+    fn other_edits_and_lines_are_linear() {
+        let ops: [Op; 4] = [
+            ("insert in the middle", |pt| pt.insert("x", pt.len() / 2)),
+            ("delete in the middle", |pt| {
+                let mid = pt.len() / 2;
+                pt.delete(mid - 201..mid + 201);
+            }),
+            ("offset to line", |pt| {
+                pt.byte_to_line(pt.len() / 2);
+            }),
+            ("line to offset", |pt| {
+                pt.line_to_byte(pt.len() / 4);
+            }),
+        ];
+        for (name, op) in ops {
+            // A few visits per piece added, so a scan repeated for each piece fails this
+            let (small, large) = (visited(SMALL, op), visited(LARGE, op));
+            assert!(small > 0, "{name} isn't counted");
+            assert!(
+                large - small <= 4 * (LARGE - SMALL),
+                "{name} visited {small} then {large} pieces"
+            );
+        }
+    }
+}
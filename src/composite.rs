@@ -0,0 +1,319 @@
+//! Documents stitched together from several tables, without copying their text.
+//!
+//! Editors show injected content inside a document, e.g. inlay hints or an embedded snippet
+//! from another file. A [`CompositeDocument`] lays out ranges of several tables one after the
+//! other as a single sequence, and routes each edit to the table which owns the text at that
+//! offset.
+
+use std::fmt::{self, Display};
+use std::ops::Range;
+
+use crate::PieceTable;
+
+/// A table of a [`CompositeDocument`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TableId(usize);
+
+/// A range of one of the tables, shown in the document.
+#[derive(Debug, Clone)]
+struct Part {
+    table: usize,
+    range: Range<usize>,
+}
+
+/// A sequence of ranges of several tables, which reads and edits like a single document.
+///
+/// A table may be shown in several parts, e.g. the text before and after an inlay hint. Edits
+/// go to the table owning the text, and the ranges of all parts of that table follow them:
+/// text inserted in a part makes it longer, and text deleted from it makes it shorter.
+///
+/// # Examples
+///
+/// ```
+/// # use piece_table::PieceTable;
+/// use piece_table::composite::CompositeDocument;
+///
+/// let mut doc = CompositeDocument::new();
+/// let code = doc.add_table(PieceTable::new("greet(\"Bob\");"));
+/// let hint = doc.add_table(PieceTable::new("name: "));
+/// doc.push_part(code, 0..6);
+/// doc.push_part(hint, 0..6);
+/// doc.push_part(code, 6..13);
+/// assert_eq!(doc.to_string(), "greet(name: \"Bob\");");
+///
+/// doc.insert(16, "by");
+/// assert_eq!(doc.to_string(), "greet(name: \"Bobby\");");
+/// assert_eq!(doc.table(code).to_string(), "greet(\"Bobby\");");
+/// ```
+#[derive(Debug, Default)]
+pub struct CompositeDocument<'ptable> {
+    tables: Vec<PieceTable<'ptable>>,
+    parts: Vec<Part>,
+}
+
+impl<'ptable> CompositeDocument<'ptable> {
+    /// Creates a document without any tables.
+    pub fn new() -> Self {
+        CompositeDocument {
+            tables: Vec::new(),
+            parts: Vec::new(),
+        }
+    }
+
+    /// Adds a table to the document, without showing any of it yet.
+    pub fn add_table(&mut self, table: PieceTable<'ptable>) -> TableId {
+        self.tables.push(table);
+        TableId(self.tables.len() - 1)
+    }
+
+    /// Adds a table to the document, showing all of it after the current end.
+    pub fn push_table(&mut self, table: PieceTable<'ptable>) -> TableId {
+        let len = table.len();
+        let id = self.add_table(table);
+        self.push_part(id, 0..len);
+        id
+    }
+
+    /// Shows `range` of `table` after the current end of the document.
+    ///
+    /// The parts of a table shouldn't overlap, since edits to the text they share would then
+    /// have to be routed to both.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds of the table, or doesn't start and end on char
+    /// boundaries.
+    pub fn push_part(&mut self, table: TableId, range: Range<usize>) {
+        if let Err(err) = self.tables[table.0].check_range(&range) {
+            panic!("invalid push_part: {err}");
+        }
+        self.parts.push(Part {
+            table: table.0,
+            range,
+        });
+    }
+
+    /// Returns a table of the document.
+    pub fn table(&self, table: TableId) -> &PieceTable<'ptable> {
+        &self.tables[table.0]
+    }
+
+    /// Returns the length of the document, in bytes.
+    pub fn len(&self) -> usize {
+        self.parts.iter().map(|part| part.range.len()).sum()
+    }
+
+    /// Checks if the document is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the parts of the document, in order, with the range of its table each one shows
+    /// and the range of the document it covers.
+    pub fn parts(&self) -> impl Iterator<Item = (TableId, Range<usize>, Range<usize>)> + '_ {
+        let mut start = 0;
+        self.parts.iter().map(move |part| {
+            let range = start..start + part.range.len();
+            start = range.end;
+            (TableId(part.table), part.range.clone(), range)
+        })
+    }
+
+    /// Returns the table owning the text at `offset`, and the offset in that table.
+    ///
+    /// An offset between two parts belongs to the part before it, so typing at the end of a
+    /// part extends it. Returns `None` if the document has no parts, or if `offset` is out of
+    /// bounds.
+    pub fn locate(&self, offset: usize) -> Option<(TableId, usize)> {
+        let (idx, start) = self.find_part(offset)?;
+        let part = &self.parts[idx];
+        Some((TableId(part.table), part.range.start + offset - start))
+    }
+
+    /// Returns an iterator over the text of the document, in chunks borrowed from the tables.
+    pub fn chunks(&self) -> impl Iterator<Item = &str> {
+        self.parts.iter().flat_map(move |part| {
+            let range = part.range.clone();
+            self.tables[part.table]
+                .pieces()
+                .filter(move |(_, _, piece, _)| piece.end > range.start && piece.start < range.end)
+                .map(move |(_, text, piece, _)| {
+                    let start = range.start.max(piece.start) - piece.start;
+                    let end = range.end.min(piece.end) - piece.start;
+                    &text[start..end]
+                })
+        })
+    }
+
+    /// Inserts `data` at `offset`, into the table owning the text there, see
+    /// [`CompositeDocument::locate`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the document has no parts, if `offset` is out of bounds, and in the same cases
+    /// as [`PieceTable::insert`].
+    pub fn insert(&mut self, offset: usize, data: &str) {
+        let Some((idx, start)) = self.find_part(offset) else {
+            panic!(
+                "invalid insert: offset {offset} is out of bounds of document of length {}",
+                self.len()
+            );
+        };
+        let table = self.parts[idx].table;
+        let table_offset = self.parts[idx].range.start + offset - start;
+        self.tables[table].insert(data, table_offset);
+
+        for (other, part) in self.parts.iter_mut().enumerate() {
+            if part.table != table {
+                continue;
+            }
+            if other != idx && part.range.start >= table_offset {
+                part.range.start += data.len();
+                part.range.end += data.len();
+            } else if other == idx || part.range.end > table_offset {
+                part.range.end += data.len();
+            }
+        }
+    }
+
+    /// Deletes `range` of the document, from each table owning text in it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds, and in the same cases as [`PieceTable::delete`].
+    pub fn delete(&mut self, range: Range<usize>) {
+        assert!(
+            range.start <= range.end && range.end <= self.len(),
+            "invalid delete: {range:?} is not a range of document of length {}",
+            self.len()
+        );
+
+        for (table, delete) in self.deletes(range) {
+            self.tables[table].delete(delete.clone());
+            for part in self.parts.iter_mut().filter(|part| part.table == table) {
+                part.range.start = shift_for_delete(part.range.start, &delete);
+                part.range.end = shift_for_delete(part.range.end, &delete);
+            }
+        }
+    }
+
+    /// Returns the deletes from each table which delete `range` of the document, from the last
+    /// to the first, so that each one leaves the ranges of the ones after it valid.
+    fn deletes(&self, range: Range<usize>) -> Vec<(usize, Range<usize>)> {
+        let mut deletes = Vec::new();
+        for (part, (_, table_range, doc_range)) in self.parts.iter().zip(self.parts()) {
+            let start = range.start.max(doc_range.start);
+            let end = range.end.min(doc_range.end);
+            if start < end {
+                let to_table = |offset: usize| table_range.start + offset - doc_range.start;
+                deletes.push((part.table, to_table(start)..to_table(end)));
+            }
+        }
+        deletes.sort_by_key(|(table, delete)| (*table, std::cmp::Reverse(delete.start)));
+        deletes
+    }
+
+    /// Returns the index of the part owning `offset`, and where it starts in the document.
+    fn find_part(&self, offset: usize) -> Option<(usize, usize)> {
+        let mut start = 0;
+        for (idx, part) in self.parts.iter().enumerate() {
+            let end = start + part.range.len();
+            if offset <= end {
+                return Some((idx, start));
+            }
+            start = end;
+        }
+        None
+    }
+}
+
+/// Returns where `offset` ends up after `range` was deleted.
+fn shift_for_delete(offset: usize, range: &Range<usize>) -> usize {
+    if offset >= range.end {
+        offset - range.len()
+    } else {
+        offset.min(range.start)
+    }
+}
+
+impl Display for CompositeDocument<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for chunk in self.chunks() {
+            f.write_str(chunk)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inlay_hint() -> (CompositeDocument<'static>, TableId, TableId) {
+        let mut doc = CompositeDocument::new();
+        let code = doc.add_table(PieceTable::new("f(a, b)"));
+        let hint = doc.add_table(PieceTable::new("x: "));
+        doc.push_part(code, 0..2);
+        doc.push_part(hint, 0..3);
+        doc.push_part(code, 2..7);
+        (doc, code, hint)
+    }
+
+    #[test]
+    fn typing_at_part_boundaries() {
+        let (mut doc, code, hint) = inlay_hint();
+        assert_eq!("f(x: a, b)", doc.to_string());
+
+        // The end of the first part, and the end of the hint
+        doc.insert(2, "0");
+        doc.insert(6, "1");
+        assert_eq!("f(0x: 1a, b)", doc.to_string());
+        assert_eq!("f(0a, b)", doc.table(code).to_string());
+        assert_eq!("x: 1", doc.table(hint).to_string());
+        assert_eq!(Some((hint, 4)), doc.locate(7));
+        assert_eq!(Some((code, 4)), doc.locate(8));
+        assert_eq!(
+            vec![(code, 0..3), (hint, 0..4), (code, 3..8)],
+            doc.parts()
+                .map(|(table, range, _)| (table, range))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn delete_across_parts() {
+        let (mut doc, code, hint) = inlay_hint();
+        doc.delete(1..7);
+
+        assert_eq!("f b)", doc.to_string());
+        assert_eq!("f b)", doc.table(code).to_string());
+        assert_eq!("", doc.table(hint).to_string());
+        assert_eq!(4, doc.len());
+
+        doc.insert(1, "(");
+        assert_eq!("f( b)", doc.to_string());
+    }
+
+    #[test]
+    fn chunks_are_borrowed_from_the_tables() {
+        let mut doc = CompositeDocument::new();
+        let mut table = PieceTable::new("abc");
+        table.insert("XY", 1);
+        doc.push_table(table);
+        doc.push_table(PieceTable::new("def"));
+
+        let chunks: Vec<&str> = doc.chunks().collect();
+        assert_eq!(vec!["a", "XY", "bc", "def"], chunks);
+        assert_eq!(None, doc.locate(9));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "invalid push_part: offset 7 is out of bounds of document of length 5"
+    )]
+    fn part_out_of_bounds() {
+        let mut doc = CompositeDocument::new();
+        let table = doc.add_table(PieceTable::new("hello"));
+        doc.push_part(table, 2..7);
+    }
+}
@@ -0,0 +1,315 @@
+//! Converting between byte, char and line indices, and snapping byte offsets to chars.
+//!
+//! Every piece caches how many chars and newlines it holds, so whole pieces are skipped without
+//! looking at their text, and only the piece containing the index is scanned.
+
+use crate::newlines::count_chars;
+use crate::range_set::Bias;
+use crate::{NodeKind, PieceTable};
+
+impl PieceTable<'_> {
+    /// Returns the index of the char containing byte `byte_idx`.
+    ///
+    /// A byte index in the middle of a char returns the index of that char, and `self.len()`
+    /// returns [`PieceTable::len_chars`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("héllo");
+    /// pt.insert("wörld ", 0);
+    /// assert_eq!(pt.byte_to_char(8), 7);
+    /// // Inside 'é'
+    /// assert_eq!(pt.byte_to_char(9), 7);
+    /// assert_eq!(pt.byte_to_char(10), 8);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `byte_idx` is out of bounds.
+    pub fn byte_to_char(&self, byte_idx: usize) -> usize {
+        self.check_index(byte_idx, self.len, "byte_to_char", "byte index");
+
+        let mut chars = 0;
+        let mut node_start = 0;
+        for node in &self.nodes {
+            let node_end = node_start + node.range.len();
+            if node_end > byte_idx {
+                if let NodeKind::Repeat(_) = node.kind {
+                    // Repeat pieces are ASCII, one char per byte
+                    return chars + byte_idx - node_start;
+                }
+                let text = node.text(self.original, &self.added);
+                let end = text.floor_char_boundary(byte_idx - node_start);
+                return chars + count_chars(&text[..end]);
+            }
+            chars += node.chars;
+            node_start = node_end;
+        }
+        chars
+    }
+
+    /// Returns the byte index where char `char_idx` starts.
+    ///
+    /// [`PieceTable::len_chars`] returns `self.len()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let pt = PieceTable::new("héllo");
+    /// assert_eq!(pt.char_to_byte(2), 3);
+    /// assert_eq!(pt.char_to_byte(5), 6);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_idx` is out of bounds.
+    pub fn char_to_byte(&self, char_idx: usize) -> usize {
+        self.check_index(char_idx, self.chars, "char_to_byte", "char index");
+
+        let mut remaining = char_idx;
+        let mut node_start = 0;
+        for node in &self.nodes {
+            if node.chars > remaining {
+                if let NodeKind::Repeat(_) = node.kind {
+                    return node_start + remaining;
+                }
+                let text = node.text(self.original, &self.added);
+                let (idx, _) = text
+                    .char_indices()
+                    .nth(remaining)
+                    .expect("piece has as many chars as it caches");
+                return node_start + idx;
+            }
+            remaining -= node.chars;
+            node_start += node.range.len();
+        }
+        self.len
+    }
+
+    /// Returns the (0-based) line containing byte `byte_idx`.
+    ///
+    /// A newline belongs to the line it ends, so `self.len()` returns the last line.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let pt = PieceTable::new("one\ntwo\n");
+    /// assert_eq!(pt.byte_to_line(3), 0);
+    /// assert_eq!(pt.byte_to_line(4), 1);
+    /// assert_eq!(pt.byte_to_line(8), 2);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `byte_idx` is out of bounds.
+    pub fn byte_to_line(&self, byte_idx: usize) -> usize {
+        self.check_index(byte_idx, self.len, "byte_to_line", "byte index");
+        self.line_of(byte_idx)
+    }
+
+    /// Returns the byte index where line `line_idx` starts.
+    ///
+    /// [`PieceTable::len_lines`] returns `self.len()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let pt = PieceTable::new("one\ntwo");
+    /// assert_eq!(pt.line_to_byte(1), 4);
+    /// assert_eq!(pt.line_to_byte(2), 7);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `line_idx` is out of bounds.
+    pub fn line_to_byte(&self, line_idx: usize) -> usize {
+        self.check_index(line_idx, self.len_lines(), "line_to_byte", "line index");
+        self.line_start(line_idx).unwrap_or(self.len)
+    }
+
+    /// Returns the (0-based) line containing char `char_idx`, see [`PieceTable::byte_to_line`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_idx` is out of bounds.
+    pub fn char_to_line(&self, char_idx: usize) -> usize {
+        self.line_of(self.char_to_byte(char_idx))
+    }
+
+    /// Returns the index of the char where line `line_idx` starts, see
+    /// [`PieceTable::line_to_byte`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `line_idx` is out of bounds.
+    pub fn line_to_char(&self, line_idx: usize) -> usize {
+        self.byte_to_char(self.line_to_byte(line_idx))
+    }
+
+    /// Checks if `offset` is on a char boundary, i.e. it's the start or end of the document or
+    /// the start of a char, like [`str::is_char_boundary`]. Offsets past the end of the
+    /// document aren't.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let pt = PieceTable::new("añb");
+    /// assert!(pt.is_char_boundary(1));
+    /// assert!(!pt.is_char_boundary(2));
+    /// assert!(pt.is_char_boundary(4));
+    /// assert!(!pt.is_char_boundary(5));
+    /// ```
+    pub fn is_char_boundary(&self, offset: usize) -> bool {
+        // Every piece starts on a char boundary, so it's enough to check that the byte at
+        // `offset` isn't a UTF-8 continuation byte
+        match self.byte(offset) {
+            Some(byte) => (byte as i8) >= -0x40,
+            None => offset == self.len,
+        }
+    }
+
+    /// Returns the char boundary nearest to `offset`, which is `offset` itself if it's on one.
+    ///
+    /// An offset inside a char moves to its start with [`Bias::Left`], and to its end with
+    /// [`Bias::Right`]. An offset past the end of the document moves to the end, so any offset,
+    /// e.g. from a click or an offset kept across edits, becomes a valid one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// use piece_table::range_set::Bias;
+    ///
+    /// let pt = PieceTable::new("a日b");
+    /// assert_eq!(pt.clamp_to_char_boundary(2, Bias::Left), 1);
+    /// assert_eq!(pt.clamp_to_char_boundary(2, Bias::Right), 4);
+    /// assert_eq!(pt.clamp_to_char_boundary(4, Bias::Left), 4);
+    /// assert_eq!(pt.clamp_to_char_boundary(99, Bias::Right), 5);
+    /// ```
+    pub fn clamp_to_char_boundary(&self, offset: usize, bias: Bias) -> usize {
+        let mut offset = offset.min(self.len);
+        // A char is at most 4 bytes long, so this takes at most 3 steps
+        while !self.is_char_boundary(offset) {
+            match bias {
+                Bias::Left => offset -= 1,
+                Bias::Right => offset += 1,
+            }
+        }
+        offset
+    }
+
+    fn check_index(&self, idx: usize, len: usize, method: &str, what: &str) {
+        assert!(
+            idx <= len,
+            "invalid {method}: {what} {idx} is out of bounds of {len}"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ropey::Rope;
+
+    use crate::workload::{Profile, generate};
+
+    use super::*;
+
+    #[test]
+    fn conversions_match_ropey() {
+        let initial = "fn main() {\n    println!(\"héllo wörld\");\n}\n// こんにちは 🦀\n";
+        for (seed, profile) in [Profile::Typing, Profile::Deleting, Profile::PasteHeavy]
+            .into_iter()
+            .enumerate()
+        {
+            let mut piece_table = PieceTable::new(initial);
+            for edit in &generate(seed as u64, profile, initial, 100).edits {
+                edit.apply(&mut piece_table);
+            }
+            piece_table.insert_char(0, 'é');
+            let rope = Rope::from_str(&piece_table.to_string());
+
+            assert_eq!(Ok(()), piece_table.check_invariants());
+            assert_eq!(rope.len_chars(), piece_table.len_chars());
+            for byte_idx in 0..=piece_table.len() {
+                assert_eq!(
+                    rope.byte_to_char(byte_idx),
+                    piece_table.byte_to_char(byte_idx)
+                );
+                assert_eq!(
+                    rope.byte_to_line(byte_idx),
+                    piece_table.byte_to_line(byte_idx)
+                );
+            }
+            for char_idx in 0..=piece_table.len_chars() {
+                assert_eq!(
+                    rope.char_to_byte(char_idx),
+                    piece_table.char_to_byte(char_idx)
+                );
+                assert_eq!(
+                    rope.char_to_line(char_idx),
+                    piece_table.char_to_line(char_idx)
+                );
+            }
+            for line_idx in 0..=piece_table.len_lines() {
+                assert_eq!(
+                    rope.line_to_byte(line_idx),
+                    piece_table.line_to_byte(line_idx)
+                );
+                assert_eq!(
+                    rope.line_to_char(line_idx),
+                    piece_table.line_to_char(line_idx)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn char_counts_survive_splits_and_checkpoints() {
+        let mut piece_table = PieceTable::new("ααα\nβββ");
+        piece_table.checkpoint("start");
+        let rest = piece_table.split_off(4);
+        piece_table.insert("γ", 2);
+        assert_eq!(3, piece_table.len_chars());
+        assert_eq!(5, rest.len_chars());
+
+        piece_table.append_table(rest);
+        assert_eq!(8, piece_table.len_chars());
+        assert!(piece_table.revert_to("start"));
+        assert_eq!(7, piece_table.len_chars());
+        assert_eq!(Ok(()), piece_table.check_invariants());
+    }
+
+    #[test]
+    fn clamping_across_pieces() {
+        let mut piece_table = PieceTable::new("😀");
+        piece_table.insert("é", 4);
+        piece_table.insert_char(0, 'ü');
+        let text = piece_table.to_string();
+
+        for offset in 0..=text.len() + 1 {
+            assert_eq!(
+                text.is_char_boundary(offset),
+                piece_table.is_char_boundary(offset)
+            );
+            let left = piece_table.clamp_to_char_boundary(offset, Bias::Left);
+            let right = piece_table.clamp_to_char_boundary(offset, Bias::Right);
+            assert!(text.is_char_boundary(left) && text.is_char_boundary(right));
+            assert!(left <= offset.min(text.len()) && offset.min(text.len()) <= right);
+            assert!(right - left <= 4);
+        }
+        assert_eq!(2, piece_table.clamp_to_char_boundary(5, Bias::Left));
+        assert_eq!(6, piece_table.clamp_to_char_boundary(5, Bias::Right));
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn char_to_byte_out_of_bounds() {
+        PieceTable::new("é").char_to_byte(2);
+    }
+}
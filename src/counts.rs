@@ -0,0 +1,118 @@
+//! Byte, char, word and line counts of a whole document, e.g. for status bars.
+//!
+//! Every piece caches its own counts, which are kept up to date on every edit, so the counts of
+//! the document are summed up from the pieces without reading their text.
+
+use crate::PieceTable;
+use crate::newlines::joins_words;
+
+/// The counts of a document, as returned by [`PieceTable::counts`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextCounts {
+    /// The length in bytes, see [`PieceTable::len`].
+    pub bytes: usize,
+    /// The number of chars, see [`PieceTable::len_chars`].
+    pub chars: usize,
+    /// The number of words, i.e. of runs of chars which aren't whitespace, as counted by
+    /// [`str::split_whitespace`].
+    pub words: usize,
+    /// The number of lines, see [`PieceTable::len_lines`].
+    pub lines: usize,
+}
+
+impl PieceTable<'_> {
+    /// Returns the byte, char, word and line counts of the document.
+    ///
+    /// The byte, char and line counts are kept for the whole document, and words are counted
+    /// per piece, so this takes time proportional to the number of pieces rather than the
+    /// length of the text. Only the chars on either side of a piece boundary are looked at, to
+    /// find words which continue from one piece to the next.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// use piece_table::counts::TextCounts;
+    ///
+    /// let mut pt = PieceTable::new("hello world\n");
+    /// pt.insert("ish wörld", 5);
+    ///
+    /// assert_eq!(
+    ///     pt.counts(),
+    ///     TextCounts {
+    ///         bytes: 22,
+    ///         chars: 21,
+    ///         words: 3,
+    ///         lines: 2,
+    ///     }
+    /// );
+    /// ```
+    pub fn counts(&self) -> TextCounts {
+        let mut words = 0;
+        let mut prev = "";
+        for node in &self.nodes {
+            let mut chunks = node.chunks(self.original, &self.added);
+            let Some(first) = chunks.next().filter(|first| !first.is_empty()) else {
+                continue;
+            };
+            words += node.words - joins_words(prev, first) as usize;
+            prev = chunks.next_back().unwrap_or(first);
+        }
+
+        TextCounts {
+            bytes: self.len,
+            chars: self.chars,
+            words,
+            lines: self.len_lines(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::workload::{Profile, generate};
+
+    use super::*;
+
+    fn expected(text: &str) -> TextCounts {
+        TextCounts {
+            bytes: text.len(),
+            chars: text.chars().count(),
+            words: text.split_whitespace().count(),
+            lines: text.matches('\n').count() + 1,
+        }
+    }
+
+    #[test]
+    fn counts_match_text_after_edits() {
+        let initial = "fn main() {\n    println!(\"héllo wörld\");\n}\n// こんにちは　🦀\n";
+        for (seed, profile) in [Profile::Typing, Profile::Deleting, Profile::PasteHeavy]
+            .into_iter()
+            .enumerate()
+        {
+            let mut piece_table = PieceTable::new(initial);
+            for edit in &generate(seed as u64, profile, initial, 200).edits {
+                edit.apply(&mut piece_table);
+                assert_eq!(expected(&piece_table.to_string()), piece_table.counts());
+            }
+            assert_eq!(Ok(()), piece_table.check_invariants());
+        }
+    }
+
+    #[test]
+    fn words_split_and_joined_by_edits() {
+        let mut piece_table = PieceTable::new("one two");
+        piece_table.insert_char(2, ' ');
+        assert_eq!(3, piece_table.counts().words);
+
+        piece_table.delete(2..3);
+        piece_table.delete(3..4);
+        assert_eq!(1, piece_table.counts().words);
+        assert_eq!("onetwo", piece_table.to_string());
+
+        piece_table.insert("\t", 6);
+        piece_table.insert("x", 7);
+        assert_eq!(2, piece_table.counts().words);
+        assert_eq!(Ok(()), piece_table.check_invariants());
+    }
+}
@@ -0,0 +1,765 @@
+//! Zero-copy access to the pieces of a `PieceTable` by offset.
+//!
+//! A [`ChunkCursor`] borrows the table, so it can't outlive an edit. To keep a position across
+//! edits, e.g. between frames, a cursor's [`CursorPosition`] can be saved instead, which records
+//! the table's generation. Resuming it after the table was edited fails, rather than reading
+//! whichever piece now happens to be at the saved index.
+
+use std::borrow::Cow;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{INLINE_CAPACITY, IterError, Node, NodeKind, PieceTable};
+
+/// The last generation handed out, shared by all tables so that no two of them ever start the
+/// same generation.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Returns a generation which no table has had before.
+pub(crate) fn next_generation() -> u64 {
+    GENERATION.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// Which buffer the text of a piece is stored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PieceSource {
+    /// The text the table was created with.
+    Original,
+    /// Text inserted since.
+    Added,
+}
+
+/// Identifies the stored text a piece refers to, e.g. to key a cache of shaped text or syntax
+/// tokens by piece, see [`PieceTable::pieces`].
+///
+/// Text is never changed once stored, so a piece keeps its ID for as long as it isn't changed,
+/// however the pieces around it are edited, and two pieces with the same ID have the same text.
+/// A piece which is split, trimmed or extended gets a new ID, and a piece split off another one
+/// has an ID inside that one's, see [`PieceId::offset_in`], so caches can reuse what they know
+/// about the piece it came from. Pieces storing their text inline, i.e. single chars typed
+/// between other pieces, are identified by their text alone, and are only inside themselves.
+/// Runs of a repeated char are identified by the char and their length, and are inside every
+/// run of the same char at least as long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PieceId {
+    buffer: IdBuffer,
+    start: usize,
+    end: usize,
+}
+
+/// Where the text identified by a [`PieceId`] is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum IdBuffer {
+    Original,
+    Added,
+    /// The text itself, padded with zeros
+    Inline([u8; INLINE_CAPACITY]),
+    /// The char which is repeated, see [`NodeKind::Repeat`]
+    Repeat(u8),
+}
+
+impl PieceId {
+    /// Returns the ID of the text of `node`.
+    fn of(node: &Node) -> PieceId {
+        let buffer = match node.kind {
+            NodeKind::Original => IdBuffer::Original,
+            NodeKind::Added => IdBuffer::Added,
+            NodeKind::Repeat(byte) => IdBuffer::Repeat(byte),
+            NodeKind::Inline(bytes) => {
+                let mut text = [0; INLINE_CAPACITY];
+                text[..node.range.len()].copy_from_slice(&bytes[node.range.clone()]);
+                return PieceId {
+                    buffer: IdBuffer::Inline(text),
+                    start: 0,
+                    end: node.range.len(),
+                };
+            }
+        };
+        PieceId {
+            buffer,
+            start: node.range.start,
+            end: node.range.end,
+        }
+    }
+
+    /// Returns the length of the identified text, in bytes.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Checks if the identified text is empty.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Returns where the text identified by `self` starts in the text identified by `parent`,
+    /// if it's part of it, e.g. because it was split off a piece with that ID.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello world");
+    /// let (.., whole) = pt.pieces().next().unwrap();
+    ///
+    /// pt.insert("!", 5);
+    /// let ids: Vec<_> = pt.pieces().map(|(.., id)| id).collect();
+    /// assert_eq!(ids[0].offset_in(whole), Some(0));
+    /// assert_eq!(ids[1].offset_in(whole), None);
+    /// assert_eq!(ids[2].offset_in(whole), Some(5));
+    /// ```
+    pub fn offset_in(&self, parent: PieceId) -> Option<usize> {
+        let inside = match (self.buffer, parent.buffer) {
+            (IdBuffer::Inline(_), _) | (_, IdBuffer::Inline(_)) => *self == parent,
+            (IdBuffer::Repeat(byte), IdBuffer::Repeat(parent_byte)) => {
+                byte == parent_byte && self.len() <= parent.len()
+            }
+            (IdBuffer::Repeat(_), _) | (_, IdBuffer::Repeat(_)) => false,
+            (buffer, parent_buffer) => {
+                buffer == parent_buffer && parent.start <= self.start && self.end <= parent.end
+            }
+        };
+        inside.then(|| self.start - parent.start)
+    }
+}
+
+impl From<NodeKind> for PieceSource {
+    fn from(kind: NodeKind) -> Self {
+        match kind {
+            NodeKind::Original => PieceSource::Original,
+            NodeKind::Added | NodeKind::Inline(_) | NodeKind::Repeat(_) => PieceSource::Added,
+        }
+    }
+}
+
+impl<'ptable> PieceTable<'ptable> {
+    /// Returns an iterator over the pieces, in document order, with the buffer each one comes
+    /// from, the range of the document it covers and its [`PieceId`].
+    ///
+    /// The text of a run of a repeated char isn't stored anywhere, so it's returned in chunks
+    /// of at most 128 bytes, each of which counts as a piece.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// # use piece_table::cursor::PieceSource;
+    /// let mut pt = PieceTable::new("ac");
+    /// pt.insert("b", 1);
+    ///
+    /// let pieces: Vec<_> = pt
+    ///     .pieces()
+    ///     .map(|(source, text, range, _)| (source, text, range))
+    ///     .collect();
+    /// assert_eq!(
+    ///     pieces,
+    ///     vec![
+    ///         (PieceSource::Original, "a", 0..1),
+    ///         (PieceSource::Added, "b", 1..2),
+    ///         (PieceSource::Original, "c", 2..3),
+    ///     ]
+    /// );
+    /// ```
+    pub fn pieces(&self) -> impl Iterator<Item = (PieceSource, &str, Range<usize>, PieceId)> {
+        let mut start = 0;
+        self.nodes
+            .iter()
+            .flat_map(|node| {
+                let source = match node.kind {
+                    NodeKind::Added if self.stream.is_loaded(node.range.start) => {
+                        PieceSource::Original
+                    }
+                    kind => kind.into(),
+                };
+                let id = PieceId::of(node);
+                node.chunks(self.original, &self.added).map(move |text| {
+                    // Only the chunks of a repeat piece are shorter than the piece, and they're
+                    // identified by their own length
+                    let id = PieceId {
+                        end: id.start + text.len(),
+                        ..id
+                    };
+                    (source, text, id)
+                })
+            })
+            .map(move |(source, text, id)| {
+                let range = start..start + text.len();
+                start = range.end;
+                (source, text, range, id)
+            })
+    }
+
+    /// Returns the ranges of the document whose text was inserted since the table was created,
+    /// i.e. which doesn't come from the original buffer, in order and with adjacent ranges
+    /// merged.
+    ///
+    /// Text which was deleted and inserted again counts as changed, even if it's the same as
+    /// before.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello world");
+    /// pt.insert("!", 11);
+    /// pt.insert("?", 11);
+    /// pt.delete(0..1);
+    /// pt.insert("H", 0);
+    /// assert_eq!(pt.changed_ranges(), vec![0..1, 11..13]);
+    /// ```
+    pub fn changed_ranges(&self) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for (source, _, range, _) in self.pieces() {
+            if source == PieceSource::Original {
+                continue;
+            }
+            match ranges.last_mut() {
+                Some(last) if last.end == range.start => last.end = range.end,
+                _ => ranges.push(range),
+            }
+        }
+        ranges
+    }
+
+    /// Returns the text of the piece containing the byte at `offset`, along with the range of
+    /// the document it covers.
+    ///
+    /// An `offset` equal to the length of the document refers to the last piece. Returns `None`
+    /// if `offset` is past the end of the document, or if the document is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello");
+    /// pt.insert(" world", 5);
+    /// assert_eq!(pt.chunk_at(3), Some(("hello", 0..5)));
+    /// assert_eq!(pt.chunk_at(5), Some((" world", 5..11)));
+    /// assert_eq!(pt.chunk_at(11), Some((" world", 5..11)));
+    /// assert_eq!(pt.chunk_at(12), None);
+    /// ```
+    pub fn chunk_at(&self, offset: usize) -> Option<(&str, Range<usize>)> {
+        self.chunk_cursor(offset).chunk()
+    }
+
+    /// Returns the text in `range`, borrowed from the table if it lies within a single piece,
+    /// and copied otherwise.
+    ///
+    /// Short reads, e.g. of the lines in a viewport, usually hit a single piece, so they don't
+    /// allocate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// use std::borrow::Cow;
+    ///
+    /// let mut pt = PieceTable::new("hello world");
+    /// pt.insert(",", 5);
+    /// assert!(matches!(pt.get_range(0..4), Cow::Borrowed("hell")));
+    /// assert!(matches!(pt.get_range(4..8), Cow::Owned(text) if text == "o, w"));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds, or doesn't start and end on char boundaries.
+    pub fn get_range(&self, range: Range<usize>) -> Cow<'_, str> {
+        if let Err(err) = self.check_range(&range) {
+            panic!("invalid get_range: {err}");
+        }
+        let mut cursor = self.chunk_cursor(range.start);
+        let Some((text, piece)) = cursor.chunk().filter(|_| !range.is_empty()) else {
+            return Cow::Borrowed("");
+        };
+        if piece.end >= range.end {
+            return Cow::Borrowed(&text[range.start - piece.start..range.end - piece.start]);
+        }
+
+        let mut owned = String::with_capacity(range.len());
+        owned.push_str(&text[range.start - piece.start..]);
+        while owned.len() < range.len() && cursor.move_next() {
+            let (text, _) = cursor.chunk().expect("the cursor is on a piece");
+            owned.push_str(&text[..text.len().min(range.len() - owned.len())]);
+        }
+        Cow::Owned(owned)
+    }
+
+    /// Returns the bytes in `range` as the contiguous runs they're stored in, in order, with
+    /// the range of the document each run covers.
+    ///
+    /// This is for consumers which only need bytes, e.g. uploading text to a GPU or hashing
+    /// it, so `range` may start and end inside a char, and a run may start or end inside one
+    /// too. Nothing is copied.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello world");
+    /// pt.insert(",", 5);
+    /// let runs: Vec<_> = pt.byte_runs(3..8).collect();
+    /// assert_eq!(
+    ///     runs,
+    ///     vec![(&b"lo"[..], 3..5), (&b","[..], 5..6), (&b" w"[..], 6..8)]
+    /// );
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds, or its start is after its end.
+    pub fn byte_runs(&self, range: Range<usize>) -> impl Iterator<Item = (&[u8], Range<usize>)> {
+        assert!(
+            range.start <= range.end && range.end <= self.len,
+            "invalid byte_runs: {range:?} is not a range of document of length {}",
+            self.len
+        );
+        let mut cursor = self.chunk_cursor(range.start);
+        let mut done = range.is_empty();
+        std::iter::from_fn(move || {
+            if done {
+                return None;
+            }
+            let (text, piece) = cursor.chunk()?;
+            let start = range.start.max(piece.start);
+            let end = range.end.min(piece.end);
+            done = piece.end >= range.end || !cursor.move_next();
+            Some((
+                &text.as_bytes()[start - piece.start..end - piece.start],
+                start..end,
+            ))
+        })
+        .filter(|(run, _)| !run.is_empty())
+    }
+
+    /// Returns a cursor over the pieces, positioned at the piece containing the byte at
+    /// `offset`, see [`PieceTable::chunk_at`].
+    pub fn chunk_cursor(&self, offset: usize) -> ChunkCursor<'_, 'ptable> {
+        let (idx, node_start) = match self.find_node(offset) {
+            Some(found) => found,
+            None if offset == self.len && !self.nodes.is_empty() => {
+                let idx = self.nodes.len() - 1;
+                (idx, self.len - self.nodes[idx].range.len())
+            }
+            None => (self.nodes.len(), self.len),
+        };
+        let within = match self.nodes.get(idx) {
+            Some(node) => {
+                node.chunk_at(offset - node_start, self.original, &self.added)
+                    .1
+            }
+            None => 0,
+        };
+        ChunkCursor {
+            table: self,
+            idx,
+            start: node_start + within,
+            within,
+        }
+    }
+
+    /// Returns the table's generation, which changes on every edit.
+    ///
+    /// Generations are unique across all tables, so two tables only have the same generation if
+    /// one is a clone of the other and neither was edited since. Anything computed from the
+    /// table, such as offsets or piece indices, is still valid as long as the generation stays
+    /// the same.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("abc");
+    /// let before = pt.generation();
+    /// assert_eq!(pt.clone().generation(), before);
+    ///
+    /// pt.insert("d", 3);
+    /// assert_ne!(pt.generation(), before);
+    /// ```
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Returns a cursor at `position`, which was saved from a cursor over this table.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::{IterError, PieceTable};
+    /// let mut pt = PieceTable::new("ac");
+    /// pt.insert("b", 1);
+    ///
+    /// let mut cursor = pt.chunk_cursor(0);
+    /// cursor.move_next();
+    /// let position = cursor.position();
+    ///
+    /// let cursor = pt.resume_cursor(position).unwrap();
+    /// assert_eq!(cursor.chunk(), Some(("b", 1..2)));
+    ///
+    /// pt.delete(0..1);
+    /// assert!(matches!(
+    ///     pt.resume_cursor(position),
+    ///     Err(IterError::Invalidated { .. })
+    /// ));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IterError::Invalidated`] if this table's generation isn't the one `position`
+    /// was saved at, i.e. if it was edited since, or if `position` is from another table.
+    pub fn resume_cursor(
+        &self,
+        position: CursorPosition,
+    ) -> Result<ChunkCursor<'_, 'ptable>, IterError> {
+        if position.generation != self.generation {
+            return Err(IterError::Invalidated {
+                saved: position.generation,
+                current: self.generation,
+            });
+        }
+        Ok(ChunkCursor {
+            table: self,
+            idx: position.idx,
+            start: position.start,
+            within: position.within,
+        })
+    }
+}
+
+/// The position of a [`ChunkCursor`], which doesn't borrow the table, see
+/// [`PieceTable::resume_cursor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CursorPosition {
+    generation: u64,
+    idx: usize,
+    start: usize,
+    within: usize,
+}
+
+impl CursorPosition {
+    /// Returns the generation of the table when the position was saved.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Returns the offset where the piece the cursor was on starts.
+    pub fn offset(&self) -> usize {
+        self.start
+    }
+}
+
+/// A cursor which moves over the pieces of a `PieceTable`, created by
+/// [`PieceTable::chunk_cursor`].
+///
+/// Each piece is returned as a `&str` borrowed from the table's buffers, along with the range of
+/// the document it covers, so renderers and parsers can walk the text in either direction
+/// without copying it. Runs of a repeated char are returned in chunks, see
+/// [`PieceTable::pieces`].
+///
+/// # Examples
+///
+/// ```
+/// # use piece_table::PieceTable;
+/// let mut pt = PieceTable::new("ac");
+/// pt.insert("b", 1);
+///
+/// let mut cursor = pt.chunk_cursor(2);
+/// assert_eq!(cursor.chunk(), Some(("c", 2..3)));
+/// assert!(cursor.move_prev());
+/// assert_eq!(cursor.chunk(), Some(("b", 1..2)));
+/// assert!(cursor.move_prev());
+/// assert!(!cursor.move_prev());
+/// assert_eq!(cursor.chunk(), Some(("a", 0..1)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChunkCursor<'t, 'ptable> {
+    table: &'t PieceTable<'ptable>,
+    /// The index of the current node, or `nodes.len()` if the table is empty or the cursor was
+    /// created past its end
+    idx: usize,
+    /// The offset where the current chunk starts
+    start: usize,
+    /// Where the current chunk starts in the current node, which is only ever past 0 for a
+    /// repeat node
+    within: usize,
+}
+
+impl<'t> ChunkCursor<'t, '_> {
+    /// Returns the position of the cursor, which can be kept while the table is edited, and
+    /// resumed with [`PieceTable::resume_cursor`] if it wasn't.
+    pub fn position(&self) -> CursorPosition {
+        CursorPosition {
+            generation: self.table.generation,
+            idx: self.idx,
+            start: self.start,
+            within: self.within,
+        }
+    }
+
+    /// Returns the current piece's text and the range of the document it covers, or `None` if
+    /// the cursor isn't on a piece.
+    pub fn chunk(&self) -> Option<(&'t str, Range<usize>)> {
+        let node = self.table.nodes.get(self.idx)?;
+        let (text, _) = node.chunk_at(self.within, self.table.original, &self.table.added);
+        Some((text, self.start..self.start + text.len()))
+    }
+
+    /// Moves to the next piece, returning `false` (and staying put) if this is the last one.
+    pub fn move_next(&mut self) -> bool {
+        let Some((text, _)) = self.chunk() else {
+            return false;
+        };
+        let node = &self.table.nodes[self.idx];
+        if self.within + text.len() < node.range.len() {
+            self.within += text.len();
+        } else if self.idx + 1 < self.table.nodes.len() {
+            self.idx += 1;
+            self.within = 0;
+        } else {
+            return false;
+        }
+        self.start += text.len();
+        true
+    }
+
+    /// Moves to the previous piece, returning `false` (and staying put) if this is the first
+    /// one.
+    pub fn move_prev(&mut self) -> bool {
+        if self.within == 0 && (self.idx == 0 || self.idx >= self.table.nodes.len()) {
+            return false;
+        }
+        let (original, added) = (self.table.original, &self.table.added);
+        if self.within == 0 {
+            self.idx -= 1;
+            let node = &self.table.nodes[self.idx];
+            self.within = node.chunk_at(node.range.len(), original, added).1;
+        } else {
+            let node = &self.table.nodes[self.idx];
+            self.within = node.chunk_at(self.within - 1, original, added).1;
+        }
+        let (text, _) = self.chunk().expect("the cursor is on a piece");
+        self.start -= text.len();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_walks_all_pieces() {
+        let mut piece_table = PieceTable::new("one three");
+        piece_table.insert("two ", 4);
+        piece_table.insert("zero ", 0);
+
+        let mut cursor = piece_table.chunk_cursor(0);
+        let mut forward = vec![cursor.chunk().unwrap()];
+        while cursor.move_next() {
+            forward.push(cursor.chunk().unwrap());
+        }
+        let mut backward = vec![cursor.chunk().unwrap()];
+        while cursor.move_prev() {
+            backward.push(cursor.chunk().unwrap());
+        }
+        backward.reverse();
+
+        assert_eq!(
+            vec![
+                ("zero ", 0..5),
+                ("one ", 5..9),
+                ("two ", 9..13),
+                ("three", 13..18)
+            ],
+            forward
+        );
+        assert_eq!(forward, backward);
+    }
+
+    #[test]
+    fn chunk_at_piece_boundaries() {
+        let mut piece_table = PieceTable::new("abcd");
+        piece_table.insert("xy", 2);
+
+        assert_eq!(Some(("ab", 0..2)), piece_table.chunk_at(1));
+        assert_eq!(Some(("xy", 2..4)), piece_table.chunk_at(2));
+        assert_eq!(Some(("cd", 4..6)), piece_table.chunk_at(4));
+        assert_eq!(Some(("cd", 4..6)), piece_table.chunk_at(6));
+    }
+
+    #[test]
+    fn pieces_cover_document() {
+        let mut piece_table = PieceTable::new("hello world");
+        piece_table.insert("big ", 6);
+        piece_table.delete(0..1);
+        piece_table.insert("H", 0);
+
+        let pieces: Vec<_> = piece_table
+            .pieces()
+            .map(|(source, text, range, _)| (source, text, range))
+            .collect();
+        assert_eq!(
+            vec![
+                (PieceSource::Added, "H", 0..1),
+                (PieceSource::Original, "ello ", 1..6),
+                (PieceSource::Added, "big ", 6..10),
+                (PieceSource::Original, "world", 10..15),
+            ],
+            pieces
+        );
+        assert_eq!(
+            piece_table.to_string(),
+            pieces.iter().map(|(_, text, _)| *text).collect::<String>()
+        );
+    }
+
+    #[test]
+    fn changed_ranges_after_deletes() {
+        let mut piece_table = PieceTable::new("one two three");
+        assert_eq!(Vec::<Range<usize>>::new(), piece_table.changed_ranges());
+
+        piece_table.insert("2", 4);
+        piece_table.insert("x", 5);
+        piece_table.delete(6..9);
+        piece_table.insert("3", 12);
+
+        assert_eq!("one 2x three3", piece_table.to_string());
+        assert_eq!(vec![4..6, 12..13], piece_table.changed_ranges());
+
+        piece_table.delete(5..12);
+        assert_eq!("one 23", piece_table.to_string());
+        assert_eq!(vec![4..6], piece_table.changed_ranges());
+    }
+
+    #[test]
+    fn get_range_across_pieces() {
+        let mut piece_table = PieceTable::new("abcdef");
+        piece_table.insert("XY", 3);
+        piece_table.insert_char(8, '!');
+
+        let text = piece_table.to_string();
+        for start in 0..=text.len() {
+            for end in start..=text.len() {
+                let got = piece_table.get_range(start..end);
+                assert_eq!(&text[start..end], got);
+                let in_one_piece = piece_table
+                    .chunk_at(start)
+                    .is_none_or(|(_, piece)| end <= piece.end);
+                assert_eq!(in_one_piece, matches!(got, Cow::Borrowed(_)));
+            }
+        }
+    }
+
+    #[test]
+    fn empty_table() {
+        let piece_table = PieceTable::from(String::new());
+        let mut cursor = piece_table.chunk_cursor(0);
+
+        assert_eq!(None, cursor.chunk());
+        assert_eq!(0, piece_table.pieces().count());
+        assert!(!cursor.move_next());
+        assert!(!cursor.move_prev());
+    }
+
+    #[test]
+    fn every_edit_invalidates_positions() {
+        let mut piece_table = PieceTable::new("hello world");
+        let edits: [fn(&mut PieceTable); 6] = [
+            |table| table.insert_char(0, 'x'),
+            |table| table.insert("abc", 3),
+            |table| table.delete(1..2),
+            |table| table.move_range(0..2, 5),
+            |table| table.reorder_lines(0..1, &[0]),
+            |table| drop(table.split_off(4)),
+        ];
+        for (i, edit) in edits.into_iter().enumerate() {
+            let position = piece_table.chunk_cursor(1).position();
+            let same = i == 4;
+            edit(&mut piece_table);
+
+            assert_eq!(same, piece_table.resume_cursor(position).is_ok());
+        }
+    }
+
+    #[test]
+    fn positions_are_tied_to_their_table() {
+        let piece_table = PieceTable::new("abc");
+        let mut clone = piece_table.clone();
+        let position = piece_table.chunk_cursor(0).position();
+
+        assert!(clone.resume_cursor(position).is_ok());
+        clone.insert("d", 3);
+        assert_eq!(
+            Err(IterError::Invalidated {
+                saved: piece_table.generation(),
+                current: clone.generation()
+            }),
+            clone.resume_cursor(position).map(|_| ())
+        );
+        assert!(PieceTable::new("abc").resume_cursor(position).is_err());
+    }
+
+    #[test]
+    fn byte_runs_cover_range() {
+        let mut piece_table = PieceTable::new("日本");
+        piece_table.insert("x", 3);
+
+        let runs: Vec<_> = piece_table.byte_runs(1..7).collect();
+        assert_eq!(
+            vec![
+                (&"日".as_bytes()[1..], 1..3),
+                (&b"x"[..], 3..4),
+                (&"本".as_bytes()[..3], 4..7)
+            ],
+            runs
+        );
+        assert_eq!(0, piece_table.byte_runs(7..7).count());
+        assert_eq!(1, piece_table.byte_runs(3..4).count());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "invalid get_range: offset 9 is out of bounds of document of length 3"
+    )]
+    fn get_range_out_of_bounds() {
+        let piece_table = PieceTable::new("abc");
+        piece_table.get_range(1..9);
+    }
+
+    #[test]
+    fn piece_ids_follow_edits() {
+        let ids = |piece_table: &PieceTable| -> Vec<PieceId> {
+            piece_table.pieces().map(|(.., id)| id).collect()
+        };
+        let mut piece_table = PieceTable::new("one two");
+        piece_table.insert(" three", 7);
+        let before = ids(&piece_table);
+
+        // Extending the second piece changes its ID, but not the first one's
+        piece_table.insert("!", 13);
+        let after = ids(&piece_table);
+        assert_eq!(before[0], after[0]);
+        assert_ne!(before[1], after[1]);
+        assert_eq!(Some(0), before[1].offset_in(after[1]));
+
+        // Splitting the first piece gives both halves IDs inside its ID
+        piece_table.delete(3..4);
+        let split = ids(&piece_table);
+        assert_eq!(Some(0), split[0].offset_in(before[0]));
+        assert_eq!(Some(4), split[1].offset_in(before[0]));
+        assert_eq!((3, 3), (split[0].len(), split[1].len()));
+        assert_eq!(None, split[2].offset_in(before[0]));
+    }
+
+    #[test]
+    fn inline_pieces_are_identified_by_text() {
+        let mut piece_table = PieceTable::new("ab");
+        piece_table.insert_char(1, 'x');
+        piece_table.insert_char(0, 'x');
+        let ids: Vec<_> = piece_table.pieces().map(|(.., id)| id).collect();
+
+        assert_eq!("xaxb", piece_table.to_string());
+        assert_eq!(ids[0], ids[2]);
+        assert_eq!(Some(0), ids[0].offset_in(ids[2]));
+        assert_eq!(None, ids[1].offset_in(ids[0]));
+    }
+}
@@ -0,0 +1,320 @@
+//! Batches of edits as retain/insert/delete runs, as used by collaborative editing protocols.
+//!
+//! xi-rope deltas, CodeMirror change sets and Quill deltas all describe a change as a walk over
+//! the old document: keep the next bytes, insert some text, or skip the next bytes. A [`Delta`]
+//! is that walk, so it converts to and from those formats one operation at a time.
+
+use std::ops::Range;
+
+use crate::edit_builder::EditBuilder;
+use crate::{EditError, PieceTable};
+
+/// An operation of a [`Delta`], with lengths in bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeltaOp {
+    /// Keeps the next bytes of the old document.
+    Retain(usize),
+    /// Inserts text.
+    Insert(String),
+    /// Removes the next bytes of the old document.
+    Delete(usize),
+}
+
+/// A change to a whole document, as a sequence of operations covering all of the old document.
+///
+/// Operations are kept in a canonical form: there are no empty operations, no two operations of
+/// the same kind in a row, and an insert always comes before a delete at the same place. So two
+/// deltas making the same change compare equal.
+///
+/// # Examples
+///
+/// ```
+/// # use piece_table::PieceTable;
+/// use piece_table::delta::{Delta, DeltaOp};
+/// use piece_table::edit_builder::EditBuilder;
+///
+/// let mut pt = PieceTable::new("hello world");
+/// let mut builder = EditBuilder::new(&pt);
+/// builder.replace(0..1, "H").insert(11, "!");
+///
+/// let delta = builder.to_delta().unwrap();
+/// assert_eq!(
+///     delta.ops(),
+///     [
+///         DeltaOp::Insert("H".to_string()),
+///         DeltaOp::Delete(1),
+///         DeltaOp::Retain(10),
+///         DeltaOp::Insert("!".to_string()),
+///     ]
+/// );
+///
+/// pt.apply_delta(&delta).unwrap();
+/// assert_eq!(pt.to_string(), "Hello world!");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Delta {
+    ops: Vec<DeltaOp>,
+}
+
+impl Delta {
+    /// Creates an empty delta, which applies to an empty document.
+    pub fn new() -> Self {
+        Delta::default()
+    }
+
+    /// Appends a retain of `len` bytes.
+    pub fn retain(&mut self, len: usize) -> &mut Self {
+        if len > 0 {
+            match self.ops.last_mut() {
+                Some(DeltaOp::Retain(last)) => *last += len,
+                _ => self.ops.push(DeltaOp::Retain(len)),
+            }
+        }
+        self
+    }
+
+    /// Appends an insert of `text`.
+    pub fn insert(&mut self, text: &str) -> &mut Self {
+        if text.is_empty() {
+            return self;
+        }
+        // Keep inserts before deletes, so there's only one way to write a replacement
+        let deleted = match self.ops.last() {
+            Some(&DeltaOp::Delete(len)) => {
+                self.ops.pop();
+                Some(len)
+            }
+            _ => None,
+        };
+        match self.ops.last_mut() {
+            Some(DeltaOp::Insert(last)) => last.push_str(text),
+            _ => self.ops.push(DeltaOp::Insert(text.to_string())),
+        }
+        if let Some(len) = deleted {
+            self.ops.push(DeltaOp::Delete(len));
+        }
+        self
+    }
+
+    /// Appends a delete of `len` bytes.
+    pub fn delete(&mut self, len: usize) -> &mut Self {
+        if len > 0 {
+            match self.ops.last_mut() {
+                Some(DeltaOp::Delete(last)) => *last += len,
+                _ => self.ops.push(DeltaOp::Delete(len)),
+            }
+        }
+        self
+    }
+
+    /// Returns the operations.
+    pub fn ops(&self) -> &[DeltaOp] {
+        &self.ops
+    }
+
+    /// Returns the length of the document the delta applies to.
+    pub fn base_len(&self) -> usize {
+        self.ops
+            .iter()
+            .map(|op| match op {
+                DeltaOp::Retain(len) | DeltaOp::Delete(len) => *len,
+                DeltaOp::Insert(_) => 0,
+            })
+            .sum()
+    }
+
+    /// Returns the length of the document after applying the delta.
+    pub fn target_len(&self) -> usize {
+        self.ops
+            .iter()
+            .map(|op| match op {
+                DeltaOp::Retain(len) => *len,
+                DeltaOp::Insert(text) => text.len(),
+                DeltaOp::Delete(_) => 0,
+            })
+            .sum()
+    }
+
+    /// Checks if applying the delta leaves the document as it is.
+    pub fn is_identity(&self) -> bool {
+        self.ops.iter().all(|op| matches!(op, DeltaOp::Retain(_)))
+    }
+}
+
+impl FromIterator<DeltaOp> for Delta {
+    /// Collects operations into a delta, bringing them into canonical form.
+    fn from_iter<I: IntoIterator<Item = DeltaOp>>(ops: I) -> Self {
+        let mut delta = Delta::new();
+        for op in ops {
+            match op {
+                DeltaOp::Retain(len) => delta.retain(len),
+                DeltaOp::Insert(text) => delta.insert(&text),
+                DeltaOp::Delete(len) => delta.delete(len),
+            };
+        }
+        delta
+    }
+}
+
+impl EditBuilder {
+    /// Returns the batch as a delta against the document the builder was created for.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EditError::InvalidRange`] or [`EditError::OutOfBounds`] if an edit's range is
+    /// invalid, and [`EditError::OverlappingEdits`] if two edits overlap. Since the builder
+    /// doesn't hold on to the document, char boundaries are only checked when the delta is
+    /// applied.
+    pub fn to_delta(&self) -> Result<Delta, EditError> {
+        let mut sorted = self.clone();
+        sorted.sort_edits();
+        for (range, _) in &sorted.edits {
+            check_range(range, self.len)?;
+        }
+        sorted.check_overlaps()?;
+
+        let mut delta = Delta::new();
+        let mut offset = 0;
+        for (range, text) in &sorted.edits {
+            delta
+                .retain(range.start - offset)
+                .insert(text)
+                .delete(range.len());
+            offset = range.end;
+        }
+        delta.retain(self.len - offset);
+        Ok(delta)
+    }
+}
+
+impl PieceTable<'_> {
+    /// Applies `delta` to the document, see [`EditBuilder::apply`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EditError::LengthMismatch`] if the delta applies to a document of another
+    /// length, and otherwise the errors of [`EditBuilder::apply`], e.g. if an operation starts
+    /// or ends inside a char. If an error is returned, the document is left untouched.
+    pub fn apply_delta(&mut self, delta: &Delta) -> Result<(), EditError> {
+        if delta.base_len() != self.len() {
+            return Err(EditError::LengthMismatch {
+                expected: delta.base_len(),
+                len: self.len(),
+            });
+        }
+
+        let mut builder = EditBuilder::new(self);
+        let mut offset = 0;
+        for op in delta.ops() {
+            match op {
+                DeltaOp::Retain(len) => offset += len,
+                DeltaOp::Insert(text) => {
+                    builder.insert(offset, text.as_str());
+                }
+                DeltaOp::Delete(len) => {
+                    builder.delete(offset..offset + len);
+                    offset += len;
+                }
+            }
+        }
+        builder.apply(self)
+    }
+}
+
+/// Checks that `range` is a valid range of a document of length `len`.
+fn check_range(range: &Range<usize>, len: usize) -> Result<(), EditError> {
+    if range.start > range.end {
+        Err(EditError::InvalidRange {
+            start: range.start,
+            end: range.end,
+        })
+    } else if range.end > len {
+        Err(EditError::OutOfBounds {
+            offset: range.end,
+            len,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_form() {
+        let delta: Delta = [
+            DeltaOp::Retain(2),
+            DeltaOp::Retain(0),
+            DeltaOp::Retain(1),
+            DeltaOp::Delete(1),
+            DeltaOp::Insert("a".to_string()),
+            DeltaOp::Delete(2),
+            DeltaOp::Insert("b".to_string()),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            &[
+                DeltaOp::Retain(3),
+                DeltaOp::Insert("ab".to_string()),
+                DeltaOp::Delete(3),
+            ],
+            delta.ops()
+        );
+        assert_eq!(6, delta.base_len());
+        assert_eq!(5, delta.target_len());
+    }
+
+    #[test]
+    fn round_trips_through_builder() {
+        let mut piece_table = PieceTable::new("one two three");
+        let mut builder = EditBuilder::new(&piece_table);
+        builder
+            .replace(8..13, "3")
+            .delete(3..4)
+            .insert(0, "zero ")
+            .replace(4..7, "2");
+        let delta = builder.to_delta().unwrap();
+
+        assert_eq!(13, delta.base_len());
+        piece_table.apply_delta(&delta).unwrap();
+        assert_eq!("zero one2 3", piece_table.to_string());
+        assert_eq!(delta.target_len(), piece_table.len());
+    }
+
+    #[test]
+    fn invalid_deltas() {
+        let mut piece_table = PieceTable::new("héllo");
+        let mut delta = Delta::new();
+        delta.retain(2).delete(4);
+        assert_eq!(
+            Err(EditError::NotCharBoundary { offset: 2 }),
+            piece_table.apply_delta(&delta)
+        );
+
+        delta.retain(1);
+        assert_eq!(
+            Err(EditError::LengthMismatch {
+                expected: 7,
+                len: 6
+            }),
+            piece_table.apply_delta(&delta)
+        );
+        assert_eq!("héllo", piece_table.to_string());
+        assert!(Delta::new().is_identity());
+    }
+
+    #[test]
+    fn builder_errors() {
+        let piece_table = PieceTable::new("abc");
+        let mut builder = EditBuilder::new(&piece_table);
+        builder.delete(2..4);
+        assert_eq!(
+            Err(EditError::OutOfBounds { offset: 4, len: 3 }),
+            builder.to_delta()
+        );
+    }
+}
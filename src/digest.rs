@@ -0,0 +1,158 @@
+//! A hash of a `PieceTable`'s content, for cheaply checking whether anything changed.
+//!
+//! Enabled by the `digest` feature. Every piece stores a polynomial hash of its text, where the
+//! hash of `b[0], ..., b[n - 1]` is `b[0] * B^(n - 1) + ... + b[n - 1]`, wrapping on overflow.
+//! The hash of two texts one after the other can be computed from their hashes and lengths, so
+//! the hash of the document is combined from the pieces without looking at any text, and a piece
+//! which is cut in two only has to hash the shorter side.
+
+use std::ops::Range;
+
+use crate::PieceTable;
+
+/// The base of the polynomial. It must be odd, so it has a multiplicative inverse.
+const BASE: u64 = 0x100_0000_01b3;
+
+/// Returns the hash of `text`.
+pub(crate) fn hash(text: &str) -> u64 {
+    text.bytes().fold(0, |acc, byte| {
+        acc.wrapping_mul(BASE).wrapping_add(byte as u64)
+    })
+}
+
+/// Returns the hash of text with hash `first` followed by `second_len` bytes with hash `second`.
+pub(crate) fn combine(first: u64, second: u64, second_len: usize) -> u64 {
+    first
+        .wrapping_mul(pow(BASE, second_len))
+        .wrapping_add(second)
+}
+
+/// Returns the hash of `byte` repeated `count` times, without going over every byte.
+pub(crate) fn repeat(byte: u8, count: usize) -> u64 {
+    // The hash of `byte` repeated `run` times, doubling `run` for every bit of `count`
+    let (mut run, mut run_hash) = (1, byte as u64);
+    let mut hash = 0;
+    let mut remaining = count;
+    while remaining > 0 {
+        if remaining & 1 == 1 {
+            hash = combine(hash, run_hash, run);
+        }
+        remaining >>= 1;
+        if remaining > 0 {
+            run_hash = combine(run_hash, run_hash, run);
+            run *= 2;
+        }
+    }
+    hash
+}
+
+/// Returns the hash of `text[sub]`, given that `whole` is the hash of `text`.
+pub(crate) fn sub_hash(whole: u64, text: &str, sub: Range<usize>) -> u64 {
+    if sub.len() * 2 <= text.len() {
+        return hash(&text[sub]);
+    }
+
+    // whole = prefix * B^(sub + suffix) + sub * B^suffix + suffix
+    let prefix = hash(&text[..sub.start]);
+    let suffix = hash(&text[sub.end..]);
+    let suffix_len = text.len() - sub.end;
+    whole
+        .wrapping_sub(prefix.wrapping_mul(pow(BASE, text.len() - sub.start)))
+        .wrapping_sub(suffix)
+        .wrapping_mul(pow(inverse(BASE), suffix_len))
+}
+
+fn pow(mut base: u64, mut exp: usize) -> u64 {
+    let mut result: u64 = 1;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result.wrapping_mul(base);
+        }
+        base = base.wrapping_mul(base);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Returns the inverse of an odd `x` modulo 2^64, using Newton's iteration.
+const fn inverse(x: u64) -> u64 {
+    // Correct to 3 bits, and each step doubles that
+    let mut inv = x;
+    let mut i = 0;
+    while i < 5 {
+        inv = inv.wrapping_mul(2u64.wrapping_sub(x.wrapping_mul(inv)));
+        i += 1;
+    }
+    inv
+}
+
+impl PieceTable<'_> {
+    /// Returns a hash of the text.
+    ///
+    /// Tables with the same text always have the same digest, no matter how they were edited.
+    /// This takes time proportional to the number of pieces rather than the length of the text,
+    /// so it can be checked e.g. before every highlighting pass, to skip it if nothing changed.
+    ///
+    /// The digest is not cryptographic, and is only stable within a version of this crate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello world");
+    /// let saved = pt.content_digest();
+    ///
+    /// pt.insert("!", 11);
+    /// assert_ne!(pt.content_digest(), saved);
+    ///
+    /// pt.delete(11..12);
+    /// assert_eq!(pt.content_digest(), saved);
+    /// ```
+    pub fn content_digest(&self) -> u64 {
+        self.nodes
+            .iter()
+            .fold(0, |acc, node| combine(acc, node.hash, node.range.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workload::{Profile, generate};
+
+    #[test]
+    fn inverse_of_base() {
+        assert_eq!(1, BASE.wrapping_mul(inverse(BASE)));
+    }
+
+    #[test]
+    fn sub_hash_matches_hash() {
+        let text = "the quick brown fox jumps over the lazy dog";
+        let whole = hash(text);
+        for start in 0..text.len() {
+            for end in start..=text.len() {
+                assert_eq!(hash(&text[start..end]), sub_hash(whole, text, start..end));
+            }
+        }
+    }
+
+    #[test]
+    fn repeat_matches_hash() {
+        for count in 0..100 {
+            assert_eq!(hash(&"x".repeat(count)), repeat(b'x', count));
+        }
+    }
+
+    #[test]
+    fn digest_matches_fresh_table() {
+        let initial = "fn main() {\n    println!(\"hello\");\n}\n";
+        for profile in [Profile::Typing, Profile::PasteHeavy, Profile::Deleting] {
+            let mut piece_table = PieceTable::new(initial);
+            for edit in &generate(3, profile, initial, 200).edits {
+                edit.apply(&mut piece_table);
+                let text = piece_table.to_string();
+                assert_eq!(hash(&text), piece_table.content_digest());
+            }
+        }
+    }
+}
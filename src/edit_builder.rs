@@ -0,0 +1,198 @@
+//! Applying a batch of edits expressed in the coordinates of a single document state.
+
+use std::ops::Range;
+
+use crate::{EditError, PieceTable};
+
+/// A batch of edits, all expressed in byte offsets of the document as it was when the builder
+/// was created.
+///
+/// Applying edits one by one means every edit has to account for how the previous ones shifted
+/// the text after them, which is easy to get wrong, e.g. when applying search-and-replace
+/// results. The builder takes the edits as they are, and applies them from the last to the
+/// first, so that no edit moves the text any of the remaining ones refer to.
+///
+/// Edits may not overlap, although they may touch. Several insertions at the same offset end up
+/// in the order they were added.
+///
+/// # Examples
+///
+/// ```
+/// # use piece_table::PieceTable;
+/// # use piece_table::edit_builder::EditBuilder;
+/// let mut pt = PieceTable::new("let a = a + a;");
+/// let mut builder = EditBuilder::new(&pt);
+/// for (offset, _) in pt.to_string().match_indices('a') {
+///     builder.replace(offset..offset + 1, "value");
+/// }
+/// builder.apply(&mut pt).unwrap();
+/// assert_eq!(pt.to_string(), "let value = value + value;");
+/// ```
+#[derive(Debug, Clone)]
+pub struct EditBuilder {
+    /// The length of the document when the builder was created
+    pub(crate) len: usize,
+    pub(crate) edits: Vec<(Range<usize>, String)>,
+}
+
+impl EditBuilder {
+    /// Creates an empty batch for the current state of `doc`.
+    pub fn new(doc: &PieceTable) -> Self {
+        EditBuilder {
+            len: doc.len(),
+            edits: Vec::new(),
+        }
+    }
+
+    /// Returns the number of edits in the batch.
+    pub fn len(&self) -> usize {
+        self.edits.len()
+    }
+
+    /// Checks if the batch has no edits.
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// Adds an insertion of `text` at `offset`.
+    pub fn insert(&mut self, offset: usize, text: impl Into<String>) -> &mut Self {
+        self.replace(offset..offset, text)
+    }
+
+    /// Adds a deletion of `range`.
+    pub fn delete(&mut self, range: Range<usize>) -> &mut Self {
+        self.replace(range, String::new())
+    }
+
+    /// Adds a replacement of the text in `range` with `text`.
+    pub fn replace(&mut self, range: Range<usize>, text: impl Into<String>) -> &mut Self {
+        self.edits.push((range, text.into()));
+        self
+    }
+
+    /// Applies every edit to `doc`, which must be in the same state as when the builder was
+    /// created.
+    ///
+    /// Every edit is validated before any of them is applied, so if an error is returned `doc`
+    /// is left untouched. Besides the errors of [`PieceTable::try_delete`] and
+    /// [`PieceTable::try_insert`], this returns [`EditError::OverlappingEdits`] if two edits
+    /// overlap. Limits are checked for the batch as a whole, assuming every edit splits pieces.
+    pub fn apply(mut self, doc: &mut PieceTable) -> Result<(), EditError> {
+        debug_assert_eq!(
+            self.len,
+            doc.len(),
+            "the document was edited after the builder was created"
+        );
+
+        self.sort_edits();
+        for (range, _) in &self.edits {
+            doc.check_range(range)?;
+        }
+        self.check_overlaps()?;
+        let inserted: usize = self.edits.iter().map(|(_, text)| text.len()).sum();
+        let deleted: usize = self.edits.iter().map(|(range, _)| range.len()).sum();
+        doc.check_growth(
+            inserted.saturating_sub(deleted),
+            inserted,
+            2 * self.edits.len(),
+        )?;
+
+        for (range, text) in self.edits.into_iter().rev() {
+            let offset = range.start;
+            doc.delete_unchecked(range);
+            if !text.is_empty() {
+                doc.insert_unchecked(&text, offset);
+            }
+        }
+        Ok(())
+    }
+
+    /// Sorts the edits by their ranges.
+    pub(crate) fn sort_edits(&mut self) {
+        // Sorting is stable, so insertions at the same offset keep their order, and they come
+        // before a deletion starting at that offset
+        self.edits
+            .sort_by_key(|(range, _)| (range.start, range.end));
+    }
+
+    /// Checks that no two of the sorted edits overlap.
+    pub(crate) fn check_overlaps(&self) -> Result<(), EditError> {
+        for pair in self.edits.windows(2) {
+            let (first, second) = (&pair[0].0, &pair[1].0);
+            if first.end > second.start {
+                return Err(EditError::OverlappingEdits {
+                    offset: second.start,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_in_any_order() {
+        let mut piece_table = PieceTable::new("one two three");
+        let mut builder = EditBuilder::new(&piece_table);
+        builder
+            .replace(8..13, "3")
+            .delete(3..4)
+            .insert(0, "zero ")
+            .replace(4..7, "2");
+
+        builder.apply(&mut piece_table).unwrap();
+
+        assert_eq!("zero one2 3", piece_table.to_string());
+    }
+
+    #[test]
+    fn insertions_at_same_offset_keep_order() {
+        let mut piece_table = PieceTable::new("ad");
+        let mut builder = EditBuilder::new(&piece_table);
+        builder.insert(1, "b").delete(1..2).insert(1, "c");
+
+        builder.apply(&mut piece_table).unwrap();
+
+        assert_eq!("abc", piece_table.to_string());
+    }
+
+    #[test]
+    fn touching_edits() {
+        let mut piece_table = PieceTable::new("abcdef");
+        let mut builder = EditBuilder::new(&piece_table);
+        builder.replace(0..2, "x").replace(2..4, "y").insert(4, "z");
+
+        builder.apply(&mut piece_table).unwrap();
+
+        assert_eq!("xyzef", piece_table.to_string());
+    }
+
+    #[test]
+    fn overlapping_edits_are_rejected() {
+        let mut piece_table = PieceTable::new("abcdef");
+        let mut builder = EditBuilder::new(&piece_table);
+        builder.delete(0..1).replace(1..4, "x").insert(2, "y");
+
+        assert_eq!(
+            Err(EditError::OverlappingEdits { offset: 2 }),
+            builder.apply(&mut piece_table)
+        );
+        assert_eq!("abcdef", piece_table.to_string());
+    }
+
+    #[test]
+    fn invalid_edits_leave_document_untouched() {
+        let mut piece_table = PieceTable::new("héllo");
+        let mut builder = EditBuilder::new(&piece_table);
+        builder.delete(0..1).insert(2, "x");
+
+        assert_eq!(
+            Err(EditError::NotCharBoundary { offset: 2 }),
+            builder.apply(&mut piece_table)
+        );
+        assert_eq!("héllo", piece_table.to_string());
+    }
+}
@@ -0,0 +1,280 @@
+//! Opening and saving text in encodings other than UTF-8.
+//!
+//! Tables always hold UTF-8, so text is decoded once when it's opened and encoded again when
+//! it's saved. Decoding uses [`encoding_rs`], encoding is done here, one piece at a time, since
+//! `encoding_rs` only encodes to the encodings allowed on the web, which excludes UTF-16.
+
+use std::fmt;
+use std::io::{self, Write};
+
+use encoding_rs::{DecoderResult, UTF_8, UTF_16BE, UTF_16LE};
+
+use crate::PieceTable;
+
+/// A supported encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// UTF-8 without a byte order mark.
+    Utf8,
+    /// UTF-8 starting with a byte order mark.
+    Utf8Bom,
+    /// UTF-16, little endian, starting with a byte order mark.
+    Utf16Le,
+    /// UTF-16, big endian, starting with a byte order mark.
+    Utf16Be,
+    /// ISO-8859-1, where every byte is the char with the same value.
+    Latin1,
+}
+
+impl Encoding {
+    /// Returns the encoding given by the byte order mark `bytes` start with, if there's one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use piece_table::encoding::Encoding;
+    ///
+    /// assert_eq!(Encoding::from_bom(b"\xFF\xFEh\0i\0"), Some(Encoding::Utf16Le));
+    /// assert_eq!(Encoding::from_bom(b"hi"), None);
+    /// ```
+    pub fn from_bom(bytes: &[u8]) -> Option<Encoding> {
+        let (encoding, _) = encoding_rs::Encoding::for_bom(bytes)?;
+        if encoding == UTF_8 {
+            Some(Encoding::Utf8Bom)
+        } else if encoding == UTF_16LE {
+            Some(Encoding::Utf16Le)
+        } else {
+            Some(Encoding::Utf16Be)
+        }
+    }
+
+    /// Returns the byte order mark written at the start of text in this encoding.
+    fn bom(self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 | Encoding::Latin1 => b"",
+            Encoding::Utf8Bom => b"\xEF\xBB\xBF",
+            Encoding::Utf16Le => b"\xFF\xFE",
+            Encoding::Utf16Be => b"\xFE\xFF",
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Encoding::Utf8 => write!(f, "UTF-8"),
+            Encoding::Utf8Bom => write!(f, "UTF-8 with BOM"),
+            Encoding::Utf16Le => write!(f, "UTF-16LE"),
+            Encoding::Utf16Be => write!(f, "UTF-16BE"),
+            Encoding::Latin1 => write!(f, "ISO-8859-1"),
+        }
+    }
+}
+
+/// The input of [`PieceTable::from_bytes`] isn't valid in the given encoding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    /// The encoding the input was decoded as.
+    pub encoding: Encoding,
+    /// The offset of the first invalid byte in the input.
+    pub offset: usize,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid {} at byte {}", self.encoding, self.offset)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl PieceTable<'_> {
+    /// Creates a table holding `bytes` decoded from `encoding`.
+    ///
+    /// A byte order mark matching `encoding` is skipped, for all encodings which can have one.
+    /// Use [`Encoding::from_bom`] to tell the encoding of a file which starts with one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// use piece_table::encoding::Encoding;
+    ///
+    /// let pt = PieceTable::from_bytes(b"\xFE\xFF\0h\0\xE9", Encoding::Utf16Be).unwrap();
+    /// assert_eq!(pt.to_string(), "hé");
+    ///
+    /// let pt = PieceTable::from_bytes(b"caf\xE9", Encoding::Latin1).unwrap();
+    /// assert_eq!(pt.to_string(), "café");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `bytes` isn't valid in `encoding`, rather than replacing the invalid
+    /// bytes, so that saving the text again never silently changes it.
+    pub fn from_bytes(
+        bytes: &[u8],
+        encoding: Encoding,
+    ) -> Result<PieceTable<'static>, DecodeError> {
+        let bom = encoding.bom();
+        let skipped = if bytes.starts_with(bom) { bom.len() } else { 0 };
+        let bytes = &bytes[skipped..];
+
+        let decoder = match encoding {
+            Encoding::Utf8 | Encoding::Utf8Bom => UTF_8,
+            Encoding::Utf16Le => UTF_16LE,
+            Encoding::Utf16Be => UTF_16BE,
+            Encoding::Latin1 => {
+                let text: String = bytes.iter().map(|&byte| char::from(byte)).collect();
+                return Ok(PieceTable::from(text));
+            }
+        };
+        let mut decoder = decoder.new_decoder_without_bom_handling();
+        let mut text = String::with_capacity(
+            decoder
+                .max_utf8_buffer_length_without_replacement(bytes.len())
+                .unwrap_or(bytes.len()),
+        );
+        match decoder.decode_to_string_without_replacement(bytes, &mut text, true) {
+            (DecoderResult::InputEmpty, _) => Ok(PieceTable::from(text)),
+            (DecoderResult::Malformed(len, after), read) => Err(DecodeError {
+                encoding,
+                offset: skipped + read - len as usize - after as usize,
+            }),
+            (DecoderResult::OutputFull, _) => unreachable!("the output has room for all input"),
+        }
+    }
+
+    /// Writes the text to `writer`, encoded in `encoding`, one piece at a time.
+    ///
+    /// A byte order mark is written first for [`Encoding::Utf8Bom`], [`Encoding::Utf16Le`] and
+    /// [`Encoding::Utf16Be`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// use piece_table::encoding::Encoding;
+    ///
+    /// let mut pt = PieceTable::new("h");
+    /// pt.insert("é", 1);
+    ///
+    /// let mut out = Vec::new();
+    /// pt.encode_to(&mut out, Encoding::Utf16Le).unwrap();
+    /// assert_eq!(out, b"\xFF\xFEh\0\xE9\0");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `writer`, and an error of kind [`io::ErrorKind::InvalidData`] if the
+    /// text has a char which `encoding` can't represent, which only happens for
+    /// [`Encoding::Latin1`]. In that case, the text before that char has already been written.
+    pub fn encode_to<W: Write>(&self, mut writer: W, encoding: Encoding) -> io::Result<()> {
+        writer.write_all(encoding.bom())?;
+
+        let mut buf = Vec::new();
+        let mut offset = 0;
+        for chunk in self.chunks_from(0) {
+            buf.clear();
+            match encoding {
+                Encoding::Utf8 | Encoding::Utf8Bom => {
+                    writer.write_all(chunk.as_bytes())?;
+                    continue;
+                }
+                Encoding::Utf16Le => {
+                    buf.extend(chunk.encode_utf16().flat_map(u16::to_le_bytes));
+                }
+                Encoding::Utf16Be => {
+                    buf.extend(chunk.encode_utf16().flat_map(u16::to_be_bytes));
+                }
+                Encoding::Latin1 => {
+                    for (idx, c) in chunk.char_indices() {
+                        let Ok(byte) = u8::try_from(c) else {
+                            writer.write_all(&buf)?;
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                format!("{c:?} at offset {} isn't in {encoding}", offset + idx),
+                            ));
+                        };
+                        buf.push(byte);
+                    }
+                }
+            }
+            writer.write_all(&buf)?;
+            offset += chunk.len();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENCODINGS: [Encoding; 5] = [
+        Encoding::Utf8,
+        Encoding::Utf8Bom,
+        Encoding::Utf16Le,
+        Encoding::Utf16Be,
+        Encoding::Latin1,
+    ];
+
+    #[test]
+    fn round_trips() {
+        let mut piece_table = PieceTable::new("naïve\r\n");
+        piece_table.insert("café ", 0);
+        piece_table.insert_char(5, 'ÿ');
+
+        for encoding in ENCODINGS {
+            let mut bytes = Vec::new();
+            piece_table.encode_to(&mut bytes, encoding).unwrap();
+            let decoded = PieceTable::from_bytes(&bytes, encoding).unwrap();
+
+            assert_eq!(piece_table.to_string(), decoded.to_string());
+            let has_bom = !matches!(encoding, Encoding::Utf8 | Encoding::Latin1);
+            assert_eq!(has_bom.then_some(encoding), Encoding::from_bom(&bytes));
+        }
+    }
+
+    #[test]
+    fn utf16_surrogate_pairs() {
+        let piece_table = PieceTable::new("🦀");
+        let mut bytes = Vec::new();
+        piece_table
+            .encode_to(&mut bytes, Encoding::Utf16Be)
+            .unwrap();
+
+        assert_eq!(b"\xFE\xFF\xD8\x3E\xDD\x80", bytes.as_slice());
+    }
+
+    #[test]
+    fn invalid_input() {
+        assert_eq!(
+            Err(DecodeError {
+                encoding: Encoding::Utf8Bom,
+                offset: 5
+            }),
+            PieceTable::from_bytes(b"\xEF\xBB\xBFab\xFFc", Encoding::Utf8Bom).map(|_| ())
+        );
+        // An unpaired surrogate
+        assert_eq!(
+            Err(DecodeError {
+                encoding: Encoding::Utf16Le,
+                offset: 2
+            }),
+            PieceTable::from_bytes(b"a\0\x00\xD8b\0", Encoding::Utf16Le).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn latin1_rejects_other_chars() {
+        let mut piece_table = PieceTable::new("ab");
+        piece_table.insert("€", 1);
+
+        let mut bytes = Vec::new();
+        let err = piece_table
+            .encode_to(&mut bytes, Encoding::Latin1)
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+        assert_eq!(b"a", bytes.as_slice());
+    }
+}
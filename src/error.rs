@@ -0,0 +1,81 @@
+use std::fmt;
+
+use crate::limits::Limit;
+
+/// The reason an edit was rejected.
+///
+/// Returned by the `try_*` methods of `PieceTable`. The infallible methods perform the same
+/// checks in debug builds (or when the `strict` feature is enabled) and panic with this error's
+/// message instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditError {
+    /// The offset is past the end of the document.
+    OutOfBounds { offset: usize, len: usize },
+    /// The offset falls inside a multi-byte UTF-8 character.
+    NotCharBoundary { offset: usize },
+    /// The range's start is after its end.
+    InvalidRange { start: usize, end: usize },
+    /// Two edits in a batch both change the text at `offset`.
+    OverlappingEdits { offset: usize },
+    /// The edit could take the table past one of its [`Limits`](crate::limits::Limits).
+    LimitExceeded { limit: Limit, max: usize },
+    /// The edits were made against a document of length `expected`, not `len`.
+    LengthMismatch { expected: usize, len: usize },
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::OutOfBounds { offset, len } => {
+                write!(
+                    f,
+                    "offset {offset} is out of bounds of document of length {len}"
+                )
+            }
+            EditError::NotCharBoundary { offset } => {
+                write!(f, "offset {offset} is not on a char boundary")
+            }
+            EditError::InvalidRange { start, end } => {
+                write!(f, "range start {start} is greater than range end {end}")
+            }
+            EditError::OverlappingEdits { offset } => {
+                write!(f, "edits overlap at offset {offset}")
+            }
+            EditError::LimitExceeded { limit, max } => {
+                write!(f, "edit could exceed the maximum {limit} of {max}")
+            }
+            EditError::LengthMismatch { expected, len } => {
+                write!(
+                    f,
+                    "edits for a document of length {expected} don't apply to one of length {len}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+/// The reason a cursor couldn't be resumed.
+///
+/// Returned by [`PieceTable::resume_cursor`](crate::PieceTable::resume_cursor) when the table
+/// was edited since the cursor's position was saved, since the piece it was on may have been
+/// split, moved or removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IterError {
+    /// The position was saved at generation `saved`, and the table is now at `current`.
+    Invalidated { saved: u64, current: u64 },
+}
+
+impl fmt::Display for IterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IterError::Invalidated { saved, current } => write!(
+                f,
+                "position from generation {saved} is invalid at generation {current}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for IterError {}
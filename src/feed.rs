@@ -0,0 +1,127 @@
+//! Feeding the text to an incremental parser, one chunk at a time.
+//!
+//! Tokenizers usually want the text in order from some offset, e.g. the start of the first line
+//! an edit touched, and stop once they're back in sync with what they parsed before. The text is
+//! handed over straight from the pieces, so nothing past the point where the parser stops is
+//! read, and a token split between two pieces can be seen whole by asking for some overlap.
+
+use std::ops::ControlFlow;
+
+use crate::PieceTable;
+
+impl PieceTable<'_> {
+    /// Calls `f` with the text from `from_offset` to the end, in chunks, until it returns
+    /// [`ControlFlow::Break`], and returns the offset where the text passed to it ends.
+    ///
+    /// With an `overlap` of 0 the chunks are the text of the pieces, without any copying.
+    /// Otherwise each chunk after the first starts with the last `overlap` bytes of the one
+    /// before it, or a bit less so that it starts on a char boundary, so a token of up to
+    /// `overlap + 1` bytes is always whole in some chunk. Chunks are only copied to prepend the
+    /// overlap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// use std::ops::ControlFlow;
+    ///
+    /// let mut pt = PieceTable::new("let x = 1;\nlet y = 2;\n");
+    /// pt.insert("mut ", 4);
+    ///
+    /// // Stop at the first chunk holding a whole `let`
+    /// let mut seen = Vec::new();
+    /// let end = pt.feed_parser(2, 2, |chunk| {
+    ///     seen.push(chunk.to_string());
+    ///     if chunk.contains("let") {
+    ///         ControlFlow::Break(())
+    ///     } else {
+    ///         ControlFlow::Continue(())
+    ///     }
+    /// });
+    /// assert_eq!(seen, ["t ", "t mut ", "t x = 1;\nlet y = 2;\n"]);
+    /// assert_eq!(end, pt.len());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `from_offset` is out of bounds or not on a char boundary.
+    pub fn feed_parser<F>(&self, from_offset: usize, overlap: usize, mut f: F) -> usize
+    where
+        F: FnMut(&str) -> ControlFlow<()>,
+    {
+        if let Err(err) = self.check_offset(from_offset) {
+            panic!("invalid feed_parser: {err}");
+        }
+
+        let mut end = from_offset;
+        let mut carry = String::new();
+        for chunk in self.chunks_from(from_offset) {
+            if chunk.is_empty() {
+                continue;
+            }
+            end += chunk.len();
+            let flow = if carry.is_empty() {
+                f(chunk)
+            } else {
+                carry.push_str(chunk);
+                f(&carry)
+            };
+            if flow.is_break() {
+                break;
+            }
+            if overlap > 0 {
+                let text = if carry.is_empty() { chunk } else { &carry };
+                let mut start = text.len() - overlap.min(text.len());
+                while !text.is_char_boundary(start) {
+                    start += 1;
+                }
+                carry = text[start..].to_string();
+            }
+        }
+        end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_without_overlap_are_pieces() {
+        let mut piece_table = PieceTable::new("abcdef");
+        piece_table.insert("123", 3);
+
+        let mut chunks = Vec::new();
+        let end = piece_table.feed_parser(1, 0, |chunk| {
+            chunks.push(chunk.to_string());
+            ControlFlow::Continue(())
+        });
+        assert_eq!(vec!["bc", "123", "def"], chunks);
+        assert_eq!(9, end);
+
+        let end = piece_table.feed_parser(1, 0, |_| ControlFlow::Break(()));
+        assert_eq!(3, end);
+        assert_eq!(9, piece_table.feed_parser(9, 4, |_| unreachable!()));
+    }
+
+    #[test]
+    fn overlap_stops_on_char_boundaries() {
+        let mut piece_table = PieceTable::new("aé");
+        piece_table.insert_char(3, 'b');
+        piece_table.insert("日c", 4);
+
+        let mut chunks = Vec::new();
+        piece_table.feed_parser(0, 2, |chunk| {
+            chunks.push(chunk.to_string());
+            ControlFlow::Continue(())
+        });
+        // The overlap of "aé" is only "é", and tiny pieces carry over what came before them
+        assert_eq!(vec!["aé", "éb", "b日c"], chunks);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid feed_parser: offset 2 is not on a char boundary")]
+    fn offset_inside_a_char_panics() {
+        PieceTable::new("aé").feed_parser(2, 0, |_| ControlFlow::Continue(()));
+    }
+}
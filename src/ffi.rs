@@ -0,0 +1,352 @@
+//! A C interface, for embedding a `PieceTable` in C and C++ editors.
+//!
+//! Tables created through this interface own their text, and are only ever handled through an
+//! opaque `PTable` pointer, which must be freed with [`ptable_free`]. All text crossing the
+//! boundary is UTF-8 given as a pointer and a length in bytes, and all offsets are in bytes.
+//! Functions which can fail return one of the `PTABLE_*` status codes. The declarations are in
+//! `include/piece_table.h`.
+//!
+//! Enabled by the `ffi` feature, so the exported symbols are only in builds which ask for them.
+//! To link against the library, build it as a static or dynamic library, e.g. with
+//! `cargo rustc --release --features ffi --crate-type staticlib`.
+
+use std::ffi::c_void;
+use std::ptr;
+use std::slice;
+
+use crate::{EditError, PieceTable};
+
+/// The operation succeeded.
+pub const PTABLE_OK: i32 = 0;
+/// A required pointer was null.
+pub const PTABLE_NULL_POINTER: i32 = 1;
+/// The given text isn't valid UTF-8.
+pub const PTABLE_INVALID_UTF8: i32 = 2;
+/// An offset is past the end of the document.
+pub const PTABLE_OUT_OF_BOUNDS: i32 = 3;
+/// An offset falls inside a multi-byte UTF-8 character.
+pub const PTABLE_NOT_CHAR_BOUNDARY: i32 = 4;
+/// A range's start is after its end.
+pub const PTABLE_INVALID_RANGE: i32 = 5;
+/// The output buffer is too small.
+pub const PTABLE_BUFFER_TOO_SMALL: i32 = 6;
+/// The edit could exceed one of the table's limits.
+pub const PTABLE_LIMIT_EXCEEDED: i32 = 7;
+
+/// An opaque handle to a `PieceTable` which owns its text.
+pub struct PTable {
+    table: PieceTable<'static>,
+}
+
+impl From<EditError> for i32 {
+    fn from(err: EditError) -> i32 {
+        match err {
+            EditError::OutOfBounds { .. } => PTABLE_OUT_OF_BOUNDS,
+            EditError::NotCharBoundary { .. } => PTABLE_NOT_CHAR_BOUNDARY,
+            EditError::InvalidRange { .. }
+            | EditError::OverlappingEdits { .. }
+            | EditError::LengthMismatch { .. } => PTABLE_INVALID_RANGE,
+            EditError::LimitExceeded { .. } => PTABLE_LIMIT_EXCEEDED,
+        }
+    }
+}
+
+/// Reads `len` bytes of UTF-8 text from `text`, which may only be null if `len` is 0.
+///
+/// # Safety
+///
+/// If `text` isn't null, it must point to `len` readable bytes.
+unsafe fn read_text<'t>(text: *const u8, len: usize) -> Result<&'t str, i32> {
+    if len == 0 {
+        return Ok("");
+    } else if text.is_null() {
+        return Err(PTABLE_NULL_POINTER);
+    }
+
+    // SAFETY: the caller guarantees `text` points to `len` readable bytes
+    let bytes = unsafe { slice::from_raw_parts(text, len) };
+    std::str::from_utf8(bytes).map_err(|_| PTABLE_INVALID_UTF8)
+}
+
+/// Creates a table containing a copy of the `len` bytes at `text`.
+///
+/// Returns null if `text` is null (and `len` isn't 0) or isn't valid UTF-8.
+///
+/// # Safety
+///
+/// If `text` isn't null, it must point to `len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ptable_new(text: *const u8, len: usize) -> *mut PTable {
+    // SAFETY: forwarded from the caller
+    match unsafe { read_text(text, len) } {
+        Ok(text) => Box::into_raw(Box::new(PTable {
+            table: PieceTable::from(text.to_string()),
+        })),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Frees a table. Does nothing if `table` is null.
+///
+/// # Safety
+///
+/// `table` must be null or a pointer returned by [`ptable_new`] which wasn't freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ptable_free(table: *mut PTable) {
+    if !table.is_null() {
+        // SAFETY: the caller guarantees `table` came from `Box::into_raw` in `ptable_new`
+        drop(unsafe { Box::from_raw(table) });
+    }
+}
+
+/// Returns the length of the table's text in bytes, or 0 if `table` is null.
+///
+/// # Safety
+///
+/// `table` must be null or a valid pointer returned by [`ptable_new`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ptable_len(table: *const PTable) -> usize {
+    // SAFETY: the caller guarantees `table` is null or valid
+    unsafe { table.as_ref() }.map_or(0, |t| t.table.len())
+}
+
+/// Inserts the `len` bytes at `text` at byte `offset`.
+///
+/// # Safety
+///
+/// `table` must be null or a valid pointer returned by [`ptable_new`], and if `text` isn't
+/// null it must point to `len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ptable_insert(
+    table: *mut PTable,
+    offset: usize,
+    text: *const u8,
+    len: usize,
+) -> i32 {
+    // SAFETY: the caller guarantees `table` is null or valid
+    let Some(table) = (unsafe { table.as_mut() }) else {
+        return PTABLE_NULL_POINTER;
+    };
+    // SAFETY: forwarded from the caller
+    let text = match unsafe { read_text(text, len) } {
+        Ok(text) => text,
+        Err(code) => return code,
+    };
+
+    match table.table.try_insert(text, offset) {
+        Ok(()) => PTABLE_OK,
+        Err(err) => err.into(),
+    }
+}
+
+/// Deletes the bytes from `start` up to (but not including) `end`.
+///
+/// # Safety
+///
+/// `table` must be null or a valid pointer returned by [`ptable_new`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ptable_delete(table: *mut PTable, start: usize, end: usize) -> i32 {
+    // SAFETY: the caller guarantees `table` is null or valid
+    let Some(table) = (unsafe { table.as_mut() }) else {
+        return PTABLE_NULL_POINTER;
+    };
+
+    match table.table.try_delete(start..end) {
+        Ok(()) => PTABLE_OK,
+        Err(err) => err.into(),
+    }
+}
+
+/// Copies the text from byte `start` up to (but not including) `end` into `buf`, which can hold
+/// `buf_len` bytes. The text isn't NUL-terminated.
+///
+/// On success, and when `buf` is too small (returning [`PTABLE_BUFFER_TOO_SMALL`]), the length
+/// of the text is written to `written` if it isn't null, so a caller can pass a null `buf` with
+/// a `buf_len` of 0 to find out how big a buffer it needs.
+///
+/// # Safety
+///
+/// `table` must be null or a valid pointer returned by [`ptable_new`], `buf` must be null or
+/// point to `buf_len` writable bytes, and `written` must be null or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ptable_slice_utf8(
+    table: *const PTable,
+    start: usize,
+    end: usize,
+    buf: *mut u8,
+    buf_len: usize,
+    written: *mut usize,
+) -> i32 {
+    // SAFETY: the caller guarantees `table` is null or valid
+    let Some(table) = (unsafe { table.as_ref() }) else {
+        return PTABLE_NULL_POINTER;
+    };
+    if let Err(err) = table.table.check_range(&(start..end)) {
+        return err.into();
+    }
+
+    let len = end - start;
+    if !written.is_null() {
+        // SAFETY: the caller guarantees `written` is valid for writes
+        unsafe { *written = len };
+    }
+    if len == 0 {
+        return PTABLE_OK;
+    } else if buf_len < len {
+        return PTABLE_BUFFER_TOO_SMALL;
+    } else if buf.is_null() {
+        return PTABLE_NULL_POINTER;
+    }
+
+    // SAFETY: the caller guarantees `buf` points to `buf_len >= len` writable bytes
+    let out = unsafe { slice::from_raw_parts_mut(buf, len) };
+    let mut copied = 0;
+    for chunk in table.table.chunks_from(start) {
+        let n = chunk.len().min(len - copied);
+        out[copied..copied + n].copy_from_slice(&chunk.as_bytes()[..n]);
+        copied += n;
+        if copied == len {
+            break;
+        }
+    }
+    PTABLE_OK
+}
+
+/// Calls `callback` with each contiguous run of the bytes in `[start, end)`, in order, along
+/// with `user_data`, without copying them, see [`PieceTable::byte_runs`].
+///
+/// `start` and `end` don't need to be on char boundaries, and neither do the runs.
+///
+/// # Safety
+///
+/// `table` must be null or a valid pointer returned by [`ptable_new`]. The pointers passed to
+/// `callback` are only valid until it returns, and `callback` must not edit or free `table`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ptable_for_each_run(
+    table: *const PTable,
+    start: usize,
+    end: usize,
+    callback: Option<unsafe extern "C" fn(run: *const u8, len: usize, user_data: *mut c_void)>,
+    user_data: *mut c_void,
+) -> i32 {
+    // SAFETY: the caller guarantees `table` is null or valid
+    let (Some(table), Some(callback)) = (unsafe { table.as_ref() }, callback) else {
+        return PTABLE_NULL_POINTER;
+    };
+    if start > end {
+        return PTABLE_INVALID_RANGE;
+    } else if end > table.table.len() {
+        return PTABLE_OUT_OF_BOUNDS;
+    }
+
+    for (run, _) in table.table.byte_runs(start..end) {
+        // SAFETY: the caller guarantees `callback` can be called with a run and `user_data`
+        unsafe { callback(run.as_ptr(), run.len(), user_data) };
+    }
+    PTABLE_OK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slice(table: *const PTable, start: usize, end: usize) -> Result<String, i32> {
+        let mut needed = 0;
+        let code = unsafe { ptable_slice_utf8(table, start, end, ptr::null_mut(), 0, &mut needed) };
+        if code != PTABLE_OK && code != PTABLE_BUFFER_TOO_SMALL {
+            return Err(code);
+        }
+
+        let mut buf = vec![0; needed];
+        let mut written = 0;
+        let code = unsafe {
+            ptable_slice_utf8(table, start, end, buf.as_mut_ptr(), buf.len(), &mut written)
+        };
+        assert_eq!(PTABLE_OK, code);
+        assert_eq!(needed, written);
+        Ok(String::from_utf8(buf).unwrap())
+    }
+
+    #[test]
+    fn edit_and_read_back() {
+        let text = "hello world";
+        let table = unsafe { ptable_new(text.as_ptr(), text.len()) };
+        assert!(!table.is_null());
+
+        let inserted = ", there";
+        assert_eq!(PTABLE_OK, unsafe {
+            ptable_insert(table, 5, inserted.as_ptr(), inserted.len())
+        });
+        assert_eq!(PTABLE_OK, unsafe { ptable_delete(table, 0, 1) });
+
+        assert_eq!(17, unsafe { ptable_len(table) });
+        assert_eq!(Ok("ello, there world".to_string()), slice(table, 0, 17));
+        assert_eq!(Ok("there wo".to_string()), slice(table, 6, 14));
+        assert_eq!(Ok(String::new()), slice(table, 3, 3));
+
+        unsafe { ptable_free(table) };
+    }
+
+    #[test]
+    fn errors() {
+        let table = unsafe { ptable_new(ptr::null(), 0) };
+        let invalid = [0xff];
+        let text = "é";
+
+        assert!(unsafe { ptable_new(invalid.as_ptr(), 1) }.is_null());
+        assert!(unsafe { ptable_new(ptr::null(), 1) }.is_null());
+        assert_eq!(PTABLE_INVALID_UTF8, unsafe {
+            ptable_insert(table, 0, invalid.as_ptr(), 1)
+        });
+        assert_eq!(PTABLE_OK, unsafe {
+            ptable_insert(table, 0, text.as_ptr(), 2)
+        });
+        assert_eq!(PTABLE_NOT_CHAR_BOUNDARY, unsafe {
+            ptable_insert(table, 1, text.as_ptr(), 2)
+        });
+        assert_eq!(PTABLE_OUT_OF_BOUNDS, unsafe { ptable_delete(table, 0, 3) });
+        assert_eq!(PTABLE_INVALID_RANGE, unsafe { ptable_delete(table, 2, 0) });
+        assert_eq!(Err(PTABLE_NOT_CHAR_BOUNDARY), slice(table, 0, 1));
+        assert_eq!(PTABLE_NULL_POINTER, unsafe {
+            ptable_delete(ptr::null_mut(), 0, 0)
+        });
+
+        let mut small = [0; 1];
+        assert_eq!(PTABLE_BUFFER_TOO_SMALL, unsafe {
+            ptable_slice_utf8(table, 0, 2, small.as_mut_ptr(), 1, ptr::null_mut())
+        });
+
+        unsafe { ptable_free(table) };
+        unsafe { ptable_free(ptr::null_mut()) };
+    }
+
+    #[test]
+    fn runs_are_passed_without_copying() {
+        unsafe extern "C" fn collect(run: *const u8, len: usize, user_data: *mut c_void) {
+            // SAFETY: `user_data` is the vector below, and `run` points to `len` bytes
+            let runs = unsafe { &mut *user_data.cast::<Vec<Vec<u8>>>() };
+            runs.push(unsafe { slice::from_raw_parts(run, len) }.to_vec());
+        }
+
+        let text = "aé";
+        let table = unsafe { ptable_new(text.as_ptr(), text.len()) };
+        assert_eq!(PTABLE_OK, unsafe {
+            ptable_insert(table, 1, b"xy".as_ptr(), 2)
+        });
+
+        let mut runs: Vec<Vec<u8>> = Vec::new();
+        let user_data = (&raw mut runs).cast();
+        assert_eq!(PTABLE_OK, unsafe {
+            ptable_for_each_run(table, 0, 4, Some(collect), user_data)
+        });
+        assert_eq!(vec![b"a".to_vec(), b"xy".to_vec(), vec![0xc3]], runs);
+        assert_eq!(PTABLE_OUT_OF_BOUNDS, unsafe {
+            ptable_for_each_run(table, 0, 6, Some(collect), user_data)
+        });
+        assert_eq!(PTABLE_NULL_POINTER, unsafe {
+            ptable_for_each_run(table, 0, 1, None, user_data)
+        });
+
+        unsafe { ptable_free(table) };
+    }
+}
@@ -0,0 +1,242 @@
+//! Filling parts of a document with a single repeated char.
+//!
+//! Aligning columns or writing fixed-width records takes runs of padding which may be long, and
+//! building them as a string only to copy it into the table wastes a copy. A run of an ASCII char
+//! isn't stored at all: it's a single piece holding the char and the length of the run, whose
+//! text is made up as it's read, see [`PieceTable::insert_repeated`]. Runs of other chars are
+//! written straight into the "added" buffer, where they're referenced by a single piece.
+
+use std::ops::Range;
+
+use crate::{EditError, Node, NodeKind, PieceTable, VALIDATE, normalize};
+
+impl PieceTable<'_> {
+    /// Inserts `count` copies of `ch` at `offset`.
+    ///
+    /// A run of an ASCII char takes the same memory however long it is, since it's kept as the
+    /// char and the length of the run, and read in chunks of at most 128 bytes, e.g. by
+    /// [`PieceTable::pieces`]. Inserting more of the same char right after it extends it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("ab");
+    /// pt.insert_repeated(1, '-', 1_000_000);
+    /// pt.insert_repeated(1_000_001, '-', 2);
+    /// assert_eq!(pt.len(), 1_000_004);
+    /// assert_eq!(pt.char(500_000), Some('-'));
+    /// assert_eq!(pt.pieces().filter(|(_, text, ..)| text.contains('-')).count(), 7813);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`PieceTable::insert`].
+    pub fn insert_repeated(&mut self, offset: usize, ch: char, count: usize) {
+        if VALIDATE && let Err(err) = self.check_offset(offset) {
+            panic!("invalid insert_repeated: {err}");
+        }
+        if let Err(err) = self.check_repeated_limits(offset, ch, count) {
+            panic!("invalid insert_repeated: {err}");
+        }
+        self.insert_repeated_unchecked(offset, ch, count);
+    }
+
+    /// Appends `ch` until the document is `len` chars long, and returns how many were appended.
+    ///
+    /// Nothing is appended if the document is already at least `len` chars long.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("név");
+    /// assert_eq!(pt.pad_end(6, '.'), 3);
+    /// assert_eq!(pt.to_string(), "név...");
+    /// assert_eq!(pt.pad_end(4, '.'), 0);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if appending would exceed the table's limits.
+    pub fn pad_end(&mut self, len: usize, ch: char) -> usize {
+        let count = len.saturating_sub(self.chars);
+        if let Err(err) = self.check_repeated_limits(self.len, ch, count) {
+            panic!("invalid pad_end: {err}");
+        }
+        self.insert_repeated_unchecked(self.len, ch, count);
+        count
+    }
+
+    /// Replaces every char in `range` with `ch`, keeping the number of chars the same.
+    ///
+    /// The length of the range in bytes changes if `ch` is encoded with a different number of
+    /// bytes than the chars it replaces.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("id: 4é2, name: x");
+    /// pt.fill_range(4..8, '*');
+    /// assert_eq!(pt.to_string(), "id: ***, name: x");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`PieceTable::delete`], or if the filled text would exceed
+    /// the table's limits.
+    pub fn fill_range(&mut self, range: Range<usize>, ch: char) {
+        let checked = if VALIDATE {
+            self.check_range(&range)
+        } else {
+            self.check_range_bounds(&range)
+        };
+        if let Err(err) = checked {
+            panic!("invalid fill_range: {err}");
+        }
+        let count = self.byte_to_char(range.end) - self.byte_to_char(range.start);
+        if let Err(err) = self
+            .check_delete_limits(&range)
+            .and_then(|()| self.check_repeated_limits(range.start, ch, count))
+        {
+            panic!("invalid fill_range: {err}");
+        }
+        let offset = range.start;
+        self.delete_unchecked(range);
+        self.insert_repeated_unchecked(offset, ch, count);
+    }
+
+    /// Checks that inserting `count` copies of `ch` at `offset` stays within the limits. Only
+    /// runs of chars other than ASCII ones take space in the "added" buffer.
+    fn check_repeated_limits(
+        &self,
+        offset: usize,
+        ch: char,
+        count: usize,
+    ) -> Result<(), EditError> {
+        let len = ch.len_utf8() * count;
+        let added = if ch.is_ascii() { 0 } else { len };
+        self.check_growth(len, added, 1 + self.splits_at(offset))
+    }
+
+    /// Inserts `count` copies of `ch` at `offset`, which must be a valid offset, as a single
+    /// piece.
+    fn insert_repeated_unchecked(&mut self, offset: usize, ch: char, count: usize) {
+        if count == 0 {
+            return;
+        }
+        let node = if ch.is_ascii() {
+            Node::repeat(ch as u8, count)
+        } else if self.normalize && !normalize::is_nfc(ch.encode_utf8(&mut [0; 4])) {
+            return self.insert_unchecked(&ch.to_string().repeat(count), offset);
+        } else {
+            let range = self.added.push_repeated(ch, count);
+            Node::new(NodeKind::Added, range.clone(), &self.added[range])
+        };
+        let insert_idx = self.split_at_offset(offset);
+        self.splice_node(insert_idx, offset, node);
+        self.publish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::Direction;
+
+    #[test]
+    fn fill_is_a_single_piece() {
+        let mut piece_table = PieceTable::new("a\nbc\nd");
+        piece_table.fill_range(1..5, '-');
+
+        assert_eq!("a----d", piece_table.to_string());
+        assert_eq!(3, piece_table.nodes.len());
+        assert_eq!(1, piece_table.len_lines());
+        assert_eq!(6, piece_table.len_chars());
+
+        piece_table.fill_range(0..6, '\n');
+        assert_eq!("\n".repeat(6), piece_table.to_string());
+        assert_eq!(7, piece_table.len_lines());
+    }
+
+    #[test]
+    fn pad_end_counts_chars() {
+        let mut piece_table = PieceTable::new("日本");
+        assert_eq!(2, piece_table.pad_end(4, '語'));
+        assert_eq!("日本語語", piece_table.to_string());
+        assert_eq!(12, piece_table.len());
+
+        // Padding with an ASCII char after typing adds a single repeat piece, which later
+        // padding with the same char extends
+        piece_table.insert("x", 12);
+        let pieces = piece_table.nodes.len();
+        piece_table.pad_end(1000, ' ');
+        piece_table.pad_end(1200, ' ');
+        assert_eq!(pieces + 1, piece_table.nodes.len());
+        assert_eq!(1200, piece_table.len_chars());
+        assert_eq!(" ".repeat(1195), piece_table.to_string()[13..]);
+        assert_eq!(6 + 1, piece_table.added.len());
+    }
+
+    #[test]
+    fn repeat_pieces_read_in_chunks() {
+        let mut piece_table = PieceTable::new("ab\ncd");
+        piece_table.insert_repeated(4, '\n', 300);
+        piece_table.insert_repeated(2, '=', 200);
+        let expected = format!("ab{}\nc{}d", "=".repeat(200), "\n".repeat(300));
+        assert_eq!(Ok(()), piece_table.check_invariants());
+
+        assert_eq!(expected, piece_table.to_string());
+        assert_eq!(None, piece_table.as_str());
+        assert_eq!(&expected[100..450], piece_table.slice(100..450).to_string());
+        assert_eq!(302, piece_table.len_lines());
+        assert_eq!(Some(b'='), piece_table.byte(150));
+        assert_eq!(Some('\n'), piece_table.char(400));
+        assert_eq!(205, piece_table.line_to_byte(2));
+        assert_eq!(102, piece_table.byte_to_line(305));
+        assert_eq!(302, piece_table.byte_to_char(302));
+        assert_eq!(
+            Some(201),
+            piece_table.scan_until(450, |c| c == '=', Direction::Backward)
+        );
+        assert!(piece_table.pieces().all(|(_, text, ..)| text.len() <= 128));
+
+        let mut cursor = piece_table.chunk_cursor(0);
+        let mut forward = String::new();
+        while let Some((chunk, range)) = cursor.chunk() {
+            assert_eq!(&expected[range], chunk);
+            forward.push_str(chunk);
+            if !cursor.move_next() {
+                break;
+            }
+        }
+        assert_eq!(expected, forward);
+        let mut backward = Vec::new();
+        loop {
+            backward.push(cursor.chunk().unwrap().0);
+            if !cursor.move_prev() {
+                break;
+            }
+        }
+        backward.reverse();
+        assert_eq!(expected, backward.concat());
+    }
+
+    #[test]
+    fn editing_inside_repeat_pieces() {
+        let mut piece_table = PieceTable::new("");
+        piece_table.insert_repeated(0, 'x', usize::MAX / 4);
+        assert_eq!(1, piece_table.nodes.len());
+        assert_eq!(0, piece_table.added.len());
+
+        piece_table.delete(10..usize::MAX / 4 - 10);
+        piece_table.insert("yy", 5);
+        assert_eq!(Ok(()), piece_table.check_invariants());
+        assert_eq!("xxxxxyyxxxxxxxxxxxxxxx", piece_table.to_string());
+        assert_eq!(
+            Some(7),
+            piece_table.scan_until(5, |c| c == 'x', Direction::Forward)
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! Removing every char or substring matching a condition, in a single pass.
+//!
+//! Stripping carriage returns or trailing spaces from a huge document one [`PieceTable::delete`]
+//! at a time rescans the text after every edit. These methods scan the text once, merge
+//! neighbouring deletions, and apply them all as one batch.
+
+use std::ops::Range;
+
+use crate::PieceTable;
+use crate::edit_builder::EditBuilder;
+
+impl PieceTable<'_> {
+    /// Keeps only the chars for which `keep` returns `true`, given each char and its offset.
+    ///
+    /// Offsets are those of the document before anything is removed, and chars are visited in
+    /// order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("one  \r\ntwo \r\n");
+    /// pt.retain(|c, _| c != '\r');
+    /// assert_eq!(pt.to_string(), "one  \ntwo \n");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if removing the chars would exceed the table's limits.
+    pub fn retain<F: FnMut(char, usize) -> bool>(&mut self, mut keep: F) {
+        let mut removed: Vec<Range<usize>> = Vec::new();
+        for (offset, c) in self.char_indices_from(0) {
+            if keep(c, offset) {
+                continue;
+            }
+            let end = offset + c.len_utf8();
+            match removed.last_mut() {
+                Some(last) if last.end == offset => last.end = end,
+                _ => removed.push(offset..end),
+            }
+        }
+        self.delete_all(removed, "retain");
+    }
+
+    /// Removes every non-overlapping occurrence of `needle`, from the first to the last, and
+    /// returns how many were removed.
+    ///
+    /// Occurrences split across pieces are found without copying the text. An empty `needle`
+    /// removes nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("a,, b, c");
+    /// pt.insert(",", 5);
+    /// assert_eq!(pt.remove_matches(",,"), 2);
+    /// assert_eq!(pt.to_string(), "a b c");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if removing the occurrences would exceed the table's limits.
+    pub fn remove_matches(&mut self, needle: &str) -> usize {
+        let matches = self.find_matches(needle.as_bytes());
+        let count = matches.len();
+        self.delete_all(matches, "remove_matches");
+        count
+    }
+
+    /// Returns the ranges of the non-overlapping occurrences of `needle`, found with
+    /// Knuth-Morris-Pratt so the text is read once, one chunk at a time.
+    fn find_matches(&self, needle: &[u8]) -> Vec<Range<usize>> {
+        if needle.is_empty() {
+            return Vec::new();
+        }
+        // `fallback[i]` is the length of the longest proper border of `needle[..=i]`
+        let mut fallback = vec![0; needle.len()];
+        let mut border = 0;
+        for i in 1..needle.len() {
+            while border > 0 && needle[i] != needle[border] {
+                border = fallback[border - 1];
+            }
+            if needle[i] == needle[border] {
+                border += 1;
+            }
+            fallback[i] = border;
+        }
+
+        let mut matches = Vec::new();
+        let mut matched = 0;
+        let bytes = self.chunks_from(0).flat_map(str::bytes).enumerate();
+        for (offset, byte) in bytes {
+            while matched > 0 && byte != needle[matched] {
+                matched = fallback[matched - 1];
+            }
+            if byte == needle[matched] {
+                matched += 1;
+            }
+            if matched == needle.len() {
+                matches.push(offset + 1 - needle.len()..offset + 1);
+                matched = 0;
+            }
+        }
+        matches
+    }
+
+    /// Deletes the sorted, non-overlapping `ranges` as a single batch.
+    fn delete_all(&mut self, ranges: Vec<Range<usize>>, op: &str) {
+        if ranges.is_empty() {
+            return;
+        }
+        let mut builder = EditBuilder::new(self);
+        for range in ranges {
+            builder.delete(range);
+        }
+        if let Err(err) = builder.apply(self) {
+            panic!("invalid {op}: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_trailing_spaces() {
+        let mut piece_table = PieceTable::new("a  \nb \n  c\n");
+        piece_table.insert(" ", 5);
+        let text = piece_table.to_string();
+
+        // Only keep spaces with something other than spaces after them on their line
+        piece_table.retain(|c, offset| {
+            c != ' ' || !text[offset..].trim_start_matches(' ').starts_with('\n')
+        });
+        assert_eq!("a\nb\n  c\n", piece_table.to_string());
+        assert_eq!(Ok(()), piece_table.check_invariants());
+    }
+
+    #[test]
+    fn matches_across_pieces() {
+        let mut piece_table = PieceTable::new("xaaby");
+        piece_table.insert("a", 1);
+        piece_table.split_at_offset(4);
+        assert_eq!("xaaaby", piece_table.to_string());
+
+        // The first two "a"s start a match which fails, and the last two start the real one
+        assert_eq!(1, piece_table.remove_matches("aab"));
+        assert_eq!("xay", piece_table.to_string());
+        assert_eq!(0, piece_table.remove_matches(""));
+        assert_eq!(Ok(()), piece_table.check_invariants());
+    }
+
+    #[test]
+    fn overlapping_matches_are_removed_from_the_left() {
+        let mut piece_table = PieceTable::new("aaaaa\r\n\r\n");
+
+        assert_eq!(2, piece_table.remove_matches("aa"));
+        assert_eq!(2, piece_table.remove_matches("\r\n"));
+        assert_eq!("a", piece_table.to_string());
+        piece_table.retain(|_, _| false);
+        assert!(piece_table.is_empty());
+    }
+}
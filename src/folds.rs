@@ -0,0 +1,258 @@
+//! Folded regions of a document, and mapping between document lines and display lines.
+
+use std::ops::Range;
+
+use crate::PieceTable;
+use crate::spans::{InsertMode, Spans};
+
+/// The folded lines of a document, kept in place across edits.
+///
+/// Each fold is stored in a [`Spans`] overlay as the bytes from the start of its first line to
+/// the start of the line after it, so it moves with the text like any other span: lines inserted
+/// inside a fold are folded too, while lines inserted right before or after it aren't. A line is
+/// hidden if it starts inside a fold, so a fold stays well defined even after an edit leaves its
+/// ends in the middle of lines. Folds may nest and overlap.
+///
+/// After each edit to the document, [`FoldMap::on_insert`] or [`FoldMap::on_delete`] must be
+/// called. Queries take the document, to find where its lines start.
+///
+/// # Examples
+///
+/// ```
+/// # use piece_table::PieceTable;
+/// use piece_table::folds::FoldMap;
+///
+/// let mut pt = PieceTable::new("fn f() {\n    a();\n    b();\n}\nfn g() {}\n");
+/// let mut folds = FoldMap::new();
+/// // Fold the body of `f`
+/// folds.fold(&pt, 1..3);
+///
+/// pt.insert("    c();\n", 18);
+/// folds.on_insert(18, 9);
+///
+/// assert_eq!(folds.folded_lines(&pt), vec![1..4]);
+/// assert_eq!(folds.visible_lines(&pt, 0..3), vec![0, 4, 5]);
+/// assert_eq!(folds.doc_to_display_line(&pt, 5), Some(2));
+/// ```
+#[derive(Debug, Clone)]
+pub struct FoldMap {
+    folds: Spans<()>,
+}
+
+impl Default for FoldMap {
+    fn default() -> Self {
+        FoldMap::new()
+    }
+}
+
+impl FoldMap {
+    /// Creates a map without any folds.
+    pub fn new() -> Self {
+        FoldMap {
+            folds: Spans::new(InsertMode::Expand),
+        }
+    }
+
+    /// Returns the number of folds.
+    pub fn len(&self) -> usize {
+        self.folds.len()
+    }
+
+    /// Checks if there are no folds.
+    pub fn is_empty(&self) -> bool {
+        self.folds.is_empty()
+    }
+
+    /// Hides `lines` of `doc`. Empty ranges of lines are ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `lines` isn't a valid range of lines of `doc`.
+    pub fn fold(&mut self, doc: &PieceTable, lines: Range<usize>) {
+        assert!(
+            lines.start <= lines.end && lines.end <= doc.len_lines(),
+            "invalid fold: {lines:?} is not a range of the {} lines of the document",
+            doc.len_lines()
+        );
+        if !lines.is_empty() {
+            let range = doc.line_to_byte(lines.start)..doc.line_to_byte(lines.end);
+            self.folds.add(range, ());
+        }
+    }
+
+    /// Removes every fold hiding `line`, returning how many were removed.
+    pub fn unfold(&mut self, doc: &PieceTable, line: usize) -> usize {
+        let before = self.folds.len();
+        self.folds
+            .retain(|range, _| !hidden_lines(doc, range).contains(&line));
+        before - self.folds.len()
+    }
+
+    /// Removes every fold.
+    pub fn clear(&mut self) {
+        self.folds.clear();
+    }
+
+    /// Updates the folds after `len` bytes were inserted at `offset`.
+    pub fn on_insert(&mut self, offset: usize, len: usize) {
+        self.folds.on_insert(offset, len);
+    }
+
+    /// Updates the folds after `range` was deleted. Folds whose text was deleted entirely are
+    /// removed.
+    pub fn on_delete(&mut self, range: Range<usize>) {
+        self.folds.on_delete(range);
+        self.folds.retain(|range, _| !range.is_empty());
+    }
+
+    /// Returns the ranges of hidden lines, sorted and with overlapping and adjacent folds
+    /// merged.
+    pub fn folded_lines(&self, doc: &PieceTable) -> Vec<Range<usize>> {
+        let mut merged: Vec<Range<usize>> = Vec::new();
+        for (range, _) in self.folds.iter() {
+            let lines = hidden_lines(doc, &range);
+            if lines.is_empty() {
+                continue;
+            }
+            match merged.last_mut() {
+                Some(last) if last.end >= lines.start => last.end = last.end.max(lines.end),
+                _ => merged.push(lines),
+            }
+        }
+        merged
+    }
+
+    /// Checks if `line` of `doc` is hidden by a fold.
+    pub fn is_hidden(&self, doc: &PieceTable, line: usize) -> bool {
+        self.folded_lines(doc)
+            .iter()
+            .any(|lines| lines.contains(&line))
+    }
+
+    /// Returns the display line showing `line` of `doc`, or `None` if it's hidden.
+    pub fn doc_to_display_line(&self, doc: &PieceTable, line: usize) -> Option<usize> {
+        let mut hidden = 0;
+        for lines in self.folded_lines(doc) {
+            if lines.start > line {
+                break;
+            }
+            if lines.contains(&line) {
+                return None;
+            }
+            hidden += lines.len();
+        }
+        Some(line - hidden)
+    }
+
+    /// Returns the line of `doc` shown on `display_line`, or `None` if there are fewer display
+    /// lines.
+    pub fn display_to_doc_line(&self, doc: &PieceTable, display_line: usize) -> Option<usize> {
+        let mut line = display_line;
+        for lines in self.folded_lines(doc) {
+            if lines.start > line {
+                break;
+            }
+            line += lines.len();
+        }
+        (line < doc.len_lines()).then_some(line)
+    }
+
+    /// Returns the lines of `doc` shown on the display lines in `viewport`, in order.
+    ///
+    /// Display lines past the last visible line are left out.
+    pub fn visible_lines(&self, doc: &PieceTable, viewport: Range<usize>) -> Vec<usize> {
+        let Some(mut line) = self.display_to_doc_line(doc, viewport.start) else {
+            return Vec::new();
+        };
+        let folded = self.folded_lines(doc);
+        let mut next_fold = folded.partition_point(|lines| lines.end <= line);
+
+        let mut visible = Vec::with_capacity(viewport.len());
+        while visible.len() < viewport.len() && line < doc.len_lines() {
+            match folded.get(next_fold) {
+                Some(lines) if lines.start == line => {
+                    line = lines.end;
+                    next_fold += 1;
+                }
+                _ => {
+                    visible.push(line);
+                    line += 1;
+                }
+            }
+        }
+        visible
+    }
+}
+
+/// Returns the lines of `doc` which start in `range`.
+fn hidden_lines(doc: &PieceTable, range: &Range<usize>) -> Range<usize> {
+    let first_line_from = |offset: usize| {
+        let line = doc.byte_to_line(offset);
+        match doc.line_to_byte(line) < offset {
+            true => line + 1,
+            false => line,
+        }
+    };
+    first_line_from(range.start)..first_line_from(range.end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_folds() {
+        let piece_table = PieceTable::new("0\n1\n2\n3\n4\n5\n6");
+        let mut folds = FoldMap::new();
+        folds.fold(&piece_table, 1..5);
+        folds.fold(&piece_table, 2..3);
+        folds.fold(&piece_table, 5..6);
+
+        assert_eq!(vec![1..6], folds.folded_lines(&piece_table));
+        assert_eq!(Some(1), folds.doc_to_display_line(&piece_table, 6));
+        assert_eq!(None, folds.doc_to_display_line(&piece_table, 3));
+        assert_eq!(Some(6), folds.display_to_doc_line(&piece_table, 1));
+        assert_eq!(None, folds.display_to_doc_line(&piece_table, 2));
+
+        assert_eq!(2, folds.unfold(&piece_table, 2));
+        assert_eq!(vec![5..6], folds.folded_lines(&piece_table));
+        assert_eq!(vec![3, 4, 6], folds.visible_lines(&piece_table, 3..10));
+    }
+
+    #[test]
+    fn edits_around_folds() {
+        let mut piece_table = PieceTable::new("a\nb\nc\nd\n");
+        let mut folds = FoldMap::new();
+        folds.fold(&piece_table, 1..3);
+
+        // A line before the fold, and one right after it
+        piece_table.insert("x\n", 0);
+        folds.on_insert(0, 2);
+        piece_table.insert("y\n", 8);
+        folds.on_insert(8, 2);
+        assert_eq!("x\na\nb\nc\ny\nd\n", piece_table.to_string());
+        assert_eq!(vec![2..4], folds.folded_lines(&piece_table));
+
+        // Joining the line before the fold with its first line
+        piece_table.delete(3..4);
+        folds.on_delete(3..4);
+        assert_eq!(vec![2..3], folds.folded_lines(&piece_table));
+
+        piece_table.delete(2..8);
+        folds.on_delete(2..8);
+        assert!(folds.is_empty());
+    }
+
+    #[test]
+    fn fold_at_end_of_document() {
+        let mut piece_table = PieceTable::new("a\nb");
+        let mut folds = FoldMap::new();
+        folds.fold(&piece_table, 1..2);
+
+        piece_table.insert("c", 3);
+        folds.on_insert(3, 1);
+
+        assert!(folds.is_hidden(&piece_table, 1));
+        assert_eq!(vec![0], folds.visible_lines(&piece_table, 0..5));
+    }
+}
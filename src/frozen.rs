@@ -0,0 +1,270 @@
+//! A read-only form of a `PieceTable`, for documents which are loaded once and then mostly read.
+//!
+//! Finding a line of a `PieceTable` walks its pieces, and a table which went through many edits
+//! has many of them. Freezing a table pays for that once: its text is copied into a single
+//! piece, if it isn't in one already, and the start of every line is indexed, so afterwards any
+//! line or range of the document is a slice of one string.
+
+use std::ops::Range;
+
+use crate::{Node, NodeKind, PieceTable};
+
+/// A `PieceTable` which can't be edited, with all of its text in one string and the start of
+/// every line indexed, see [`PieceTable::freeze`].
+///
+/// The table itself is available through [`FrozenPieceTable::table`] for any other read, and
+/// [`FrozenPieceTable::thaw`] gives it back for editing.
+#[derive(Debug, Clone)]
+pub struct FrozenPieceTable<'ptable> {
+    table: PieceTable<'ptable>,
+    /// The offset where each line starts
+    line_starts: Vec<usize>,
+}
+
+impl<'ptable> PieceTable<'ptable> {
+    /// Makes the table read-only, copying its text into a single piece and indexing its lines.
+    ///
+    /// A table with a single piece, e.g. one which was never edited, isn't copied, and neither
+    /// is one whose pieces are still the original text in order. The copy is appended to the
+    /// "added" buffer, so checkpoints and readers of the table stay valid, but the whole text is
+    /// then added text: [`PieceTable::changed_ranges`] returns the whole document, and
+    /// [`PieceTable::replacements`] nothing, for the frozen table and after thawing it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("one\nthree\n");
+    /// pt.insert("two\n", 4);
+    ///
+    /// let frozen = pt.freeze();
+    /// assert_eq!(frozen.as_str(), "one\ntwo\nthree\n");
+    /// assert_eq!(frozen.line(1), Some("two"));
+    /// assert_eq!(frozen.byte_to_line(9), 2);
+    ///
+    /// let mut pt = frozen.thaw();
+    /// pt.delete(0..4);
+    /// assert_eq!(pt.to_string(), "two\nthree\n");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if copying the text would exceed the table's limits.
+    pub fn freeze(mut self) -> FrozenPieceTable<'ptable> {
+        self.compact();
+        let text = self
+            .as_str()
+            .expect("a compacted table has at most one piece");
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        FrozenPieceTable {
+            table: self,
+            line_starts,
+        }
+    }
+
+    /// Copies the text into a single piece, unless it's already stored contiguously.
+    fn compact(&mut self) {
+        // A single repeat piece can still be too long to be read at once
+        if self.as_str().is_some() {
+            return;
+        }
+        let mut pieces = self.nodes.iter().filter(|node| !node.range.is_empty());
+        if let Some(first) = pieces.next() {
+            let mut end = first.range.end;
+            let unedited = first.kind == NodeKind::Original
+                && pieces.all(|node| {
+                    let next = node.kind == NodeKind::Original && node.range.start == end;
+                    end = node.range.end;
+                    next
+                });
+            if unedited {
+                let range = first.range.start..end;
+                let node = Node::new(NodeKind::Original, range.clone(), &self.original[range]);
+                self.nodes.clear();
+                self.nodes.push_back(node);
+                self.pieces_changed();
+                self.publish();
+                return;
+            }
+        }
+        if let Err(err) = self.check_growth(0, self.len, 0) {
+            panic!("invalid freeze: {err}");
+        }
+        let text = self.as_string();
+        let node = Node::new(NodeKind::Added, self.added.push_str(&text), &text);
+        self.nodes.clear();
+        self.nodes.push_back(node);
+        self.pieces_changed();
+        self.publish();
+    }
+}
+
+impl<'ptable> FrozenPieceTable<'ptable> {
+    /// Returns the table, which can be edited again.
+    pub fn thaw(self) -> PieceTable<'ptable> {
+        self.table
+    }
+
+    /// Returns the table, for the reads which aren't provided here.
+    pub fn table(&self) -> &PieceTable<'ptable> {
+        &self.table
+    }
+
+    /// Returns the whole text.
+    pub fn as_str(&self) -> &str {
+        self.table
+            .as_str()
+            .expect("a frozen table has at most one piece")
+    }
+
+    /// Returns the length of the text, in bytes.
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Checks if the text is empty.
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// Returns the number of lines, see [`PieceTable::len_lines`].
+    pub fn len_lines(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Returns the number of chars.
+    pub fn len_chars(&self) -> usize {
+        self.table.len_chars()
+    }
+
+    /// Returns the text in `range`, or `None` if it's out of bounds or not on char boundaries.
+    pub fn get(&self, range: Range<usize>) -> Option<&str> {
+        self.as_str().get(range)
+    }
+
+    /// Returns the text of line `line_idx` (0-based) without its trailing newline, or `None` if
+    /// there are fewer lines.
+    pub fn line(&self, line_idx: usize) -> Option<&str> {
+        let start = *self.line_starts.get(line_idx)?;
+        let end = match self.line_starts.get(line_idx + 1) {
+            Some(next) => next - 1,
+            None => self.len(),
+        };
+        Some(&self.as_str()[start..end])
+    }
+
+    /// Returns the (0-based) line containing byte `byte_idx`, see [`PieceTable::byte_to_line`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `byte_idx` is out of bounds.
+    pub fn byte_to_line(&self, byte_idx: usize) -> usize {
+        assert!(
+            byte_idx <= self.len(),
+            "invalid byte_to_line: byte index {byte_idx} is out of bounds of {}",
+            self.len()
+        );
+        self.line_starts.partition_point(|&start| start <= byte_idx) - 1
+    }
+
+    /// Returns the byte index where line `line_idx` starts, see [`PieceTable::line_to_byte`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `line_idx` is out of bounds.
+    pub fn line_to_byte(&self, line_idx: usize) -> usize {
+        assert!(
+            line_idx <= self.len_lines(),
+            "invalid line_to_byte: line index {line_idx} is out of bounds of {}",
+            self.len_lines()
+        );
+        self.line_starts
+            .get(line_idx)
+            .copied()
+            .unwrap_or(self.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_match_the_table() {
+        let mut piece_table = PieceTable::new("a\nbc\n\nd");
+        piece_table.insert("é\n", 2);
+        piece_table.delete(0..1);
+        let expected = piece_table.clone();
+        let frozen = piece_table.freeze();
+
+        assert_eq!(1, frozen.table().nodes.len());
+        assert_eq!(expected.len_lines(), frozen.len_lines());
+        for byte_idx in 0..=expected.len() {
+            assert_eq!(
+                expected.byte_to_line(byte_idx),
+                frozen.byte_to_line(byte_idx)
+            );
+        }
+        for line_idx in 0..=expected.len_lines() {
+            assert_eq!(
+                expected.line_to_byte(line_idx),
+                frozen.line_to_byte(line_idx)
+            );
+        }
+        assert_eq!(Some(""), frozen.line(0));
+        assert_eq!(Some("é"), frozen.line(1));
+        assert_eq!(Some("d"), frozen.line(4));
+        assert_eq!(None, frozen.line(5));
+        assert_eq!(None, frozen.get(2..4));
+    }
+
+    #[test]
+    fn single_pieces_are_not_copied() {
+        let piece_table = PieceTable::new("hello\n");
+        let frozen = piece_table.freeze();
+        assert_eq!(0, frozen.table().added.len());
+        assert_eq!(2, frozen.len_lines());
+
+        let empty = PieceTable::new("").freeze();
+        assert_eq!("", empty.as_str());
+        assert_eq!(1, empty.len_lines());
+        assert_eq!(0, empty.byte_to_line(0));
+    }
+
+    #[test]
+    fn unedited_text_stays_original() {
+        let mut piece_table = PieceTable::new("hello world, this is the original");
+        piece_table.split_at_offset(5);
+        piece_table.split_at_offset(20);
+        let frozen = piece_table.freeze();
+        assert_eq!(1, frozen.table().nodes.len());
+        assert_eq!(0, frozen.table().added.len());
+        assert!(frozen.table().changed_ranges().is_empty());
+
+        // Edited tables are copied, so their changes and replacements are forgotten
+        let mut piece_table = PieceTable::new("hello world, this is the original");
+        piece_table.insert("X", 5);
+        let frozen = piece_table.freeze();
+        assert_eq!(vec![0..34], frozen.table().changed_ranges());
+        let piece_table = PieceTable::from_bytes_lossy(b"ab\xFFcd");
+        assert_eq!(vec![2..5], piece_table.replacements());
+        assert!(piece_table.freeze().thaw().replacements().is_empty());
+    }
+
+    #[test]
+    fn long_repeat_pieces_are_copied() {
+        let mut piece_table = PieceTable::new("");
+        piece_table.insert_repeated(0, ' ', 1000);
+        let frozen = piece_table.freeze();
+        assert_eq!(" ".repeat(1000), frozen.as_str());
+        assert_eq!(1000, frozen.table().added.len());
+
+        let mut piece_table = PieceTable::new("");
+        piece_table.insert_repeated(0, '\n', 3);
+        let frozen = piece_table.freeze();
+        assert_eq!(0, frozen.table().added.len());
+        assert_eq!(Some(""), frozen.line(3));
+    }
+}
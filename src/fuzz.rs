@@ -0,0 +1,245 @@
+//! Reusable property-test harness.
+//!
+//! The harness generates random sequences of edits ([`Op`]) and read queries ([`Query`]), runs
+//! them against a backend and the [`Baseline`] model, and fails as soon as the two disagree. Any
+//! [`EditableText`] implementation can be compared with [`compare_with_baseline`], while
+//! [`compare_piece_table`] additionally checks the `PieceTable`-specific queries. Workloads from
+//! [`generate`] can be compared the same way with [`compare_generated`].
+//!
+//! This module is always compiled for the crate's own tests, and is exported behind the
+//! `test-utils` feature so other backends can reuse it.
+
+use std::fmt::Display;
+use std::ops::Range;
+
+use proptest::prelude::*;
+use proptest::test_runner::TestCaseError;
+
+use crate::PieceTable;
+use crate::baseline::Baseline;
+use crate::interface::{EditableText, check_equivalence};
+use crate::range_set::Bias;
+use crate::workload::{Edit, Profile, Workload, generate};
+
+/// A single edit operation.
+///
+/// Offsets are arbitrary and get snapped into a valid position of the document the operation is
+/// applied to (see [`apply_op`]), so any sequence of `Op`s is valid for any document.
+#[derive(Debug, Clone)]
+pub enum Op {
+    Insert(String, usize),
+    Delete(usize, usize),
+}
+
+/// A single read-only query, with the same offset snapping rules as [`Op`].
+#[derive(Debug, Clone)]
+pub enum Query {
+    Byte(usize),
+    Char(usize),
+    /// Checks and clamps the offset as is, without snapping it.
+    CharBoundary(usize),
+    Slice(usize, usize),
+    SubSlice(usize, usize, usize, usize),
+}
+
+/// Generates offsets which are mostly within small documents, but can be anywhere.
+fn offset() -> impl Strategy<Value = usize> {
+    prop_oneof![4 => 0..256usize, 1 => any::<usize>()]
+}
+
+impl Arbitrary for Op {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            (any::<String>(), offset()).prop_map(|(s, i)| Op::Insert(s, i)),
+            ("[a-c\n]{0,8}", offset()).prop_map(|(s, i)| Op::Insert(s, i)),
+            (offset(), offset()).prop_map(|(i, j)| Op::Delete(i, j)),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for Query {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            offset().prop_map(Query::Byte),
+            offset().prop_map(Query::Char),
+            offset().prop_map(Query::CharBoundary),
+            (offset(), offset()).prop_map(|(i, j)| Query::Slice(i, j)),
+            (offset(), offset(), offset(), offset())
+                .prop_map(|(i, j, k, l)| Query::SubSlice(i, j, k, l)),
+        ]
+        .boxed()
+    }
+}
+
+/// Snaps `offset` into `text`, moving it backwards until it lands on a char boundary.
+pub fn snap_offset(text: &str, offset: usize) -> usize {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+/// Snaps both ends of a range into `text`, swapping them if they're reversed.
+pub fn snap_range(text: &str, start: usize, end: usize) -> Range<usize> {
+    let start = snap_offset(text, start);
+    let end = snap_offset(text, end);
+    start.min(end)..start.max(end)
+}
+
+/// Applies `op` to `doc`, where `string_before_op` is the current content of `doc`.
+pub fn apply_op<'a, T: EditableText<'a>>(doc: &mut T, op: &Op, string_before_op: &str) {
+    match op {
+        Op::Insert(text, offset) => {
+            doc.insert(text, snap_offset(string_before_op, *offset));
+        }
+        Op::Delete(start, end) => {
+            doc.delete(snap_range(string_before_op, *start, *end));
+        }
+    }
+}
+
+impl Arbitrary for Profile {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        prop::sample::select(&Profile::ALL[..]).boxed()
+    }
+}
+
+/// Runs `query` against `table`, checking the result against `model`.
+pub fn check_query(table: &PieceTable, model: &str, query: &Query) -> Result<(), TestCaseError> {
+    match *query {
+        Query::Byte(at) => {
+            prop_assert_eq!(model.as_bytes().get(at).copied(), table.byte(at));
+        }
+        Query::Char(at) => {
+            let at = snap_offset(model, at);
+            prop_assert_eq!(model[at..].chars().next(), table.char(at));
+        }
+        Query::CharBoundary(at) => {
+            prop_assert_eq!(model.is_char_boundary(at), table.is_char_boundary(at));
+            let left = snap_offset(model, at);
+            let right = (at.min(model.len())..=model.len())
+                .find(|&offset| model.is_char_boundary(offset))
+                .unwrap();
+            prop_assert_eq!(left, table.clamp_to_char_boundary(at, Bias::Left));
+            prop_assert_eq!(right, table.clamp_to_char_boundary(at, Bias::Right));
+        }
+        Query::Slice(start, end) => {
+            let range = snap_range(model, start, end);
+            let slice = table.slice(range.clone());
+            prop_assert_eq!(range.len(), slice.len());
+            prop_assert_eq!(&model[range], slice.to_string());
+        }
+        Query::SubSlice(start, end, sub_start, sub_end) => {
+            let range = snap_range(model, start, end);
+            let slice = table.slice(range.clone());
+            let model = &model[range];
+            let sub_range = snap_range(model, sub_start, sub_end);
+            let sub_slice = slice.slice(sub_range.clone());
+            prop_assert_eq!(&model[sub_range], sub_slice.to_string());
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks every whole-document property of `table` against `model`.
+pub fn check_document(table: &PieceTable, model: &str) -> Result<(), TestCaseError> {
+    prop_assert_eq!(model, table.to_string());
+    prop_assert_eq!(model.len(), table.len());
+    prop_assert_eq!(model.is_empty(), table.is_empty());
+    prop_assert_eq!(model.matches('\n').count() + 1, table.len_lines());
+    prop_assert_eq!(Ok(()), table.check_invariants());
+    Ok(())
+}
+
+/// Returns the edits `ops` make when applied one after the other to `initial_text`, with their
+/// offsets snapped, so they can be replayed against any backend.
+pub fn to_workload(initial_text: &str, ops: &[Op]) -> Workload {
+    let mut model = initial_text.to_string();
+    let mut workload = Workload::default();
+    for op in ops {
+        let edit = match op {
+            Op::Insert(text, offset) => {
+                let offset = snap_offset(&model, *offset);
+                model.insert_str(offset, text);
+                Edit::Insert {
+                    offset,
+                    text: text.clone(),
+                }
+            }
+            Op::Delete(start, end) => {
+                let range = snap_range(&model, *start, *end);
+                model.replace_range(range.clone(), "");
+                Edit::Delete {
+                    offset: range.start,
+                    len: range.len(),
+                }
+            }
+        };
+        workload.edits.push(edit);
+    }
+    workload
+}
+
+/// Applies `ops` to a new `T` and a new [`Baseline`], comparing their contents after every edit,
+/// see [`check_equivalence`].
+pub fn compare_with_baseline<'a, T: EditableText<'a> + Display>(
+    initial_text: &'a str,
+    ops: &[Op],
+) -> Result<(), TestCaseError> {
+    let workload = to_workload(initial_text, ops);
+    check_equivalence::<Baseline, T>(initial_text, &workload)
+        .map_err(|divergence| TestCaseError::fail(divergence.to_string()))
+}
+
+/// Applies `edits` edits generated from `seed` with `profile`, see [`generate`], to a new `T` and
+/// a new [`Baseline`], comparing their contents after every edit.
+///
+/// Unlike random [`Op`]s, generated edits look like a real editing session, and a failure is
+/// reproduced by the seed alone.
+pub fn compare_generated<'a, T: EditableText<'a> + Display>(
+    initial_text: &'a str,
+    seed: u64,
+    profile: Profile,
+    edits: usize,
+) -> Result<(), TestCaseError> {
+    let workload = generate(seed, profile, initial_text, edits);
+    check_equivalence::<Baseline, T>(initial_text, &workload).map_err(|divergence| {
+        TestCaseError::fail(format!("seed {seed}, {profile:?}: {divergence}"))
+    })
+}
+
+/// Like [`compare_with_baseline`] for a `PieceTable`, but also runs `queries` after every edit.
+pub fn compare_piece_table(
+    initial_text: &str,
+    ops: &[Op],
+    queries: &[Query],
+) -> Result<(), TestCaseError> {
+    let mut table = PieceTable::new(initial_text);
+    let mut baseline = Baseline::new(initial_text);
+
+    for op in ops {
+        let s = baseline.to_string();
+        apply_op(&mut table, op, &s);
+        apply_op(&mut baseline, op, &s);
+
+        let model = baseline.to_string();
+        check_document(&table, &model)?;
+        for query in queries {
+            check_query(&table, &model, query)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,208 @@
+//! Fuzzy searching for command palettes, matching a pattern as a subsequence of each line.
+//!
+//! The matcher works like fzf's fast one: it finds the first place where a line contains the
+//! pattern's chars in order, then looks backwards from its end for the shortest such span, and
+//! scores it. Matches on word boundaries and consecutive matches score higher, gaps score lower.
+//! The text is read straight from the pieces, one line at a time.
+
+use std::cmp::Reverse;
+use std::ops::Range;
+
+use crate::PieceTable;
+
+/// The score of every matched char.
+const SCORE_MATCH: i32 = 16;
+/// The penalty for the first char of a gap between matched chars.
+const PENALTY_GAP_START: i32 = 3;
+/// The penalty for every other char of a gap.
+const PENALTY_GAP_EXTENSION: i32 = 1;
+/// The bonus for matching the first char of a word.
+const BONUS_BOUNDARY: i32 = 8;
+/// The bonus for matching an uppercase letter after a lowercase one, or a digit after a
+/// non-digit, as in `camelCase` or `utf8`.
+const BONUS_CAMEL: i32 = 7;
+/// The minimum bonus for matching a char right after another matched char.
+const BONUS_CONSECUTIVE: i32 = 4;
+/// How much more the bonus for the pattern's first char counts.
+const BONUS_FIRST_CHAR_MULTIPLIER: i32 = 2;
+
+impl PieceTable<'_> {
+    /// Returns the best match of `pattern` in every line which contains its chars in order,
+    /// along with the match's score, from the highest score to the lowest.
+    ///
+    /// The range of a match goes from its first matched char to its last one, and matches with
+    /// the same score are in document order. If `pattern` contains no uppercase letters, case is
+    /// ignored. An empty pattern, or one containing a newline, matches nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let pt = PieceTable::new("proof\nopen_file\nreopen");
+    ///
+    /// let text = pt.to_string();
+    /// let found: Vec<_> = pt
+    ///     .fuzzy_find("of")
+    ///     .into_iter()
+    ///     .map(|(_, range)| &text[range])
+    ///     .collect();
+    /// // Both chars start words in "open_file", so it scores higher than the run in "proof"
+    /// assert_eq!(found, ["open_f", "of"]);
+    /// ```
+    pub fn fuzzy_find(&self, pattern: &str) -> Vec<(i32, Range<usize>)> {
+        let ignore_case = !pattern.chars().any(char::is_uppercase);
+        let pattern: Vec<char> = pattern.chars().collect();
+        if pattern.is_empty() || pattern.contains(&'\n') {
+            return Vec::new();
+        }
+
+        let mut found = Vec::new();
+        let mut line = Vec::new();
+        let mut chars = self.char_indices_from(0).fuse();
+        loop {
+            line.clear();
+            let mut ended = true;
+            for (offset, c) in chars.by_ref() {
+                if c == '\n' {
+                    ended = false;
+                    break;
+                }
+                line.push((offset, c));
+            }
+            found.extend(match_line(&line, &pattern, ignore_case));
+            if ended {
+                break;
+            }
+        }
+
+        found.sort_by_key(|(score, _)| Reverse(*score));
+        found
+    }
+}
+
+/// Returns the score and range of the best match of `pattern` in `line`, if there's any.
+fn match_line(
+    line: &[(usize, char)],
+    pattern: &[char],
+    ignore_case: bool,
+) -> Option<(i32, Range<usize>)> {
+    let eq = |c: char, p: char| c == p || (ignore_case && c.to_lowercase().eq([p]));
+
+    // The first place where all chars have been matched
+    let mut next = 0;
+    let end = line.iter().position(|&(_, c)| {
+        next += eq(c, pattern[next]) as usize;
+        next == pattern.len()
+    })?;
+
+    // The last place before that where the match can start
+    let mut remaining = pattern.len();
+    let start = line[..=end].iter().rposition(|&(_, c)| {
+        remaining -= eq(c, pattern[remaining - 1]) as usize;
+        remaining == 0
+    })?;
+
+    let mut score = 0;
+    let mut next = 0;
+    let mut in_gap = false;
+    let mut consecutive = 0;
+    let mut first_bonus = 0;
+    for idx in start..=end {
+        let c = line[idx].1;
+        if next < pattern.len() && eq(c, pattern[next]) {
+            let prev = idx.checked_sub(1).map(|prev| line[prev].1);
+            let mut bonus = bonus(prev, c);
+            if consecutive == 0 {
+                first_bonus = bonus;
+            } else {
+                if bonus >= BONUS_BOUNDARY {
+                    first_bonus = bonus;
+                }
+                bonus = bonus.max(first_bonus).max(BONUS_CONSECUTIVE);
+            }
+            score += SCORE_MATCH
+                + match next {
+                    0 => bonus * BONUS_FIRST_CHAR_MULTIPLIER,
+                    _ => bonus,
+                };
+            next += 1;
+            consecutive += 1;
+            in_gap = false;
+        } else {
+            score -= match in_gap {
+                true => PENALTY_GAP_EXTENSION,
+                false => PENALTY_GAP_START,
+            };
+            consecutive = 0;
+            in_gap = true;
+        }
+    }
+
+    let (last_offset, last) = line[end];
+    Some((score, line[start].0..last_offset + last.len_utf8()))
+}
+
+/// Returns the bonus for matching `c`, which comes after `prev`, or starts the line.
+fn bonus(prev: Option<char>, c: char) -> i32 {
+    if !c.is_alphanumeric() {
+        return 0;
+    }
+    match prev {
+        None => BONUS_BOUNDARY,
+        Some(prev) if !prev.is_alphanumeric() => BONUS_BOUNDARY,
+        Some(prev) if prev.is_lowercase() && c.is_uppercase() => BONUS_CAMEL,
+        Some(prev) if !prev.is_numeric() && c.is_numeric() => BONUS_CAMEL,
+        Some(_) => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matched(piece_table: &PieceTable, pattern: &str) -> Vec<String> {
+        let text = piece_table.to_string();
+        piece_table
+            .fuzzy_find(pattern)
+            .into_iter()
+            .map(|(_, range)| text[range].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn matches_across_pieces() {
+        let mut piece_table = PieceTable::new("hllo\nwö");
+        piece_table.insert("rld wo", piece_table.len());
+        piece_table.insert_char(1, 'é');
+
+        assert_eq!(vec!["wörld w"], matched(&piece_table, "wö w"));
+        assert_eq!(vec!["hé"], matched(&piece_table, "hé"));
+        assert!(piece_table.fuzzy_find("wörld\n").is_empty());
+        assert!(piece_table.fuzzy_find("").is_empty());
+    }
+
+    #[test]
+    fn shortest_span_is_matched() {
+        let piece_table = PieceTable::new("a_a_b_c");
+
+        assert_eq!(vec!["a_b_c"], matched(&piece_table, "abc"));
+    }
+
+    #[test]
+    fn smart_case() {
+        let piece_table = PieceTable::new("FooBar\nfoobar");
+
+        assert_eq!(vec!["FooB", "foob"], matched(&piece_table, "foob"));
+        assert_eq!(vec!["FooB"], matched(&piece_table, "FooB"));
+    }
+
+    #[test]
+    fn boundaries_and_runs_score_higher() {
+        let piece_table = PieceTable::new("xfxoxo\nfoo\nfrobnicate_options\nFrobOptions");
+
+        assert_eq!(
+            vec!["foo", "FrobO", "frobnicate_o", "fxoxo"],
+            matched(&piece_table, "foo")
+        );
+    }
+}
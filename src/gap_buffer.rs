@@ -0,0 +1,203 @@
+use crate::interface::EditableText;
+use std::fmt;
+use std::ops::Range;
+
+/// The minimum size of the gap after the buffer grows
+const MIN_GAP: usize = 64;
+
+/// A gap buffer: a single byte buffer with a movable "gap" of unused space.
+///
+/// The text lives in `buffer[..gap.start]` followed by `buffer[gap.end..]`. Edits move the gap
+/// to the edit position first, after which inserting is just filling the gap and deleting is just
+/// widening it. This makes consecutive edits at nearby positions (the typical typing pattern)
+/// very cheap, while edits far apart pay for moving the text in between.
+pub struct GapBuffer {
+    buffer: Vec<u8>,
+    gap: Range<usize>,
+}
+
+impl GapBuffer {
+    /// Returns the length of the text in bytes.
+    pub fn len(&self) -> usize {
+        self.buffer.len() - self.gap.len()
+    }
+
+    /// Checks if the buffer contains no text.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Moves the gap so that it starts at `offset`.
+    fn move_gap(&mut self, offset: usize) {
+        let gap_len = self.gap.len();
+        if offset < self.gap.start {
+            // Move the text between `offset` and the gap to after the gap
+            self.buffer
+                .copy_within(offset..self.gap.start, offset + gap_len);
+        } else if offset > self.gap.start {
+            // Move the text between the gap and `offset` to before the gap
+            self.buffer
+                .copy_within(self.gap.end..offset + gap_len, self.gap.start);
+        }
+        self.gap = offset..offset + gap_len;
+    }
+
+    /// Makes sure the gap is at least `needed` bytes long, reallocating if needed.
+    fn reserve(&mut self, needed: usize) {
+        if self.gap.len() >= needed {
+            return;
+        }
+
+        let len = self.len();
+        let new_gap_len = (len + needed).max(needed + MIN_GAP);
+        let mut buffer = Vec::with_capacity(len + new_gap_len);
+        buffer.extend_from_slice(&self.buffer[..self.gap.start]);
+        buffer.resize(self.gap.start + new_gap_len, 0);
+        buffer.extend_from_slice(&self.buffer[self.gap.end..]);
+
+        self.buffer = buffer;
+        self.gap = self.gap.start..self.gap.start + new_gap_len;
+    }
+}
+
+impl EditableText<'_> for GapBuffer {
+    fn new(string: &str) -> Self {
+        let mut buffer = Vec::with_capacity(string.len() + MIN_GAP);
+        buffer.extend_from_slice(string.as_bytes());
+        buffer.resize(string.len() + MIN_GAP, 0);
+        GapBuffer {
+            buffer,
+            gap: string.len()..string.len() + MIN_GAP,
+        }
+    }
+
+    fn insert(&mut self, data: &str, offset: usize) {
+        self.reserve(data.len());
+        self.move_gap(offset);
+        let end = self.gap.start + data.len();
+        self.buffer[self.gap.start..end].copy_from_slice(data.as_bytes());
+        self.gap.start = end;
+    }
+
+    fn delete(&mut self, range: Range<usize>) {
+        if range.start >= range.end {
+            return;
+        }
+
+        self.move_gap(range.start);
+        self.gap.end += range.len();
+    }
+}
+
+impl fmt::Display for GapBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for part in [&self.buffer[..self.gap.start], &self.buffer[self.gap.end..]] {
+            f.write_str(std::str::from_utf8(part).map_err(|_| fmt::Error)?)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let string = "hello!";
+        let buffer = GapBuffer::new(string);
+
+        assert_eq!(string, buffer.to_string());
+    }
+
+    #[test]
+    fn insert_once() {
+        let original = "hello, ";
+        let mut buffer = GapBuffer::new(original);
+
+        let added = "world!";
+        buffer.insert(added, original.len());
+
+        assert_eq!(original.to_owned() + added, buffer.to_string());
+    }
+
+    #[test]
+    fn insert_once_middle() {
+        let mut buffer = GapBuffer::new("hello!");
+
+        buffer.insert(", world", 5);
+
+        assert_eq!("hello, world!", buffer.to_string());
+    }
+
+    #[test]
+    fn insert_once_start() {
+        let mut buffer = GapBuffer::new("bc");
+
+        buffer.insert("a", 0);
+
+        assert_eq!("abc", buffer.to_string());
+    }
+
+    #[test]
+    fn insert_moves_gap_back_and_forth() {
+        let mut buffer = GapBuffer::new("ace");
+
+        buffer.insert("d", 2);
+        buffer.insert("b", 1);
+        buffer.insert("f", 5);
+
+        assert_eq!("abcdef", buffer.to_string());
+    }
+
+    #[test]
+    fn insert_larger_than_gap() {
+        let mut buffer = GapBuffer::new("ab");
+        let large = "x".repeat(MIN_GAP * 3);
+
+        buffer.insert(&large, 1);
+
+        assert_eq!(format!("a{large}b"), buffer.to_string());
+        assert_eq!(large.len() + 2, buffer.len());
+    }
+
+    #[test]
+    fn delete_original_whole() {
+        let mut buffer = GapBuffer::new("ab");
+
+        buffer.delete(0..2);
+
+        assert_eq!("", buffer.to_string());
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn delete_original_middle() {
+        let mut buffer = GapBuffer::new("abc");
+
+        buffer.delete(1..2);
+
+        assert_eq!("ac", buffer.to_string());
+    }
+
+    #[test]
+    fn delete_after_gap() {
+        let mut buffer = GapBuffer::new("abcdef");
+        buffer.insert("x", 1);
+
+        buffer.delete(4..6);
+
+        assert_eq!("axbcf", buffer.to_string());
+    }
+
+    #[test]
+    fn add_delete_add() {
+        let mut buffer = GapBuffer::new("ab");
+
+        buffer.insert("c", 2);
+        buffer.delete(0..3);
+        buffer.insert("ab", 0);
+
+        assert_eq!("ab", buffer.to_string());
+    }
+}
@@ -0,0 +1,186 @@
+//! Moving and deleting by grapheme cluster, i.e. by what a user sees as a single character.
+//!
+//! An emoji with a skin tone, a flag or a letter with combining accents is made of several
+//! chars, and backspace should remove all of them at once. Boundaries are found with
+//! [`GraphemeCursor`], which is fed the pieces one at a time, so a cluster split across pieces
+//! needs no copying.
+
+use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete};
+
+use crate::PieceTable;
+
+impl PieceTable<'_> {
+    /// Returns the end of the grapheme cluster starting at `offset`, or `None` if `offset` is
+    /// the end of the document.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let pt = PieceTable::new("e\u{301}!");
+    /// assert_eq!(pt.next_grapheme_boundary(0), Some(3));
+    /// assert_eq!(pt.next_grapheme_boundary(4), None);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is out of bounds, or isn't on a char boundary.
+    pub fn next_grapheme_boundary(&self, offset: usize) -> Option<usize> {
+        if let Err(err) = self.check_offset(offset) {
+            panic!("invalid next_grapheme_boundary: {err}");
+        }
+        let mut graphemes = GraphemeCursor::new(offset, self.len, true);
+        let mut chunks = self.chunk_cursor(offset);
+        loop {
+            let (chunk, range) = chunks.chunk().unwrap_or(("", offset..offset));
+            match graphemes.next_boundary(chunk, range.start) {
+                Ok(boundary) => return boundary,
+                Err(GraphemeIncomplete::NextChunk) => {
+                    chunks.move_next();
+                }
+                Err(GraphemeIncomplete::PreContext(end)) => {
+                    self.provide_context(&mut graphemes, end)
+                }
+                Err(err) => unreachable!("unexpected {err:?} looking for the next boundary"),
+            }
+        }
+    }
+
+    /// Returns the start of the grapheme cluster ending at `offset`, or `None` if `offset` is
+    /// 0.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is out of bounds, or isn't on a char boundary.
+    pub fn prev_grapheme_boundary(&self, offset: usize) -> Option<usize> {
+        if let Err(err) = self.check_offset(offset) {
+            panic!("invalid prev_grapheme_boundary: {err}");
+        }
+        let mut graphemes = GraphemeCursor::new(offset, self.len, true);
+        let mut chunks = self.chunk_cursor(offset);
+        loop {
+            let (chunk, range) = chunks.chunk().unwrap_or(("", offset..offset));
+            match graphemes.prev_boundary(chunk, range.start) {
+                Ok(boundary) => return boundary,
+                Err(GraphemeIncomplete::PrevChunk) => {
+                    chunks.move_prev();
+                }
+                Err(GraphemeIncomplete::PreContext(end)) => {
+                    self.provide_context(&mut graphemes, end)
+                }
+                Err(err) => unreachable!("unexpected {err:?} looking for the previous boundary"),
+            }
+        }
+    }
+
+    /// Deletes the grapheme cluster ending at `offset`, like backspace, and returns where the
+    /// cursor goes, i.e. the start of the deleted cluster. Nothing is deleted at offset 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// // A family emoji, made of 3 people joined by zero width joiners
+    /// let mut pt = PieceTable::new("hi 👩\u{200D}👩\u{200D}👦");
+    /// let end = pt.len();
+    /// assert_eq!(pt.delete_backward(end), 3);
+    /// assert_eq!(pt.to_string(), "hi ");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is out of bounds, or isn't on a char boundary.
+    pub fn delete_backward(&mut self, offset: usize) -> usize {
+        let Some(start) = self.prev_grapheme_boundary(offset) else {
+            return offset;
+        };
+        self.delete(start..offset);
+        start
+    }
+
+    /// Deletes the grapheme cluster starting at `offset`, like the delete key, and returns where
+    /// the cursor goes, which is `offset` itself. Nothing is deleted at the end of the document.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is out of bounds, or isn't on a char boundary.
+    pub fn delete_forward(&mut self, offset: usize) -> usize {
+        if let Some(end) = self.next_grapheme_boundary(offset) {
+            self.delete(offset..end);
+        }
+        offset
+    }
+
+    /// Gives `graphemes` the text of the piece ending at `end`, which it asked for to tell
+    /// whether there's a boundary at `end`.
+    fn provide_context(&self, graphemes: &mut GraphemeCursor, end: usize) {
+        let (chunk, range) = self
+            .chunk_cursor(end - 1)
+            .chunk()
+            .expect("context is only asked for before the end of a piece");
+        graphemes.provide_context(&chunk[..end - range.start], range.start);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use unicode_segmentation::UnicodeSegmentation;
+
+    use super::*;
+
+    /// Builds a table holding `text`, with a piece boundary after every char.
+    fn split_everywhere(text: &str) -> PieceTable<'_> {
+        let mut piece_table = PieceTable::new(text);
+        for (offset, _) in text.char_indices().skip(1) {
+            piece_table.split_at_offset(offset);
+        }
+        piece_table
+    }
+
+    #[test]
+    fn boundaries_across_pieces() {
+        let text = "a\r\n🇫🇷e\u{301}\u{302}👍🏽!";
+        let piece_table = split_everywhere(text);
+        assert_eq!(text.chars().count(), piece_table.nodes.len());
+
+        let mut expected: Vec<usize> = text.grapheme_indices(true).map(|(idx, _)| idx).collect();
+        expected.push(text.len());
+        for pair in expected.windows(2) {
+            assert_eq!(Some(pair[1]), piece_table.next_grapheme_boundary(pair[0]));
+            assert_eq!(Some(pair[0]), piece_table.prev_grapheme_boundary(pair[1]));
+        }
+        assert_eq!(None, piece_table.next_grapheme_boundary(text.len()));
+        assert_eq!(None, piece_table.prev_grapheme_boundary(0));
+    }
+
+    #[test]
+    fn delete_whole_clusters() {
+        let mut piece_table = split_everywhere("x👍🏽\r\ne\u{301}y");
+        let end = piece_table.len();
+
+        assert_eq!(11, piece_table.delete_backward(end - 1));
+        assert_eq!("x👍🏽\r\ny", piece_table.to_string());
+        assert_eq!(1, piece_table.delete_forward(1));
+        assert_eq!("x\r\ny", piece_table.to_string());
+        assert_eq!(1, piece_table.delete_backward(3));
+        assert_eq!("xy", piece_table.to_string());
+    }
+
+    #[test]
+    fn empty_document() {
+        let mut piece_table = PieceTable::new("");
+
+        assert_eq!(0, piece_table.delete_backward(0));
+        assert_eq!(0, piece_table.delete_forward(0));
+        assert_eq!(None, piece_table.next_grapheme_boundary(0));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "invalid prev_grapheme_boundary: offset 5 is out of bounds of document of length 3"
+    )]
+    fn boundary_out_of_bounds() {
+        let piece_table = PieceTable::new("e\u{301}");
+        piece_table.prev_grapheme_boundary(5);
+    }
+}
@@ -0,0 +1,453 @@
+//! Named checkpoints of a `PieceTable`'s state.
+//!
+//! Since text is never removed from the buffers of a `PieceTable`, the state of the document at
+//! any point in time is fully described by its list of pieces. Saving a checkpoint only copies
+//! that list, and reverting to it just puts it back, no matter how much text was edited since.
+//!
+//! Each checkpoint still costs a copy of the list of pieces, so a long session which saves one
+//! after every few edits can set a budget. Once there are more checkpoints than that, the ones
+//! which changed the least since the checkpoint before them are dropped, which merges runs of
+//! small edits into a single step, while pinned checkpoints are always kept.
+
+use std::collections::VecDeque;
+
+use crate::workload::Edit;
+use crate::{Node, PieceTable};
+
+/// The state of a `PieceTable` when a checkpoint was made.
+#[derive(Debug, Clone)]
+pub(crate) struct Checkpoint {
+    pub(crate) nodes: VecDeque<Node>,
+    len: usize,
+    newlines: usize,
+    chars: usize,
+    pinned: bool,
+    /// Orders the checkpoints by when they were last saved, since saving one again under the
+    /// same name keeps its place in the list
+    saved: u64,
+}
+
+impl PieceTable<'_> {
+    /// Saves the current state under `name`, replacing any checkpoint with the same name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello");
+    /// pt.checkpoint("saved");
+    /// pt.insert(" world", 5);
+    /// pt.delete(0..1);
+    ///
+    /// assert!(pt.revert_to("saved"));
+    /// assert_eq!(pt.to_string(), "hello");
+    /// ```
+    pub fn checkpoint(&mut self, name: impl Into<String>) {
+        let name = name.into();
+        let checkpoint = Checkpoint {
+            nodes: self.nodes.clone(),
+            len: self.len,
+            newlines: self.newlines,
+            chars: self.chars,
+            pinned: false,
+            saved: self
+                .checkpoints
+                .iter()
+                .map(|(_, checkpoint)| checkpoint.saved + 1)
+                .max()
+                .unwrap_or(0),
+        };
+        match self.checkpoints.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, existing)) => {
+                *existing = Checkpoint {
+                    pinned: existing.pinned,
+                    ..checkpoint
+                }
+            }
+            None => self.checkpoints.push((name, checkpoint)),
+        }
+        self.compact_checkpoints();
+    }
+
+    /// Restores the state saved under `name`, returning `false` if there is no such checkpoint.
+    ///
+    /// All checkpoints are kept, including those made after `name`, so it's possible to revert
+    /// back and forth between them.
+    pub fn revert_to(&mut self, name: &str) -> bool {
+        let Some(checkpoint) = self.find_checkpoint(name) else {
+            return false;
+        };
+
+        let Checkpoint {
+            nodes,
+            len,
+            newlines,
+            chars,
+            pinned: _,
+            saved: _,
+        } = checkpoint.clone();
+        self.nodes = nodes;
+        self.pieces_changed();
+        self.len = len;
+        self.newlines = newlines;
+        self.chars = chars;
+        self.stream.clamp(len);
+        self.publish();
+        true
+    }
+
+    /// Returns the edits which turn the state saved under `name` into the current one, or
+    /// `None` if there is no such checkpoint.
+    ///
+    /// The edits cover a single changed region: everything between the first and the last byte
+    /// that differ is deleted and re-inserted. Pieces which weren't touched since the
+    /// checkpoint are skipped without looking at their text.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// # use piece_table::workload::Edit;
+    /// let mut pt = PieceTable::new("hello world");
+    /// pt.checkpoint("saved");
+    /// pt.insert(",", 5);
+    ///
+    /// assert_eq!(
+    ///     pt.diff_since("saved"),
+    ///     Some(vec![Edit::Insert { offset: 5, text: ",".to_string() }])
+    /// );
+    /// ```
+    pub fn diff_since(&self, name: &str) -> Option<Vec<Edit>> {
+        let old = &self.find_checkpoint(name)?.nodes;
+        Some(self.diff_nodes(old, &self.nodes))
+    }
+
+    /// Returns the edits which turn the text of `old` into the text of `new`, see
+    /// [`PieceTable::diff_since`].
+    fn diff_nodes(&self, old: &VecDeque<Node>, new: &VecDeque<Node>) -> Vec<Edit> {
+        let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+        let suffix = old
+            .iter()
+            .rev()
+            .zip(new.iter().rev())
+            .take(old.len().min(new.len()) - prefix)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let start: usize = old.range(..prefix).map(|node| node.range.len()).sum();
+        let text = |nodes: &VecDeque<Node>| -> String {
+            nodes
+                .range(prefix..nodes.len() - suffix)
+                .flat_map(|node| node.chunks(self.original, &self.added))
+                .collect()
+        };
+        let (old_text, new_text) = (text(old), text(new));
+
+        // The pieces around the change usually share some text too
+        let common_prefix: usize = old_text
+            .chars()
+            .zip(new_text.chars())
+            .take_while(|(a, b)| a == b)
+            .map(|(c, _)| c.len_utf8())
+            .sum();
+        let (old_rest, new_rest) = (&old_text[common_prefix..], &new_text[common_prefix..]);
+        let common_suffix: usize = old_rest
+            .chars()
+            .rev()
+            .zip(new_rest.chars().rev())
+            .take_while(|(a, b)| a == b)
+            .map(|(c, _)| c.len_utf8())
+            .sum::<usize>()
+            .min(old_rest.len().min(new_rest.len()));
+        let deleted = &old_rest[..old_rest.len() - common_suffix];
+        let inserted = &new_rest[..new_rest.len() - common_suffix];
+
+        let offset = start + common_prefix;
+        let mut edits = Vec::new();
+        if !deleted.is_empty() {
+            edits.push(Edit::Delete {
+                offset,
+                len: deleted.len(),
+            });
+        }
+        if !inserted.is_empty() {
+            edits.push(Edit::Insert {
+                offset,
+                text: inserted.to_string(),
+            });
+        }
+        edits
+    }
+
+    /// Removes the checkpoint saved under `name`, returning `false` if there is no such
+    /// checkpoint.
+    pub fn remove_checkpoint(&mut self, name: &str) -> bool {
+        let len = self.checkpoints.len();
+        self.checkpoints.retain(|(n, _)| n != name);
+        self.checkpoints.len() != len
+    }
+
+    /// Returns the names of all checkpoints, in the order they were first made.
+    pub fn checkpoints(&self) -> impl Iterator<Item = &str> {
+        self.checkpoints.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Keeps at most `budget` checkpoints which aren't pinned, or any number if it's `None`.
+    ///
+    /// Whenever there are more, the checkpoint with the fewest bytes changed since the one
+    /// saved before it (or since the empty document, for the first one) is dropped, until the
+    /// budget is met. Checkpoints without any changes are dropped first, and the most recently
+    /// saved checkpoint is always kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("");
+    /// pt.checkpoint("empty");
+    /// pt.pin_checkpoint("empty");
+    /// pt.set_checkpoint_budget(Some(2));
+    ///
+    /// pt.insert("fn main() {}", 0);
+    /// pt.checkpoint("typed");
+    /// pt.insert_char(11, ' ');
+    /// pt.checkpoint("space");
+    /// pt.insert("x", 1);
+    /// pt.checkpoint("typo");
+    ///
+    /// // Adding the space was the smallest step, so it was merged into the next one
+    /// assert_eq!(pt.checkpoints().collect::<Vec<_>>(), ["empty", "typed", "typo"]);
+    /// ```
+    pub fn set_checkpoint_budget(&mut self, budget: Option<usize>) {
+        self.checkpoint_budget = budget;
+        self.compact_checkpoints();
+    }
+
+    /// Returns the most unpinned checkpoints kept, see [`PieceTable::set_checkpoint_budget`].
+    pub fn checkpoint_budget(&self) -> Option<usize> {
+        self.checkpoint_budget
+    }
+
+    /// Keeps the checkpoint saved under `name` from being dropped to meet the checkpoint
+    /// budget, returning `false` if there is no such checkpoint.
+    ///
+    /// A pinned checkpoint stays pinned when it's replaced by a checkpoint with the same name.
+    pub fn pin_checkpoint(&mut self, name: &str) -> bool {
+        self.set_pinned(name, true)
+    }
+
+    /// Lets the checkpoint saved under `name` be dropped to meet the checkpoint budget again,
+    /// returning `false` if there is no such checkpoint.
+    pub fn unpin_checkpoint(&mut self, name: &str) -> bool {
+        let found = self.set_pinned(name, false);
+        self.compact_checkpoints();
+        found
+    }
+
+    fn set_pinned(&mut self, name: &str, pinned: bool) -> bool {
+        match self.checkpoints.iter_mut().find(|(n, _)| n == name) {
+            Some((_, checkpoint)) => {
+                checkpoint.pinned = pinned;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drops checkpoints until the budget is met, see [`PieceTable::set_checkpoint_budget`].
+    fn compact_checkpoints(&mut self) {
+        let Some(budget) = self.checkpoint_budget else {
+            return;
+        };
+        let mut unpinned = self.checkpoints.iter().filter(|(_, c)| !c.pinned).count();
+
+        let empty = VecDeque::new();
+        while unpinned > budget {
+            // The steps between checkpoints, in the order they were saved
+            let mut by_save: Vec<usize> = (0..self.checkpoints.len()).collect();
+            by_save.sort_unstable_by_key(|&idx| self.checkpoints[idx].1.saved);
+            let smallest = (0..by_save.len() - 1)
+                .filter(|&step| !self.checkpoints[by_save[step]].1.pinned)
+                .min_by_key(|&step| {
+                    let before = match step.checked_sub(1) {
+                        Some(prev) => &self.checkpoints[by_save[prev]].1.nodes,
+                        None => &empty,
+                    };
+                    let edits = self.diff_nodes(before, &self.checkpoints[by_save[step]].1.nodes);
+                    edits
+                        .iter()
+                        .map(|edit| match edit {
+                            Edit::Insert { text, .. } => text.len(),
+                            Edit::Delete { len, .. } => *len,
+                        })
+                        .sum::<usize>()
+                });
+            let Some(step) = smallest else {
+                break;
+            };
+            self.checkpoints.remove(by_save[step]);
+            unpinned -= 1;
+        }
+    }
+
+    pub(crate) fn find_checkpoint(&self, name: &str) -> Option<&Checkpoint> {
+        self.checkpoints
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, checkpoint)| checkpoint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workload::{Profile, generate};
+
+    #[test]
+    fn revert_back_and_forth() {
+        let mut piece_table = PieceTable::new("one\ntwo");
+        piece_table.checkpoint("start");
+        piece_table.insert("\nthree", 7);
+        piece_table.checkpoint("three");
+        piece_table.delete(0..4);
+
+        assert!(piece_table.revert_to("start"));
+        assert_eq!("one\ntwo", piece_table.to_string());
+        assert_eq!(2, piece_table.len_lines());
+
+        assert!(piece_table.revert_to("three"));
+        assert_eq!("one\ntwo\nthree", piece_table.to_string());
+        assert_eq!(3, piece_table.len_lines());
+    }
+
+    #[test]
+    fn unknown_checkpoint() {
+        let mut piece_table = PieceTable::new("abc");
+
+        assert!(!piece_table.revert_to("nope"));
+        assert_eq!(None, piece_table.diff_since("nope"));
+        assert!(!piece_table.remove_checkpoint("nope"));
+    }
+
+    #[test]
+    fn checkpoint_replaces_same_name() {
+        let mut piece_table = PieceTable::new("abc");
+        piece_table.checkpoint("a");
+        piece_table.checkpoint("b");
+        piece_table.insert("d", 3);
+        piece_table.checkpoint("a");
+
+        assert_eq!(
+            vec!["a", "b"],
+            piece_table.checkpoints().collect::<Vec<_>>()
+        );
+        assert_eq!(Some(Vec::new()), piece_table.diff_since("a"));
+        assert!(piece_table.remove_checkpoint("a"));
+        assert_eq!(vec!["b"], piece_table.checkpoints().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn diff_is_trimmed_to_changed_text() {
+        let mut piece_table = PieceTable::new("hello world");
+        piece_table.checkpoint("saved");
+        piece_table.delete(6..11);
+        piece_table.insert("there", 6);
+        piece_table.delete(6..7);
+        piece_table.insert("w", 6);
+
+        assert_eq!(
+            Some(vec![
+                Edit::Delete { offset: 7, len: 4 },
+                Edit::Insert {
+                    offset: 7,
+                    text: "here".to_string()
+                },
+            ]),
+            piece_table.diff_since("saved")
+        );
+    }
+
+    #[test]
+    fn diff_applied_to_checkpoint_gives_current_state() {
+        let initial = "the piece table stores the text in pieces\n";
+        for seed in 0..8 {
+            let mut piece_table = PieceTable::new(initial);
+            generate(seed, Profile::Typing, initial, 20).replay(&mut piece_table);
+            piece_table.checkpoint("saved");
+            let saved = piece_table.clone();
+            generate(seed + 100, Profile::Deleting, &saved.to_string(), 20)
+                .replay(&mut piece_table);
+
+            let mut replayed = saved;
+            for edit in piece_table.diff_since("saved").unwrap() {
+                edit.apply(&mut replayed);
+            }
+
+            assert_eq!(piece_table.to_string(), replayed.to_string());
+        }
+    }
+
+    #[test]
+    fn budget_drops_smallest_steps_first() {
+        let mut piece_table = PieceTable::new("start\n");
+        piece_table.set_checkpoint_budget(Some(3));
+        piece_table.checkpoint("0");
+        piece_table.insert("a long line of text\n", 6);
+        piece_table.checkpoint("1");
+        piece_table.checkpoint("unchanged");
+        piece_table.insert("x", 0);
+        piece_table.checkpoint("2");
+        piece_table.delete(0..7);
+        piece_table.checkpoint("3");
+
+        assert_eq!(
+            vec!["0", "1", "3"],
+            piece_table.checkpoints().collect::<Vec<_>>()
+        );
+        assert!(piece_table.revert_to("1"));
+        assert_eq!("start\na long line of text\n", piece_table.to_string());
+    }
+
+    #[test]
+    fn pinned_checkpoints_are_kept() {
+        let mut piece_table = PieceTable::new("abc");
+        piece_table.checkpoint("pinned");
+        assert!(piece_table.pin_checkpoint("pinned"));
+        piece_table.checkpoint("a");
+        piece_table.insert("d", 3);
+        piece_table.checkpoint("b");
+
+        piece_table.set_checkpoint_budget(Some(0));
+        assert_eq!(
+            vec!["pinned", "b"],
+            piece_table.checkpoints().collect::<Vec<_>>()
+        );
+
+        // Replacing a pinned checkpoint keeps it pinned
+        piece_table.checkpoint("pinned");
+        piece_table.checkpoint("c");
+        assert_eq!(
+            vec!["pinned", "c"],
+            piece_table.checkpoints().collect::<Vec<_>>()
+        );
+        assert!(piece_table.unpin_checkpoint("pinned"));
+        assert_eq!(vec!["c"], piece_table.checkpoints().collect::<Vec<_>>());
+        assert!(!piece_table.pin_checkpoint("nope"));
+    }
+
+    #[test]
+    fn budget_keeps_the_checkpoint_saved_last() {
+        let mut piece_table = PieceTable::new("abc");
+        piece_table.checkpoint("a");
+        piece_table.insert("d", 3);
+        piece_table.checkpoint("p");
+        piece_table.pin_checkpoint("p");
+        piece_table.insert("e", 4);
+        piece_table.checkpoint("a");
+
+        piece_table.set_checkpoint_budget(Some(1));
+        assert!(piece_table.unpin_checkpoint("p"));
+        assert_eq!(vec!["a"], piece_table.checkpoints().collect::<Vec<_>>());
+        assert!(piece_table.revert_to("a"));
+        assert_eq!("abcde", piece_table.to_string());
+    }
+}
@@ -0,0 +1,117 @@
+//! Counters of the work done inside a `PieceTable`, for checking algorithmic changes against
+//! real workloads.
+//!
+//! Enabled by the `instrument` feature. Without it none of the counting code is compiled in, so
+//! it has no cost.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::PieceTable;
+
+/// The work done by a `PieceTable` since it was created, or since the counters were last reset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// The number of times a piece was looked up by offset.
+    pub find_node_calls: usize,
+    /// The number of pieces visited by those lookups, by lookups of lines, and by building the
+    /// cached offsets of the pieces which make later lookups binary searches.
+    pub nodes_visited: usize,
+    /// The number of pieces split in two.
+    pub splits: usize,
+    /// The number of insertions which extended an existing piece, see
+    /// [`CoalescePolicy`](crate::CoalescePolicy).
+    pub merges: usize,
+    /// The number of times the list of pieces or the "added" buffer had to grow.
+    pub allocations: usize,
+}
+
+/// The live counters, which are atomic so that counting works through `&self`.
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    pub(crate) find_node_calls: AtomicUsize,
+    pub(crate) nodes_visited: AtomicUsize,
+    pub(crate) splits: AtomicUsize,
+    pub(crate) merges: AtomicUsize,
+    pub(crate) allocations: AtomicUsize,
+}
+
+impl Counters {
+    fn get(&self) -> Metrics {
+        let load = |counter: &AtomicUsize| counter.load(Ordering::Relaxed);
+        Metrics {
+            find_node_calls: load(&self.find_node_calls),
+            nodes_visited: load(&self.nodes_visited),
+            splits: load(&self.splits),
+            merges: load(&self.merges),
+            allocations: load(&self.allocations),
+        }
+    }
+}
+
+/// Clones start counting from zero.
+impl Clone for Counters {
+    fn clone(&self) -> Self {
+        Counters::default()
+    }
+}
+
+impl PieceTable<'_> {
+    /// Returns the work done since the table was created, or since the last call to
+    /// [`PieceTable::reset_metrics`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello world");
+    /// pt.insert(",", 5);
+    /// assert_eq!(pt.metrics().splits, 1);
+    ///
+    /// pt.reset_metrics();
+    /// pt.insert("!", 12);
+    /// assert_eq!(pt.metrics().splits, 0);
+    /// ```
+    pub fn metrics(&self) -> Metrics {
+        self.counters.get()
+    }
+
+    /// Resets all counters to zero, returning their values from before.
+    pub fn reset_metrics(&mut self) -> Metrics {
+        std::mem::take(&mut self.counters).get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CoalescePolicy;
+
+    #[test]
+    fn counts_splits_and_merges() {
+        let mut piece_table = PieceTable::new("abcdef");
+        piece_table.insert("x", 3);
+        piece_table.insert("y", 4);
+        piece_table.delete(1..2);
+
+        let metrics = piece_table.reset_metrics();
+        assert_eq!(3, metrics.splits);
+        assert_eq!(1, metrics.merges);
+        assert!(metrics.nodes_visited >= metrics.find_node_calls);
+        assert_eq!(Metrics::default(), piece_table.metrics());
+    }
+
+    #[test]
+    fn typing_without_coalescing_splits_nothing() {
+        let mut piece_table = PieceTable::new("");
+        piece_table.set_coalesce_policy(CoalescePolicy::Off);
+        for (i, c) in "hello".chars().enumerate() {
+            piece_table.insert_char(i, c);
+        }
+
+        let metrics = piece_table.metrics();
+        assert_eq!(0, metrics.splits);
+        assert_eq!(0, metrics.merges);
+        assert!(metrics.find_node_calls >= 5);
+        assert!(metrics.allocations > 0);
+    }
+}
@@ -1,5 +1,10 @@
 // TODO move TextRange definition and impl to here
 use std::ops::Range;
+#[cfg(any(test, feature = "test-utils"))]
+use std::{fmt, fmt::Display};
+
+#[cfg(any(test, feature = "test-utils"))]
+use crate::workload::{Edit, Workload};
 
 pub trait EditableText<'a> {
     fn new(string: &'a str) -> Self;
@@ -8,3 +13,175 @@ pub trait EditableText<'a> {
 
     fn delete(&mut self, range: Range<usize>);
 }
+
+/// How many bytes of text on each side of a divergence are kept for context.
+#[cfg(any(test, feature = "test-utils"))]
+const CONTEXT: usize = 16;
+
+/// Where two backends replaying the same workload first disagreed, see [`check_equivalence`].
+#[cfg(any(test, feature = "test-utils"))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// The index of the edit after which the texts differed, and the edit itself, or `None` if
+    /// they differed before any edit.
+    pub after: Option<(usize, Edit)>,
+    /// The first byte at which the texts differ.
+    pub offset: usize,
+    /// The text of each backend around `offset`.
+    pub context: (String, String),
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.after {
+            Some((idx, edit)) => write!(f, "after edit {idx} ({edit:?}), ")?,
+            None => write!(f, "before any edit, ")?,
+        }
+        write!(
+            f,
+            "texts differ at byte {}: {:?} vs {:?}",
+            self.offset, self.context.0, self.context.1
+        )
+    }
+}
+
+/// Replays `workload` against a new `A` and a new `B` made from `initial_text`, comparing their
+/// text after every edit, and returns where they first disagree.
+///
+/// This is exported behind the `test-utils` feature, for checking a new backend against an
+/// existing one.
+///
+/// # Examples
+///
+/// ```
+/// # use piece_table::PieceTable;
+/// use piece_table::gap_buffer::GapBuffer;
+/// use piece_table::interface::check_equivalence;
+/// use piece_table::workload::{Profile, generate};
+///
+/// let workload = generate(7, Profile::PasteHeavy, "hello", 50);
+/// assert_eq!(
+///     check_equivalence::<PieceTable, GapBuffer>("hello", &workload),
+///     Ok(())
+/// );
+/// ```
+#[cfg(any(test, feature = "test-utils"))]
+pub fn check_equivalence<'a, A, B>(
+    initial_text: &'a str,
+    workload: &Workload,
+) -> Result<(), Divergence>
+where
+    A: EditableText<'a> + Display,
+    B: EditableText<'a> + Display,
+{
+    let mut a = A::new(initial_text);
+    let mut b = B::new(initial_text);
+    compare(&a, &b, None)?;
+
+    for (idx, edit) in workload.edits.iter().enumerate() {
+        edit.apply(&mut a);
+        edit.apply(&mut b);
+        compare(&a, &b, Some((idx, edit)))?;
+    }
+    Ok(())
+}
+
+/// Compares the texts of `a` and `b`, which have just had `after` applied to them.
+#[cfg(any(test, feature = "test-utils"))]
+fn compare(
+    a: &impl Display,
+    b: &impl Display,
+    after: Option<(usize, &Edit)>,
+) -> Result<(), Divergence> {
+    let (a, b) = (a.to_string(), b.to_string());
+    if a == b {
+        return Ok(());
+    }
+    let offset = a
+        .bytes()
+        .zip(b.bytes())
+        .position(|(x, y)| x != y)
+        .unwrap_or(a.len().min(b.len()));
+    let around = |text: &str| {
+        let mut start = offset.saturating_sub(CONTEXT);
+        while !text.is_char_boundary(start) {
+            start -= 1;
+        }
+        let mut end = (offset + CONTEXT).min(text.len());
+        while !text.is_char_boundary(end) {
+            end += 1;
+        }
+        text[start..end].to_string()
+    };
+    Err(Divergence {
+        after: after.map(|(idx, edit)| (idx, edit.clone())),
+        offset,
+        context: (around(&a), around(&b)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PieceTable;
+    use crate::baseline::Baseline;
+
+    /// A backend which loses the last byte of every deletion of more than one byte.
+    struct ShortDeletes(Baseline);
+
+    impl EditableText<'_> for ShortDeletes {
+        fn new(string: &str) -> Self {
+            ShortDeletes(Baseline::new(string))
+        }
+
+        fn insert(&mut self, data: &str, offset: usize) {
+            self.0.insert(data, offset);
+        }
+
+        fn delete(&mut self, range: Range<usize>) {
+            let end = if range.len() > 1 {
+                range.end - 1
+            } else {
+                range.end
+            };
+            self.0.delete(range.start..end);
+        }
+    }
+
+    impl Display for ShortDeletes {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.fmt(f)
+        }
+    }
+
+    #[test]
+    fn reports_first_divergence() {
+        let workload = Workload::parse("INSERT 0 ab\nDELETE 3 1\nDELETE 0 3").unwrap();
+        let divergence = check_equivalence::<PieceTable, ShortDeletes>("xyz", &workload);
+
+        assert_eq!(
+            Err(Divergence {
+                after: Some((2, Edit::Delete { offset: 0, len: 3 })),
+                offset: 0,
+                context: ("z".to_string(), "xz".to_string()),
+            }),
+            divergence
+        );
+        assert_eq!(
+            "after edit 2 (Delete { offset: 0, len: 3 }), texts differ at byte 0: \"z\" vs \"xz\"",
+            divergence.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn context_is_cut_on_char_boundaries() {
+        let a = "é".repeat(20);
+        let b = "é".repeat(10) + "e" + &"é".repeat(9);
+        let divergence = compare(&a, &b, None).unwrap_err();
+
+        assert_eq!(20, divergence.offset);
+        assert_eq!("é".repeat(16), divergence.context.0);
+        assert_eq!("é".repeat(8) + "e" + &"é".repeat(8), divergence.context.1);
+    }
+}
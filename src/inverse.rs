@@ -0,0 +1,255 @@
+//! Edits which return how to undo them, for applications keeping their own history.
+//!
+//! Text is never removed from the buffers of a `PieceTable`, so undoing a deletion only needs
+//! the pieces which covered the deleted text, not a copy of it. An [`InverseEdit`] holds those
+//! pieces and the length of the inserted text, which is all it takes to put the document back.
+
+use std::ops::Range;
+
+use crate::{Node, PieceTable, VALIDATE};
+
+/// How to undo an edit: delete the text it inserted at its offset, and put back the pieces it
+/// removed.
+///
+/// An `InverseEdit` refers to the buffers of the table which returned it, so it must only be
+/// applied to that table, or a clone of it, when its text is the one right after the edit, e.g.
+/// after undoing every later edit. Applying it in another state gives meaningless text, and
+/// applying it to a table with other buffers, e.g. another table or one whose buffers were
+/// replaced by a [compaction](crate::PieceTable::apply_compaction), panics.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InverseEdit {
+    offset: usize,
+    inserted: usize,
+    removed: Vec<Node>,
+    /// The buffers `removed` refers to
+    buffers: u64,
+}
+
+impl InverseEdit {
+    /// Returns the offset of the edit.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the number of bytes the edit inserted, which undoing it deletes.
+    pub fn inserted_len(&self) -> usize {
+        self.inserted
+    }
+
+    /// Returns the number of bytes the edit deleted, which undoing it inserts back.
+    pub fn removed_len(&self) -> usize {
+        self.removed.iter().map(|node| node.range.len()).sum()
+    }
+
+    /// Returns the range undoing the edit deletes.
+    fn inserted_range(&self) -> Range<usize> {
+        self.offset..self.offset + self.inserted
+    }
+}
+
+impl PieceTable<'_> {
+    /// Inserts `data` at `offset` like [`PieceTable::insert`], and returns how to undo it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello");
+    /// let undo = pt.insert_with_inverse(" world", 5);
+    /// assert_eq!(pt.to_string(), "hello world");
+    ///
+    /// let redo = pt.apply_inverse(undo);
+    /// assert_eq!(pt.to_string(), "hello");
+    /// pt.apply_inverse(redo);
+    /// assert_eq!(pt.to_string(), "hello world");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`PieceTable::insert`].
+    pub fn insert_with_inverse(&mut self, data: &str, offset: usize) -> InverseEdit {
+        let len = self.len;
+        self.insert(data, offset);
+        InverseEdit {
+            offset,
+            // Normalization may have changed the length of `data`
+            inserted: self.len - len,
+            removed: Vec::new(),
+            buffers: self.buffers,
+        }
+    }
+
+    /// Deletes `range` like [`PieceTable::delete`], and returns how to undo it.
+    ///
+    /// The deleted text isn't copied, the returned `InverseEdit` only keeps the pieces which
+    /// covered it.
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`PieceTable::delete`].
+    pub fn delete_with_inverse(&mut self, range: Range<usize>) -> InverseEdit {
+        let checked = if VALIDATE {
+            self.check_range(&range)
+        } else {
+            self.check_range_bounds(&range)
+        };
+        if let Err(err) = checked {
+            panic!("invalid delete: {err}");
+        }
+        if let Err(err) = self.check_delete_limits(&range) {
+            panic!("invalid delete: {err}");
+        }
+        let removed = self.take_nodes(range.clone());
+        self.publish();
+        InverseEdit {
+            offset: range.start,
+            inserted: 0,
+            removed,
+            buffers: self.buffers,
+        }
+    }
+
+    /// Replaces the text in `range` with `data`, and returns how to undo it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello world");
+    /// let undo = pt.replace_range_with_inverse(6..11, "there");
+    /// assert_eq!(pt.to_string(), "hello there");
+    /// assert_eq!((undo.inserted_len(), undo.removed_len()), (5, 5));
+    ///
+    /// pt.apply_inverse(undo);
+    /// assert_eq!(pt.to_string(), "hello world");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`PieceTable::delete`] and [`PieceTable::insert`], before
+    /// changing anything.
+    pub fn replace_range_with_inverse(&mut self, range: Range<usize>, data: &str) -> InverseEdit {
+        let checked = if VALIDATE {
+            self.check_range(&range)
+        } else {
+            self.check_range_bounds(&range)
+        };
+        if let Err(err) = checked {
+            panic!("invalid replace_range: {err}");
+        }
+        if let Err(err) = self
+            .check_delete_limits(&range)
+            .and_then(|()| self.check_insert_limits(range.start, data.len()))
+        {
+            panic!("invalid replace_range: {err}");
+        }
+        let removed = self.take_nodes(range.clone());
+        let len = self.len;
+        self.insert_unchecked(data, range.start);
+        InverseEdit {
+            offset: range.start,
+            inserted: self.len - len,
+            removed,
+            buffers: self.buffers,
+        }
+    }
+
+    /// Undoes the edit `inverse` was returned for, and returns how to redo it.
+    ///
+    /// Applying the returned `InverseEdit` puts the edit back, and returns one undoing it
+    /// again, so an application's history can move back and forth by swapping them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `inverse` was returned by a table with other buffers, or if the text it would
+    /// delete isn't in the document, which happens if it's applied in another state, see
+    /// [`InverseEdit`]. Panics if putting the removed pieces back
+    /// would exceed the table's limits.
+    pub fn apply_inverse(&mut self, inverse: InverseEdit) -> InverseEdit {
+        assert!(
+            inverse.buffers == self.buffers,
+            "invalid apply_inverse: the edit refers to buffers the table no longer uses"
+        );
+        let range = inverse.inserted_range();
+        if let Err(err) = self.check_range(&range) {
+            panic!("invalid apply_inverse: {err}");
+        }
+        let removed_len = inverse.removed_len();
+        if let Err(err) = self.check_growth(removed_len, 0, inverse.removed.len() + 2) {
+            panic!("invalid apply_inverse: {err}");
+        }
+
+        let removed = self.take_nodes(range);
+        self.insert_nodes(inverse.offset, inverse.removed);
+        self.publish();
+        InverseEdit {
+            offset: inverse.offset,
+            inserted: removed_len,
+            removed,
+            buffers: self.buffers,
+        }
+    }
+
+    /// Removes the pieces covering `range`, which must be valid, and returns them. The caller
+    /// still has to publish the change.
+    fn take_nodes(&mut self, range: Range<usize>) -> Vec<Node> {
+        if range.is_empty() {
+            return Vec::new();
+        }
+        let first = self.split_at_offset(range.start);
+        let last = self.split_at_offset(range.end);
+        let removed: Vec<Node> = self.nodes.drain(first..last).collect();
+        for node in &removed {
+            self.newlines -= node.newlines;
+            self.chars -= node.chars;
+        }
+        self.len -= range.len();
+        self.stream.on_delete(range);
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_and_redo_a_history() {
+        let mut piece_table = PieceTable::new("one two three");
+        let mut history = vec![
+            piece_table.delete_with_inverse(3..7),
+            piece_table.insert_with_inverse("\n2\n", 3),
+            piece_table.replace_range_with_inverse(0..3, "ONE"),
+        ];
+        assert_eq!("ONE\n2\n three", piece_table.to_string());
+
+        let mut redo = Vec::new();
+        while let Some(inverse) = history.pop() {
+            redo.push(piece_table.apply_inverse(inverse));
+            assert_eq!(Ok(()), piece_table.check_invariants());
+        }
+        assert_eq!("one two three", piece_table.to_string());
+        assert_eq!(1, piece_table.len_lines());
+
+        while let Some(inverse) = redo.pop() {
+            piece_table.apply_inverse(inverse);
+        }
+        assert_eq!("ONE\n2\n three", piece_table.to_string());
+        assert_eq!(3, piece_table.len_lines());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid apply_inverse")]
+    fn inverse_past_the_end_panics() {
+        let mut piece_table = PieceTable::new("abc");
+        let inverse = piece_table.insert_with_inverse("def", 3);
+        piece_table.delete(2..6);
+        piece_table.apply_inverse(inverse);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid delete: offset 50 is out of bounds of document of length 3")]
+    fn delete_past_the_end_panics() {
+        PieceTable::new("abc").delete_with_inverse(1..50);
+    }
+}
@@ -0,0 +1,235 @@
+//! Streaming the text of a `PieceTable` through [`std::io::Read`], and writing snapshots out.
+
+use std::io::{self, BufRead, Read, Write};
+
+use crate::{PTableSlice, PieceTable};
+
+impl<'ptable> PieceTable<'ptable> {
+    /// Returns a reader over the bytes of the text, starting at `offset`.
+    ///
+    /// The reader implements [`Read`] and [`BufRead`], so the text can be fed to parsers,
+    /// hashers and encoders without first copying it into a `String`. Each call to
+    /// [`BufRead::fill_buf`] returns (the rest of) one piece, straight from the table's buffers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// use std::io::BufRead;
+    ///
+    /// let mut pt = PieceTable::new("one\nthree\n");
+    /// pt.insert("two\n", 4);
+    ///
+    /// let lines: Vec<String> = pt.byte_reader(0).lines().map(Result::unwrap).collect();
+    /// assert_eq!(lines, vec!["one", "two", "three"]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is out of bounds.
+    pub fn byte_reader(&self, offset: usize) -> ByteReader<'_, 'ptable> {
+        assert!(
+            offset <= self.len,
+            "invalid byte_reader: offset {offset} is out of bounds of document of length {}",
+            self.len
+        );
+
+        let (idx, node_start) = self.find_node(offset).unwrap_or((self.nodes.len(), offset));
+        ByteReader {
+            table: self,
+            idx,
+            pos: offset - node_start,
+        }
+    }
+}
+
+/// A reader over the bytes of a `PieceTable`, created by [`PieceTable::byte_reader`].
+///
+/// The reader borrows the table, so it always sees the text as it was when it was created.
+#[derive(Debug, Clone)]
+pub struct ByteReader<'t, 'ptable> {
+    table: &'t PieceTable<'ptable>,
+    /// The index of the current node
+    idx: usize,
+    /// The position within the current node
+    pos: usize,
+}
+
+impl<'t> ByteReader<'t, '_> {
+    /// Returns the rest of the current piece, moving on to the next non-empty one if the
+    /// current one is done.
+    fn current(&mut self) -> &'t [u8] {
+        while let Some(node) = self.table.nodes.get(self.idx) {
+            if self.pos < node.range.len() {
+                let (original, added) = (self.table.original, &self.table.added);
+                let (chunk, start) = node.chunk_at(self.pos, original, added);
+                return &chunk.as_bytes()[self.pos - start..];
+            }
+            self.idx += 1;
+            self.pos = 0;
+        }
+        &[]
+    }
+}
+
+impl Read for ByteReader<'_, '_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut read = 0;
+        while read < buf.len() {
+            let chunk = self.current();
+            if chunk.is_empty() {
+                break;
+            }
+            let n = chunk.len().min(buf.len() - read);
+            buf[read..read + n].copy_from_slice(&chunk[..n]);
+            self.pos += n;
+            read += n;
+        }
+        Ok(read)
+    }
+}
+
+impl BufRead for ByteReader<'_, '_> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(self.current())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt;
+    }
+}
+
+impl PTableSlice<'_> {
+    /// Copies the text of the slice into a new `PieceTable` which owns all of its data.
+    ///
+    /// The returned table doesn't borrow from the table this slice was taken from, so it can be
+    /// sent to another thread and written out there, while the original keeps being edited.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello");
+    /// let saved = pt.create_slice().to_owned_table();
+    /// pt.insert(" world", 5);
+    ///
+    /// let handle = std::thread::spawn(move || saved.to_string());
+    /// assert_eq!(handle.join().unwrap(), "hello");
+    /// ```
+    pub fn to_owned_table(&self) -> PieceTable<'static> {
+        PieceTable::from(String::from(self))
+    }
+
+    /// Writes the text of the slice to `writer`, one piece at a time.
+    ///
+    /// Unlike going through `String::from(&slice)`, the text is never gathered into a single
+    /// buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello");
+    /// pt.insert(" world", 5);
+    ///
+    /// let mut out = Vec::new();
+    /// pt.slice(3..11).write_to(&mut out).unwrap();
+    /// assert_eq!(out, b"lo world");
+    /// ```
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let (original, added) = self.buffers();
+        for node in &self.nodes {
+            for chunk in node.chunks(original, added) {
+                writer.write_all(chunk.as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_in_small_buffers() {
+        let mut piece_table = PieceTable::new("hello world");
+        piece_table.insert("big ", 6);
+        piece_table.delete(0..1);
+        piece_table.insert("H", 0);
+
+        for size in 1..8 {
+            let mut reader = piece_table.byte_reader(0);
+            let mut buf = vec![0; size];
+            let mut out = Vec::new();
+            loop {
+                let n = reader.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                out.extend_from_slice(&buf[..n]);
+            }
+            assert_eq!(b"Hello big world".as_slice(), out);
+        }
+    }
+
+    #[test]
+    fn read_from_offset() {
+        let mut piece_table = PieceTable::new("abcdef");
+        piece_table.insert("XY", 3);
+
+        let mut text = String::new();
+        piece_table
+            .byte_reader(4)
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!("Ydef", text);
+
+        let mut end = piece_table.byte_reader(8);
+        assert!(end.fill_buf().unwrap().is_empty());
+    }
+
+    #[test]
+    fn fill_buf_skips_empty_pieces() {
+        let mut piece_table = PieceTable::new("");
+        piece_table.insert("abc", 0);
+
+        let mut reader = piece_table.byte_reader(0);
+        assert_eq!(b"abc", reader.fill_buf().unwrap());
+        reader.consume(2);
+        assert_eq!(b"c", reader.fill_buf().unwrap());
+        reader.consume(1);
+        assert!(reader.fill_buf().unwrap().is_empty());
+    }
+
+    #[test]
+    fn owned_table_is_independent() {
+        let mut piece_table = PieceTable::new("hello world");
+        piece_table.insert(",", 5);
+        let owned = piece_table.slice(3..9).to_owned_table();
+
+        piece_table.delete(0..12);
+        piece_table.insert("gone", 0);
+
+        let text = std::thread::spawn(move || owned.to_string())
+            .join()
+            .unwrap();
+        assert_eq!("lo, wo", text);
+    }
+
+    #[test]
+    fn write_to_across_pieces() {
+        let mut piece_table = PieceTable::new("one\nthree\n");
+        piece_table.insert("two\n", 4);
+        let slice = piece_table.create_slice();
+        piece_table.delete(0..4);
+
+        let mut out = Vec::new();
+        slice.write_to(&mut out).unwrap();
+        assert_eq!(b"one\ntwo\nthree\n".as_slice(), out);
+
+        let mut out = Vec::new();
+        piece_table.slice(0..0).write_to(&mut out).unwrap();
+        assert!(out.is_empty());
+    }
+}
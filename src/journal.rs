@@ -0,0 +1,407 @@
+//! A log of timestamped edits, exported as JSON Lines for external tools.
+//!
+//! Each edit is written as one JSON object on its own line:
+//!
+//! ```text
+//! {"op":"insert","offset":5,"text":" world","timestamp":1700000000000}
+//! {"op":"delete","offset":0,"len":1,"timestamp":1700000000250}
+//! ```
+//!
+//! Offsets and lengths are in bytes, as in a [`Workload`], and timestamps are milliseconds since
+//! the Unix epoch. Analytics scripts and sync debuggers can read this with any JSON parser, one
+//! line at a time, so the log can be appended to while it's being read.
+
+use std::io::{self, BufRead, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::workload::{Edit, Workload};
+
+/// An edit and when it was made.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalEntry {
+    pub edit: Edit,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+/// Edits in the order they were made, with when they were made.
+///
+/// # Examples
+///
+/// ```
+/// use piece_table::journal::Journal;
+/// use piece_table::workload::Edit;
+///
+/// let mut journal = Journal::new();
+/// journal.record_at(Edit::Insert { offset: 0, text: "hi\n".to_string() }, 1000);
+/// journal.record_at(Edit::Delete { offset: 2, len: 1 }, 1250);
+///
+/// let mut jsonl = Vec::new();
+/// journal.export_journal_jsonl(&mut jsonl).unwrap();
+/// assert_eq!(
+///     String::from_utf8(jsonl.clone()).unwrap(),
+///     "{\"op\":\"insert\",\"offset\":0,\"text\":\"hi\\n\",\"timestamp\":1000}\n\
+///      {\"op\":\"delete\",\"offset\":2,\"len\":1,\"timestamp\":1250}\n"
+/// );
+/// assert_eq!(Journal::import_journal_jsonl(&jsonl[..]).unwrap(), journal);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Journal {
+    pub entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    /// Creates an empty journal.
+    pub fn new() -> Self {
+        Journal::default()
+    }
+
+    /// Appends `edit`, made now.
+    ///
+    /// This reads the system clock, which isn't available on every target, e.g. WebAssembly
+    /// without WASI. Use [`Journal::record_at`] there.
+    pub fn record(&mut self, edit: Edit) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis() as u64);
+        self.record_at(edit, timestamp);
+    }
+
+    /// Appends `edit`, made at `timestamp` milliseconds since the Unix epoch.
+    pub fn record_at(&mut self, edit: Edit, timestamp: u64) {
+        self.entries.push(JournalEntry { edit, timestamp });
+    }
+
+    /// Returns the edits without their timestamps, to be replayed.
+    pub fn to_workload(&self) -> Workload {
+        Workload {
+            edits: self
+                .entries
+                .iter()
+                .map(|entry| entry.edit.clone())
+                .collect(),
+        }
+    }
+
+    /// Writes the journal to `writer` as JSON Lines, see the [module docs](self).
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `writer`.
+    pub fn export_journal_jsonl<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for entry in &self.entries {
+            match &entry.edit {
+                Edit::Insert { offset, text } => {
+                    write!(
+                        writer,
+                        "{{\"op\":\"insert\",\"offset\":{offset},\"text\":\""
+                    )?;
+                    write_escaped(&mut writer, text)?;
+                    write!(writer, "\"")?;
+                }
+                Edit::Delete { offset, len } => write!(
+                    writer,
+                    "{{\"op\":\"delete\",\"offset\":{offset},\"len\":{len}"
+                )?,
+            }
+            writeln!(writer, ",\"timestamp\":{}}}", entry.timestamp)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a journal written as JSON Lines, see the [module docs](self).
+    ///
+    /// Keys may come in any order, unknown keys are ignored so other tools can annotate the
+    /// edits, and empty lines are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `reader`, and an error of kind [`io::ErrorKind::InvalidData`] if a
+    /// line isn't a JSON object describing an edit.
+    pub fn import_journal_jsonl<R: BufRead>(reader: R) -> io::Result<Journal> {
+        let mut journal = Journal::new();
+        for (idx, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = parse_entry(&line).map_err(|message| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {message}", idx + 1),
+                )
+            })?;
+            journal.entries.push(entry);
+        }
+        Ok(journal)
+    }
+}
+
+/// Writes `text` with the escapes a JSON string needs.
+fn write_escaped<W: Write>(writer: &mut W, text: &str) -> io::Result<()> {
+    let mut start = 0;
+    for (idx, c) in text.char_indices() {
+        let escape = match c {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            c if c < ' ' => "",
+            _ => continue,
+        };
+        writer.write_all(&text.as_bytes()[start..idx])?;
+        if escape.is_empty() {
+            write!(writer, "\\u{:04x}", c as u32)?;
+        } else {
+            writer.write_all(escape.as_bytes())?;
+        }
+        start = idx + c.len_utf8();
+    }
+    writer.write_all(&text.as_bytes()[start..])
+}
+
+/// A value of a journal entry's object.
+#[derive(Debug)]
+enum Value {
+    String(String),
+    Number(u64),
+}
+
+/// Parses a line holding a single JSON object into an entry.
+fn parse_entry(line: &str) -> Result<JournalEntry, String> {
+    let mut parser = Parser { rest: line };
+    let mut op = None;
+    let mut offset = None;
+    let mut text = None;
+    let mut len = None;
+    let mut timestamp = None;
+
+    parser.expect('{')?;
+    if !parser.eat('}') {
+        loop {
+            let key = parser.string()?;
+            parser.expect(':')?;
+            let value = parser.value()?;
+            match (key.as_str(), value) {
+                ("op", Value::String(value)) => op = Some(value),
+                ("text", Value::String(value)) => text = Some(value),
+                ("offset", Value::Number(value)) => offset = Some(value as usize),
+                ("len", Value::Number(value)) => len = Some(value as usize),
+                ("timestamp", Value::Number(value)) => timestamp = Some(value),
+                ("op" | "text" | "offset" | "len" | "timestamp", value) => {
+                    return Err(format!("unexpected value {value:?} for `{key}`"));
+                }
+                _ => {}
+            }
+            if parser.eat('}') {
+                break;
+            }
+            parser.expect(',')?;
+        }
+    }
+    if !parser.rest.trim().is_empty() {
+        return Err(format!(
+            "unexpected `{}` after the object",
+            parser.rest.trim()
+        ));
+    }
+
+    let missing = |key: &str| format!("missing `{key}`");
+    let offset = offset.ok_or_else(|| missing("offset"))?;
+    let edit = match op.as_deref() {
+        Some("insert") => Edit::Insert {
+            offset,
+            text: text.ok_or_else(|| missing("text"))?,
+        },
+        Some("delete") => Edit::Delete {
+            offset,
+            len: len.ok_or_else(|| missing("len"))?,
+        },
+        Some(op) => return Err(format!("unknown op `{op}`")),
+        None => return Err(missing("op")),
+    };
+    Ok(JournalEntry {
+        edit,
+        timestamp: timestamp.ok_or_else(|| missing("timestamp"))?,
+    })
+}
+
+/// Reads the subset of JSON journal entries use: strings and non-negative integers.
+struct Parser<'s> {
+    rest: &'s str,
+}
+
+impl Parser<'_> {
+    /// Skips whitespace, then skips `c` if it comes next.
+    fn eat(&mut self, c: char) -> bool {
+        self.rest = self.rest.trim_start();
+        match self.rest.strip_prefix(c) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(format!("expected `{c}`"))
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.rest = self.rest.trim_start();
+        if self.rest.starts_with('"') {
+            return self.string().map(Value::String);
+        }
+        let end = self
+            .rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(self.rest.len());
+        let (digits, rest) = self.rest.split_at(end);
+        let number = digits
+            .parse()
+            .map_err(|_| "expected a string or a non-negative integer".to_string())?;
+        self.rest = rest;
+        Ok(Value::Number(number))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut string = String::new();
+        let mut chars = self.rest.char_indices();
+        while let Some((idx, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.rest = &self.rest[idx + 1..];
+                    return Ok(string);
+                }
+                '\\' => {
+                    let escaped = match chars.next().map(|(_, c)| c) {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => unicode_escape(&mut chars)?,
+                        _ => return Err("invalid escape sequence".to_string()),
+                    };
+                    string.push(escaped);
+                }
+                c => string.push(c),
+            }
+        }
+        Err("unterminated string".to_string())
+    }
+}
+
+/// Reads the rest of a `\u` escape, along with the low surrogate following a high one.
+fn unicode_escape(chars: &mut std::str::CharIndices) -> Result<char, String> {
+    let hex = |chars: &mut std::str::CharIndices| {
+        let digits: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .filter(|_| digits.len() == 4)
+            .ok_or_else(|| format!("invalid unicode escape `\\u{digits}`"))
+    };
+    let first = hex(chars)?;
+    let code = if (0xD800..0xDC00).contains(&first) {
+        let rest = chars.as_str();
+        if !rest.starts_with("\\u") {
+            return Err("unpaired surrogate".to_string());
+        }
+        chars.nth(1);
+        let second = hex(chars)?;
+        if !(0xDC00..0xE000).contains(&second) {
+            return Err("unpaired surrogate".to_string());
+        }
+        0x10000 + ((first - 0xD800) << 10) + (second - 0xDC00)
+    } else {
+        first
+    };
+    char::from_u32(code).ok_or_else(|| "unpaired surrogate".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_awkward_text() {
+        let mut journal = Journal::new();
+        journal.record_at(
+            Edit::Insert {
+                offset: 3,
+                text: "\"quoted\" \\ tab\there\u{1}\r\n🦀".to_string(),
+            },
+            u64::MAX,
+        );
+        journal.record(Edit::Delete { offset: 1, len: 20 });
+
+        let mut jsonl = Vec::new();
+        journal.export_journal_jsonl(&mut jsonl).unwrap();
+        assert_eq!(2, jsonl.iter().filter(|&&b| b == b'\n').count());
+        assert_eq!(journal, Journal::import_journal_jsonl(&jsonl[..]).unwrap());
+        assert!(journal.entries[1].timestamp > 0);
+    }
+
+    #[test]
+    fn reads_what_other_tools_write() {
+        let jsonl = r#"
+            { "timestamp": 7, "text": "é🦀\/", "user": "bob", "op": "insert", "offset": 0 }
+
+            {"op":"delete","len":2,"offset":1,"timestamp":8,"meta":{"x":1}}
+        "#;
+        let err = Journal::import_journal_jsonl(jsonl.as_bytes()).unwrap_err();
+        // Nested objects aren't supported
+        assert_eq!(
+            "line 4: expected a string or a non-negative integer",
+            err.to_string()
+        );
+
+        let journal =
+            Journal::import_journal_jsonl(jsonl.lines().take(3).collect::<String>().as_bytes())
+                .unwrap();
+        assert_eq!(
+            vec![JournalEntry {
+                edit: Edit::Insert {
+                    offset: 0,
+                    text: "é🦀/".to_string()
+                },
+                timestamp: 7
+            }],
+            journal.entries
+        );
+    }
+
+    #[test]
+    fn invalid_entries() {
+        for (line, message) in [
+            (
+                r#"{"op":"move","offset":0,"timestamp":0}"#,
+                "unknown op `move`",
+            ),
+            (
+                r#"{"op":"delete","offset":0,"timestamp":0}"#,
+                "missing `len`",
+            ),
+            (
+                r#"{"op":"insert","offset":-1}"#,
+                "expected a string or a non-negative integer",
+            ),
+            (r#"{"op":"insert","text":"\ud800"}"#, "unpaired surrogate"),
+            (r#"{"op":"insert"} x"#, "unexpected `x` after the object"),
+        ] {
+            let err = Journal::import_journal_jsonl(line.as_bytes()).unwrap_err();
+            assert_eq!(io::ErrorKind::InvalidData, err.kind());
+            assert_eq!(format!("line 1: {message}"), err.to_string());
+        }
+    }
+}
@@ -1,9 +1,98 @@
-use std::{collections::VecDeque, fmt::Display, ops::Range};
-
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    fmt::Display,
+    ops::{Bound, Range, RangeBounds},
+};
+
+use crate::added::AddedBuffer;
+pub use crate::error::{EditError, IterError};
 use crate::interface::EditableText;
+use crate::newlines::{CrCounts, count_chars, count_newlines, count_words, joins_words};
+
+/// Adds `$n` to one of a table's [`instrument::Metrics`] counters, if the `instrument` feature
+/// is enabled.
+macro_rules! count {
+    ($table:expr, $counter:ident, $n:expr) => {
+        #[cfg(feature = "instrument")]
+        $table
+            .counters
+            .$counter
+            .fetch_add($n, std::sync::atomic::Ordering::Relaxed);
+    };
+}
 
+mod added;
+pub mod arena;
 pub mod baseline;
+pub mod block;
+pub mod compaction;
+pub mod complexity;
+pub mod composite;
+pub mod convert;
+pub mod counts;
+pub mod cursor;
+pub mod delta;
+#[cfg(feature = "digest")]
+mod digest;
+pub mod edit_builder;
+#[cfg(feature = "encoding_rs")]
+pub mod encoding;
+mod error;
+pub mod feed;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fill;
+pub mod filter;
+pub mod folds;
+pub mod frozen;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod fuzz;
+pub mod fuzzy;
+pub mod gap_buffer;
+pub mod graphemes;
+pub mod history;
+#[cfg(feature = "instrument")]
+pub mod instrument;
 pub mod interface;
+pub mod inverse;
+pub mod io;
+pub mod journal;
+pub mod limits;
+pub mod line_buffer;
+pub mod line_endings;
+pub mod lines;
+pub mod lossy;
+pub mod lsp;
+pub mod newlines;
+pub mod normalize;
+pub mod patch;
+pub mod piece_map;
+pub mod point;
+pub mod position;
+mod prefix_sums;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod range_set;
+pub mod reader;
+pub mod render;
+pub mod replace;
+pub mod scan;
+pub mod search;
+pub mod sharing;
+pub mod sources;
+pub mod spans;
+mod streaming;
+pub mod tabs;
+pub mod view;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod workload;
+#[cfg(feature = "unicode-width")]
+pub mod wrap;
+
+/// Whether the infallible editing methods validate their offsets (and panic on invalid ones).
+const VALIDATE: bool = cfg!(any(debug_assertions, feature = "strict"));
 
 /// A piece table data structure for efficient string manipulation.
 ///
@@ -20,44 +109,171 @@ pub mod interface;
 ///
 /// # Invariants
 /// - The `original` string reference is immutable and must outlive the `PieceTable`.
-/// - The `added` string buffer is append-only; text is never removed or modified from it,
-///   only referenced by `Node`s.
+/// - The `added` buffer is append-only; text is never removed, modified or moved in it,
+///   only referenced by `Node`s. The same bytes may be referenced by several `Node`s (see
+///   [`PieceTable::duplicate_range`]), so text can only be dropped from it once no `Node`
+///   refers to it anymore.
 /// - The sequence of `Node`s in `nodes` always represents the current, correct state of the
 ///   entire text. Concatenating the text from all nodes, in order, yields the full document.
+/// - Every piece starts and ends on a char boundary, so its text is always a valid `&str` on
+///   its own. Iterators over the pieces, such as [`PieceTable::pieces`] and
+///   [`PieceTable::chunk_cursor`], hand out that text as it is. Edits which would cut a piece
+///   inside a char panic, even when offsets aren't validated up front, see
+///   [`PieceTable::check_invariants`].
+///
+/// # Tracing
+///
+/// With the `trace` feature, insertions, deletions and slices are wrapped in [`tracing`] spans
+/// at the `DEBUG` level, which record the offsets involved and the number of pieces when they
+/// start. A subscriber which times spans then shows how long each of them took, so latency
+/// spikes can be matched with the state of the table.
 #[derive(Debug, Clone)]
 pub struct PieceTable<'a> {
     original: &'a str,
-    added: String,
+    added: AddedBuffer<'a>,
+    /// The sources attached with [`PieceTable::attach_source`], in the order they were attached,
+    /// along with where they start in the "added" buffer
+    sources: Vec<(usize, &'a str)>,
     nodes: VecDeque<Node>,
+    /// Built by lookups and dropped by `pieces_changed`, which edits call through
+    /// `split_at_offset` and `insert_node`
+    prefix_sums: prefix_sums::PrefixSums,
+    /// The offset of the last deletion and the index of the piece starting there, if the pieces
+    /// haven't changed since, so that backspacing extends the hole without looking for a piece
+    last_delete: Option<(usize, usize)>,
     len: usize,
+    newlines: usize,
+    chars: usize,
+    publisher: reader::Publisher<'a>,
+    checkpoints: Vec<(String, history::Checkpoint)>,
+    /// The most unpinned checkpoints kept, see [`PieceTable::set_checkpoint_budget`]
+    checkpoint_budget: Option<usize>,
+    coalesce: CoalescePolicy,
+    /// Whether inserted text is NFC-normalized, see [`PieceTable::set_normalize_inserts`]
+    normalize: bool,
+    limits: limits::Limits,
+    /// Changed by every edit, see [`PieceTable::generation`]
+    generation: u64,
+    /// Identifies the buffers the pieces refer to, which only change when a compaction replaces
+    /// them, see [`inverse::InverseEdit`]
+    buffers: u64,
+    stream: streaming::Stream,
+    #[cfg(feature = "instrument")]
+    counters: instrument::Counters,
+}
+
+/// When an insertion right after a piece of inserted text extends that piece, instead of adding
+/// a new one.
+///
+/// This only happens when the two are contiguous in the "added" buffer, which is the case when
+/// typing, so the number of pieces grows with the number of places edited rather than the
+/// number of keystrokes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CoalescePolicy {
+    /// Always extend the piece.
+    #[default]
+    Always,
+    /// Only extend the piece if it ends up at most this many bytes long.
+    MaxPieceLen(usize),
+    /// Never extend pieces, so every insertion adds a new one.
+    Off,
+}
+
+impl CoalescePolicy {
+    /// Checks if a piece can be extended to cover `range` of the "added" buffer.
+    fn allows(self, range: Range<usize>) -> bool {
+        match self {
+            CoalescePolicy::Always => true,
+            CoalescePolicy::MaxPieceLen(max) => range.len() <= max,
+            CoalescePolicy::Off => false,
+        }
+    }
 }
 
 /// Represents a continuous slice of text in one of the two buffers
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Node {
     kind: NodeKind,
     range: Range<usize>,
+    /// Number of `'\n'` bytes in the text referenced by `range`
+    newlines: usize,
+    /// Number of chars in the text referenced by `range`
+    chars: usize,
+    /// Number of words in the text referenced by `range`, on its own, so a word which continues
+    /// in the next piece is counted in both
+    words: usize,
+    /// The `'\r'` bytes in the text referenced by `range`, on their own, so a CRLF split
+    /// between two pieces is counted in neither
+    crs: CrCounts,
+    /// Hash of the text referenced by `range`
+    #[cfg(feature = "digest")]
+    hash: u64,
 }
 
+/// The most bytes a `Node` can store inline, see [`NodeKind::Inline`].
+///
+/// This fits in what would otherwise be padding after the tag of `NodeKind`, so inline pieces
+/// don't make `Node` any larger.
+const INLINE_CAPACITY: usize = 6;
+
 /// What buffer the data from this `Node` is stored in
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum NodeKind {
     Original,
     Added,
+    /// The text is stored in the node itself, with `range` referring to these bytes.
+    ///
+    /// Used for chars inserted with [`PieceTable::insert_char`] where they can't extend an
+    /// existing piece, so scattered single-char edits don't touch the "added" buffer. Once an
+    /// inline piece is full, typing after it moves it to the "added" buffer.
+    Inline([u8; INLINE_CAPACITY]),
+    /// The text is `range.len()` copies of this ASCII char, which isn't stored anywhere, with
+    /// `range` starting at 0.
+    ///
+    /// Used for runs of a single char inserted with [`PieceTable::insert_repeated`], such as
+    /// padding, so they take the same memory however long they are. Their text is read
+    /// [`REPEAT_CHUNK`] bytes at a time, see [`Node::chunks`].
+    Repeat(u8),
 }
 
+/// The most bytes of a repeat piece read at once, see [`NodeKind::Repeat`].
+const REPEAT_CHUNK: usize = 128;
+
+/// Every ASCII char repeated [`REPEAT_CHUNK`] times, one after the other, which the text of
+/// repeat pieces is read from.
+static REPEATS: &str = {
+    const BYTES: [u8; 128 * REPEAT_CHUNK] = {
+        let mut bytes = [0; 128 * REPEAT_CHUNK];
+        let mut idx = 0;
+        while idx < bytes.len() {
+            bytes[idx] = (idx / REPEAT_CHUNK) as u8;
+            idx += 1;
+        }
+        bytes
+    };
+    match std::str::from_utf8(&BYTES) {
+        Ok(text) => text,
+        Err(_) => panic!("ASCII is valid UTF-8"),
+    }
+};
+
 /// An immutable view into a PieceTable.
 ///
 /// A `PTableSlice` provides a snapshot of the `PieceTable`'s content at a specific point in time.
 /// It lives as long as its corresponding `PieceTable` and will not be affected by any changes
 /// made to the `PieceTable` after the slice was created. This makes it useful for operations
 /// that require a stable view of the text, such as iteration or complex transformations.
+///
+/// Every iterator over a slice yields the text as it was when the slice was created, even if
+/// the `PieceTable` is edited in between. To hand a snapshot to another thread, e.g. to save it
+/// in the background, turn it into a table of its own with [`PTableSlice::to_owned_table`].
 #[derive(Debug)]
 pub struct PTableSlice<'ptable> {
     nodes: Vec<Node>,
-    original: *const str,
-    added: *const String, // SAFETY: must never be mutated, only read
-    _marker: std::marker::PhantomData<&'ptable ()>,
+    original: &'ptable str,
+    /// Shares the segments of the table's "added" buffer, which are never written to while
+    /// they are shared
+    added: AddedBuffer<'ptable>,
 }
 
 impl<'ptable> PieceTable<'ptable> {
@@ -65,7 +281,7 @@ impl<'ptable> PieceTable<'ptable> {
     ///
     /// This is the primary constructor for the `PieceTable`. It initializes the table with the
     /// provided `string`, which becomes the "original" text. The entire original text is
-    /// represented by a single `Node` of kind `Original`.
+    /// represented by a single `Node` of kind `Original`, or by no `Node` at all if it's empty.
     ///
     /// The `PieceTable` takes a reference to the input string, so the original data must outlive
     /// the `PieceTable`. Any modifications (insertions) will be stored in a separate internal buffer.
@@ -79,18 +295,124 @@ impl<'ptable> PieceTable<'ptable> {
     /// assert_eq!(pt.to_string(), initial_text);
     /// ```
     pub fn new(string: &'ptable str) -> Self {
+        let newlines = count_newlines(string);
+        let chars = count_chars(string);
         let mut nodes = VecDeque::new();
-        nodes.push_back(Node {
-            kind: NodeKind::Original,
-            range: 0..string.len(),
-        });
+        if !string.is_empty() {
+            nodes.push_back(Node {
+                kind: NodeKind::Original,
+                range: 0..string.len(),
+                newlines,
+                chars,
+                words: count_words(string),
+                crs: CrCounts::of(string),
+                #[cfg(feature = "digest")]
+                hash: digest::hash(string),
+            });
+        }
 
         PieceTable {
             original: string,
-            added: String::new(),
+            added: AddedBuffer::default(),
+            sources: Vec::new(),
             nodes,
             len: string.len(),
+            newlines,
+            chars,
+            prefix_sums: Default::default(),
+            last_delete: None,
+            publisher: Default::default(),
+            checkpoints: Vec::new(),
+            checkpoint_budget: None,
+            coalesce: CoalescePolicy::default(),
+            normalize: false,
+            limits: Default::default(),
+            generation: cursor::next_generation(),
+            buffers: cursor::next_generation(),
+            stream: Default::default(),
+            #[cfg(feature = "instrument")]
+            counters: Default::default(),
+        }
+    }
+
+    /// Creates an empty `PieceTable`, e.g. for a new, unsaved document.
+    ///
+    /// Every operation works on an empty table: it has one (empty) line, inserting at offset 0
+    /// and deleting or slicing the empty range `0..0` are allowed, and lookups return `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::empty();
+    /// assert!(pt.is_empty());
+    /// assert_eq!(pt.len_lines(), 1);
+    ///
+    /// pt.insert("hello", 0);
+    /// assert_eq!(pt.to_string(), "hello");
+    /// ```
+    pub fn empty() -> Self {
+        PieceTable::new("")
+    }
+
+    /// Creates a new `PieceTable` containing the concatenation of `chunks`, e.g. as read from a
+    /// network stream or a rope.
+    ///
+    /// The first non-empty chunk becomes the original text, so it's referenced without being
+    /// copied, and the rest are copied straight into the "added" buffer, which is represented by
+    /// one piece per segment it fills. The text is never concatenated into a temporary `String`
+    /// first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let pt = PieceTable::from_chunks(["hello", "", " wor", "ld\n"]);
+    /// assert_eq!(pt.to_string(), "hello world\n");
+    /// assert_eq!(pt.len_lines(), 2);
+    /// ```
+    pub fn from_chunks<I: IntoIterator<Item = &'ptable str>>(chunks: I) -> Self {
+        let mut chunks = chunks.into_iter().filter(|chunk| !chunk.is_empty());
+        let mut table = PieceTable::new(chunks.next().unwrap_or(""));
+
+        for chunk in chunks {
+            let range = table.added.push_str(chunk);
+            let newlines = count_newlines(chunk);
+            let chars = count_chars(chunk);
+            table.len += chunk.len();
+            table.newlines += newlines;
+            table.chars += chars;
+            match table.nodes.back_mut() {
+                Some(last)
+                    if last.kind == NodeKind::Added
+                        && table.added.same_segment(last.range.start, range.start) =>
+                {
+                    let last_text = last.text(table.original, &table.added);
+                    let joins = joins_words(last_text, chunk);
+                    let crs = last.crs.append(last_text, chunk);
+                    last.range.end = range.end;
+                    last.crs = crs;
+                    last.newlines += newlines;
+                    last.chars += chars;
+                    last.words += count_words(chunk) - joins as usize;
+                    #[cfg(feature = "digest")]
+                    {
+                        last.hash = digest::combine(last.hash, digest::hash(chunk), chunk.len());
+                    }
+                }
+                _ => table.nodes.push_back(Node {
+                    kind: NodeKind::Added,
+                    range,
+                    newlines,
+                    chars,
+                    words: count_words(chunk),
+                    crs: CrCounts::of(chunk),
+                    #[cfg(feature = "digest")]
+                    hash: digest::hash(chunk),
+                }),
+            }
         }
+        table
     }
 
     /// Returns the total length of the text in the `PieceTable`, in bytes.
@@ -109,10 +431,122 @@ impl<'ptable> PieceTable<'ptable> {
     /// assert_eq!(pt.len(), 6);
     /// ```
     pub fn len(&self) -> usize {
-        debug_assert_eq!(self.len, self.to_string().len());
         self.len
     }
 
+    /// Checks the table's internal consistency, returning a description of the first problem
+    /// found.
+    ///
+    /// This verifies that every piece lies within its buffer and starts and ends on char
+    /// boundaries, and that the cached length and newline counts match the pieces. It walks the
+    /// whole text, so it's meant for tests and debugging rather than being called on every edit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("héllo\n");
+    /// pt.insert("wörld", 7);
+    /// pt.delete(1..3);
+    /// assert_eq!(pt.check_invariants(), Ok(()));
+    /// ```
+    pub fn check_invariants(&self) -> Result<(), String> {
+        let mut len = 0;
+        let mut newlines = 0;
+        let mut chars = 0;
+        for (idx, node) in self.nodes.iter().enumerate() {
+            if let NodeKind::Repeat(byte) = node.kind {
+                if !byte.is_ascii() || *node != Node::repeat(byte, node.range.len()) {
+                    return Err(format!(
+                        "piece {idx} repeats {byte:#x} with range {:?}, but its counts don't match",
+                        node.range
+                    ));
+                }
+                len += node.range.len();
+                newlines += node.newlines;
+                chars += node.chars;
+                continue;
+            }
+            let (buffer_len, text) = match node.kind {
+                NodeKind::Original => (self.original.len(), self.original.get(node.range.clone())),
+                NodeKind::Added => (self.added.len(), self.added.get(node.range.clone())),
+                NodeKind::Inline(ref bytes) => (
+                    INLINE_CAPACITY,
+                    bytes
+                        .get(node.range.clone())
+                        .and_then(|bytes| std::str::from_utf8(bytes).ok()),
+                ),
+                NodeKind::Repeat(_) => unreachable!("repeat pieces were checked above"),
+            };
+            let range = &node.range;
+            if range.start > range.end || range.end > buffer_len {
+                return Err(format!(
+                    "piece {idx} has range {range:?}, outside of its {:?} buffer of length \
+                     {buffer_len}",
+                    node.kind
+                ));
+            }
+            let Some(text) = text else {
+                return Err(format!(
+                    "piece {idx} has range {range:?}, which isn't on char boundaries or spans \
+                     several segments"
+                ));
+            };
+
+            let actual = count_newlines(text);
+            if node.newlines != actual {
+                return Err(format!(
+                    "piece {idx} caches {} newlines but has {actual}",
+                    node.newlines
+                ));
+            }
+            let actual_chars = count_chars(text);
+            if node.chars != actual_chars {
+                return Err(format!(
+                    "piece {idx} caches {} chars but has {actual_chars}",
+                    node.chars
+                ));
+            }
+            let actual_words = count_words(text);
+            if node.words != actual_words {
+                return Err(format!(
+                    "piece {idx} caches {} words but has {actual_words}",
+                    node.words
+                ));
+            }
+            if node.crs != CrCounts::of(text) {
+                return Err(format!("piece {idx} caches the wrong count of CRs"));
+            }
+            #[cfg(feature = "digest")]
+            if node.hash != digest::hash(text) {
+                return Err(format!("piece {idx} caches the wrong hash"));
+            }
+            len += range.len();
+            newlines += actual;
+            chars += actual_chars;
+        }
+
+        if self.len != len {
+            return Err(format!(
+                "cached length is {} but pieces sum to {len}",
+                self.len
+            ));
+        }
+        if self.newlines != newlines {
+            return Err(format!(
+                "cached newline count is {} but pieces have {newlines}",
+                self.newlines
+            ));
+        }
+        if self.chars != chars {
+            return Err(format!(
+                "cached char count is {} but pieces have {chars}",
+                self.chars
+            ));
+        }
+        Ok(())
+    }
+
     /// Checks if the `PieceTable` is empty.
     ///
     /// Returns `true` if the `PieceTable` contains no text, `false` otherwise.
@@ -132,12 +566,54 @@ impl<'ptable> PieceTable<'ptable> {
         self.len() == 0
     }
 
+    /// Returns the number of lines in the `PieceTable`.
+    ///
+    /// Lines are separated by `'\n'`, so this is always one more than the number of newlines:
+    /// an empty document has one (empty) line, and a trailing newline starts a new empty line.
+    /// This matches ropey's `len_lines`.
+    ///
+    /// Newline counts are kept per piece and updated on every edit, so this is O(1).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello\nworld");
+    /// assert_eq!(pt.len_lines(), 2);
+    ///
+    /// pt.insert("\n", 11);
+    /// assert_eq!(pt.len_lines(), 3);
+    /// ```
+    pub fn len_lines(&self) -> usize {
+        self.newlines + 1
+    }
+
+    /// Returns the number of chars in the `PieceTable`.
+    ///
+    /// Like newlines, chars are counted per piece and kept up to date on every edit, so this is
+    /// O(1).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("héllo");
+    /// assert_eq!(pt.len_chars(), 5);
+    ///
+    /// pt.insert_char(6, '!');
+    /// assert_eq!(pt.len_chars(), 6);
+    /// ```
+    pub fn len_chars(&self) -> usize {
+        self.chars
+    }
+
     /// Inserts a single character at the specified byte offset.
     ///
     /// This method inserts the given character `c` into the text at the byte `offset`.
-    /// The character is appended to the internal "added" buffer, and a new `Node` of kind `Added`
-    /// is created to reference it. This new node is then inserted into the sequence of pieces
-    /// at the correct logical position.
+    /// If it can't extend the piece before it, the character is stored inline in a new piece,
+    /// without going through the "added" buffer. This new piece is then inserted into the
+    /// sequence of pieces at the correct logical position. A few more characters typed after it
+    /// are stored in the same piece, which is moved to the "added" buffer once it's full.
     ///
     /// If the `offset` falls within an existing piece, that piece may be split into two to
     /// accommodate the new character. If the `offset` is at the end of the text, the new
@@ -155,28 +631,139 @@ impl<'ptable> PieceTable<'ptable> {
     /// pt.insert_char(0, ' '); // Insert space at the beginning
     /// assert_eq!(pt.to_string(), " world");
     /// ```
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, or when the `strict` feature is enabled, panics if `offset` is out of
+    /// bounds or not on a char boundary. See [`PieceTable::try_insert_char`] for a non-panicking
+    /// version.
     pub fn insert_char(&mut self, offset: usize, c: char) {
-        // The node we'll insert
-        let node_range = self.added.len()..self.added.len() + c.len_utf8();
-        self.added.push(c);
-        let node = Node {
-            kind: NodeKind::Added,
-            range: node_range,
+        if VALIDATE && let Err(err) = self.check_offset(offset) {
+            panic!("invalid insert: {err}");
+        }
+        if let Err(err) = self.check_insert_limits(offset, c.len_utf8()) {
+            panic!("invalid insert: {err}");
+        }
+        self.insert_char_unchecked(offset, c);
+    }
+
+    /// Inserts a single character at the specified byte offset, or returns an error if `offset`
+    /// is out of bounds or not on a char boundary.
+    ///
+    /// Unlike [`PieceTable::insert_char`], the offset is always validated, regardless of build
+    /// mode. If an error is returned the `PieceTable` is left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::{EditError, PieceTable};
+    /// let mut pt = PieceTable::new("hllo");
+    /// assert_eq!(pt.try_insert_char(1, 'e'), Ok(()));
+    /// assert_eq!(pt.try_insert_char(9, '!'), Err(EditError::OutOfBounds { offset: 9, len: 5 }));
+    /// assert_eq!(pt.to_string(), "hello");
+    /// ```
+    pub fn try_insert_char(&mut self, offset: usize, c: char) -> Result<(), EditError> {
+        self.check_offset(offset)?;
+        self.check_insert_limits(offset, c.len_utf8())?;
+        self.insert_char_unchecked(offset, c);
+        Ok(())
+    }
+
+    #[cfg_attr(
+        feature = "trace",
+        tracing::instrument(level = "debug", skip(self), fields(pieces = self.nodes.len()))
+    )]
+    fn insert_char_unchecked(&mut self, offset: usize, c: char) {
+        if self.normalize && !normalize::is_nfc(c.encode_utf8(&mut [0; 4])) {
+            return self.insert_unchecked(c.encode_utf8(&mut [0; 4]), offset);
+        }
+        let mut buf = [0; 4];
+        let data = c.encode_utf8(&mut buf);
+        let newlines = (c == '\n') as usize;
+
+        let insert_idx = self.split_at_offset(offset);
+        let Some(prev_idx) = insert_idx.checked_sub(1) else {
+            return self.insert_inline(insert_idx, offset, data, newlines);
         };
+        let prev = &self.nodes[prev_idx];
+        match prev.kind {
+            NodeKind::Added if prev.range.end == self.added.len() => {
+                self.insert_added_at(insert_idx, offset, data, newlines)
+            }
+            NodeKind::Inline(_)
+                if self
+                    .coalesce
+                    .allows(prev.range.start..prev.range.end + data.len()) =>
+            {
+                self.extend_inline(prev_idx, offset, data, newlines)
+            }
+            _ => self.insert_inline(insert_idx, offset, data, newlines),
+        }
+    }
 
-        if let Some((node_idx, node_pos)) = self.find_node(offset) {
-            let insert_idx = if self.split_node(node_idx, offset - node_pos) {
-                node_idx + 1
-            } else {
-                node_idx
-            };
+    /// Inserts a new inline node holding `data` at index `idx`, which starts at `offset`.
+    fn insert_inline(&mut self, idx: usize, offset: usize, data: &str, newlines: usize) {
+        let mut bytes = [0; INLINE_CAPACITY];
+        bytes[..data.len()].copy_from_slice(data.as_bytes());
+        self.newlines += newlines;
+        self.chars += 1;
+        self.len += data.len();
+        self.stream.on_insert(offset, data.len());
+        self.insert_node(
+            idx,
+            Node {
+                kind: NodeKind::Inline(bytes),
+                range: 0..data.len(),
+                newlines,
+                chars: 1,
+                words: count_words(data),
+                crs: CrCounts::of(data),
+                #[cfg(feature = "digest")]
+                hash: digest::hash(data),
+            },
+        );
+        self.publish();
+    }
 
-            self.nodes.insert(insert_idx, node);
+    /// Appends `data` to the inline node at index `idx`, which ends at `offset`, moving it to
+    /// the "added" buffer if it doesn't fit.
+    fn extend_inline(&mut self, idx: usize, offset: usize, data: &str, newlines: usize) {
+        self.newlines += newlines;
+        self.chars += 1;
+        self.len += data.len();
+        self.stream.on_insert(offset, data.len());
+
+        let text = self.nodes[idx].text(self.original, &self.added);
+        let joins = joins_words(text, data);
+        let crs = self.nodes[idx].crs.append(text, data);
+        let node = &mut self.nodes[idx];
+        let NodeKind::Inline(bytes) = &mut node.kind else {
+            unreachable!("extend_inline called on a piece which isn't inline");
+        };
+        let end = node.range.end + data.len();
+        if end <= INLINE_CAPACITY {
+            bytes[node.range.end..end].copy_from_slice(data.as_bytes());
+            node.range.end = end;
         } else {
-            self.nodes.push_back(node);
+            let mut text = [0; INLINE_CAPACITY + 4];
+            let len = node.range.len();
+            text[..len].copy_from_slice(&bytes[node.range.clone()]);
+            text[len..len + data.len()].copy_from_slice(data.as_bytes());
+            let text = std::str::from_utf8(&text[..len + data.len()])
+                .expect("inline text followed by a char is valid UTF-8");
+            node.kind = NodeKind::Added;
+            node.range = self.added.push_str(text);
         }
-
-        self.len += c.len_utf8();
+        node.newlines += newlines;
+        node.chars += 1;
+        node.words += count_words(data) - joins as usize;
+        node.crs = crs;
+        #[cfg(feature = "digest")]
+        {
+            node.hash = digest::combine(node.hash, digest::hash(data), data.len());
+        }
+        count!(self, merges, 1);
+        self.publish();
     }
 
     /// Inserts a string slice at the specified byte offset.
@@ -202,38 +789,337 @@ impl<'ptable> PieceTable<'ptable> {
     /// pt.insert("beginning ", 0);
     /// assert_eq!(pt.to_string(), "beginning start");
     /// ```
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, or when the `strict` feature is enabled, panics if `offset` is out of
+    /// bounds or not on a char boundary. See [`PieceTable::try_insert`] for a non-panicking
+    /// version.
     pub fn insert(&mut self, data: &str, offset: usize) {
-        // The node we'll insert
-        let node_range = self.added.len()..self.added.len() + data.len();
-        self.added.push_str(data);
+        if VALIDATE && let Err(err) = self.check_offset(offset) {
+            panic!("invalid insert: {err}");
+        }
+        if let Err(err) = self.check_insert_limits(offset, data.len()) {
+            panic!("invalid insert: {err}");
+        }
+        self.insert_unchecked(data, offset);
+    }
+
+    /// Inserts a string slice at the specified byte offset, or returns an error if `offset` is
+    /// out of bounds or not on a char boundary.
+    ///
+    /// Unlike [`PieceTable::insert`], the offset is always validated, regardless of build mode.
+    /// If an error is returned the `PieceTable` is left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::{EditError, PieceTable};
+    /// let mut pt = PieceTable::new("héllo");
+    /// assert_eq!(pt.try_insert("!", 6), Ok(()));
+    /// // Offset 2 is in the middle of 'é'
+    /// assert_eq!(pt.try_insert("x", 2), Err(EditError::NotCharBoundary { offset: 2 }));
+    /// assert_eq!(pt.to_string(), "héllo!");
+    /// ```
+    pub fn try_insert(&mut self, data: &str, offset: usize) -> Result<(), EditError> {
+        self.check_offset(offset)?;
+        self.check_insert_limits(offset, data.len())?;
+        self.insert_unchecked(data, offset);
+        Ok(())
+    }
+
+    /// Inserts `data` at byte `offset` without copying it, referring to it where it is instead.
+    ///
+    /// This is for huge pastes, or text taken from another document, which would otherwise be
+    /// copied into the "added" buffer. `data` must outlive the table, like its original text,
+    /// and becomes a segment of the "added" buffer of its own, which is never written to. Text
+    /// typed right after it goes into a separate piece. When NFC normalization is enabled and
+    /// `data` isn't normalized, the normalized copy is inserted instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let pasted = "a very long paste".to_string();
+    /// let mut pt = PieceTable::new("[]");
+    /// pt.insert_borrowed(&pasted, 1);
+    /// assert_eq!(pt.to_string(), "[a very long paste]");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`PieceTable::insert`].
+    pub fn insert_borrowed(&mut self, data: &'ptable str, offset: usize) {
+        if VALIDATE && let Err(err) = self.check_offset(offset) {
+            panic!("invalid insert_borrowed: {err}");
+        }
+        let copied = self.normalize && !normalize::is_nfc(data);
+        let added = if copied { data.len() } else { 0 };
+        if let Err(err) = self.check_growth(data.len(), added, 1 + self.splits_at(offset)) {
+            panic!("invalid insert_borrowed: {err}");
+        }
+        if copied {
+            return self.insert_unchecked(data, offset);
+        }
+        if data.is_empty() {
+            return;
+        }
+
+        let insert_idx = self.split_at_offset(offset);
+        let node = Node::new(NodeKind::Added, self.added.push_borrowed(data), data);
+        self.splice_node(insert_idx, offset, node);
+        self.publish();
+    }
+
+    /// Inserts each string at its byte offset, where every offset is into the text before any
+    /// of the insertions, e.g. when typing at several cursors at once.
+    ///
+    /// Strings inserted at the same offset end up in the order they're given. This is faster
+    /// than inserting them one by one: all of them are appended to the "added" buffer at once,
+    /// and the pieces are split in a single pass from the end of the text, so earlier offsets
+    /// don't have to be adjusted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("a\nb\nc");
+    /// pt.insert_many(&[(5, "!"), (0, "- "), (2, "- "), (4, "- ")]);
+    /// assert_eq!(pt.to_string(), "- a\n- b\n- c!");
+    ///
+    /// pt.insert_many(&[(3, "x"), (3, "y")]);
+    /// assert_eq!(pt.to_string(), "- axy\n- b\n- c!");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, or when the `strict` feature is enabled, panics if any offset is out of
+    /// bounds or not on a char boundary. See [`PieceTable::try_insert_many`] for a non-panicking
+    /// version.
+    pub fn insert_many(&mut self, inserts: &[(usize, &str)]) {
+        if VALIDATE
+            && let Err(err) = inserts
+                .iter()
+                .try_for_each(|&(offset, _)| self.check_offset(offset))
+        {
+            panic!("invalid insert_many: {err}");
+        }
+        if let Err(err) = self.check_insert_many_limits(inserts) {
+            panic!("invalid insert_many: {err}");
+        }
+        self.insert_many_unchecked(inserts);
+    }
+
+    /// Inserts each string at its byte offset like [`PieceTable::insert_many`], or returns an
+    /// error if any offset is out of bounds or not on a char boundary.
+    ///
+    /// If an error is returned, none of the strings are inserted.
+    pub fn try_insert_many(&mut self, inserts: &[(usize, &str)]) -> Result<(), EditError> {
+        for &(offset, _) in inserts {
+            self.check_offset(offset)?;
+        }
+        self.check_insert_many_limits(inserts)?;
+        self.insert_many_unchecked(inserts);
+        Ok(())
+    }
+
+    #[cfg_attr(
+        feature = "trace",
+        tracing::instrument(
+            level = "debug",
+            skip(self, inserts),
+            fields(count = inserts.len(), pieces = self.nodes.len())
+        )
+    )]
+    fn insert_many_unchecked(&mut self, inserts: &[(usize, &str)]) {
+        let mut order: Vec<usize> = (0..inserts.len())
+            .filter(|&idx| !inserts[idx].1.is_empty())
+            .collect();
+        if order.is_empty() {
+            return;
+        }
+        // Stable, so strings at the same offset keep their order
+        order.sort_by_key(|&idx| inserts[idx].0);
+        let normalized: Vec<Cow<str>> = order
+            .iter()
+            .map(|&idx| match inserts[idx].1 {
+                data if self.normalize && !normalize::is_nfc(data) => {
+                    Cow::Owned(normalize::nfc(data))
+                }
+                data => Cow::Borrowed(data),
+            })
+            .collect();
+        let data: Vec<&str> = normalized.iter().map(|data| data.as_ref()).collect();
+
+        #[cfg(feature = "instrument")]
+        let segments = self.added.segment_count();
+        let mut end = self.added.push_strs(&data).end;
+        count!(
+            self,
+            allocations,
+            (self.added.segment_count() != segments) as usize
+        );
+
+        for (&idx, data) in order.iter().zip(data).rev() {
+            let offset = inserts[idx].0;
+            let start = end - data.len();
+            let node = Node::new(NodeKind::Added, start..end, data);
+            let insert_idx = self.split_at_offset(offset);
+            self.splice_node(insert_idx, offset, node);
+            end = start;
+        }
+        self.publish();
+    }
+
+    #[cfg_attr(
+        feature = "trace",
+        tracing::instrument(
+            level = "debug",
+            skip(self, data),
+            fields(len = data.len(), pieces = self.nodes.len())
+        )
+    )]
+    fn insert_unchecked(&mut self, data: &str, offset: usize) {
+        if self.normalize && !normalize::is_nfc(data) {
+            let data = normalize::nfc(data);
+            return self.insert_added(offset, &data, count_newlines(&data));
+        }
+        self.insert_added(offset, data, count_newlines(data));
+    }
+
+    /// Pushes `data`, which contains `newlines` newlines, to `added` and inserts a node
+    /// referencing it at `offset`, extending the node before it instead if the coalescing policy
+    /// allows it.
+    fn insert_added(&mut self, offset: usize, data: &str, newlines: usize) {
+        let insert_idx = self.split_at_offset(offset);
+        self.insert_added_at(insert_idx, offset, data, newlines);
+    }
+
+    /// Like [`PieceTable::insert_added`], where `idx` is the index of the node starting at
+    /// `offset`, as returned by [`PieceTable::split_at_offset`].
+    fn insert_added_at(&mut self, insert_idx: usize, offset: usize, data: &str, newlines: usize) {
+        #[cfg(feature = "instrument")]
+        let segments = self.added.segment_count();
         let node = Node {
             kind: NodeKind::Added,
-            range: node_range,
+            range: self.added.push_str(data),
+            newlines,
+            chars: count_chars(data),
+            words: count_words(data),
+            crs: CrCounts::of(data),
+            #[cfg(feature = "digest")]
+            hash: digest::hash(data),
         };
+        count!(
+            self,
+            allocations,
+            (self.added.segment_count() != segments) as usize
+        );
+        self.splice_node(insert_idx, offset, node);
+        self.publish();
+    }
+
+    /// Inserts `node` at `idx`, the index of the node starting at `offset`. If both are Added
+    /// nodes, extends the node before it instead if the coalescing policy allows it, and if both
+    /// repeat the same char, always does.
+    fn splice_node(&mut self, insert_idx: usize, offset: usize, node: Node) {
+        self.newlines += node.newlines;
+        self.chars += node.chars;
+        self.len += node.range.len();
+        self.stream.on_insert(offset, node.range.len());
+
+        let prev = insert_idx.checked_sub(1).map(|idx| &mut self.nodes[idx]);
+        match prev {
+            Some(prev)
+                if prev.kind == NodeKind::Added
+                    && node.kind == NodeKind::Added
+                    && prev.range.end == node.range.start
+                    && self.added.same_segment(prev.range.start, node.range.start)
+                    && self.coalesce.allows(prev.range.start..node.range.end)
+                    && self.stream.is_loaded(prev.range.start)
+                        == self.stream.is_loaded(node.range.start) =>
+            {
+                let prev_text = &self.added[prev.range.clone()];
+                let data = &self.added[node.range.clone()];
+                let joins = joins_words(prev_text, data);
+                prev.crs = prev.crs.append(prev_text, data);
+                prev.range.end = node.range.end;
+                prev.newlines += node.newlines;
+                prev.chars += node.chars;
+                prev.words += node.words - joins as usize;
+                #[cfg(feature = "digest")]
+                {
+                    prev.hash = digest::combine(prev.hash, node.hash, node.range.len());
+                }
+                count!(self, merges, 1);
+            }
+            Some(prev) if matches!(node.kind, NodeKind::Repeat(_)) && prev.kind == node.kind => {
+                let NodeKind::Repeat(byte) = node.kind else {
+                    unreachable!("the node was just checked to be a repeat node");
+                };
+                *prev = Node::repeat(byte, prev.range.len() + node.range.len());
+                count!(self, merges, 1);
+            }
+            _ => self.insert_node(insert_idx, node),
+        }
+    }
 
-        if let Some((node_idx, node_pos)) = self.find_node(offset) {
-            let insert_idx = if self.split_node(node_idx, offset - node_pos) {
-                node_idx + 1
-            } else {
-                node_idx
-            };
+    /// Returns the policy for extending pieces on insertion.
+    pub fn coalesce_policy(&self) -> CoalescePolicy {
+        self.coalesce
+    }
 
-            self.nodes.insert(insert_idx, node);
-        } else {
-            self.nodes.push_back(node);
-        }
+    /// Sets the policy for extending pieces on insertion, see [`CoalescePolicy`]. This only
+    /// affects future insertions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::{CoalescePolicy, PieceTable};
+    /// let mut pt = PieceTable::new("()");
+    /// for (i, c) in "hello".chars().enumerate() {
+    ///     pt.insert_char(1 + i, c);
+    /// }
+    /// assert_eq!(pt.pieces().count(), 3);
+    ///
+    /// let mut pt = PieceTable::new("()");
+    /// pt.set_coalesce_policy(CoalescePolicy::MaxPieceLen(2));
+    /// for (i, c) in "hello".chars().enumerate() {
+    ///     pt.insert_char(1 + i, c);
+    /// }
+    /// // "(", "he", "ll", "o", ")"
+    /// assert_eq!(pt.pieces().count(), 5);
+    /// ```
+    pub fn set_coalesce_policy(&mut self, policy: CoalescePolicy) {
+        self.coalesce = policy;
+    }
 
-        self.len += data.len();
+    /// Sets the size in bytes of the segments the "added" buffer is stored in, which defaults to
+    /// 64 KiB. This only affects segments started from now on.
+    ///
+    /// Inserted text is appended to the current segment, and a new one is started once it's
+    /// full, so existing text is never moved or copied as the buffer grows. Larger segments mean
+    /// fewer allocations, while smaller ones waste less memory when a segment is abandoned
+    /// because a [`PTableSlice`] still refers to it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` is 0.
+    pub fn set_added_segment_size(&mut self, bytes: usize) {
+        assert!(
+            bytes > 0,
+            "invalid set_added_segment_size: size must not be 0"
+        );
+        self.added.set_segment_size(bytes);
     }
 
     /// Deletes a range of text specified by byte offsets.
     ///
     /// This method removes the text within the given `range` (inclusive of `range.start` and
     /// exclusive of `range.end`). The deletion process involves:
-    /// 1. Identifying the `Node`(s) that overlap with the specified range.
-    /// 2. Removing any `Node`s that are entirely contained within the range.
-    /// 3. Adjusting the boundaries of `Node`s that are partially covered by the range. This may
-    ///    involve splitting a `Node` if the deletion range starts or ends in its middle.
+    /// 1. Splitting the `Node`s which contain `range.start` and `range.end`, so that both ends
+    ///    of the range fall on piece boundaries.
+    /// 2. Removing every `Node` in between, which are now entirely contained within the range.
     ///
     /// Deletion never modifies any of the data in `original` or `added` buffers, only the nodes
     /// themselves.
@@ -250,72 +1136,599 @@ impl<'ptable> PieceTable<'ptable> {
     /// pt.delete(1..4); // Delete "bcd"
     /// assert_eq!(pt.to_string(), "ae");
     /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range.start` is greater than `range.end`, or if `range.end` is past the end of
+    /// the document. In debug builds, or when the `strict` feature is enabled, also panics if
+    /// either end is not on a char boundary. See [`PieceTable::try_delete`] for a non-panicking
+    /// version, and [`PieceTable::delete_clamped`] for one which accepts any range.
     pub fn delete(&mut self, range: Range<usize>) {
-        if let Some((start, byte_idx)) = self.find_node(range.start) {
-            self.delete_complete_nodes(start, byte_idx, &range);
-
-            if let Some(node) = self.nodes.get(start)
-                && byte_idx <= range.start
-                && range.end <= byte_idx + node.range.len()
-                && self.split_node(start, range.end - byte_idx)
-            {
-                self.nodes.get_mut(start).unwrap().range.end -= range.end - range.start;
-            }
+        let checked = if VALIDATE {
+            self.check_range(&range)
+        } else {
+            self.check_range_bounds(&range)
+        };
+        if let Err(err) = checked {
+            panic!("invalid delete: {err}");
         }
-
-        self.len -= range.len();
+        if let Err(err) = self.check_delete_limits(&range) {
+            panic!("invalid delete: {err}");
+        }
+        self.delete_unchecked(range);
     }
 
-    /// Replaces a range of text with a new string.
+    /// Deletes a range of text specified by byte offsets, or returns an error if the range is
+    /// invalid.
     ///
-    /// This method first deletes the text starting at `offset` up to`data.len()` bytes, and then
-    /// inserts the new `data` at the same `offset`.
+    /// Unlike [`PieceTable::delete`], the range is always validated, regardless of build mode.
+    /// If an error is returned the `PieceTable` is left untouched.
     ///
     /// # Examples
     ///
     /// ```
-    /// # use piece_table::PieceTable;
-    /// let mut pt = PieceTable::new("hello, world!");
-    /// // Replace "world" (7 bytes) with "cruel world"
-    /// pt.replace("cruel world", 7);
-    /// assert_eq!(pt.to_string(), "hello, cruel world!");
+    /// # use piece_table::{EditError, PieceTable};
+    /// let mut pt = PieceTable::new("hello");
+    /// assert_eq!(pt.try_delete(3..9), Err(EditError::OutOfBounds { offset: 9, len: 5 }));
+    /// assert_eq!(pt.try_delete(3..1), Err(EditError::InvalidRange { start: 3, end: 1 }));
+    /// assert_eq!(pt.try_delete(1..4), Ok(()));
+    /// assert_eq!(pt.to_string(), "ho");
     /// ```
-    pub fn replace(&mut self, data: &str, offset: usize) {
-        let end = (offset + data.len()).min(self.len() - 1);
-        self.delete(offset..end);
-        self.insert(data, offset);
+    pub fn try_delete(&mut self, range: Range<usize>) -> Result<(), EditError> {
+        self.check_range(&range)?;
+        self.check_delete_limits(&range)?;
+        self.delete_unchecked(range);
+        Ok(())
     }
 
-    /// Creates an immutable snapshot of the `PieceTable`'s current state.
-    ///
-    /// This method captures the entire content of the `PieceTable` at the moment it is called
-    /// and returns it as a `PTableSlice`. The returned slice is immutable and will not reflect
-    /// any subsequent modifications (insertions, deletions) made to the original `PieceTable`.
-    /// This is useful for operations that require a consistent, unchanging view of the text,
-    /// such as for rendering, saving, or complex analysis.
+    /// Deletes the part of `range` which lies inside the document, and returns the range which
+    /// was actually deleted.
     ///
-    /// The lifetime of the returned slice is tied to the lifetime of the `PieceTable` itself,
-    /// ensuring that the underlying text data remains valid while still alowing for mutation
-    /// of the original `PieceTable`.
+    /// Ends past the end of the document are moved back to it, and a `range.start` past
+    /// `range.end` is moved back to `range.end`, so nothing is deleted for it. This suits ranges
+    /// coming from the user, like a selection made before the document shrank, which should be
+    /// cut down rather than rejected. Both ends must still be on char boundaries, as for
+    /// [`PieceTable::delete`].
     ///
     /// # Examples
     ///
     /// ```
     /// # use piece_table::PieceTable;
     /// let mut pt = PieceTable::new("hello");
-    /// let snapshot = pt.create_slice();
-    /// assert_eq!(snapshot.to_string(), "hello");
-    ///
-    /// pt.insert(" world", 5);
-    /// assert_eq!(pt.to_string(), "hello world");
-    /// assert_eq!(snapshot.to_string(), "hello");
+    /// assert_eq!(pt.delete_clamped(3..9), 3..5);
+    /// assert_eq!(pt.to_string(), "hel");
+    /// assert_eq!(pt.delete_clamped(2..1), 1..1);
+    /// assert_eq!(pt.delete_clamped(7..8), 3..3);
+    /// assert_eq!(pt.to_string(), "hel");
+    /// ```
+    pub fn delete_clamped(&mut self, range: Range<usize>) -> Range<usize> {
+        let end = range.end.min(self.len);
+        let range = range.start.min(end)..end;
+        self.delete(range.clone());
+        range
+    }
+
+    #[cfg_attr(
+        feature = "trace",
+        tracing::instrument(
+            level = "debug",
+            skip(self, range),
+            fields(start = range.start, end = range.end, pieces = self.nodes.len())
+        )
+    )]
+    fn delete_unchecked(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+
+        let first = match self.extend_last_delete(&range) {
+            Some(first) => first,
+            None => {
+                let first = self.split_at_offset(range.start);
+                let last = self.split_at_offset(range.end);
+                for node in self.nodes.drain(first..last) {
+                    self.newlines -= node.newlines;
+                    self.chars -= node.chars;
+                }
+                first
+            }
+        };
+        self.last_delete = Some((range.start, first));
+
+        self.len -= range.len();
+        self.stream.on_delete(range);
+        self.publish();
+    }
+
+    /// Deletes `range` by trimming one of the pieces next to the last deletion, if `range` is
+    /// right before or after it and within that piece, returning the index of the piece which
+    /// now starts at `range.start`.
+    ///
+    /// This is the common case of pressing backspace or delete repeatedly, which then needs
+    /// neither a lookup nor a split. The caller still has to update the length and the stream.
+    fn extend_last_delete(&mut self, range: &Range<usize>) -> Option<usize> {
+        let (offset, idx) = self.last_delete?;
+        let (trim_idx, sub) = if range.end == offset {
+            // Backspace, so the end of the piece before the hole is cut off
+            let prev = idx.checked_sub(1)?;
+            let len = self.nodes[prev].range.len();
+            (
+                prev,
+                0..len.checked_sub(range.len()).filter(|&end| end > 0)?,
+            )
+        } else if range.start == offset {
+            // Delete, so the start of the piece after the hole is cut off
+            let len = self.nodes.get(idx)?.range.len();
+            (idx, range.len()..len)
+        } else {
+            return None;
+        };
+        if sub.is_empty() {
+            return None;
+        }
+
+        let node = &self.nodes[trim_idx];
+        let trimmed = node.sub(sub, self.original, &self.added);
+        self.newlines -= node.newlines - trimmed.newlines;
+        self.chars -= node.chars - trimmed.chars;
+        self.nodes[trim_idx] = trimmed;
+        self.prefix_sums.invalidate();
+        Some(idx)
+    }
+
+    /// Returns the contents of the `PieceTable` as a `String`.
+    ///
+    /// This produces the same result as `to_string()`, but since the total length is known up
+    /// front the `String` is allocated exactly once and every piece is copied straight into it,
+    /// instead of going through the formatting machinery.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello");
+    /// pt.insert(" world", 5);
+    /// assert_eq!(pt.as_string(), "hello world");
+    /// ```
+    pub fn as_string(&self) -> String {
+        let mut string = String::with_capacity(self.len);
+        self.copy_into(&mut string);
+        string
+    }
+
+    /// Returns the contents of the `PieceTable` as a `&str`, if they are stored contiguously.
+    ///
+    /// This is the case when the text is represented by at most one piece, such as a freshly
+    /// created `PieceTable`, so no allocation is needed. Returns `None` otherwise, in which case
+    /// [`PieceTable::as_string`] can be used instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello");
+    /// assert_eq!(pt.as_str(), Some("hello"));
+    ///
+    /// pt.insert(" world", 5);
+    /// assert_eq!(pt.as_str(), None);
+    /// ```
+    pub fn as_str(&self) -> Option<&str> {
+        match self.nodes.len() {
+            0 => Some(""),
+            1 => self.nodes[0].contiguous_text(self.original, &self.added),
+            _ => None,
+        }
+    }
+
+    /// Appends the contents of the `PieceTable` to `buf`.
+    ///
+    /// `buf` is grown at most once, so reusing the same buffer across calls (e.g. once per
+    /// rendered frame) avoids allocating altogether.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let pt = PieceTable::new("world");
+    /// let mut buf = String::from("hello ");
+    /// pt.copy_into(&mut buf);
+    /// assert_eq!(buf, "hello world");
+    /// ```
+    pub fn copy_into(&self, buf: &mut String) {
+        buf.reserve(self.len);
+        for node in &self.nodes {
+            node.chunks(self.original, &self.added)
+                .for_each(|chunk| buf.push_str(chunk));
+        }
+    }
+
+    /// Returns an iterator over the text of every piece, from the last one to the first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello");
+    /// pt.insert(" world", 5);
+    /// assert_eq!(pt.chunks_rev().collect::<Vec<_>>(), vec![" world", "hello"]);
+    /// ```
+    pub fn chunks_rev(&self) -> impl Iterator<Item = &str> {
+        self.nodes
+            .iter()
+            .rev()
+            .flat_map(|node| node.chunks(self.original, &self.added).rev())
+    }
+
+    /// Returns an iterator over the chars of the text, from the last one to the first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hé");
+    /// pt.insert("!", 3);
+    /// assert_eq!(pt.chars_rev().collect::<String>(), "!éh");
+    /// ```
+    pub fn chars_rev(&self) -> impl Iterator<Item = char> {
+        self.chunks_rev().flat_map(|chunk| chunk.chars().rev())
+    }
+
+    /// Returns an iterator over the bytes of the text, from the last one to the first.
+    pub fn bytes_rev(&self) -> impl Iterator<Item = u8> {
+        self.chunks_rev().flat_map(|chunk| chunk.bytes().rev())
+    }
+
+    /// Replaces a range of text with a new string.
+    ///
+    /// This method first deletes the text starting at `offset` up to `data.len()` bytes, and then
+    /// inserts the new `data` at the same `offset`. The last byte of the text is never deleted,
+    /// so replacing at the end of the text, or in an empty one, only inserts `data`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello, world!");
+    /// // Replace "world" (7 bytes) with "cruel world"
+    /// pt.replace("cruel world", 7);
+    /// assert_eq!(pt.to_string(), "hello, cruel world!");
+    /// ```
+    pub fn replace(&mut self, data: &str, offset: usize) {
+        let end = (offset + data.len())
+            .min(self.len.saturating_sub(1))
+            .max(offset);
+        self.delete(offset..end);
+        self.insert(data, offset);
+    }
+
+    /// Removes a range of text and returns it as a new, standalone `PieceTable`.
+    ///
+    /// This behaves like [`PieceTable::delete`], except the removed text is copied (exactly once,
+    /// into a buffer of the right size) into the returned table. The returned table owns all of
+    /// its data, so it doesn't borrow from `self` or from the original text, and can be kept
+    /// around (e.g. in a kill ring) or pasted into any other document.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello cruel world");
+    /// let cut = pt.extract(6..12);
+    /// assert_eq!(pt.to_string(), "hello world");
+    /// assert_eq!(cut.to_string(), "cruel ");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`PieceTable::delete`].
+    pub fn extract(&mut self, range: Range<usize>) -> PieceTable<'static> {
+        let checked = if VALIDATE {
+            self.check_range(&range)
+        } else {
+            self.check_range_bounds(&range)
+        };
+        if let Err(err) = checked {
+            panic!("invalid extract: {err}");
+        }
+        if let Err(err) = self.check_delete_limits(&range) {
+            panic!("invalid extract: {err}");
+        }
+        if range.is_empty() {
+            return PieceTable::from(String::new());
+        }
+
+        let mut text = String::with_capacity(range.len());
+        let first = self.split_at_offset(range.start);
+        let last = self.split_at_offset(range.end);
+        for node in self.nodes.drain(first..last) {
+            node.chunks(self.original, &self.added)
+                .for_each(|chunk| text.push_str(chunk));
+            self.newlines -= node.newlines;
+            self.chars -= node.chars;
+        }
+        self.len -= range.len();
+        self.stream.on_delete(range);
+        self.publish();
+
+        PieceTable::from(text)
+    }
+
+    /// Moves the text in `src` to `dest_offset`, without copying it.
+    ///
+    /// `dest_offset` is an offset in the document before the move, so moving text to the start
+    /// of the next line works the same whether it's above or below. The pieces covering `src`
+    /// are relocated in the list of pieces, and neither buffer is touched. An offset inside
+    /// `src` (or at either end of it) leaves the text where it is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("one\ntwo\nthree\n");
+    /// // Drag "one\n" below "two\n"
+    /// pt.move_range(0..4, 8);
+    /// assert_eq!(pt.to_string(), "two\none\nthree\n");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `src` or `dest_offset` is out of bounds, or if splitting the pieces at either end
+    /// of `src` and at `dest_offset` could exceed one of the table's [`Limits`](limits::Limits).
+    /// In debug builds, or when the `strict` feature is enabled, also panics if either end of
+    /// `src` or `dest_offset` isn't on a char boundary.
+    pub fn move_range(&mut self, src: Range<usize>, dest_offset: usize) {
+        let checked = if VALIDATE {
+            self.check_range(&src)
+                .and_then(|()| self.check_offset(dest_offset))
+        } else {
+            self.check_range_bounds(&src)
+                .and_then(|()| self.check_offset_bounds(dest_offset))
+        };
+        if let Err(err) = checked {
+            panic!("invalid move_range: {err}");
+        }
+        if (src.start..=src.end).contains(&dest_offset) {
+            return;
+        }
+        let splits = self.splits_at(src.start) + self.splits_at(src.end);
+        if let Err(err) = self.check_growth(0, 0, splits + self.splits_at(dest_offset)) {
+            panic!("invalid move_range: {err}");
+        }
+
+        if dest_offset < src.start {
+            let dest = self.split_at_offset(dest_offset);
+            let first = self.split_at_offset(src.start);
+            let last = self.split_at_offset(src.end);
+            self.nodes.make_contiguous()[dest..last].rotate_right(last - first);
+        } else {
+            let first = self.split_at_offset(src.start);
+            let last = self.split_at_offset(src.end);
+            let dest = self.split_at_offset(dest_offset);
+            self.nodes.make_contiguous()[first..dest].rotate_left(last - first);
+        }
+
+        let dest_offset = if dest_offset < src.start {
+            dest_offset
+        } else {
+            dest_offset - src.len()
+        };
+        self.stream.on_delete(src.clone());
+        self.stream.on_insert(dest_offset, src.len());
+        self.publish();
+    }
+
+    /// Inserts a copy of the text in `range` at `dest_offset`, without copying the text itself.
+    ///
+    /// The copy is made of new pieces referring to the same bytes as the ones covering `range`,
+    /// so e.g. "duplicate line" takes time proportional to the number of pieces rather than the
+    /// length of the text. `dest_offset` is an offset in the document before the copy, and may
+    /// be inside `range`.
+    ///
+    /// Afterwards some bytes of the "added" buffer are referenced by more than one piece. This
+    /// is invisible to edits, but anything which reclaims or rewrites parts of the buffer has
+    /// to keep bytes alive until their last piece is gone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("let x = 1;\n");
+    /// pt.duplicate_range(0..11, 11);
+    /// assert_eq!(pt.to_string(), "let x = 1;\nlet x = 1;\n");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, or when the `strict` feature is enabled, panics if `range` isn't a valid
+    /// range or `dest_offset` isn't a valid offset. Panics if the copy could exceed one of the
+    /// table's [`Limits`](limits::Limits).
+    pub fn duplicate_range(&mut self, range: Range<usize>, dest_offset: usize) {
+        if VALIDATE
+            && let Err(err) = self
+                .check_range(&range)
+                .and_then(|()| self.check_offset(dest_offset))
+        {
+            panic!("invalid duplicate_range: {err}");
+        }
+        if range.is_empty() {
+            return;
+        }
+
+        if let Err(err) = self.check_duplicate_limits(&range, dest_offset) {
+            panic!("invalid duplicate_range: {err}");
+        }
+
+        let first = self.split_at_offset(range.start);
+        let last = self.split_at_offset(range.end);
+        let nodes = self.nodes.range(first..last).cloned().collect();
+        self.insert_nodes(dest_offset, nodes);
+        self.publish();
+    }
+
+    /// Splits the `PieceTable` in two at the given byte offset.
+    ///
+    /// Afterwards `self` contains the text in `[0, at)`, and the returned table contains the
+    /// text in `[at, len)`. This works at the piece level: the pieces after `at` are moved to
+    /// the new table, which shares the original text, the segments of the "added" buffer and
+    /// the attached [sources](sources) with `self`, so no text is copied.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello world");
+    /// pt.insert(",", 5);
+    /// let rest = pt.split_off(6);
+    /// assert_eq!(pt.to_string(), "hello,");
+    /// assert_eq!(rest.to_string(), " world");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, or when the `strict` feature is enabled, panics if `at` is out of bounds
+    /// or not on a char boundary.
+    pub fn split_off(&mut self, at: usize) -> PieceTable<'ptable> {
+        if VALIDATE && let Err(err) = self.check_offset(at) {
+            panic!("invalid split_off: {err}");
+        }
+
+        let first = self.split_at_offset(at);
+        let mut nodes = VecDeque::with_capacity(self.nodes.len() - first);
+        let mut newlines = 0;
+        let mut chars = 0;
+        for node in self.nodes.drain(first..) {
+            newlines += node.newlines;
+            chars += node.chars;
+            nodes.push_back(node);
+        }
+
+        let len = self.len - at;
+        self.len = at;
+        self.stream.clamp(at);
+        self.newlines -= newlines;
+        self.chars -= chars;
+        self.publish();
+
+        PieceTable {
+            original: self.original,
+            added: self.added.clone(),
+            sources: self.sources.clone(),
+            nodes,
+            len,
+            newlines,
+            chars,
+            prefix_sums: Default::default(),
+            last_delete: None,
+            publisher: Default::default(),
+            checkpoints: Vec::new(),
+            checkpoint_budget: self.checkpoint_budget,
+            coalesce: self.coalesce,
+            normalize: self.normalize,
+            limits: self.limits,
+            generation: cursor::next_generation(),
+            buffers: cursor::next_generation(),
+            stream: Default::default(),
+            #[cfg(feature = "instrument")]
+            counters: Default::default(),
+        }
+    }
+
+    /// Appends the contents of another `PieceTable` to the end of this one.
+    ///
+    /// This works at the piece level: `other`'s "added" buffer is appended to this table's, and
+    /// its pieces are moved over with their ranges offset accordingly. If both tables share the
+    /// same original text (e.g. when `other` was created with [`PieceTable::split_off`]), pieces
+    /// of the original text are moved as-is; otherwise their text is copied into the "added"
+    /// buffer.
+    ///
+    /// The [sources](sources) attached to `other` are attached to this table after its own, so
+    /// the source with index `i` in `other` gets the index `i` plus the number of sources
+    /// attached to this table.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello world");
+    /// let rest = pt.split_off(5);
+    /// pt.insert(",", 5);
+    /// pt.append_table(rest);
+    /// assert_eq!(pt.to_string(), "hello, world");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if appending `other` would exceed one of this table's [`Limits`](limits::Limits).
+    pub fn append_table(&mut self, other: PieceTable<'ptable>) {
+        let shares_original = std::ptr::eq(self.original, other.original);
+        let copied: usize = other
+            .nodes
+            .iter()
+            .filter(|node| !shares_original && matches!(node.kind, NodeKind::Original))
+            .map(|node| node.range.len())
+            .sum();
+        let added = other.added.owned_len() + copied;
+        if let Err(err) = self.check_growth(other.len, added, other.nodes.len()) {
+            panic!("invalid append_table: {err}");
+        }
+        let added_offset = self.added.append(other.added);
+        self.sources.extend(
+            other
+                .sources
+                .into_iter()
+                .map(|(start, text)| (start + added_offset, text)),
+        );
+
+        for mut node in other.nodes {
+            match node.kind {
+                NodeKind::Added => {
+                    node.range = node.range.start + added_offset..node.range.end + added_offset;
+                }
+                NodeKind::Original if !shares_original => {
+                    node.kind = NodeKind::Added;
+                    node.range = self.added.push_str(&other.original[node.range.clone()]);
+                }
+                NodeKind::Original | NodeKind::Inline(_) | NodeKind::Repeat(_) => {}
+            }
+            self.nodes.push_back(node);
+        }
+
+        self.len += other.len;
+        self.newlines += other.newlines;
+        self.chars += other.chars;
+        self.pieces_changed();
+        self.publish();
+    }
+
+    /// Creates an immutable snapshot of the `PieceTable`'s current state.
+    ///
+    /// This method captures the entire content of the `PieceTable` at the moment it is called
+    /// and returns it as a `PTableSlice`. The returned slice is immutable and will not reflect
+    /// any subsequent modifications (insertions, deletions) made to the original `PieceTable`.
+    /// This is useful for operations that require a consistent, unchanging view of the text,
+    /// such as for rendering, saving, or complex analysis.
+    ///
+    /// The lifetime of the returned slice is tied to the lifetime of the `PieceTable` itself,
+    /// ensuring that the underlying text data remains valid while still alowing for mutation
+    /// of the original `PieceTable`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello");
+    /// let snapshot = pt.create_slice();
+    /// assert_eq!(snapshot.to_string(), "hello");
+    ///
+    /// pt.insert(" world", 5);
+    /// assert_eq!(pt.to_string(), "hello world");
+    /// assert_eq!(snapshot.to_string(), "hello");
     /// ```
     pub fn create_slice(&self) -> PTableSlice<'ptable> {
         PTableSlice {
             nodes: self.nodes.iter().cloned().collect(),
-            original: self.original as *const str,
-            added: &self.added as *const _,
-            _marker: std::marker::PhantomData,
+            original: self.original,
+            added: self.added.clone(),
         }
     }
 
@@ -347,6 +1760,14 @@ impl<'ptable> PieceTable<'ptable> {
     /// let slice = pt.slice(1..7); // "bcdefg"
     /// assert_eq!(slice.to_string(), "bcdefg");
     /// ```
+    #[cfg_attr(
+        feature = "trace",
+        tracing::instrument(
+            level = "debug",
+            skip(self, range),
+            fields(start = range.start, end = range.end, pieces = self.nodes.len())
+        )
+    )]
     pub fn slice(&self, range: Range<usize>) -> PTableSlice<'ptable> {
         let mut nodes = Vec::new();
         let mut byte_idx = 0;
@@ -364,25 +1785,16 @@ impl<'ptable> PieceTable<'ptable> {
                     nodes.push(node.clone());
                 }
                 if !found_start {
-                    // First node in range - may need to adjust start
+                    // First node in range - may need to adjust start (and end, if the whole
+                    // range is contained in this node)
                     let start_offset = range.start.saturating_sub(node_start);
-                    let mut new_node = node.clone();
-                    new_node.range.start += start_offset;
-
-                    if node_end >= range.end {
-                        new_node.range.end -= node_end - range.end;
-                        nodes.push(new_node);
-                        found_start = true;
-                    } else {
-                        nodes.push(new_node);
-                        found_start = true;
-                    }
+                    let end_offset = node_len - node_end.saturating_sub(range.end);
+                    nodes.push(node.sub(start_offset..end_offset, self.original, &self.added));
+                    found_start = true;
                 } else if node_end >= range.end {
                     // Last node in range - may need to adjust end
-                    let end_offset = node_end - range.end;
-                    let mut new_node = node.clone();
-                    new_node.range.end -= end_offset;
-                    nodes.push(new_node);
+                    let end_offset = node_len - (node_end - range.end);
+                    nodes.push(node.sub(0..end_offset, self.original, &self.added));
                     break;
                 }
             }
@@ -392,9 +1804,8 @@ impl<'ptable> PieceTable<'ptable> {
 
         PTableSlice {
             nodes,
-            original: self.original as *const str,
-            added: &self.added as *const _,
-            _marker: std::marker::PhantomData,
+            original: self.original,
+            added: self.added.clone(),
         }
     }
 
@@ -402,13 +1813,9 @@ impl<'ptable> PieceTable<'ptable> {
         if let Some((idx, byte_idx)) = self.find_node(at) {
             let offset = at - byte_idx;
             let node = &self.nodes[idx];
+            let (chunk, chunk_start) = node.chunk_at(offset, self.original, &self.added);
 
-            let byte = match node.kind {
-                NodeKind::Original => self.original.as_bytes()[node.range.start + offset],
-                NodeKind::Added => self.added.as_bytes()[node.range.start + offset],
-            };
-
-            Some(byte)
+            Some(chunk.as_bytes()[offset - chunk_start])
         } else {
             None
         }
@@ -418,63 +1825,212 @@ impl<'ptable> PieceTable<'ptable> {
         if let Some((idx, byte_idx)) = self.find_node(at) {
             let offset = at - byte_idx;
             let node = &self.nodes[idx];
+            let (chunk, chunk_start) = node.chunk_at(offset, self.original, &self.added);
 
-            if let Some(byte) = match node.kind {
-                NodeKind::Original => self.original[node.range.start + offset..].chars().next(),
-                NodeKind::Added => self.added[node.range.start + offset..].chars().next(),
-            } {
-                Some(byte)
-            } else {
-                None
-            }
+            chunk[offset - chunk_start..].chars().next()
         } else {
             None
         }
     }
 
-    /// Deletes all nodes which are entirely contained within the specified range
+    /// Checks that `offset` is a valid position to edit at
+    fn check_offset(&self, offset: usize) -> Result<(), EditError> {
+        if offset > self.len {
+            Err(EditError::OutOfBounds {
+                offset,
+                len: self.len,
+            })
+        } else if !self.is_edit_boundary(offset) {
+            Err(EditError::NotCharBoundary { offset })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Like [`PieceTable::is_char_boundary`] for an `offset <= self.len`, but never builds the
+    /// prefix sums, so validating an edit doesn't take a pass over all pieces.
+    fn is_edit_boundary(&self, offset: usize) -> bool {
+        let Some((idx, node_start)) = self.find_node_for_edit(offset) else {
+            return true;
+        };
+        let offset = offset - node_start;
+        let (chunk, chunk_start) = self.nodes[idx].chunk_at(offset, self.original, &self.added);
+        (chunk.as_bytes()[offset - chunk_start] as i8) >= -0x40
+    }
+
+    /// Checks that `range` is a valid range to edit
+    fn check_range(&self, range: &Range<usize>) -> Result<(), EditError> {
+        self.check_range_bounds(range)?;
+        self.check_offset(range.end)?;
+        self.check_offset(range.start)
+    }
+
+    /// Checks that `range` isn't reversed and lies inside the document, without looking at the
+    /// text, so it's cheap enough to do on every edit
+    fn check_range_bounds(&self, range: &Range<usize>) -> Result<(), EditError> {
+        if range.start > range.end {
+            Err(EditError::InvalidRange {
+                start: range.start,
+                end: range.end,
+            })
+        } else if range.end > self.len {
+            Err(EditError::OutOfBounds {
+                offset: range.end,
+                len: self.len,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks that `offset` lies inside the document, like [`PieceTable::check_range_bounds`]
+    fn check_offset_bounds(&self, offset: usize) -> Result<(), EditError> {
+        self.check_range_bounds(&(offset..offset))
+    }
+
+    /// Inserts already existing pieces at `offset`, which must be a valid offset.
     ///
-    /// Any nodes which are only partially in the range will not be deleted and must be dealt with
-    /// by the caller.
-    fn delete_complete_nodes(&mut self, idx: usize, byte_idx: usize, range: &Range<usize>) {
-        let mut start = None;
-        let mut end = None;
-        let mut byte_idx = byte_idx;
+    /// The pieces must refer to this table's buffers.
+    fn insert_nodes(&mut self, offset: usize, nodes: Vec<Node>) {
+        let insert_idx = self.split_at_offset(offset);
+        for node in nodes.into_iter().rev() {
+            self.len += node.range.len();
+            self.newlines += node.newlines;
+            self.chars += node.chars;
+            self.stream.on_insert(offset, node.range.len());
+            self.nodes.insert(insert_idx, node);
+        }
+    }
 
-        for i in idx..self.nodes.len() {
-            let node = &self.nodes[i];
-            let node_len = node.range.len();
+    /// Returns the text from `offset` to the end, one piece at a time.
+    fn chunks_from(&self, offset: usize) -> impl Iterator<Item = &str> {
+        let (first, node_start) = self.find_node(offset).unwrap_or((self.nodes.len(), offset));
+        self.nodes
+            .range(first..)
+            .enumerate()
+            .flat_map(move |(i, node)| {
+                let start = if i == 0 { offset - node_start } else { 0 };
+                node.chunks_in(start..node.range.len(), self.original, &self.added)
+            })
+    }
 
-            if byte_idx >= range.start && byte_idx + node_len <= range.end {
-                if start.is_none() {
-                    start = Some(i);
-                }
-                end = Some(i);
-            }
+    /// Returns the byte offset where line `line` (0-based) starts, or `None` if there are fewer
+    /// lines.
+    ///
+    /// Whole pieces are skipped using their newline counts, so only the piece containing the
+    /// line's start is scanned.
+    fn line_start(&self, line: usize) -> Option<usize> {
+        if line == 0 {
+            return Some(0);
+        }
 
-            byte_idx += node_len;
-            if byte_idx >= range.end {
-                break;
+        let mut remaining = line;
+        let mut node_start = 0;
+        for node in &self.nodes {
+            count!(self, nodes_visited, 1);
+            if node.newlines >= remaining {
+                let idx = match node.kind {
+                    // A repeat piece with newlines is nothing but newlines
+                    NodeKind::Repeat(_) => remaining - 1,
+                    _ => {
+                        let text = node.text(self.original, &self.added);
+                        text.match_indices('\n').nth(remaining - 1)?.0
+                    }
+                };
+                return Some(node_start + idx + 1);
             }
+            remaining -= node.newlines;
+            node_start += node.range.len();
         }
+        None
+    }
 
-        if let (Some(first), Some(last)) = (start, end) {
-            self.nodes.drain(first..=last);
+    /// Returns the (0-based) line containing `offset`, assuming `offset <= self.len`, which may
+    /// be inside a char.
+    fn line_of(&self, offset: usize) -> usize {
+        let mut line = 0;
+        let mut node_start = 0;
+        for node in &self.nodes {
+            count!(self, nodes_visited, 1);
+            let node_end = node_start + node.range.len();
+            if node_end > offset {
+                return line
+                    + node.newlines_before(offset - node_start, self.original, &self.added);
+            }
+            line += node.newlines;
+            node_start = node_end;
         }
+        line
     }
 
     /// Internal helper method to find the node that contains the char at `offset`
+    ///
+    /// With enough pieces this builds the cached prefix sums if they are out of date, so the
+    /// lookups after it are binary searches.
     fn find_node(&self, offset: usize) -> Option<(usize, usize)> {
-        let mut byte_idx = 0;
-
-        for (idx, node) in self.nodes.iter().enumerate() {
-            if byte_idx + node.range.len() > offset {
-                return Some((idx, byte_idx));
+        #[cfg(feature = "instrument")]
+        let built = self.prefix_sums.get().is_none();
+        match self.prefix_sums.get_or_build(&self.nodes) {
+            Some(ends) => {
+                // Building the sums visits every piece
+                count!(
+                    self,
+                    nodes_visited,
+                    if built { self.nodes.len() } else { 0 }
+                );
+                self.find_node_in(ends, offset)
             }
-            byte_idx += node.range.len();
+            None => self.find_node_linear(offset),
         }
+    }
 
-        None
+    /// Like [`PieceTable::find_node`], but only uses the prefix sums if they are up to date, so
+    /// edits never pay for building them.
+    fn find_node_for_edit(&self, offset: usize) -> Option<(usize, usize)> {
+        match self.prefix_sums.get() {
+            Some(ends) => self.find_node_in(ends, offset),
+            None => self.find_node_linear(offset),
+        }
+    }
+
+    fn find_node_in(&self, ends: &[usize], offset: usize) -> Option<(usize, usize)> {
+        count!(self, find_node_calls, 1);
+        count!(
+            self,
+            nodes_visited,
+            (usize::BITS - ends.len().leading_zeros()) as usize
+        );
+        prefix_sums::find(ends, offset)
+    }
+
+    /// Finds the piece containing `offset` by walking the pieces from the nearer end of the
+    /// document, so edits near either end don't depend on the number of pieces.
+    fn find_node_linear(&self, offset: usize) -> Option<(usize, usize)> {
+        count!(self, find_node_calls, 1);
+        if offset >= self.len {
+            return None;
+        }
+
+        if offset < self.len / 2 {
+            let mut byte_idx = 0;
+            for (idx, node) in self.nodes.iter().enumerate() {
+                if byte_idx + node.range.len() > offset {
+                    count!(self, nodes_visited, idx + 1);
+                    return Some((idx, byte_idx));
+                }
+                byte_idx += node.range.len();
+            }
+        } else {
+            let mut node_start = self.len;
+            for (idx, node) in self.nodes.iter().enumerate().rev() {
+                node_start -= node.range.len();
+                if node_start <= offset && !node.range.is_empty() {
+                    count!(self, nodes_visited, self.nodes.len() - idx);
+                    return Some((idx, node_start));
+                }
+            }
+        }
+        unreachable!("offset {offset} is inside the document but not in any piece")
     }
 
     /// Tries to split a node and returns `true` if succeeded and `false` otherwise
@@ -487,21 +2043,250 @@ impl<'ptable> PieceTable<'ptable> {
     ///    Same as case 1
     ///
     /// 3. `offset != 0 && offset < range.len()`:
-    ///    The node is split
+    ///    The node is split, and the newline counts of both halves are recomputed
     fn split_node(&mut self, piece_idx: usize, offset: usize) -> bool {
-        let first_node = self.nodes.get_mut(piece_idx).unwrap();
-
-        if offset == 0 {
-            false
-        } else if first_node.range.len() > offset {
-            let mut second_node = first_node.clone();
-            first_node.range.end -= first_node.range.len() - offset;
-            second_node.range.start += offset;
-            self.nodes.insert(piece_idx + 1, second_node);
-            true
+        let node = &self.nodes[piece_idx];
+        if offset == 0 || offset >= node.range.len() {
+            return false;
+        }
+
+        let first = node.sub(0..offset, self.original, &self.added);
+        let second = node.sub(offset..node.range.len(), self.original, &self.added);
+        self.nodes[piece_idx] = first;
+        self.insert_node(piece_idx + 1, second);
+        count!(self, splits, 1);
+        true
+    }
+
+    /// Drops everything cached about the pieces, which must be done whenever they change.
+    fn pieces_changed(&mut self) {
+        self.prefix_sums.invalidate();
+        self.last_delete = None;
+    }
+
+    /// Inserts a node at `idx`, counting the allocation if the list of nodes has to grow.
+    fn insert_node(&mut self, idx: usize, node: Node) {
+        #[cfg(feature = "instrument")]
+        let capacity = self.nodes.capacity();
+        self.pieces_changed();
+        self.nodes.insert(idx, node);
+        count!(
+            self,
+            allocations,
+            (self.nodes.capacity() != capacity) as usize
+        );
+    }
+
+    /// Makes sure there's a piece boundary at `offset`, splitting a node if needed.
+    ///
+    /// Returns the index of the node which starts at `offset`, or `self.nodes.len()` if `offset`
+    /// is at (or past) the end of the text. Since it's called before the pieces are changed, it
+    /// also calls [`PieceTable::pieces_changed`].
+    fn split_at_offset(&mut self, offset: usize) -> usize {
+        let found = self.find_node_for_edit(offset);
+        self.pieces_changed();
+        match found {
+            Some((node_idx, node_pos)) => {
+                if self.split_node(node_idx, offset - node_pos) {
+                    node_idx + 1
+                } else {
+                    node_idx
+                }
+            }
+            None => self.nodes.len(),
+        }
+    }
+}
+
+/// Returns the text of an inline node, see [`NodeKind::Inline`].
+fn inline_text(bytes: &[u8; INLINE_CAPACITY], range: Range<usize>) -> &str {
+    std::str::from_utf8(&bytes[range]).expect("inline pieces hold whole chars")
+}
+
+/// Returns `len` bytes of the text of a repeat node of `byte`, in chunks of at most
+/// [`REPEAT_CHUNK`] bytes, see [`NodeKind::Repeat`].
+fn repeat_chunks<'s>(byte: u8, len: usize) -> impl DoubleEndedIterator<Item = &'s str> {
+    let start = byte as usize * REPEAT_CHUNK;
+    let chunk: &'s str = &REPEATS[start..start + REPEAT_CHUNK];
+    let rest = &chunk[..len % REPEAT_CHUNK];
+    std::iter::repeat_n(chunk, len / REPEAT_CHUNK).chain((!rest.is_empty()).then_some(rest))
+}
+
+impl Node {
+    /// Creates a node of `kind` referring to `range`, which holds `text`, computing its counts.
+    fn new(kind: NodeKind, range: Range<usize>, text: &str) -> Node {
+        Node {
+            kind,
+            range,
+            newlines: count_newlines(text),
+            chars: count_chars(text),
+            words: count_words(text),
+            crs: CrCounts::of(text),
+            #[cfg(feature = "digest")]
+            hash: digest::hash(text),
+        }
+    }
+
+    /// Creates a node holding `len` copies of the ASCII char `byte`, see [`NodeKind::Repeat`].
+    fn repeat(byte: u8, len: usize) -> Node {
+        debug_assert!(byte.is_ascii(), "repeat pieces hold ASCII chars");
+        Node {
+            kind: NodeKind::Repeat(byte),
+            range: 0..len,
+            newlines: if byte == b'\n' { len } else { 0 },
+            chars: len,
+            words: (len > 0 && !byte.is_ascii_whitespace()) as usize,
+            crs: CrCounts {
+                crs: if byte == b'\r' { len } else { 0 },
+                crlfs: 0,
+            },
+            #[cfg(feature = "digest")]
+            hash: digest::repeat(byte, len),
+        }
+    }
+
+    /// Returns the text this node refers to
+    ///
+    /// # Panics
+    ///
+    /// Panics for a repeat node, whose text is never stored in full, see [`Node::chunks`].
+    fn text<'s>(&'s self, original: &'s str, added: &'s AddedBuffer) -> &'s str {
+        match self.kind {
+            NodeKind::Original => &original[self.range.clone()],
+            NodeKind::Added => &added[self.range.clone()],
+            NodeKind::Inline(ref bytes) => inline_text(bytes, self.range.clone()),
+            NodeKind::Repeat(_) => panic!("the text of a repeat piece is only read in chunks"),
+        }
+    }
+
+    /// Returns the text this node refers to, unless it's a repeat node longer than a single
+    /// chunk, see [`Node::chunks`].
+    fn contiguous_text<'s>(
+        &'s self,
+        original: &'s str,
+        added: &'s AddedBuffer<'s>,
+    ) -> Option<&'s str> {
+        match self.kind {
+            NodeKind::Repeat(_) if self.range.len() > REPEAT_CHUNK => None,
+            NodeKind::Repeat(_) => Some(self.chunk_at(0, original, added).0),
+            _ => Some(self.text(original, added)),
+        }
+    }
+
+    /// Returns the text this node refers to in chunks, which is a single one unless this is a
+    /// repeat node.
+    fn chunks<'s>(
+        &'s self,
+        original: &'s str,
+        added: &'s AddedBuffer<'s>,
+    ) -> impl DoubleEndedIterator<Item = &'s str> {
+        self.chunks_in(0..self.range.len(), original, added)
+    }
+
+    /// Returns the text of `sub`, which is relative to the start of this node, in chunks, see
+    /// [`Node::chunks`].
+    fn chunks_in<'s>(
+        &'s self,
+        sub: Range<usize>,
+        original: &'s str,
+        added: &'s AddedBuffer<'s>,
+    ) -> impl DoubleEndedIterator<Item = &'s str> {
+        let (text, repeat) = match self.kind {
+            NodeKind::Repeat(byte) => (None, Some(repeat_chunks(byte, sub.len()))),
+            _ => (Some(&self.text(original, added)[sub]), None),
+        };
+        text.into_iter().chain(repeat.into_iter().flatten())
+    }
+
+    /// Returns the text of the chunk of this node containing the byte at `offset`, along with
+    /// where it starts in the node. Only repeat nodes have more than one chunk, see
+    /// [`Node::chunks`].
+    fn chunk_at<'s>(
+        &'s self,
+        offset: usize,
+        original: &'s str,
+        added: &'s AddedBuffer,
+    ) -> (&'s str, usize) {
+        match self.kind {
+            NodeKind::Repeat(byte) => {
+                let start =
+                    offset.min(self.range.len().saturating_sub(1)) / REPEAT_CHUNK * REPEAT_CHUNK;
+                let len = (self.range.len() - start).min(REPEAT_CHUNK);
+                let chunk = repeat_chunks(byte, len).next().unwrap_or_default();
+                (chunk, start)
+            }
+            _ => (self.text(original, added), 0),
+        }
+    }
+
+    /// Returns the number of newlines before `offset`, which is relative to the start of this
+    /// node and may be inside a char.
+    fn newlines_before(&self, offset: usize, original: &str, added: &AddedBuffer) -> usize {
+        match self.kind {
+            NodeKind::Repeat(b'\n') => offset,
+            NodeKind::Repeat(_) => 0,
+            _ => {
+                let text = self.text(original, added);
+                count_newlines(&text[..text.floor_char_boundary(offset)])
+            }
+        }
+    }
+
+    /// Creates a node referring to `sub`, which is relative to the start of this node.
+    ///
+    /// The newline and char counts are computed from whichever side of the cut is shorter, so
+    /// trimming a few bytes off a large piece stays cheap.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sub` doesn't start and end on char boundaries of the node's text, since the
+    /// pieces must always hold whole chars.
+    fn sub(&self, sub: Range<usize>, original: &str, added: &AddedBuffer) -> Node {
+        if let NodeKind::Repeat(byte) = self.kind {
+            return Node::repeat(byte, sub.len());
+        }
+        let text = self.text(original, added);
+        assert!(
+            text.is_char_boundary(sub.start) && text.is_char_boundary(sub.end),
+            "piece boundaries must be on char boundaries, but {sub:?} splits a char of a piece \
+             of length {}",
+            text.len()
+        );
+        let newlines = if sub.len() * 2 <= text.len() {
+            count_newlines(&text[sub.clone()])
         } else {
-            first_node.range.end -= offset - 1;
-            false
+            self.newlines - count_newlines(&text[..sub.start]) - count_newlines(&text[sub.end..])
+        };
+        let chars = if sub.len() * 2 <= text.len() {
+            count_chars(&text[sub.clone()])
+        } else {
+            self.chars - count_chars(&text[..sub.start]) - count_chars(&text[sub.end..])
+        };
+        let words = if sub.len() * 2 <= text.len() {
+            count_words(&text[sub.clone()])
+        } else {
+            // A word cut in two is counted on both sides of the cut
+            let (before, inner, after) = (&text[..sub.start], &text[sub.clone()], &text[sub.end..]);
+            self.words + joins_words(before, inner) as usize + joins_words(inner, after) as usize
+                - count_words(before)
+                - count_words(after)
+        };
+        let crs = if sub.len() * 2 <= text.len() {
+            CrCounts::of(&text[sub.clone()])
+        } else {
+            self.crs
+                .inner(&text[..sub.start], &text[sub.clone()], &text[sub.end..])
+        };
+
+        Node {
+            kind: self.kind,
+            range: self.range.start + sub.start..self.range.start + sub.end,
+            newlines,
+            chars,
+            words,
+            crs,
+            #[cfg(feature = "digest")]
+            hash: digest::sub_hash(self.hash, text, sub),
         }
     }
 }
@@ -523,24 +2308,68 @@ impl<'a> EditableText<'a> for PieceTable<'a> {
 impl<'a> Display for PieceTable<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for node in &self.nodes {
-            write!(
-                f,
-                "{}",
-                match node.kind {
-                    NodeKind::Original => unsafe {
-                        self.original
-                            .get_unchecked(node.range.start..node.range.end)
-                    },
-                    NodeKind::Added => unsafe {
-                        self.added.get_unchecked(node.range.start..node.range.end)
-                    },
-                }
-            )?;
+            for chunk in node.chunks(self.original, &self.added) {
+                f.write_str(chunk)?;
+            }
         }
         Ok(())
     }
 }
 
+impl From<String> for PieceTable<'static> {
+    /// Creates a `PieceTable` which owns its text.
+    ///
+    /// The string becomes the "added" buffer instead of the original text, so no copy is made
+    /// and the resulting table has no borrowed data.
+    fn from(string: String) -> Self {
+        let newlines = count_newlines(&string);
+        let chars = count_chars(&string);
+        let mut nodes = VecDeque::new();
+        if !string.is_empty() {
+            nodes.push_back(Node {
+                kind: NodeKind::Added,
+                range: 0..string.len(),
+                newlines,
+                chars,
+                words: count_words(&string),
+                crs: CrCounts::of(&string),
+                #[cfg(feature = "digest")]
+                hash: digest::hash(&string),
+            });
+        }
+
+        PieceTable {
+            original: "",
+            len: string.len(),
+            added: AddedBuffer::from(string),
+            sources: Vec::new(),
+            nodes,
+            newlines,
+            chars,
+            prefix_sums: Default::default(),
+            last_delete: None,
+            publisher: Default::default(),
+            checkpoints: Vec::new(),
+            checkpoint_budget: None,
+            coalesce: CoalescePolicy::default(),
+            normalize: false,
+            limits: Default::default(),
+            generation: cursor::next_generation(),
+            buffers: cursor::next_generation(),
+            stream: Default::default(),
+            #[cfg(feature = "instrument")]
+            counters: Default::default(),
+        }
+    }
+}
+
+impl Default for PieceTable<'_> {
+    /// Creates an empty `PieceTable`, see [`PieceTable::empty`].
+    fn default() -> Self {
+        PieceTable::empty()
+    }
+}
+
 impl<'a> From<&'a str> for PieceTable<'a> {
     fn from(s: &'a str) -> Self {
         PieceTable::new(s)
@@ -549,13 +2378,15 @@ impl<'a> From<&'a str> for PieceTable<'a> {
 
 impl<'a> From<PieceTable<'a>> for String {
     fn from(p: PieceTable<'a>) -> Self {
-        p.to_string()
+        p.as_string()
     }
 }
 
 impl<'a> PartialEq for PieceTable<'a> {
     fn eq(&self, other: &Self) -> bool {
-        *other == self.to_string()
+        let this = self.as_string();
+        let other = other.as_string();
+        this == other
     }
 }
 
@@ -563,23 +2394,28 @@ impl<'a> Eq for PieceTable<'a> {}
 
 impl<'a> PartialEq<String> for PieceTable<'a> {
     fn eq(&self, other: &String) -> bool {
-        &self.to_string() == other
+        &self.as_string() == other
     }
 }
 
 impl<'a> PartialEq<&str> for PieceTable<'a> {
     fn eq(&self, other: &&str) -> bool {
-        &self.to_string() == other
+        &self.as_string() == other
     }
 }
 
 impl<'a> PartialOrd for PieceTable<'a> {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.to_string().cmp(&other.to_string()))
+        Some(self.as_string().cmp(&other.as_string()))
     }
 }
 
 impl<'ptable> PTableSlice<'ptable> {
+    /// Returns the `original` and `added` buffers of the `PieceTable` this slice was created from
+    fn buffers(&self) -> (&'ptable str, &AddedBuffer<'ptable>) {
+        (self.original, &self.added)
+    }
+
     /// Returns the total length of the text in the slice, in bytes.
     ///
     /// This method iterates through all the nodes in the slice and sums up their individual lengths.
@@ -616,14 +2452,63 @@ impl<'ptable> PTableSlice<'ptable> {
         self.len() == 0
     }
 
+    /// Returns the contents of the slice as a `&str`, if they are stored contiguously.
+    ///
+    /// This is the case when the slice covers at most one piece of its `PieceTable`. Returns
+    /// `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello world");
+    /// pt.insert("!", 11);
+    /// assert_eq!(pt.slice(0..5).as_str(), Some("hello"));
+    /// assert_eq!(pt.slice(6..12).as_str(), None);
+    /// ```
+    pub fn as_str(&self) -> Option<&str> {
+        let (original, added) = self.buffers();
+        match self.nodes.as_slice() {
+            [] => Some(""),
+            [node] => node.contiguous_text(original, added),
+            _ => None,
+        }
+    }
+
+    /// Returns an iterator over the text of every piece in the slice, in order.
+    pub fn chunks(&self) -> impl Iterator<Item = &str> {
+        let (original, added) = self.buffers();
+        self.nodes
+            .iter()
+            .flat_map(move |node| node.chunks(original, added))
+    }
+
+    /// Returns an iterator over the text of every piece in the slice, from the last one to the
+    /// first.
+    pub fn chunks_rev(&self) -> impl Iterator<Item = &str> {
+        let (original, added) = self.buffers();
+        self.nodes
+            .iter()
+            .rev()
+            .flat_map(move |node| node.chunks(original, added).rev())
+    }
+
+    /// Returns an iterator over the chars of the slice, from the last one to the first.
+    pub fn chars_rev(&self) -> impl Iterator<Item = char> {
+        self.chunks_rev().flat_map(|chunk| chunk.chars().rev())
+    }
+
+    /// Returns an iterator over the bytes of the slice, from the last one to the first.
+    pub fn bytes_rev(&self) -> impl Iterator<Item = u8> {
+        self.chunks_rev().flat_map(|chunk| chunk.bytes().rev())
+    }
+
     /// Creates a sub-slice from this slice.
     ///
     /// This method allows you to create a new `PTableSlice` that represents a portion of the current slice.
     /// The `range` argument specifies the byte offsets within *this slice* (not the original `PieceTable`)
-    /// that the new slice should cover.
-    ///
-    /// If the specified range is invalid (e.g., `start` > `end`, or the range is out of bounds),
-    /// this method returns `None`. It also returns `None` if the resulting slice would be empty.
+    /// that the new slice should cover, and can be any kind of range, like for `str`. An empty
+    /// range gives an empty slice.
     ///
     /// # Examples
     ///
@@ -631,252 +2516,994 @@ impl<'ptable> PTableSlice<'ptable> {
     /// # use piece_table::PieceTable;
     /// let pt = PieceTable::new("hello world");
     /// let slice = pt.slice(0..11); // "hello world"
-    /// let sub_slice = slice.slice(0..5).unwrap(); // "hello"
-    /// assert_eq!(sub_slice.to_string(), "hello");
+    /// assert_eq!(slice.slice(..5).to_string(), "hello");
+    /// assert_eq!(slice.slice(6..).to_string(), "world");
+    /// assert_eq!(slice.slice(..).to_string(), "hello world");
+    /// assert!(slice.slice(3..3).is_empty());
     /// ```
-    pub fn slice(&self, range: Range<usize>) -> Option<PTableSlice<'ptable>> {
-        let mut new_nodes = Vec::new();
-        let mut byte_idx = 0;
-        let mut remaining = range.end - range.start;
-        let start_offset = range.start;
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of `range` is after its end, if its end is past the end of the slice,
+    /// or if either end isn't on a char boundary.
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> PTableSlice<'ptable> {
+        let len = self.len();
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end + 1,
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => len,
+        };
+        if start > end {
+            panic!("invalid slice: {}", EditError::InvalidRange { start, end });
+        }
+        if end > len {
+            panic!(
+                "invalid slice: {}",
+                EditError::OutOfBounds { offset: end, len }
+            );
+        }
+
+        let (original, added) = self.buffers();
+        let mut nodes = Vec::new();
+        let mut node_start = 0;
+        for node in &self.nodes {
+            let node_end = node_start + node.range.len();
+            if node_start >= end {
+                break;
+            }
+            if node_end > start {
+                let sub = start.saturating_sub(node_start)..end.min(node_end) - node_start;
+                // Pieces hold whole chars, so only offsets inside a piece can split one
+                for offset in [sub.start, sub.end] {
+                    if offset > 0 && offset < node.range.len() {
+                        let (chunk, chunk_start) = node.chunk_at(offset, original, added);
+                        if (chunk.as_bytes()[offset - chunk_start] as i8) < -0x40 {
+                            let offset = node_start + offset;
+                            panic!("invalid slice: {}", EditError::NotCharBoundary { offset });
+                        }
+                    }
+                }
+                nodes.push(node.sub(sub, original, added));
+            }
+            node_start = node_end;
+        }
+
+        PTableSlice {
+            nodes,
+            original: self.original,
+            added: self.added.clone(),
+        }
+    }
+}
+
+impl<'ptable> From<&PTableSlice<'ptable>> for String {
+    fn from(value: &PTableSlice<'ptable>) -> Self {
+        let (original, added) = value.buffers();
+        let mut result = String::with_capacity(value.len());
+        for node in &value.nodes {
+            node.chunks(original, added)
+                .for_each(|chunk| result.push_str(chunk));
+        }
+        result
+    }
+}
+
+impl<'ptable> Display for PTableSlice<'ptable> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from(self))
+    }
+}
+
+/// Compares the text split into `left` and `right`, however each one is split.
+fn cmp_chunks<'l, 'r>(
+    mut left: impl Iterator<Item = &'l str>,
+    mut right: impl Iterator<Item = &'r str>,
+) -> std::cmp::Ordering {
+    let mut left_chunk: &[u8] = &[];
+    let mut right_chunk: &[u8] = &[];
+    loop {
+        if left_chunk.is_empty() {
+            left_chunk = left
+                .find(|chunk| !chunk.is_empty())
+                .map_or(&[], str::as_bytes);
+        }
+        if right_chunk.is_empty() {
+            right_chunk = right
+                .find(|chunk| !chunk.is_empty())
+                .map_or(&[], str::as_bytes);
+        }
+        if left_chunk.is_empty() || right_chunk.is_empty() {
+            // At least one side is done
+            return left_chunk.len().cmp(&right_chunk.len());
+        }
+        let common = left_chunk.len().min(right_chunk.len());
+        let ordering = left_chunk[..common].cmp(&right_chunk[..common]);
+        if ordering.is_ne() {
+            return ordering;
+        }
+        left_chunk = &left_chunk[common..];
+        right_chunk = &right_chunk[common..];
+    }
+}
+
+impl PartialEq<PTableSlice<'_>> for PTableSlice<'_> {
+    fn eq(&self, other: &PTableSlice<'_>) -> bool {
+        self.len() == other.len() && cmp_chunks(self.chunks(), other.chunks()).is_eq()
+    }
+}
+
+impl Eq for PTableSlice<'_> {}
+
+impl PartialEq<str> for PTableSlice<'_> {
+    fn eq(&self, other: &str) -> bool {
+        self.len() == other.len() && cmp_chunks(self.chunks(), std::iter::once(other)).is_eq()
+    }
+}
+
+impl PartialEq<&str> for PTableSlice<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl PartialOrd for PTableSlice<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PTableSlice<'_> {
+    /// Compares the text of the slices like `str` does, byte by byte.
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        cmp_chunks(self.chunks(), other.chunks())
+    }
+}
+
+impl std::hash::Hash for PTableSlice<'_> {
+    /// Hashes the text the same way however it's split into pieces, so equal slices have equal
+    /// hashes. The text is fed to the hasher in blocks of a fixed size, since hashers may give
+    /// different results for the same bytes written in different calls.
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        let mut block = [0; 64];
+        let mut filled = 0;
+        for mut chunk in self.chunks().map(str::as_bytes) {
+            while !chunk.is_empty() {
+                let take = chunk.len().min(block.len() - filled);
+                block[filled..filled + take].copy_from_slice(&chunk[..take]);
+                filled += take;
+                chunk = &chunk[take..];
+                if filled == block.len() {
+                    state.write(&block);
+                    filled = 0;
+                }
+            }
+        }
+        state.write(&block[..filled]);
+        // Like `str`, so a slice isn't a prefix of the next value hashed
+        state.write_u8(0xff);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn slices_compare_by_text() {
+        use std::hash::BuildHasher;
+
+        let mut piece_table = PieceTable::new("abcabd");
+        piece_table.insert("c", 3);
+        piece_table.split_at_offset(1);
+        let whole = PieceTable::new("abccabd");
+        let hasher = std::collections::hash_map::RandomState::new();
+
+        let (split, unsplit) = (piece_table.slice(0..4), whole.slice(0..4));
+        assert!(split.nodes.len() > unsplit.nodes.len());
+        assert_eq!(split, unsplit);
+        assert_eq!(split, "abcc");
+        assert_eq!(hasher.hash_one(&split), hasher.hash_one(&unsplit));
+
+        assert!(piece_table.slice(0..7) < piece_table.slice(4..7));
+        assert!(piece_table.slice(1..3) > piece_table.slice(0..3));
+        assert!(piece_table.slice(0..2) < piece_table.slice(0..3));
+        assert_ne!(piece_table.slice(0..2), "abc");
+    }
+
+    #[test]
+    fn reverse_iterators() {
+        let mut piece_table = PieceTable::new("ab€");
+        piece_table.insert("xy", 1);
+        piece_table.delete(0..1);
+
+        assert_eq!(
+            vec!["b€", "xy"],
+            piece_table.chunks_rev().collect::<Vec<_>>()
+        );
+        assert_eq!("€byx", piece_table.chars_rev().collect::<String>());
+        assert_eq!(
+            vec![0xac, 0x82, 0xe2, b'b', b'y', b'x'],
+            piece_table.bytes_rev().collect::<Vec<_>>()
+        );
+        assert_eq!(0, PieceTable::new("").chars_rev().count());
+    }
+
+    #[test]
+    fn display() {
+        let string = "hello!";
+        let piece_table = PieceTable::new(string);
+
+        assert_eq!(string, piece_table.to_string());
+    }
+
+    #[test]
+    fn as_string_matches_display() {
+        let mut piece_table = PieceTable::new("hello!");
+        piece_table.insert(", world", 5);
+        piece_table.insert_char(0, '¡');
+
+        assert_eq!(piece_table.to_string(), piece_table.as_string());
+        assert_eq!(piece_table.len(), piece_table.as_string().capacity());
+    }
+
+    #[test]
+    fn copy_into_appends() {
+        let mut piece_table = PieceTable::new("b");
+        piece_table.insert("c", 1);
+        let mut buf = String::from("a");
+
+        piece_table.copy_into(&mut buf);
+        piece_table.copy_into(&mut buf);
+
+        assert_eq!("abcbc", buf);
+    }
+
+    #[test]
+    fn as_str_contiguous() {
+        let mut piece_table = PieceTable::new("hello");
+        assert_eq!(Some("hello"), piece_table.as_str());
+
+        piece_table.delete(0..5);
+        assert_eq!(Some(""), piece_table.as_str());
+
+        piece_table.insert("world", 0);
+        assert_eq!(Some("world"), piece_table.as_str());
+
+        // Typing right after inserted text extends its piece
+        piece_table.insert("!", 5);
+        assert_eq!(Some("world!"), piece_table.as_str());
+
+        piece_table.insert("?", 0);
+        assert_eq!(None, piece_table.as_str());
+    }
+
+    #[test]
+    fn as_str_after_trimming() {
+        let mut piece_table = PieceTable::new("hello world");
+        piece_table.delete(0..6);
+        piece_table.delete(3..5);
+
+        assert_eq!(Some("wor"), piece_table.as_str());
+    }
+
+    #[test]
+    fn insert_once() {
+        let original = "hello, ";
+        let mut piece_table = PieceTable::new(original);
+
+        let added = "world!";
+        piece_table.insert(added, original.len());
+
+        assert_eq!(original.to_owned() + added, piece_table.to_string());
+    }
+
+    #[test]
+    fn insert_twice() {
+        let original = "hello, ";
+        let added = "world";
+        let second = "!";
+
+        let mut piece_table = PieceTable::new(original);
+        piece_table.insert(added, original.len());
+        piece_table.insert(second, original.len() + added.len());
+
+        assert_eq!(
+            original.to_owned() + added + second,
+            piece_table.to_string()
+        );
+    }
+
+    #[test]
+    fn insert_once_middle() {
+        let original = "hello!";
+        let added = ", world";
+
+        let mut piece_table = PieceTable::new(original);
+
+        piece_table.insert(added, 5);
+
+        assert_eq!(piece_table.nodes.len(), 3);
+        assert_eq!("hello, world!", piece_table.to_string());
+    }
+
+    #[test]
+    fn insert_once_end() {
+        let original = "hello";
+        let added = ", world!";
+
+        let mut piece_table = PieceTable::new(original);
+
+        piece_table.insert(added, 5);
+
+        assert_eq!(piece_table.nodes.len(), 2);
+        assert_eq!("hello, world!", piece_table.to_string());
+    }
+
+    #[test]
+    fn insert_once_start() {
+        let original = "bc";
+        let added = "a";
+
+        let mut piece_table = PieceTable::new(original);
+
+        piece_table.insert(added, 0);
+
+        assert_eq!(piece_table.nodes.len(), 2);
+        assert_eq!("abc", piece_table.to_string());
+    }
+
+    #[test]
+    fn delete_original_whole() {
+        let original = "ab";
+
+        let mut piece_table = PieceTable::new(original);
+
+        piece_table.delete(0..2);
+
+        assert_eq!("", piece_table.to_string());
+    }
+
+    #[test]
+    fn delete_original_half() {
+        let original = "ab";
+
+        let mut piece_table = PieceTable::new(original);
+
+        piece_table.delete(0..1);
+
+        assert_eq!("b", piece_table.to_string());
+    }
+
+    #[test]
+    fn delete_original_second_half() {
+        let original = "ab";
+
+        let mut piece_table = PieceTable::new(original);
+
+        piece_table.delete(1..2);
+
+        assert_eq!("a", piece_table.to_string());
+    }
+
+    #[test]
+    fn delete_original_middle() {
+        let original = "abc";
+
+        let mut piece_table = PieceTable::new(original);
+
+        piece_table.delete(1..2);
+
+        assert_eq!("ac", piece_table.to_string());
+    }
+
+    #[test]
+    fn delete_original_two_times() {
+        let original = "ab";
+
+        let mut piece_table = PieceTable::new(original);
+
+        piece_table.delete(0..1);
+        piece_table.delete(0..1);
+
+        assert_eq!("", piece_table.to_string());
+    }
+
+    #[test]
+    fn add_then_delete() {
+        let original = "ab";
+
+        let mut piece_table = PieceTable::new(original);
+
+        piece_table.delete(0..1);
+        piece_table.delete(0..1);
+        piece_table.insert("ab", 0);
+
+        assert_eq!("ab", piece_table.to_string());
+    }
+
+    #[test]
+    fn add_at_start() {
+        let original = "world!";
+
+        let mut piece_table = PieceTable::new(original);
+
+        piece_table.insert("hello", 0);
+        piece_table.insert(", ", 5);
+
+        assert_eq!("hello, world!", piece_table.to_string());
+        assert_eq!(2, piece_table.nodes.len());
+    }
+
+    #[test]
+    fn insert_char_inline_then_spills() {
+        let mut piece_table = PieceTable::new("ab");
+        for (i, c) in "héllo world".char_indices() {
+            piece_table.insert_char(1 + i, c);
+            assert_eq!(Ok(()), piece_table.check_invariants());
+        }
+
+        assert_eq!("ahéllo worldb", piece_table.to_string());
+        assert_eq!(3, piece_table.nodes.len());
+        assert_eq!(12, piece_table.added.len());
+    }
+
+    #[test]
+    fn scattered_chars_stay_inline() {
+        let mut piece_table = PieceTable::new("one two three");
+        piece_table.insert_char(13, '!');
+        piece_table.insert_char(7, '\n');
+        piece_table.insert_char(3, '€');
+        piece_table.insert_char(0, '>');
+
+        assert_eq!(">one€ two\n three!", piece_table.to_string());
+        assert_eq!(0, piece_table.added.len());
+        assert_eq!(2, piece_table.len_lines());
+        assert_eq!(Some('€'), piece_table.char(4));
+        assert_eq!(Some(b'\n'), piece_table.byte(11));
+
+        piece_table.delete(4..7);
+        piece_table.insert_char(4, 'x');
+        assert_eq!(">onex two\n three!", piece_table.to_string());
+        assert_eq!(Ok(()), piece_table.check_invariants());
+    }
+
+    #[test]
+    fn coalesce_policies() {
+        let type_text = |policy| {
+            let mut piece_table = PieceTable::new("ab");
+            piece_table.set_coalesce_policy(policy);
+            for (i, c) in "hello\nworld".chars().enumerate() {
+                piece_table.insert_char(1 + i, c);
+            }
+            piece_table.insert("!!", 12);
+            assert_eq!("ahello\nworld!!b", piece_table.to_string());
+            assert_eq!(2, piece_table.len_lines());
+            piece_table.nodes.len()
+        };
+
+        assert_eq!(3, type_text(CoalescePolicy::Always));
+        assert_eq!(6, type_text(CoalescePolicy::MaxPieceLen(4)));
+        assert_eq!(14, type_text(CoalescePolicy::Off));
+    }
+
+    #[test]
+    fn coalesce_only_contiguous_added_text() {
+        let mut piece_table = PieceTable::new("abc");
+        piece_table.insert("x", 1);
+        piece_table.insert("y", 3);
+        piece_table.insert("z", 2);
+
+        assert_eq!("axzbyc", piece_table.to_string());
+        assert_eq!(6, piece_table.nodes.len());
+    }
+
+    #[test]
+    fn add_delete_add() {
+        let original = "ab";
+
+        let mut piece_table = PieceTable::new(original);
+
+        piece_table.insert("c", 2);
+        piece_table.delete(0..3);
+        piece_table.insert("ab", 0);
+
+        assert_eq!("ab", piece_table.to_string());
+    }
+
+    #[test]
+    fn delete_across_nodes() {
+        let mut piece_table = PieceTable::new("abcde");
+        piece_table.insert("fghij", 5);
+
+        piece_table.delete(3..7);
 
-        for node in &self.nodes {
-            let node_len = node.range.len();
+        assert_eq!("abchij", piece_table.to_string());
+        assert_eq!(6, piece_table.len());
+    }
 
-            if byte_idx + node_len > start_offset {
-                let node_start = start_offset.saturating_sub(byte_idx);
+    #[test]
+    fn delete_across_many_nodes() {
+        let mut piece_table = PieceTable::new("ad");
+        piece_table.insert("bc", 1);
+        piece_table.insert("e", 4);
 
-                let node_end = if remaining < node_len - node_start {
-                    node_start + remaining
-                } else {
-                    node_len
-                };
+        piece_table.delete(1..5);
 
-                if node_start < node_end {
-                    let mut new_node = node.clone();
-                    new_node.range.start += node_start;
-                    new_node.range.end = new_node.range.start + (node_end - node_start);
-                    new_nodes.push(new_node);
-                    remaining -= node_end - node_start;
-                }
-            }
+        assert_eq!("a", piece_table.to_string());
+    }
 
-            byte_idx += node_len;
-            if remaining == 0 {
-                break;
-            }
+    #[test]
+    fn repeated_deletes_extend_the_hole() {
+        let mut piece_table = PieceTable::new("ab\né\nc");
+        piece_table.insert("xyz", 7);
+
+        // Backspace from the end of the original text, then delete forwards
+        for range in [6..7, 3..6, 2..3, 2..3, 2..3] {
+            piece_table.delete(range);
+            assert_eq!(Ok(()), piece_table.check_invariants());
         }
 
-        if new_nodes.is_empty() {
-            return None;
-        }
+        assert_eq!("abz", piece_table.to_string());
+        assert_eq!(2, piece_table.nodes.len());
+        assert_eq!(1, piece_table.len_lines());
+        assert_eq!(3, piece_table.len_chars());
+    }
 
-        Some(PTableSlice {
-            nodes: new_nodes,
-            original: self.original,
-            added: self.added,
-            _marker: std::marker::PhantomData,
-        })
+    #[test]
+    fn delete_after_insert_doesnt_extend_the_hole() {
+        let mut piece_table = PieceTable::new("hello world");
+        piece_table.delete(5..6);
+        piece_table.insert("_", 5);
+        piece_table.delete(4..5);
+        piece_table.delete(4..5);
+
+        assert_eq!("hellworld", piece_table.to_string());
+        assert_eq!(Ok(()), piece_table.check_invariants());
     }
-}
 
-impl<'ptable> From<&PTableSlice<'ptable>> for String {
-    fn from(value: &PTableSlice<'ptable>) -> Self {
-        let mut result = String::new();
-        for node in &value.nodes {
-            match node.kind {
-                // SAFETY: Since value still valid, then its corresponding `PieceTable` is still
-                // valid and thus `added` is still valid.
-                NodeKind::Original => unsafe {
-                    result.push_str(&(&*value.original)[node.range.clone()])
-                },
-                NodeKind::Added => unsafe {
-                    result.push_str(str::from_utf8_unchecked(
-                        &(*value.added).as_bytes()[node.range.clone()],
-                    ));
-                },
-            }
-        }
-        result
+    #[test]
+    fn len_lines() {
+        let mut piece_table = PieceTable::new("one\ntwo\nthree");
+        assert_eq!(3, piece_table.len_lines());
+
+        piece_table.insert("\nfour\n", 13);
+        assert_eq!(5, piece_table.len_lines());
+
+        piece_table.insert_char(0, '\n');
+        assert_eq!(6, piece_table.len_lines());
     }
-}
 
-impl<'ptable> Display for PTableSlice<'ptable> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", String::from(self))
+    #[test]
+    fn len_lines_after_delete() {
+        let mut piece_table = PieceTable::new("a\nb\nc");
+        piece_table.insert("\nx\n", 1);
+
+        // Deletes "x\n" from the added piece and "\n" from the original
+        piece_table.delete(2..5);
+        assert_eq!("a\nb\nc", piece_table.to_string());
+        assert_eq!(3, piece_table.len_lines());
+
+        piece_table.delete(0..piece_table.len());
+        assert_eq!(1, piece_table.len_lines());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::*;
     #[test]
-    fn display() {
-        let string = "hello!";
-        let piece_table = PieceTable::new(string);
+    fn try_insert_out_of_bounds() {
+        let mut piece_table = PieceTable::new("abc");
 
-        assert_eq!(string, piece_table.to_string());
+        let result = piece_table.try_insert("d", 4);
+
+        assert_eq!(Err(EditError::OutOfBounds { offset: 4, len: 3 }), result);
+        assert_eq!("abc", piece_table.to_string());
     }
 
     #[test]
-    fn insert_once() {
-        let original = "hello, ";
-        let mut piece_table = PieceTable::new(original);
+    fn try_insert_not_char_boundary_in_added() {
+        let mut piece_table = PieceTable::new("ab");
+        piece_table.insert("ñ", 1);
 
-        let added = "world!";
-        piece_table.insert(added, original.len());
+        let result = piece_table.try_insert_char(2, 'x');
 
-        assert_eq!(original.to_owned() + added, piece_table.to_string());
+        assert_eq!(Err(EditError::NotCharBoundary { offset: 2 }), result);
+        assert_eq!("añb", piece_table.to_string());
     }
 
     #[test]
-    fn insert_twice() {
-        let original = "hello, ";
-        let added = "world";
-        let second = "!";
+    fn insert_many_matches_sequential_inserts() {
+        let mut piece_table = PieceTable::new("one\ntwo\nthree\n");
+        piece_table.insert("!", 3);
+        let mut sequential = piece_table.clone();
+        let inserts = [
+            (15, "// "),
+            (0, "// "),
+            (8, ""),
+            (5, "// "),
+            (0, "é"),
+            (15, "x"),
+        ];
+
+        piece_table.insert_many(&inserts);
+        let mut sorted = inserts;
+        sorted.sort_by_key(|&(offset, _)| offset);
+        for &(offset, data) in sorted.iter().rev() {
+            sequential.insert(data, offset);
+        }
+        assert_eq!("// éone!\n// two\nthree\n// x", piece_table.to_string());
+        assert_eq!(sequential.to_string(), piece_table.to_string());
+        assert_eq!(Ok(()), piece_table.check_invariants());
+        assert_eq!(4, piece_table.len_lines());
+        assert_eq!(piece_table.len(), piece_table.to_string().len());
+    }
 
-        let mut piece_table = PieceTable::new(original);
-        piece_table.insert(added, original.len());
-        piece_table.insert(second, original.len() + added.len());
+    #[test]
+    fn insert_borrowed_refers_to_the_caller_text() {
+        let pasted = "two\nthree\n".repeat(2);
+        let mut piece_table = PieceTable::new("one\nfour\n");
+        piece_table.set_limits(limits::Limits {
+            max_added: Some(4),
+            ..limits::Limits::default()
+        });
 
+        piece_table.insert_borrowed(&pasted, 4);
+        piece_table.insert("!", 24);
+        piece_table.insert_borrowed("", 0);
         assert_eq!(
-            original.to_owned() + added + second,
+            "one\ntwo\nthree\ntwo\nthree\n!four\n",
             piece_table.to_string()
         );
+        assert_eq!(7, piece_table.len_lines());
+        assert_eq!(pasted.as_ptr(), piece_table.added[0..20].as_ptr());
+        assert_eq!(Ok(()), piece_table.check_invariants());
+        assert_eq!(1, piece_table.added.owned_len());
     }
 
     #[test]
-    fn insert_once_middle() {
-        let original = "hello!";
-        let added = ", world";
+    fn try_insert_many_is_all_or_nothing() {
+        let mut piece_table = PieceTable::new("aé");
 
-        let mut piece_table = PieceTable::new(original);
+        assert_eq!(
+            Err(EditError::NotCharBoundary { offset: 2 }),
+            piece_table.try_insert_many(&[(0, "x"), (2, "y")])
+        );
+        assert_eq!("aé", piece_table.to_string());
+        assert_eq!(Ok(()), piece_table.try_insert_many(&[(3, "y"), (1, "x")]));
+        assert_eq!("axéy", piece_table.to_string());
+    }
 
-        piece_table.insert(added, 5);
+    #[test]
+    fn try_delete_not_char_boundary() {
+        let mut piece_table = PieceTable::new("a€b");
 
-        assert_eq!(piece_table.nodes.len(), 3);
-        assert_eq!("hello, world!", piece_table.to_string());
+        assert_eq!(
+            Err(EditError::NotCharBoundary { offset: 2 }),
+            piece_table.try_delete(0..2)
+        );
+        assert_eq!(Ok(()), piece_table.try_delete(1..4));
+        assert_eq!("ab", piece_table.to_string());
     }
 
     #[test]
-    fn insert_once_end() {
-        let original = "hello";
-        let added = ", world!";
+    fn try_delete_invalid_range() {
+        let mut piece_table = PieceTable::new("abc");
+        let (start, end) = (2, 1);
 
-        let mut piece_table = PieceTable::new(original);
+        assert_eq!(
+            Err(EditError::InvalidRange { start, end }),
+            piece_table.try_delete(start..end)
+        );
+        assert_eq!(
+            Err(EditError::OutOfBounds { offset: 10, len: 3 }),
+            piece_table.try_delete(1..10)
+        );
+        assert_eq!(3, piece_table.len());
+    }
 
-        piece_table.insert(added, 5);
+    #[test]
+    #[should_panic(expected = "invalid delete: offset 4 is out of bounds")]
+    fn delete_out_of_bounds_panics() {
+        let mut piece_table = PieceTable::new("abc");
+        piece_table.delete(1..4);
+    }
 
-        assert_eq!(piece_table.nodes.len(), 2);
-        assert_eq!("hello, world!", piece_table.to_string());
+    #[test]
+    fn delete_clamped_cuts_range_to_document() {
+        let mut piece_table = PieceTable::new("abc");
+        piece_table.insert("def", 3);
+
+        assert_eq!(4..6, piece_table.delete_clamped(4..usize::MAX));
+        assert_eq!("abcd", piece_table.to_string());
+        assert_eq!(4..4, piece_table.delete_clamped(10..12));
+        let (start, end) = (5, 2);
+        assert_eq!(2..2, piece_table.delete_clamped(start..end));
+        assert_eq!(0..4, piece_table.delete_clamped(0..5));
+        assert_eq!(0, piece_table.len());
+        assert_eq!(1, piece_table.len_lines());
     }
 
     #[test]
-    fn insert_once_start() {
-        let original = "bc";
-        let added = "a";
+    #[should_panic(expected = "invalid insert")]
+    #[cfg(debug_assertions)]
+    fn insert_not_char_boundary_panics() {
+        let mut piece_table = PieceTable::new("é");
+        piece_table.insert("x", 1);
+    }
 
-        let mut piece_table = PieceTable::new(original);
+    #[test]
+    #[should_panic(expected = "splits a char")]
+    fn unchecked_insert_inside_char_panics() {
+        let mut piece_table = PieceTable::new("aé");
+        piece_table.insert_unchecked("x", 2);
+    }
 
-        piece_table.insert(added, 0);
+    #[test]
+    #[should_panic(expected = "splits a char")]
+    fn unchecked_delete_inside_char_panics() {
+        let mut piece_table = PieceTable::new("aé");
+        piece_table.insert("b", 1);
+        piece_table.delete_unchecked(1..3);
+    }
 
-        assert_eq!(piece_table.nodes.len(), 2);
-        assert_eq!("abc", piece_table.to_string());
+    #[test]
+    fn check_invariants_catches_corruption() {
+        let mut piece_table = PieceTable::new("ab\ncd");
+        piece_table.insert("é\n", 2);
+        assert_eq!(Ok(()), piece_table.check_invariants());
+
+        let mut bad_len = piece_table.clone();
+        bad_len.len += 1;
+        assert!(bad_len.check_invariants().unwrap_err().contains("length"));
+
+        let mut bad_newlines = piece_table.clone();
+        bad_newlines.nodes[1].newlines = 0;
+        assert!(
+            bad_newlines
+                .check_invariants()
+                .unwrap_err()
+                .contains("piece 1")
+        );
+
+        let mut bad_boundary = piece_table.clone();
+        bad_boundary.nodes[1].range.start += 1;
+        assert!(
+            bad_boundary
+                .check_invariants()
+                .unwrap_err()
+                .contains("char boundaries")
+        );
+
+        let mut bad_range = piece_table;
+        bad_range.nodes[0].range.end = 100;
+        assert!(
+            bad_range
+                .check_invariants()
+                .unwrap_err()
+                .contains("outside")
+        );
     }
 
     #[test]
-    fn delete_original_whole() {
-        let original = "ab";
+    fn from_chunks() {
+        let empty = PieceTable::from_chunks([]);
+        assert_eq!("", empty.to_string());
+        assert_eq!(Ok(()), empty.check_invariants());
 
-        let mut piece_table = PieceTable::new(original);
+        let single = PieceTable::from_chunks(["", "only"]);
+        assert_eq!(Some("only"), single.as_str());
 
-        piece_table.delete(0..2);
+        let lines: Vec<String> = (0..100).map(|i| format!("line {i}\n")).collect();
+        let mut piece_table = PieceTable::from_chunks(lines.iter().map(String::as_str));
+        assert_eq!(lines.concat(), piece_table.to_string());
+        assert_eq!(101, piece_table.len_lines());
+        assert_eq!(2, piece_table.nodes.len());
+        assert_eq!(Ok(()), piece_table.check_invariants());
 
-        assert_eq!("", piece_table.to_string());
+        piece_table.insert("!", 3);
+        assert_eq!("lin!e 0\n", piece_table.slice(0..8).to_string());
     }
 
     #[test]
-    fn delete_original_half() {
-        let original = "ab";
+    fn from_string() {
+        let mut piece_table = PieceTable::from(String::from("hello\nworld"));
+        assert_eq!("hello\nworld", piece_table.to_string());
+        assert_eq!(2, piece_table.len_lines());
 
-        let mut piece_table = PieceTable::new(original);
+        piece_table.insert("!", 11);
+        assert_eq!("hello\nworld!", piece_table.to_string());
+    }
 
-        piece_table.delete(0..1);
+    #[test]
+    #[should_panic(
+        expected = "invalid move_range: offset 12 is out of bounds of document of length 10"
+    )]
+    fn move_range_past_the_end() {
+        let mut piece_table = PieceTable::new("abcdefghij");
+        piece_table.move_range(2..12, 0);
+    }
 
-        assert_eq!("b", piece_table.to_string());
+    #[test]
+    fn extract_across_nodes() {
+        let mut piece_table = PieceTable::new("hello world");
+        piece_table.insert("\nbig", 5);
+
+        let extracted = piece_table.extract(3..9);
+
+        assert_eq!("hel world", piece_table.to_string());
+        assert_eq!("lo\nbig", extracted.to_string());
+        assert_eq!(2, extracted.len_lines());
+        assert_eq!(1, piece_table.len_lines());
+        assert_eq!(Some("lo\nbig"), extracted.as_str());
     }
 
     #[test]
-    fn delete_original_second_half() {
-        let original = "ab";
+    fn move_range_both_directions() {
+        let mut piece_table = PieceTable::new("abcdef");
+        piece_table.insert("XY", 3);
 
-        let mut piece_table = PieceTable::new(original);
+        piece_table.move_range(2..5, 8);
+        assert_eq!("abdefcXY", piece_table.to_string());
 
-        piece_table.delete(1..2);
+        piece_table.move_range(5..8, 0);
+        assert_eq!("cXYabdef", piece_table.to_string());
 
-        assert_eq!("a", piece_table.to_string());
+        piece_table.move_range(2..4, 3);
+        assert_eq!("cXYabdef", piece_table.to_string());
+        assert_eq!(Ok(()), piece_table.check_invariants());
     }
 
     #[test]
-    fn delete_original_middle() {
-        let original = "abc";
+    fn move_range_keeps_buffers() {
+        let mut piece_table = PieceTable::new("one\ntwo\n");
+        piece_table.insert("three\n", 8);
+        let added = piece_table.added.len();
 
-        let mut piece_table = PieceTable::new(original);
+        piece_table.move_range(8..14, 0);
 
-        piece_table.delete(1..2);
+        assert_eq!("three\none\ntwo\n", piece_table.to_string());
+        assert_eq!(added, piece_table.added.len());
+        assert_eq!(4, piece_table.len_lines());
+    }
 
-        assert_eq!("ac", piece_table.to_string());
+    #[test]
+    fn duplicate_range_shares_bytes() {
+        let mut piece_table = PieceTable::new("ab\n");
+        piece_table.insert("cd", 2);
+        let added = piece_table.added.len();
+
+        piece_table.duplicate_range(1..5, 3);
+        assert_eq!("abcbcd\nd\n", piece_table.to_string());
+        assert_eq!(added, piece_table.added.len());
+        assert_eq!(3, piece_table.len_lines());
+
+        // Typing after either copy only changes that copy
+        piece_table.insert("x", 5);
+        piece_table.insert("y", 3);
+        assert_eq!("abcybcxd\nd\n", piece_table.to_string());
+        assert_eq!(Ok(()), piece_table.check_invariants());
+
+        let rest = piece_table.split_off(2);
+        assert_eq!("cybcxd\nd\n", rest.to_string());
     }
 
     #[test]
-    fn delete_original_two_times() {
-        let original = "ab";
+    fn extract_outlives_source() {
+        let extracted = {
+            let text = String::from("abcdef");
+            let mut piece_table = PieceTable::new(&text);
+            piece_table.extract(1..3)
+        };
 
-        let mut piece_table = PieceTable::new(original);
+        assert_eq!("bc", extracted.to_string());
+    }
 
-        piece_table.delete(0..1);
-        piece_table.delete(0..1);
+    #[test]
+    fn extract_empty() {
+        let mut piece_table = PieceTable::new("abc");
 
-        assert_eq!("", piece_table.to_string());
+        let extracted = piece_table.extract(1..1);
+
+        assert!(extracted.is_empty());
+        assert_eq!("abc", piece_table.to_string());
     }
 
     #[test]
-    fn add_then_delete() {
-        let original = "ab";
+    #[should_panic(expected = "invalid extract: offset 9 is out of bounds of document of length 3")]
+    fn extract_out_of_bounds() {
+        let mut piece_table = PieceTable::new("abc");
+        piece_table.extract(1..9);
+    }
 
-        let mut piece_table = PieceTable::new(original);
+    #[test]
+    fn split_off_middle_of_node() {
+        let mut piece_table = PieceTable::new("one\ntwo");
+        piece_table.insert("\nthree", 7);
 
-        piece_table.delete(0..1);
-        piece_table.delete(0..1);
-        piece_table.insert("ab", 0);
+        let rest = piece_table.split_off(5);
 
-        assert_eq!("ab", piece_table.to_string());
+        assert_eq!("one\nt", piece_table.to_string());
+        assert_eq!("wo\nthree", rest.to_string());
+        assert_eq!(2, piece_table.len_lines());
+        assert_eq!(2, rest.len_lines());
+        assert_eq!("\nthree", &rest.added[0..6]);
+        assert_eq!(piece_table.added[0..6].as_ptr(), rest.added[0..6].as_ptr());
     }
 
     #[test]
-    fn add_at_start() {
-        let original = "world!";
+    fn split_off_ends() {
+        let mut piece_table = PieceTable::new("abc");
 
-        let mut piece_table = PieceTable::new(original);
+        let all = piece_table.split_off(0);
+        let none = all.clone().split_off(3);
 
-        piece_table.insert("hello", 0);
-        piece_table.insert(", ", 5);
+        assert_eq!("", piece_table.to_string());
+        assert_eq!("abc", all.to_string());
+        assert!(none.is_empty());
+    }
 
-        assert_eq!("hello, world!", piece_table.to_string());
-        assert_eq!(3, piece_table.nodes.len());
+    #[test]
+    fn append_table_with_different_original() {
+        let mut piece_table = PieceTable::new("hello");
+        let mut other = PieceTable::new(" world");
+        other.insert("!", 6);
+
+        piece_table.append_table(other);
+
+        assert_eq!("hello world!", piece_table.to_string());
+        assert_eq!(12, piece_table.len());
+        piece_table.delete(4..7);
+        assert_eq!("hellorld!", piece_table.to_string());
     }
 
     #[test]
-    fn add_delete_add() {
-        let original = "ab";
+    fn empty_document() {
+        let mut piece_table = PieceTable::default();
+        assert_eq!(0, piece_table.nodes.len());
+        assert_eq!(Some(""), piece_table.as_str());
+        assert_eq!(None, piece_table.byte(0));
+        assert_eq!(None, piece_table.char(0));
+        assert_eq!(1, piece_table.len_lines());
+        assert_eq!(0, piece_table.len_chars());
+        assert_eq!("", piece_table.slice(0..0).to_string());
+        assert_eq!(Ok(()), piece_table.try_delete(0..0));
+        assert_eq!(
+            Err(EditError::OutOfBounds { offset: 1, len: 0 }),
+            piece_table.try_insert("x", 1)
+        );
+        assert_eq!(0, piece_table.split_off(0).len());
+        assert_eq!(Ok(()), piece_table.check_invariants());
 
-        let mut piece_table = PieceTable::new(original);
+        piece_table.replace("hi", 0);
+        assert_eq!("hi", piece_table.to_string());
+        piece_table.delete(0..2);
+        assert!(piece_table.is_empty());
+        piece_table.insert_char(0, 'x');
+        assert_eq!("x", piece_table.to_string());
+        assert_eq!(PieceTable::new(""), PieceTable::empty());
+    }
 
-        piece_table.insert("c", 2);
-        piece_table.delete(0..3);
-        piece_table.insert("ab", 0);
+    #[test]
+    fn replace_at_end_only_inserts() {
+        let mut piece_table = PieceTable::new("abc");
+        piece_table.replace("de", 3);
 
-        assert_eq!("ab", piece_table.to_string());
+        assert_eq!("abcde", piece_table.to_string());
+    }
+
+    #[test]
+    fn split_off_then_append_round_trips() {
+        let mut piece_table = PieceTable::new("abcdef\nghi");
+        piece_table.insert("XY", 3);
+        piece_table.insert("\n", 9);
+
+        let expected = piece_table.to_string();
+        let rest = piece_table.split_off(4);
+        piece_table.append_table(rest);
+
+        assert_eq!(expected, piece_table.to_string());
+        assert_eq!(3, piece_table.len_lines());
     }
 
     #[test]
@@ -957,10 +3584,10 @@ mod tests {
     fn byte() {
         let pt = PieceTable::new("abcd");
 
-        assert_eq!(Some('a' as u8), pt.byte(0));
-        assert_eq!(Some('b' as u8), pt.byte(1));
-        assert_eq!(Some('c' as u8), pt.byte(2));
-        assert_eq!(Some('d' as u8), pt.byte(3));
+        assert_eq!(Some(b'a'), pt.byte(0));
+        assert_eq!(Some(b'b'), pt.byte(1));
+        assert_eq!(Some(b'c'), pt.byte(2));
+        assert_eq!(Some(b'd'), pt.byte(3));
         assert_eq!(None, pt.byte(4));
         assert_eq!(None, pt.byte(5));
         assert_eq!(None, pt.byte(usize::MAX));
@@ -971,12 +3598,12 @@ mod tests {
         let mut pt = PieceTable::new("abcd");
         pt.replace("hello!", 0);
 
-        assert_eq!(Some('h' as u8), pt.byte(0));
-        assert_eq!(Some('e' as u8), pt.byte(1));
-        assert_eq!(Some('l' as u8), pt.byte(2));
-        assert_eq!(Some('l' as u8), pt.byte(3));
-        assert_eq!(Some('o' as u8), pt.byte(4));
-        assert_eq!(Some('!' as u8), pt.byte(5));
+        assert_eq!(Some(b'h'), pt.byte(0));
+        assert_eq!(Some(b'e'), pt.byte(1));
+        assert_eq!(Some(b'l'), pt.byte(2));
+        assert_eq!(Some(b'l'), pt.byte(3));
+        assert_eq!(Some(b'o'), pt.byte(4));
+        assert_eq!(Some(b'!'), pt.byte(5));
     }
 
     #[test]
@@ -1009,6 +3636,21 @@ mod ptable_slice_tests {
     use std::iter::repeat_n;
 
     use super::*;
+    use crate::baseline::Baseline;
+    use crate::workload::{Profile, generate};
+
+    #[test]
+    fn slice_reverse_iterators() {
+        let mut table = PieceTable::new("héllo");
+        table.insert(" wörld", 6);
+        let slice = table.slice(1..10);
+
+        assert_eq!(vec![" wö", "éllo"], slice.chunks_rev().collect::<Vec<_>>());
+        assert_eq!("öw ollé", slice.chars_rev().collect::<String>());
+        let mut bytes: Vec<u8> = slice.bytes_rev().collect();
+        bytes.reverse();
+        assert_eq!(String::from(&slice).into_bytes(), bytes);
+    }
 
     #[test]
     fn create_slice() {
@@ -1085,11 +3727,55 @@ mod ptable_slice_tests {
         assert_eq!(slice.to_string(), "");
     }
 
+    #[test]
+    fn empty_sub_slices() {
+        let mut table = PieceTable::new("hello");
+        table.insert(" world", 5);
+        let slice = table.slice(0..11);
+
+        for at in 0..=11 {
+            let empty = slice.slice(at..at);
+            assert!(empty.is_empty());
+            assert_eq!(Some(""), empty.as_str());
+        }
+        assert_eq!("o w", slice.slice(4..=6).to_string());
+        assert!(slice.slice(..0).is_empty());
+        assert!(slice.slice(11..).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid slice: offset 12 is out of bounds of document of length 11")]
+    fn sub_slice_out_of_bounds_panics() {
+        let table = PieceTable::new("hello world");
+        table.create_slice().slice(3..12);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid slice: offset 4 is not on a char boundary")]
+    fn sub_slice_inside_a_char_panics() {
+        let mut table = PieceTable::new("aé€b");
+        table.insert("ü", 3);
+        let slice = table.create_slice();
+        assert_eq!("é", slice.slice(1..3).to_string());
+        assert_eq!("€b", slice.slice(5..).to_string());
+        slice.slice(1..4);
+    }
+
+    #[test]
+    fn slice_as_str() {
+        let mut table = PieceTable::new("hello world");
+        table.insert(",", 5);
+
+        assert_eq!(Some("hello"), table.slice(0..5).as_str());
+        assert_eq!(None, table.slice(0..6).as_str());
+        assert_eq!(Some(" world"), table.slice(0..12).slice(6..).as_str());
+    }
+
     #[test]
     fn slice_of_slice() {
         let table = PieceTable::new("hello world");
         let slice1 = table.slice(0..5);
-        let slice2 = slice1.slice(1..4).unwrap();
+        let slice2 = slice1.slice(1..4);
 
         assert_eq!(slice1.to_string(), "hello");
         assert_eq!(slice2.to_string(), "ell");
@@ -1099,8 +3785,8 @@ mod ptable_slice_tests {
     fn slice_of_slice_of_slice() {
         let table = PieceTable::new("hello world");
         let slice1 = table.slice(0..11);
-        let slice2 = slice1.slice(0..5).unwrap();
-        let slice3 = slice2.slice(0..4).unwrap();
+        let slice2 = slice1.slice(0..5);
+        let slice3 = slice2.slice(..4);
 
         assert_eq!(slice1.to_string(), "hello world");
         assert_eq!(slice2.to_string(), "hello");
@@ -1133,87 +3819,75 @@ mod ptable_slice_tests {
 
         assert_eq!(slice.to_string(), "hello, world!");
     }
+
+    #[test]
+    fn slice_outlives_table_with_small_segments() {
+        let slice = {
+            let mut table = PieceTable::new("hello world");
+            table.set_added_segment_size(4);
+            for (i, c) in "abcdefghij".chars().enumerate() {
+                table.insert_char(5 + i, c);
+            }
+            let slice = table.slice(3..18);
+            table.insert("more text than fits in a segment", 0);
+            assert_eq!(Ok(()), table.check_invariants());
+            slice
+        };
+
+        assert_eq!("loabcdefghij wo", slice.to_string());
+    }
+
+    #[test]
+    fn small_segments_match_string() {
+        let initial = "fn main() {\n    println!(\"hello\");\n}\n";
+        for profile in [Profile::Typing, Profile::PasteHeavy, Profile::Deleting] {
+            let mut table = PieceTable::new(initial);
+            table.set_added_segment_size(7);
+            let mut expected = Baseline::new(initial);
+            for edit in &generate(5, profile, initial, 300).edits {
+                edit.apply(&mut table);
+                edit.apply(&mut expected);
+                assert_eq!(expected.to_string(), table.to_string());
+            }
+            assert_eq!(Ok(()), table.check_invariants());
+        }
+    }
 }
 
 #[cfg(test)]
 mod property_tests {
     use crate::PieceTable;
-    use crate::baseline::Baseline;
-    use crate::interface::EditableText;
+    use crate::fuzz::{Op, Query, compare_generated, compare_piece_table, compare_with_baseline};
+    use crate::gap_buffer::GapBuffer;
+    use crate::line_buffer::LineBuffer;
+    use crate::workload::Profile;
     use proptest::prelude::*;
 
-    #[derive(Debug, Clone)]
-    enum Op {
-        Insert(String, usize),
-        Delete(usize, usize),
-    }
-
-    fn do_op<'a, T: EditableText<'a> + std::fmt::Display>(
-        doc: &mut T,
-        op: &Op,
-        string_before_op: &String,
-    ) {
-        match op {
-            Op::Insert(text, offset) => {
-                let mut offset = *offset;
-                if offset > string_before_op.len() {
-                    offset = string_before_op.len();
-                }
-                while !string_before_op.is_char_boundary(offset) {
-                    offset = offset.saturating_sub(1);
-                }
-                doc.insert(text, offset);
-            }
-            Op::Delete(start, end) => {
-                let mut start = *start;
-                let mut end = *end;
-                if start > string_before_op.len() {
-                    start = string_before_op.len();
-                }
-                if end > string_before_op.len() {
-                    end = string_before_op.len();
-                }
-                if start > end {
-                    std::mem::swap(&mut start, &mut end);
-                }
-
-                while !string_before_op.is_char_boundary(start) {
-                    start = start.saturating_sub(1);
-                }
-                while !string_before_op.is_char_boundary(end) {
-                    end = end.saturating_sub(1);
-                }
-                doc.delete(start..end);
-            }
+    proptest! {
+        #[test]
+        fn compare_implementations(initial_text: String, ops: Vec<Op>) {
+            compare_with_baseline::<PieceTable>(&initial_text, &ops)?;
         }
-    }
 
-    impl Arbitrary for Op {
-        type Parameters = ();
-        type Strategy = BoxedStrategy<Self>;
-
-        fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
-            prop_oneof![
-                (any::<String>(), any::<usize>()).prop_map(|(s, i)| Op::Insert(s, i)),
-                (any::<usize>(), any::<usize>()).prop_map(|(i, j)| Op::Delete(i, j)),
-            ]
-            .boxed()
+        #[test]
+        fn compare_line_buffer(initial_text: String, ops: Vec<Op>) {
+            compare_with_baseline::<LineBuffer>(&initial_text, &ops)?;
         }
-    }
 
-    proptest! {
         #[test]
-        fn compare_implementations(initial_text: String, ops: Vec<Op>) {
-            let mut piece_table = PieceTable::new(&initial_text);
-            let mut baseline = Baseline::new(&initial_text);
+        fn compare_gap_buffer(initial_text: String, ops: Vec<Op>) {
+            compare_with_baseline::<GapBuffer>(&initial_text, &ops)?;
+        }
 
-            for op in ops {
-                let s = piece_table.to_string();
-                do_op(&mut piece_table, &op, &s);
-                do_op(&mut baseline, &op, &s);
+        #[test]
+        fn compare_generated_workloads(initial_text: String, seed: u64, profile: Profile) {
+            compare_generated::<PieceTable>(&initial_text, seed, profile, 100)?;
+            compare_generated::<GapBuffer>(&initial_text, seed, profile, 100)?;
+        }
 
-                prop_assert_eq!(baseline.to_string(), piece_table.to_string());
-            }
+        #[test]
+        fn compare_queries(initial_text: String, ops: Vec<Op>, queries: Vec<Query>) {
+            compare_piece_table(&initial_text, &ops, &queries)?;
         }
     }
 }
@@ -0,0 +1,359 @@
+//! Limits on how large a table may grow, for tables edited on behalf of untrusted clients.
+//!
+//! A collaboration server which applies edits sent over the network shouldn't let a single
+//! client grow a document, or its list of pieces, without bound. Once a limit is set, edits which
+//! could exceed it are rejected with [`EditError::LimitExceeded`] before anything is changed.
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::{EditError, PieceTable};
+
+/// The limits of a table, all of which are unset by default.
+///
+/// # Examples
+///
+/// ```
+/// # use piece_table::{EditError, PieceTable};
+/// use piece_table::limits::{Limit, Limits};
+///
+/// let mut pt = PieceTable::new("hello");
+/// pt.set_limits(Limits {
+///     max_len: Some(8),
+///     ..Limits::default()
+/// });
+///
+/// assert_eq!(pt.try_insert("!!!", 5), Ok(()));
+/// assert_eq!(
+///     pt.try_insert("!", 8),
+///     Err(EditError::LimitExceeded { limit: Limit::Len, max: 8 })
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// The maximum length of the document, in bytes.
+    pub max_len: Option<usize>,
+    /// The maximum size of the "added" buffer, in bytes. Since text is never removed from it,
+    /// this bounds the memory used by all insertions made over the table's lifetime. Text
+    /// inserted with [`PieceTable::insert_borrowed`] isn't stored there, so it isn't counted.
+    pub max_added: Option<usize>,
+    /// The maximum number of pieces.
+    pub max_pieces: Option<usize>,
+}
+
+/// One of the [`Limits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// [`Limits::max_len`].
+    Len,
+    /// [`Limits::max_added`].
+    Added,
+    /// [`Limits::max_pieces`].
+    Pieces,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Len => write!(f, "document length"),
+            Limit::Added => write!(f, "added buffer size"),
+            Limit::Pieces => write!(f, "number of pieces"),
+        }
+    }
+}
+
+impl PieceTable<'_> {
+    /// Returns the limits of this table.
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    /// Sets the limits of this table, see [`Limits`].
+    ///
+    /// They are checked by [`PieceTable::insert`], [`PieceTable::insert_char`],
+    /// [`PieceTable::delete`] and [`PieceTable::duplicate_range`], the `try_*` versions of
+    /// those, [`PieceTable::move_range`], [`PieceTable::reorder_lines`],
+    /// [`PieceTable::feed_original_chunk`], [`PieceTable::append_table`] and
+    /// [`EditBuilder::apply`](crate::edit_builder::EditBuilder::apply). The
+    /// infallible methods panic if a limit would be exceeded, regardless of build mode. A table
+    /// which is already past a newly set limit only rejects edits which grow it further.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Checks that growing the document by `len` bytes, the "added" buffer by `added` bytes
+    /// and the list of pieces by `pieces` pieces stays within the limits.
+    pub(crate) fn check_growth(
+        &self,
+        len: usize,
+        added: usize,
+        pieces: usize,
+    ) -> Result<(), EditError> {
+        let check = |limit, max: Option<usize>, growth: usize, current: usize| match max {
+            Some(max) if growth > 0 && current + growth > max => {
+                Err(EditError::LimitExceeded { limit, max })
+            }
+            _ => Ok(()),
+        };
+        check(Limit::Len, self.limits.max_len, len, self.len)?;
+        check(
+            Limit::Added,
+            self.limits.max_added,
+            added,
+            self.added.owned_len(),
+        )?;
+        check(
+            Limit::Pieces,
+            self.limits.max_pieces,
+            pieces,
+            self.nodes.len(),
+        )
+    }
+
+    /// Checks that inserting `len` bytes at `offset` stays within the limits.
+    ///
+    /// Short insertions may be stored in the pieces themselves rather than the "added" buffer,
+    /// and may extend an existing piece, so this assumes the worst case for both.
+    pub(crate) fn check_insert_limits(&self, offset: usize, len: usize) -> Result<(), EditError> {
+        self.check_growth(len, len, 1 + self.splits_at(offset))
+    }
+
+    /// Checks that inserting each of `inserts` stays within the limits, like
+    /// [`PieceTable::check_insert_limits`] does for each of them.
+    pub(crate) fn check_insert_many_limits(
+        &self,
+        inserts: &[(usize, &str)],
+    ) -> Result<(), EditError> {
+        let len = inserts.iter().map(|(_, data)| data.len()).sum();
+        let mut offsets: Vec<usize> = inserts.iter().map(|&(offset, _)| offset).collect();
+        offsets.sort_unstable();
+        offsets.dedup();
+        let splits: usize = offsets.iter().map(|&offset| self.splits_at(offset)).sum();
+        self.check_growth(len, len, inserts.len() + splits)
+    }
+
+    /// Checks that deleting `range` stays within the limits, which only happens if it's strictly
+    /// inside a piece, which then has to be split in two.
+    pub(crate) fn check_delete_limits(&self, range: &Range<usize>) -> Result<(), EditError> {
+        if range.is_empty() || self.limits.max_pieces.is_none() {
+            return Ok(());
+        }
+        let splits = match self.find_node_for_edit(range.start) {
+            Some((idx, start)) => {
+                (start != range.start && start + self.nodes[idx].range.len() > range.end) as usize
+            }
+            None => 0,
+        };
+        self.check_growth(0, 0, splits)
+    }
+
+    /// Checks that copying the non-empty `range` to `dest_offset` stays within the limits.
+    pub(crate) fn check_duplicate_limits(
+        &self,
+        range: &Range<usize>,
+        dest_offset: usize,
+    ) -> Result<(), EditError> {
+        let pieces = match self.limits.max_pieces {
+            Some(_) => {
+                let (first, _) = self.find_node_for_edit(range.start).unwrap_or_default();
+                let (last, _) = self.find_node_for_edit(range.end - 1).unwrap_or_default();
+                last - first
+                    + 1
+                    + self.splits_at(range.start)
+                    + self.splits_at(range.end)
+                    + self.splits_at(dest_offset)
+            }
+            None => 0,
+        };
+        self.check_growth(range.len(), 0, pieces)
+    }
+
+    /// Returns the number of pieces splitting the text at `offset` adds, which is only computed
+    /// if the number of pieces is limited.
+    pub(crate) fn splits_at(&self, offset: usize) -> usize {
+        if self.limits.max_pieces.is_none() {
+            return 0;
+        }
+        match self.find_node_for_edit(offset) {
+            Some((_, start)) => (start != offset) as usize,
+            None => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edit_builder::EditBuilder;
+
+    fn limited(text: &str, limits: Limits) -> PieceTable<'_> {
+        let mut piece_table = PieceTable::new(text);
+        piece_table.set_limits(limits);
+        piece_table
+    }
+
+    #[test]
+    fn max_added_counts_deleted_text() {
+        let mut piece_table = limited(
+            "",
+            Limits {
+                max_added: Some(4),
+                ..Limits::default()
+            },
+        );
+
+        assert_eq!(Ok(()), piece_table.try_insert("abc", 0));
+        piece_table.delete(0..3);
+        assert_eq!(
+            Err(EditError::LimitExceeded {
+                limit: Limit::Added,
+                max: 4
+            }),
+            piece_table.try_insert("de", 0)
+        );
+        assert_eq!(Ok(()), piece_table.try_insert_char(0, 'x'));
+        assert_eq!("x", piece_table.to_string());
+    }
+
+    #[test]
+    fn max_pieces_only_counts_splits() {
+        let mut piece_table = limited(
+            "abcdef",
+            Limits {
+                max_pieces: Some(3),
+                ..Limits::default()
+            },
+        );
+
+        assert_eq!(Ok(()), piece_table.try_insert("x", 3));
+        let err = Err(EditError::LimitExceeded {
+            limit: Limit::Pieces,
+            max: 3,
+        });
+        assert_eq!(err, piece_table.try_insert("y", 1));
+        assert_eq!(err, piece_table.try_delete(1..2));
+        // Deleting whole pieces, or the start of one, never adds any
+        assert_eq!(Ok(()), piece_table.try_delete(3..5));
+        assert_eq!(Ok(()), piece_table.try_delete(0..1));
+        assert_eq!("bcef", piece_table.to_string());
+        assert!(piece_table.nodes.len() <= 3);
+    }
+
+    #[test]
+    fn batches_are_checked_as_a_whole() {
+        let mut piece_table = limited(
+            "one two",
+            Limits {
+                max_len: Some(10),
+                ..Limits::default()
+            },
+        );
+
+        let mut builder = EditBuilder::new(&piece_table);
+        builder.replace(0..3, "three").replace(4..7, "seven");
+        assert_eq!(
+            Err(EditError::LimitExceeded {
+                limit: Limit::Len,
+                max: 10
+            }),
+            builder.apply(&mut piece_table)
+        );
+        assert_eq!("one two", piece_table.to_string());
+
+        let mut builder = EditBuilder::new(&piece_table);
+        builder.replace(0..3, "1").replace(4..7, "four");
+        assert_eq!(Ok(()), builder.apply(&mut piece_table));
+        assert_eq!("1 four", piece_table.to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "number of pieces")]
+    fn duplicate_range_checks_pieces() {
+        let mut piece_table = limited(
+            "abc",
+            Limits {
+                max_pieces: Some(3),
+                ..Limits::default()
+            },
+        );
+        piece_table.duplicate_range(1..2, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid move_range: edit could exceed the maximum number of pieces")]
+    fn move_range_checks_pieces() {
+        let mut piece_table = limited(
+            "abcdef",
+            Limits {
+                max_pieces: Some(3),
+                ..Limits::default()
+            },
+        );
+        // Moving whole pieces doesn't add any
+        piece_table.move_range(0..3, 6);
+        assert_eq!("defabc", piece_table.to_string());
+        piece_table.move_range(1..2, 5);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "invalid reorder_lines: edit could exceed the maximum number of pieces"
+    )]
+    fn reorder_lines_checks_pieces() {
+        let mut piece_table = limited(
+            "a\nb\nc\nd\n",
+            Limits {
+                max_pieces: Some(3),
+                ..Limits::default()
+            },
+        );
+        piece_table.reorder_lines(0..2, &[1, 0]);
+        assert_eq!("b\na\nc\nd\n", piece_table.to_string());
+        piece_table.reorder_lines(2..4, &[1, 0]);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "invalid feed_original_chunk: edit could exceed the maximum document length"
+    )]
+    fn feed_original_chunk_checks_len() {
+        let mut piece_table = PieceTable::new_streaming();
+        piece_table.set_limits(Limits {
+            max_len: Some(6),
+            ..Limits::default()
+        });
+        piece_table.feed_original_chunk("one\n");
+        piece_table.feed_original_chunk("two\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "document length")]
+    fn insert_panics_past_max_len() {
+        let mut piece_table = limited(
+            "abc",
+            Limits {
+                max_len: Some(3),
+                ..Limits::default()
+            },
+        );
+        piece_table.insert("d", 3);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "invalid append_table: edit could exceed the maximum added buffer size"
+    )]
+    fn append_table_checks_added() {
+        let mut piece_table = limited(
+            "abc",
+            Limits {
+                max_added: Some(4),
+                ..Limits::default()
+            },
+        );
+        let text = String::from("de");
+        piece_table.append_table(PieceTable::new(&text));
+        assert_eq!("abcde", piece_table.to_string());
+        piece_table.append_table(PieceTable::new("fgh"));
+    }
+}
@@ -1,12 +1,37 @@
 use crate::interface::EditableText;
-use crate::TextRange;
 use std::fmt;
+use std::ops::Range;
 
+/// A text buffer which stores each line in its own `String`.
+///
+/// The document is the concatenation of all `lines`, joined by `'\n'`. There's always at least
+/// one line, so an empty document is a single empty line, and a document ending in `'\n'` has an
+/// empty last line.
 pub struct LineBuffer {
     lines: Vec<String>,
+    len: usize,
 }
 
 impl LineBuffer {
+    /// Returns the length of the text in bytes, including newlines.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the buffer contains no text.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of lines, which is one more than the number of newlines.
+    pub fn len_lines(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Finds the line containing `offset` and the byte offset within that line.
+    ///
+    /// An offset pointing at a newline is placed at the end of the line the newline terminates.
+    /// Offsets past the end of the text are clamped to the end of the last line.
     fn find_pos(&self, offset: usize) -> (usize, usize) {
         let mut cumulative_offset = 0;
         for (i, line) in self.lines.iter().enumerate() {
@@ -16,9 +41,6 @@ impl LineBuffer {
             }
             cumulative_offset += line_len + 1; // for newline
         }
-        if self.lines.is_empty() {
-            return (0, 0);
-        }
         let last_line_idx = self.lines.len() - 1;
         (last_line_idx, self.lines[last_line_idx].len())
     }
@@ -26,29 +48,28 @@ impl LineBuffer {
 
 impl EditableText<'_> for LineBuffer {
     fn new(string: &str) -> Self {
-        let lines = string.lines().map(|s| s.to_string()).collect();
-        LineBuffer { lines }
+        let lines = string.split('\n').map(|s| s.to_string()).collect();
+        LineBuffer {
+            lines,
+            len: string.len(),
+        }
     }
 
     fn insert(&mut self, data: &str, offset: usize) {
         let (line_idx, col_idx) = self.find_pos(offset);
 
-        if self.lines.is_empty() {
-            self.lines.push(String::new());
-        }
-
-        let original_line = self.lines.remove(line_idx);
-        let (before, after) = original_line.split_at(col_idx);
-
-        let mut new_content = String::from(before);
+        let (before, after) = self.lines[line_idx].split_at(col_idx);
+        let mut new_content = String::with_capacity(before.len() + data.len() + after.len());
+        new_content.push_str(before);
         new_content.push_str(data);
         new_content.push_str(after);
 
-        let new_lines = new_content.lines().map(|s| s.to_string());
-        self.lines.splice(line_idx..line_idx, new_lines);
+        let new_lines: Vec<String> = new_content.split('\n').map(|s| s.to_string()).collect();
+        self.lines.splice(line_idx..=line_idx, new_lines);
+        self.len += data.len();
     }
 
-    fn delete(&mut self, range: TextRange) {
+    fn delete(&mut self, range: Range<usize>) {
         if range.start >= range.end {
             return;
         }
@@ -59,14 +80,14 @@ impl EditableText<'_> for LineBuffer {
         if start_line == end_line {
             self.lines[start_line].replace_range(start_col..end_col, "");
         } else {
-            let end_of_start_line = self.lines[start_line].split_at(start_col).0.to_string();
             let start_of_end_line = self.lines[end_line].split_at(end_col).1.to_string();
+            let merged_line = &mut self.lines[start_line];
+            merged_line.truncate(start_col);
+            merged_line.push_str(&start_of_end_line);
 
-            let merged_line = end_of_start_line + &start_of_end_line;
-            
-            self.lines[start_line] = merged_line;
             self.lines.drain((start_line + 1)..=end_line);
         }
+        self.len -= range.len();
     }
 }
 
@@ -75,3 +96,183 @@ impl fmt::Display for LineBuffer {
         write!(f, "{}", self.lines.join("\n"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let string = "hello!";
+        let buffer = LineBuffer::new(string);
+
+        assert_eq!(string, buffer.to_string());
+    }
+
+    #[test]
+    fn empty() {
+        let buffer = LineBuffer::new("");
+
+        assert_eq!("", buffer.to_string());
+        assert_eq!(1, buffer.len_lines());
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn trailing_newline_preserved() {
+        let buffer = LineBuffer::new("a\nb\n");
+
+        assert_eq!("a\nb\n", buffer.to_string());
+        assert_eq!(3, buffer.len_lines());
+    }
+
+    #[test]
+    fn insert_once() {
+        let original = "hello, ";
+        let mut buffer = LineBuffer::new(original);
+
+        let added = "world!";
+        buffer.insert(added, original.len());
+
+        assert_eq!(original.to_owned() + added, buffer.to_string());
+    }
+
+    #[test]
+    fn insert_twice() {
+        let original = "hello, ";
+        let added = "world";
+        let second = "!";
+
+        let mut buffer = LineBuffer::new(original);
+        buffer.insert(added, original.len());
+        buffer.insert(second, original.len() + added.len());
+
+        assert_eq!(original.to_owned() + added + second, buffer.to_string());
+    }
+
+    #[test]
+    fn insert_once_middle() {
+        let mut buffer = LineBuffer::new("hello!");
+
+        buffer.insert(", world", 5);
+
+        assert_eq!("hello, world!", buffer.to_string());
+    }
+
+    #[test]
+    fn insert_once_start() {
+        let mut buffer = LineBuffer::new("bc");
+
+        buffer.insert("a", 0);
+
+        assert_eq!("abc", buffer.to_string());
+    }
+
+    #[test]
+    fn insert_newline() {
+        let mut buffer = LineBuffer::new("ab");
+
+        buffer.insert("\n", 1);
+
+        assert_eq!("a\nb", buffer.to_string());
+        assert_eq!(2, buffer.len_lines());
+    }
+
+    #[test]
+    fn insert_multiple_lines() {
+        let mut buffer = LineBuffer::new("first\nlast");
+
+        buffer.insert("\nsecond\nthird", 5);
+
+        assert_eq!("first\nsecond\nthird\nlast", buffer.to_string());
+        assert_eq!(4, buffer.len_lines());
+    }
+
+    #[test]
+    fn insert_at_end_of_line() {
+        let mut buffer = LineBuffer::new("a\nb");
+
+        buffer.insert("x", 1);
+        buffer.insert("y", 4);
+
+        assert_eq!("ax\nby", buffer.to_string());
+    }
+
+    #[test]
+    fn insert_into_empty() {
+        let mut buffer = LineBuffer::new("");
+
+        buffer.insert("a\n", 0);
+
+        assert_eq!("a\n", buffer.to_string());
+        assert_eq!(2, buffer.len_lines());
+    }
+
+    #[test]
+    fn delete_original_whole() {
+        let mut buffer = LineBuffer::new("ab");
+
+        buffer.delete(0..2);
+
+        assert_eq!("", buffer.to_string());
+    }
+
+    #[test]
+    fn delete_original_half() {
+        let mut buffer = LineBuffer::new("ab");
+
+        buffer.delete(0..1);
+
+        assert_eq!("b", buffer.to_string());
+    }
+
+    #[test]
+    fn delete_original_middle() {
+        let mut buffer = LineBuffer::new("abc");
+
+        buffer.delete(1..2);
+
+        assert_eq!("ac", buffer.to_string());
+    }
+
+    #[test]
+    fn delete_newline() {
+        let mut buffer = LineBuffer::new("a\nb");
+
+        buffer.delete(1..2);
+
+        assert_eq!("ab", buffer.to_string());
+        assert_eq!(1, buffer.len_lines());
+    }
+
+    #[test]
+    fn delete_across_lines() {
+        let mut buffer = LineBuffer::new("one\ntwo\nthree");
+
+        buffer.delete(2..9);
+
+        assert_eq!("onhree", buffer.to_string());
+        assert_eq!(6, buffer.len());
+    }
+
+    #[test]
+    fn delete_trailing_newline() {
+        let mut buffer = LineBuffer::new("a\n");
+
+        buffer.delete(1..2);
+
+        assert_eq!("a", buffer.to_string());
+        assert_eq!(1, buffer.len_lines());
+    }
+
+    #[test]
+    fn add_delete_add() {
+        let mut buffer = LineBuffer::new("ab");
+
+        buffer.insert("c", 2);
+        buffer.delete(0..3);
+        buffer.insert("ab", 0);
+
+        assert_eq!("ab", buffer.to_string());
+    }
+}
@@ -0,0 +1,238 @@
+//! Finding and counting the line endings of a document.
+//!
+//! Files edited on different platforms end up with a mix of LF and CRLF line endings, which
+//! editors warn about. Each piece caches how many `'\r'` bytes and CRLF pairs it has, so
+//! [`PieceTable::eol_report`] only has to look at the ends of the pieces, never at their text.
+
+use crate::PieceTable;
+use crate::newlines::joins_crlf;
+
+/// A kind of line ending.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineEnding {
+    /// `"\n"`, used on Unix.
+    Lf,
+    /// `"\r\n"`, used on Windows.
+    CrLf,
+    /// A `'\r'` not followed by `'\n'`, used on classic Mac OS.
+    Cr,
+}
+
+impl LineEnding {
+    /// Returns the text of the line ending.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+}
+
+/// The number of line endings of each kind in a document, see [`PieceTable::eol_report`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EolReport {
+    pub lf: usize,
+    pub crlf: usize,
+    pub cr: usize,
+}
+
+impl EolReport {
+    /// Returns the total number of line endings.
+    pub fn total(&self) -> usize {
+        self.lf + self.crlf + self.cr
+    }
+
+    /// Checks if there's more than one kind of line ending.
+    pub fn is_mixed(&self) -> bool {
+        [self.lf, self.crlf, self.cr]
+            .iter()
+            .filter(|&&count| count > 0)
+            .count()
+            > 1
+    }
+
+    /// Returns the most common kind of line ending, or `None` if there are none. Ties go to LF,
+    /// then CRLF.
+    pub fn dominant(&self) -> Option<LineEnding> {
+        let kinds = [
+            (self.lf, LineEnding::Lf),
+            (self.crlf, LineEnding::CrLf),
+            (self.cr, LineEnding::Cr),
+        ];
+        // `max_by_key` returns the last maximum, so go through the kinds backwards
+        kinds
+            .into_iter()
+            .rev()
+            .filter(|&(count, _)| count > 0)
+            .max_by_key(|&(count, _)| count)
+            .map(|(_, kind)| kind)
+    }
+}
+
+impl PieceTable<'_> {
+    /// Returns the number of line endings of each kind.
+    ///
+    /// This takes time proportional to the number of pieces, and doesn't look at the text.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// use piece_table::line_endings::{EolReport, LineEnding};
+    ///
+    /// let mut pt = PieceTable::new("one\r\ntwo\r");
+    /// pt.insert("\nthree\n", 9);
+    ///
+    /// let report = pt.eol_report();
+    /// assert_eq!(report, EolReport { lf: 1, crlf: 2, cr: 0 });
+    /// assert!(report.is_mixed());
+    /// assert_eq!(report.dominant(), Some(LineEnding::CrLf));
+    /// ```
+    pub fn eol_report(&self) -> EolReport {
+        let mut crs = 0;
+        let mut crlfs = 0;
+        let mut prev_text = "";
+        for node in &self.nodes {
+            let mut chunks = node.chunks(self.original, &self.added);
+            let first = chunks.next().unwrap_or_default();
+            crs += node.crs.crs;
+            crlfs += node.crs.crlfs + joins_crlf(prev_text, first) as usize;
+            if !first.is_empty() {
+                prev_text = chunks.next_back().unwrap_or(first);
+            }
+        }
+        EolReport {
+            lf: self.newlines - crlfs,
+            crlf: crlfs,
+            cr: crs - crlfs,
+        }
+    }
+
+    /// Returns an iterator over the line endings, in order, with the offset each one starts at.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// use piece_table::line_endings::LineEnding;
+    ///
+    /// let pt = PieceTable::new("a\r\nb\rc\n");
+    /// let endings: Vec<_> = pt.line_ending_positions().collect();
+    /// assert_eq!(
+    ///     endings,
+    ///     vec![(1, LineEnding::CrLf), (4, LineEnding::Cr), (6, LineEnding::Lf)]
+    /// );
+    /// ```
+    pub fn line_ending_positions(&self) -> impl Iterator<Item = (usize, LineEnding)> + '_ {
+        let mut bytes = self
+            .chunks_from(0)
+            .flat_map(str::bytes)
+            .enumerate()
+            .peekable();
+        std::iter::from_fn(move || {
+            loop {
+                let (offset, byte) = bytes.next()?;
+                match byte {
+                    b'\n' => return Some((offset, LineEnding::Lf)),
+                    b'\r' if bytes.next_if(|&(_, next)| next == b'\n').is_some() => {
+                        return Some((offset, LineEnding::CrLf));
+                    }
+                    b'\r' => return Some((offset, LineEnding::Cr)),
+                    _ => {}
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scanned(piece_table: &PieceTable) -> EolReport {
+        let mut report = EolReport::default();
+        for (_, ending) in piece_table.line_ending_positions() {
+            match ending {
+                LineEnding::Lf => report.lf += 1,
+                LineEnding::CrLf => report.crlf += 1,
+                LineEnding::Cr => report.cr += 1,
+            }
+        }
+        report
+    }
+
+    #[test]
+    fn crlf_split_and_joined_by_edits() {
+        let mut piece_table = PieceTable::new("a\r\nb\r\nc\rd\n");
+        assert_eq!(
+            EolReport {
+                lf: 1,
+                crlf: 2,
+                cr: 1
+            },
+            piece_table.eol_report()
+        );
+
+        // Splits the first CRLF into a CR and a LF
+        piece_table.insert_char(2, 'x');
+        assert_eq!(
+            EolReport {
+                lf: 2,
+                crlf: 1,
+                cr: 2
+            },
+            piece_table.eol_report()
+        );
+
+        // And joins them again, across pieces
+        piece_table.delete(2..3);
+        piece_table.insert("\n", 8);
+        for report in [piece_table.eol_report(), scanned(&piece_table)] {
+            assert_eq!(
+                EolReport {
+                    lf: 1,
+                    crlf: 3,
+                    cr: 0
+                },
+                report
+            );
+        }
+        assert_eq!(Ok(()), piece_table.check_invariants());
+    }
+
+    #[test]
+    fn matches_scan_after_slicing_pieces() {
+        let text = "\r\n\r\r\n\n\r".repeat(3);
+        let mut piece_table = PieceTable::new(&text);
+        for offset in [20, 13, 7, 1] {
+            piece_table.insert("\r\n", offset);
+        }
+        for start in [17, 9, 4] {
+            piece_table.delete(start..start + 2);
+        }
+
+        assert_eq!(scanned(&piece_table), piece_table.eol_report());
+        assert_eq!(Ok(()), piece_table.check_invariants());
+    }
+
+    #[test]
+    fn mixed_and_dominant() {
+        let report = EolReport {
+            lf: 2,
+            crlf: 2,
+            cr: 0,
+        };
+        assert!(report.is_mixed());
+        assert_eq!(Some(LineEnding::Lf), report.dominant());
+        assert_eq!(None, EolReport::default().dominant());
+        assert!(
+            !EolReport {
+                lf: 0,
+                crlf: 3,
+                cr: 0
+            }
+            .is_mixed()
+        );
+    }
+}
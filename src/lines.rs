@@ -0,0 +1,215 @@
+//! Reordering whole lines, e.g. for "sort selection" and "move line up/down".
+//!
+//! The lines are cut out as runs of pieces and put back in their new order, so no text is
+//! copied, no matter how long the lines are.
+
+use std::cmp::Ordering;
+use std::ops::Range;
+
+use crate::PieceTable;
+
+impl PieceTable<'_> {
+    /// Reorders the lines in `lines`, so that line `lines.start + i` afterwards holds what was
+    /// line `lines.start + permutation[i]`.
+    ///
+    /// Every line keeps its text, and the newlines stay between lines, so e.g. the last line of
+    /// a document without a trailing newline still has none after moving up. This only shuffles
+    /// pieces, so it takes time proportional to the number of pieces and lines involved.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("one\ntwo\nthree");
+    ///
+    /// // Move the last line up
+    /// pt.reorder_lines(1..3, &[1, 0]);
+    /// assert_eq!(pt.to_string(), "one\nthree\ntwo");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `lines` isn't a valid range of lines, if `permutation` doesn't contain each of
+    /// `0..lines.len()` exactly once, or if splitting the pieces at the lines could exceed one
+    /// of the table's [`Limits`](crate::limits::Limits).
+    pub fn reorder_lines(&mut self, lines: Range<usize>, permutation: &[usize]) {
+        self.check_lines(&lines, "reorder_lines");
+        let mut seen = vec![false; lines.len()];
+        for &line in permutation {
+            assert!(
+                line < seen.len() && !seen[line],
+                "invalid reorder_lines: {permutation:?} is not a permutation of 0..{}",
+                lines.len()
+            );
+            seen[line] = true;
+        }
+        assert!(
+            permutation.len() == lines.len(),
+            "invalid reorder_lines: {permutation:?} is not a permutation of 0..{}",
+            lines.len()
+        );
+        if permutation.iter().enumerate().all(|(i, &line)| i == line) {
+            return;
+        }
+
+        // The last line has no newline, so it gets one while it's moved around, and whichever
+        // line ends up last loses it again.
+        let at_end = lines.end == self.len_lines();
+        let splits = match self.limits.max_pieces {
+            Some(_) => self
+                .line_bounds(&lines)
+                .iter()
+                .map(|&offset| self.splits_at(offset))
+                .sum(),
+            None => 0,
+        };
+        if let Err(err) = self.check_growth(0, at_end as usize, splits + at_end as usize) {
+            panic!("invalid reorder_lines: {err}");
+        }
+        if at_end {
+            self.insert_unchecked("\n", self.len);
+        }
+
+        let bounds = self.line_bounds(&lines);
+        let idxs: Vec<usize> = bounds
+            .iter()
+            .map(|&offset| self.split_at_offset(offset))
+            .collect();
+
+        let tail = self.nodes.split_off(idxs[lines.len()]);
+        let moved: Vec<_> = self.nodes.drain(idxs[0]..).collect();
+        let first = idxs[0];
+        for &line in permutation {
+            self.nodes.extend(
+                moved[idxs[line] - first..idxs[line + 1] - first]
+                    .iter()
+                    .cloned(),
+            );
+        }
+        self.nodes.extend(tail);
+
+        if at_end {
+            self.delete_unchecked(self.len - 1..self.len);
+        } else {
+            self.publish();
+        }
+    }
+
+    /// Sorts the lines in `lines` with the comparator `compare`, which is passed the text of two
+    /// lines without their newlines.
+    ///
+    /// The sort is stable, so equal lines keep their order. Like
+    /// [`PieceTable::reorder_lines`], the text itself isn't copied into the table.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("use std::fmt;\nuse crate::b;\nuse crate::a;\n\nfn main() {}\n");
+    /// pt.sort_lines(0..3, |a, b| a.cmp(b));
+    /// assert_eq!(pt.to_string(), "use crate::a;\nuse crate::b;\nuse std::fmt;\n\nfn main() {}\n");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `lines` isn't a valid range of lines.
+    pub fn sort_lines<F>(&mut self, lines: Range<usize>, mut compare: F)
+    where
+        F: FnMut(&str, &str) -> Ordering,
+    {
+        self.check_lines(&lines, "sort_lines");
+        let bounds = self.line_bounds(&lines);
+        let texts: Vec<String> = bounds
+            .windows(2)
+            .map(|line| {
+                let text = self.slice(line[0]..line[1]).to_string();
+                match text.strip_suffix('\n') {
+                    Some(text) => text.to_string(),
+                    None => text,
+                }
+            })
+            .collect();
+
+        let mut permutation: Vec<usize> = (0..texts.len()).collect();
+        permutation.sort_by(|&a, &b| compare(&texts[a], &texts[b]));
+        self.reorder_lines(lines, &permutation);
+    }
+
+    fn check_lines(&self, lines: &Range<usize>, method: &str) {
+        assert!(
+            lines.start <= lines.end && lines.end <= self.len_lines(),
+            "invalid {method}: lines {lines:?} are out of bounds of document with {} lines",
+            self.len_lines()
+        );
+    }
+
+    /// Returns the offsets where each line in `lines` starts, followed by the offset where the
+    /// last one ends (including its newline, if any).
+    fn line_bounds(&self, lines: &Range<usize>) -> Vec<usize> {
+        let Some(start) = self.line_start(lines.start) else {
+            return vec![self.len];
+        };
+
+        let mut bounds = vec![start];
+        let mut offset = start;
+        'chunks: for chunk in self.chunks_from(start) {
+            for (idx, _) in chunk.match_indices('\n') {
+                if bounds.len() > lines.len() {
+                    break 'chunks;
+                }
+                bounds.push(offset + idx + 1);
+            }
+            offset += chunk.len();
+        }
+        if bounds.len() <= lines.len() {
+            bounds.push(self.len);
+        }
+        bounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reorder_across_pieces() {
+        let mut piece_table = PieceTable::new("a\nc\n");
+        piece_table.insert("b\nd", 4);
+        piece_table.insert("x", 1);
+
+        piece_table.reorder_lines(0..4, &[3, 1, 0, 2]);
+
+        assert_eq!("d\nc\nax\nb", piece_table.to_string());
+        assert_eq!(4, piece_table.len_lines());
+        assert_eq!(Ok(()), piece_table.check_invariants());
+    }
+
+    #[test]
+    fn reorder_moves_trailing_newline() {
+        let mut piece_table = PieceTable::new("a\nb\n");
+
+        piece_table.reorder_lines(1..3, &[1, 0]);
+        assert_eq!("a\n\nb", piece_table.to_string());
+
+        piece_table.reorder_lines(0..3, &[2, 0, 1]);
+        assert_eq!("b\na\n", piece_table.to_string());
+        assert_eq!(Ok(()), piece_table.check_invariants());
+    }
+
+    #[test]
+    fn sort_is_stable() {
+        let mut piece_table = PieceTable::new("header\nb 1\na 2\nb 3\na 4");
+
+        piece_table.sort_lines(1..5, |a, b| a[..1].cmp(&b[..1]));
+
+        assert_eq!("header\na 2\na 4\nb 1\nb 3", piece_table.to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "not a permutation")]
+    fn reorder_rejects_duplicates() {
+        let mut piece_table = PieceTable::new("a\nb\nc");
+        piece_table.reorder_lines(0..3, &[0, 1, 1]);
+    }
+}
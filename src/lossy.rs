@@ -0,0 +1,161 @@
+//! Opening bytes which may not be valid UTF-8, replacing invalid sequences with U+FFFD.
+//!
+//! The valid text goes into the "added" buffer, and every replacement char is a piece of its
+//! own referring to the "original" buffer, which holds nothing but U+FFFD. Since nothing else
+//! ever refers to the original buffer of such a table, the pieces themselves record where the
+//! replacements are, and they move along with the text as it's edited.
+
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use crate::{Node, NodeKind, PieceTable};
+
+/// U+FFFD, in a static of its own, so that no other string can share its address.
+static REPLACEMENT_BYTES: [u8; 3] = *b"\xEF\xBF\xBD";
+
+/// The original buffer of tables created by [`PieceTable::from_bytes_lossy`].
+static REPLACEMENT: &str = match std::str::from_utf8(&REPLACEMENT_BYTES) {
+    Ok(replacement) => replacement,
+    Err(_) => panic!("U+FFFD is valid UTF-8"),
+};
+
+/// The byte order mark of UTF-8.
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+impl PieceTable<'_> {
+    /// Creates a table holding `bytes`, skipping a leading byte order mark, and replacing every
+    /// invalid UTF-8 sequence with U+FFFD, like [`String::from_utf8_lossy`].
+    ///
+    /// `bytes` is only read from. Use [`PieceTable::replacements`] to find the replaced
+    /// sequences, e.g. to warn that saving the text would change them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let pt = PieceTable::from_bytes_lossy(b"\xEF\xBB\xBFcaf\xE9!\n");
+    /// assert_eq!(pt.to_string(), "caf\u{FFFD}!\n");
+    /// assert_eq!(pt.replacements(), vec![3..6]);
+    /// ```
+    pub fn from_bytes_lossy(bytes: &[u8]) -> PieceTable<'static> {
+        let bytes = bytes.strip_prefix(BOM).unwrap_or(bytes);
+
+        let mut text = String::with_capacity(bytes.len());
+        let mut nodes = VecDeque::new();
+        let mut replacements = 0;
+        for chunk in bytes.utf8_chunks() {
+            let valid = chunk.valid();
+            if !valid.is_empty() {
+                nodes.push_back(Node::new(
+                    NodeKind::Added,
+                    text.len()..text.len() + valid.len(),
+                    valid,
+                ));
+                text.push_str(valid);
+            }
+            if !chunk.invalid().is_empty() {
+                nodes.push_back(Node::new(
+                    NodeKind::Original,
+                    0..REPLACEMENT.len(),
+                    REPLACEMENT,
+                ));
+                replacements += 1;
+            }
+        }
+
+        let mut table = PieceTable::from(text);
+        table.original = REPLACEMENT;
+        table.nodes = nodes;
+        table.len += replacements * REPLACEMENT.len();
+        table.chars += replacements;
+        table
+    }
+
+    /// Returns the ranges of the replacement chars of a table created by
+    /// [`PieceTable::from_bytes_lossy`], in document order.
+    ///
+    /// The ranges follow the replacement chars as the table is edited, and a deleted
+    /// replacement char is no longer returned. Tables created any other way have none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::from_bytes_lossy(b"a\xFFb\xFF");
+    /// pt.insert("\u{FFFD}", 0);
+    /// pt.delete(4..7);
+    ///
+    /// assert_eq!(pt.to_string(), "\u{FFFD}ab\u{FFFD}");
+    /// assert_eq!(pt.replacements(), vec![5..8]);
+    /// ```
+    pub fn replacements(&self) -> Vec<Range<usize>> {
+        if !std::ptr::eq(self.original, REPLACEMENT) {
+            return Vec::new();
+        }
+
+        let mut found = Vec::new();
+        let mut start = 0;
+        for node in &self.nodes {
+            if node.kind == NodeKind::Original && !node.range.is_empty() {
+                found.push(start..start + node.range.len());
+            }
+            start += node.range.len();
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_std() {
+        let inputs: [&[u8]; 5] = [
+            b"",
+            b"\xEF\xBB\xBF",
+            b"plain",
+            b"\xF0\x9F\xA6trunc\xE2\x82",
+            b"\xC0\x80 overlong, \xED\xA0\x80 surrogate, \xFF\xFE\n",
+        ];
+        for bytes in inputs {
+            let piece_table = PieceTable::from_bytes_lossy(bytes);
+            let expected = String::from_utf8_lossy(bytes.strip_prefix(BOM).unwrap_or(bytes));
+            let text = piece_table.to_string();
+
+            assert_eq!(expected, text);
+            assert_eq!(Ok(()), piece_table.check_invariants());
+            assert_eq!(text.chars().count(), piece_table.len_chars());
+            for range in piece_table.replacements() {
+                assert_eq!(REPLACEMENT, &text[range]);
+            }
+        }
+    }
+
+    #[test]
+    fn replacements_survive_edits() {
+        let mut piece_table = PieceTable::from_bytes_lossy(b"\xFF\xFFa\x80");
+        assert_eq!(vec![0..3, 3..6, 7..10], piece_table.replacements());
+
+        piece_table.insert("xy", 10);
+        piece_table.insert_char(6, 'z');
+        piece_table.delete(0..3);
+        assert_eq!(vec![0..3, 5..8], piece_table.replacements());
+
+        let rest = piece_table.split_off(4);
+        assert_eq!(vec![0..3], piece_table.replacements());
+        assert_eq!(vec![1..4], rest.replacements());
+    }
+
+    #[test]
+    fn other_tables_have_no_replacements() {
+        let piece_table = PieceTable::new("\u{FFFD}");
+
+        assert!(piece_table.replacements().is_empty());
+        assert!(
+            PieceTable::from_bytes_lossy(b"ok")
+                .replacements()
+                .is_empty()
+        );
+    }
+}
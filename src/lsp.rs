@@ -0,0 +1,286 @@
+//! Translating between Language Server Protocol positions and byte offsets.
+//!
+//! The LSP addresses text by line and "character", where characters are counted in UTF-16 code
+//! units. The types here mirror the ones of the same name in the `lsp_types` crate, so they can
+//! be converted field by field without this crate depending on it.
+
+use std::fmt;
+use std::ops;
+
+use crate::PieceTable;
+use crate::interface::EditableText;
+use crate::point::{ColumnUnit, Point};
+
+/// A position in a document, as a (0-based) line and UTF-16 code unit offset within that line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub line: u32,
+    pub character: u32,
+}
+
+/// A range in a document, from `start` up to (but not including) `end`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// A change to a document, as sent in `textDocument/didChange` notifications.
+///
+/// If `range` is `None`, `text` replaces the whole document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextDocumentContentChangeEvent {
+    pub range: Option<Range>,
+    pub text: String,
+}
+
+impl Position {
+    pub fn new(line: u32, character: u32) -> Self {
+        Position { line, character }
+    }
+}
+
+impl Range {
+    pub fn new(start: Position, end: Position) -> Self {
+        Range { start, end }
+    }
+}
+
+impl PieceTable<'_> {
+    /// Converts an LSP position to a byte offset.
+    ///
+    /// As required by the LSP, a `character` past the end of its line refers to the end of the
+    /// line, and a `line` past the end of the document refers to the end of the document. A
+    /// `character` which falls in the middle of a surrogate pair refers to the start of the
+    /// char.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// # use piece_table::lsp::Position;
+    /// let pt = PieceTable::new("a𐐀b\nc");
+    /// assert_eq!(pt.position_to_offset(Position::new(0, 3)), 5);
+    /// assert_eq!(pt.position_to_offset(Position::new(0, 9)), 6);
+    /// assert_eq!(pt.position_to_offset(Position::new(1, 1)), 8);
+    /// ```
+    pub fn position_to_offset(&self, position: Position) -> usize {
+        let point = Point::new(position.line as usize, position.character as usize);
+        self.point_to_offset(point, ColumnUnit::Utf16)
+    }
+
+    /// Converts a byte offset to an LSP position.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is out of bounds or not on a char boundary.
+    pub fn offset_to_position(&self, offset: usize) -> Position {
+        let point = self.offset_to_point(offset, ColumnUnit::Utf16);
+        Position::new(point.line as u32, point.column as u32)
+    }
+
+    /// Converts an LSP range to a range of byte offsets, see
+    /// [`PieceTable::position_to_offset`].
+    pub fn lsp_range_to_offsets(&self, range: Range) -> ops::Range<usize> {
+        let start = self.position_to_offset(range.start);
+        let end = self.position_to_offset(range.end);
+        start..end.max(start)
+    }
+
+    /// Converts a range of byte offsets to an LSP range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either end of `range` is out of bounds or not on a char boundary.
+    pub fn offsets_to_lsp_range(&self, range: ops::Range<usize>) -> Range {
+        Range::new(
+            self.offset_to_position(range.start),
+            self.offset_to_position(range.end),
+        )
+    }
+
+    /// Replaces the text in `range` with `text`, as described by an incremental LSP change.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// # use piece_table::lsp::{Position, Range};
+    /// let mut pt = PieceTable::new("fn main() {\n    todo!()\n}");
+    /// let range = Range::new(Position::new(1, 4), Position::new(1, 11));
+    /// pt.apply_lsp_change(range, "println!(\"hi\");");
+    /// assert_eq!(pt.to_string(), "fn main() {\n    println!(\"hi\");\n}");
+    /// ```
+    pub fn apply_lsp_change(&mut self, range: Range, text: &str) {
+        let range = self.lsp_range_to_offsets(range);
+        let offset = range.start;
+        self.delete(range);
+        self.insert(text, offset);
+    }
+
+    /// Applies a change event, which may replace the whole document.
+    pub fn apply_lsp_change_event(&mut self, change: &TextDocumentContentChangeEvent) {
+        match change.range {
+            Some(range) => self.apply_lsp_change(range, &change.text),
+            None => {
+                self.delete(0..self.len);
+                self.insert(&change.text, 0);
+            }
+        }
+    }
+}
+
+/// Wraps a `PieceTable`, recording every edit made to it as an LSP change event.
+///
+/// This is the inverse of [`PieceTable::apply_lsp_change`]: a client editing its copy of a
+/// document through this wrapper can send the recorded changes to a language server, which
+/// can apply them in order to its own copy.
+///
+/// # Examples
+///
+/// ```
+/// # use piece_table::interface::EditableText;
+/// # use piece_table::lsp::LspRecorder;
+/// let mut doc = LspRecorder::new("hello\nworld");
+/// doc.insert("!", 11);
+/// doc.delete(0..6);
+///
+/// let mut server = piece_table::PieceTable::new("hello\nworld");
+/// for change in doc.take_changes() {
+///     server.apply_lsp_change_event(&change);
+/// }
+/// assert_eq!(server.to_string(), "world!");
+/// ```
+#[derive(Debug, Clone)]
+pub struct LspRecorder<'a> {
+    table: PieceTable<'a>,
+    changes: Vec<TextDocumentContentChangeEvent>,
+}
+
+impl<'a> LspRecorder<'a> {
+    /// Starts recording edits made to `table`.
+    pub fn wrap(table: PieceTable<'a>) -> Self {
+        LspRecorder {
+            table,
+            changes: Vec::new(),
+        }
+    }
+
+    /// Returns the wrapped table.
+    pub fn table(&self) -> &PieceTable<'a> {
+        &self.table
+    }
+
+    /// Returns the changes recorded since the last call, leaving none recorded.
+    pub fn take_changes(&mut self) -> Vec<TextDocumentContentChangeEvent> {
+        std::mem::take(&mut self.changes)
+    }
+
+    /// Stops recording, returning the wrapped table.
+    pub fn into_inner(self) -> PieceTable<'a> {
+        self.table
+    }
+}
+
+impl<'a> EditableText<'a> for LspRecorder<'a> {
+    fn new(string: &'a str) -> Self {
+        LspRecorder::wrap(PieceTable::new(string))
+    }
+
+    fn insert(&mut self, data: &str, offset: usize) {
+        let position = self.table.offset_to_position(offset);
+        self.table.insert(data, offset);
+        self.changes.push(TextDocumentContentChangeEvent {
+            range: Some(Range::new(position, position)),
+            text: data.to_string(),
+        });
+    }
+
+    fn delete(&mut self, range: ops::Range<usize>) {
+        let lsp_range = self.table.offsets_to_lsp_range(range.clone());
+        self.table.delete(range);
+        self.changes.push(TextDocumentContentChangeEvent {
+            range: Some(lsp_range),
+            text: String::new(),
+        });
+    }
+}
+
+impl fmt::Display for LspRecorder<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.table.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workload::{Profile, generate};
+
+    #[test]
+    fn position_to_offset_across_pieces() {
+        let mut piece_table = PieceTable::new("one\nthree");
+        piece_table.insert("two\n", 4);
+
+        assert_eq!(0, piece_table.position_to_offset(Position::new(0, 0)));
+        assert_eq!(4, piece_table.position_to_offset(Position::new(1, 0)));
+        assert_eq!(7, piece_table.position_to_offset(Position::new(1, 3)));
+        assert_eq!(7, piece_table.position_to_offset(Position::new(1, 10)));
+        assert_eq!(10, piece_table.position_to_offset(Position::new(2, 2)));
+        assert_eq!(13, piece_table.position_to_offset(Position::new(5, 0)));
+    }
+
+    #[test]
+    fn surrogate_pairs() {
+        let piece_table = PieceTable::new("𐐀é𐐀");
+
+        assert_eq!(0, piece_table.position_to_offset(Position::new(0, 1)));
+        assert_eq!(4, piece_table.position_to_offset(Position::new(0, 2)));
+        assert_eq!(6, piece_table.position_to_offset(Position::new(0, 3)));
+        assert_eq!(Position::new(0, 3), piece_table.offset_to_position(6));
+        assert_eq!(Position::new(0, 5), piece_table.offset_to_position(10));
+    }
+
+    #[test]
+    fn offset_to_position_round_trips() {
+        let mut piece_table = PieceTable::new("héllo\nwörld\n");
+        piece_table.insert("\n€\n", 3);
+
+        let text = piece_table.to_string();
+        for (offset, _) in text.char_indices().chain([(text.len(), ' ')]) {
+            let position = piece_table.offset_to_position(offset);
+            assert_eq!(offset, piece_table.position_to_offset(position));
+        }
+    }
+
+    #[test]
+    fn full_document_change() {
+        let mut piece_table = PieceTable::new("old");
+
+        piece_table.apply_lsp_change_event(&TextDocumentContentChangeEvent {
+            range: None,
+            text: "new".to_string(),
+        });
+
+        assert_eq!("new", piece_table.to_string());
+    }
+
+    #[test]
+    fn recorded_changes_replay_on_server() {
+        let initial = "fn main() {\n    let x = 1;\n}\n";
+        for seed in 0..4 {
+            let mut doc = LspRecorder::new(initial);
+            let mut server = PieceTable::new(initial);
+            let workload = generate(seed, Profile::PasteHeavy, initial, 50);
+
+            for edit in &workload.edits {
+                edit.apply(&mut doc);
+                for change in doc.take_changes() {
+                    server.apply_lsp_change_event(&change);
+                }
+            }
+
+            assert_eq!(doc.to_string(), server.to_string());
+        }
+    }
+}
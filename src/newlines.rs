@@ -0,0 +1,223 @@
+//! Fast newline, char and word counting, used to keep per-piece counts up to date.
+//!
+//! On `x86_64` SSE2 is always available, so the bulk of the input is processed 16 bytes at a
+//! time. Everywhere else (and for the tail of the input) a plain scalar loop is used.
+
+/// Counts the number of `'\n'` bytes in `s`.
+///
+/// # Examples
+///
+/// ```
+/// # use piece_table::newlines::count_newlines;
+/// assert_eq!(count_newlines("a\nb\nc"), 2);
+/// assert_eq!(count_newlines(""), 0);
+/// ```
+pub fn count_newlines(s: &str) -> usize {
+    count_newlines_bytes(s.as_bytes())
+}
+
+#[cfg(target_arch = "x86_64")]
+fn count_newlines_bytes(bytes: &[u8]) -> usize {
+    use std::arch::x86_64::{
+        __m128i, _mm_cmpeq_epi8, _mm_loadu_si128, _mm_movemask_epi8, _mm_set1_epi8,
+    };
+
+    const LANES: usize = 16;
+
+    let chunks = bytes.chunks_exact(LANES);
+    let tail = chunks.remainder();
+    let mut count = 0;
+
+    // SAFETY: SSE2 is part of the `x86_64` baseline, and every load reads exactly `LANES` bytes
+    // from a chunk of that length.
+    unsafe {
+        let newline = _mm_set1_epi8(b'\n' as i8);
+        for chunk in chunks {
+            let block = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+            let mask = _mm_movemask_epi8(_mm_cmpeq_epi8(block, newline));
+            count += mask.count_ones() as usize;
+        }
+    }
+
+    count + count_newlines_scalar(tail)
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn count_newlines_bytes(bytes: &[u8]) -> usize {
+    count_newlines_scalar(bytes)
+}
+
+fn count_newlines_scalar(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|&&b| b == b'\n').count()
+}
+
+/// Counts the number of chars in `s`.
+///
+/// # Examples
+///
+/// ```
+/// # use piece_table::newlines::count_chars;
+/// assert_eq!(count_chars("héllo"), 5);
+/// assert_eq!(count_chars(""), 0);
+/// ```
+pub fn count_chars(s: &str) -> usize {
+    count_char_starts(s.as_bytes())
+}
+
+/// Counts the bytes which start a char, i.e. which aren't UTF-8 continuation bytes.
+#[cfg(target_arch = "x86_64")]
+pub(crate) fn count_char_starts(bytes: &[u8]) -> usize {
+    use std::arch::x86_64::{
+        __m128i, _mm_cmpgt_epi8, _mm_loadu_si128, _mm_movemask_epi8, _mm_set1_epi8,
+    };
+
+    const LANES: usize = 16;
+
+    let chunks = bytes.chunks_exact(LANES);
+    let tail = chunks.remainder();
+    let mut count = 0;
+
+    // SAFETY: see `count_newlines_bytes`.
+    unsafe {
+        // Continuation bytes are `0b10xx_xxxx`, i.e. -64 and below as signed bytes.
+        let last_continuation = _mm_set1_epi8(-0x41);
+        for chunk in chunks {
+            let block = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+            let mask = _mm_movemask_epi8(_mm_cmpgt_epi8(block, last_continuation));
+            count += mask.count_ones() as usize;
+        }
+    }
+
+    count + count_char_starts_scalar(tail)
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+pub(crate) fn count_char_starts(bytes: &[u8]) -> usize {
+    count_char_starts_scalar(bytes)
+}
+
+fn count_char_starts_scalar(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|&&b| (b as i8) >= -0x40).count()
+}
+
+/// Counts the words in `s`, i.e. the runs of chars which aren't whitespace, like
+/// [`str::split_whitespace`].
+///
+/// # Examples
+///
+/// ```
+/// # use piece_table::newlines::count_words;
+/// assert_eq!(count_words("  hello,\twörld \n"), 2);
+/// assert_eq!(count_words(" "), 0);
+/// ```
+pub fn count_words(s: &str) -> usize {
+    s.split_whitespace().count()
+}
+
+/// Checks if `left` ends and `right` starts in the middle of a word, so that putting them
+/// together makes one word out of two.
+pub(crate) fn joins_words(left: &str, right: &str) -> bool {
+    let is_word = |c: char| !c.is_whitespace();
+    left.chars().next_back().is_some_and(is_word) && right.chars().next().is_some_and(is_word)
+}
+
+/// The `'\r'` bytes in some text, which together with its newline count tell how many line
+/// endings of each kind it has.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct CrCounts {
+    /// Number of `'\r'` bytes
+    pub(crate) crs: usize,
+    /// Number of `"\r\n"` pairs
+    pub(crate) crlfs: usize,
+}
+
+impl CrCounts {
+    /// Counts the `'\r'` bytes in `s`.
+    pub(crate) fn of(s: &str) -> CrCounts {
+        let bytes = s.as_bytes();
+        let crs = bytes.iter().filter(|&&b| b == b'\r').count();
+        let crlfs = match crs {
+            0 => 0,
+            _ => bytes.windows(2).filter(|pair| pair == b"\r\n").count(),
+        };
+        CrCounts { crs, crlfs }
+    }
+
+    /// Returns the counts of `left` followed by `right`, where `self` are the counts of `left`.
+    pub(crate) fn append(self, left: &str, right: &str) -> CrCounts {
+        let right_counts = CrCounts::of(right);
+        CrCounts {
+            crs: self.crs + right_counts.crs,
+            crlfs: self.crlfs + right_counts.crlfs + joins_crlf(left, right) as usize,
+        }
+    }
+
+    /// Returns the counts of `inner`, where `self` are the counts of `before`, `inner` and
+    /// `after` put together.
+    pub(crate) fn inner(self, before: &str, inner: &str, after: &str) -> CrCounts {
+        let (before_counts, after_counts) = (CrCounts::of(before), CrCounts::of(after));
+        CrCounts {
+            crs: self.crs - before_counts.crs - after_counts.crs,
+            crlfs: self.crlfs
+                - before_counts.crlfs
+                - after_counts.crlfs
+                - joins_crlf(before, inner) as usize
+                - joins_crlf(inner, after) as usize,
+        }
+    }
+}
+
+/// Checks if `left` ends with `'\r'` and `right` starts with `'\n'`, so that putting them
+/// together makes a CRLF out of a lone CR and a LF.
+pub(crate) fn joins_crlf(left: &str, right: &str) -> bool {
+    left.ends_with('\r') && right.starts_with('\n')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        assert_eq!(count_newlines(""), 0);
+    }
+
+    #[test]
+    fn only_newlines() {
+        assert_eq!(count_newlines("\n\n\n"), 3);
+    }
+
+    #[test]
+    fn crlf_counts_once() {
+        assert_eq!(count_newlines("a\r\nb\r\n"), 2);
+    }
+
+    #[test]
+    fn longer_than_one_block() {
+        let text = "abc\n".repeat(37) + "tail\n";
+        assert_eq!(count_newlines(&text), 38);
+    }
+
+    #[test]
+    fn matches_scalar_on_every_prefix() {
+        let text = "héllo\nwörld\n\nこんにちは\n".repeat(5);
+        for end in (0..=text.len()).filter(|&i| text.is_char_boundary(i)) {
+            assert_eq!(
+                count_newlines(&text[..end]),
+                count_newlines_scalar(&text.as_bytes()[..end])
+            );
+        }
+    }
+
+    #[test]
+    fn chars_match_std_on_every_prefix() {
+        let text = "héllo\nwörld\n\nこんにちは🦀\n".repeat(5);
+        for end in 0..=text.len() {
+            let bytes = &text.as_bytes()[..end];
+            assert_eq!(count_char_starts(bytes), count_char_starts_scalar(bytes));
+            if text.is_char_boundary(end) {
+                assert_eq!(count_chars(&text[..end]), text[..end].chars().count());
+            }
+        }
+    }
+}
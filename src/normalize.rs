@@ -0,0 +1,107 @@
+//! Keeping the text in Unicode Normalization Form C.
+//!
+//! The same text can be encoded in several ways, e.g. "é" as a single char or as "e" followed by
+//! a combining accent, which makes equal-looking strings compare differently. Applications which
+//! must store one form can have the table normalize inserted text to NFC, the form most
+//! text is already in, and check the whole document with [`PieceTable::is_normalized`].
+
+use unicode_normalization::{IsNormalized, UnicodeNormalization, is_nfc_quick};
+
+use crate::PieceTable;
+
+/// Checks if `text` is in NFC.
+pub(crate) fn is_nfc(text: &str) -> bool {
+    unicode_normalization::is_nfc(text)
+}
+
+/// Returns `text` in NFC.
+pub(crate) fn nfc(text: &str) -> String {
+    text.nfc().collect()
+}
+
+impl PieceTable<'_> {
+    /// Sets whether inserted text is normalized to NFC before it's stored. This only affects
+    /// future insertions, and the text a table is created or loaded with is kept as it is.
+    ///
+    /// Each insertion is normalized on its own, so an insertion which starts with a combining
+    /// char isn't composed with the char before it, since that would change text outside the
+    /// insertion. Normalizing can change the length of the inserted text, so callers tracking
+    /// offsets should use the length of the document before and after the insertion.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("caf");
+    /// pt.set_normalize_inserts(true);
+    /// pt.insert("e\u{301}", 3);
+    /// assert_eq!(pt.to_string(), "café");
+    /// assert!(pt.is_normalized());
+    ///
+    /// // A lone accent can't be composed with the "o" before it
+    /// pt.insert("o", 5);
+    /// pt.insert_char(6, '\u{308}');
+    /// assert!(!pt.is_normalized());
+    /// ```
+    pub fn set_normalize_inserts(&mut self, normalize: bool) {
+        self.normalize = normalize;
+    }
+
+    /// Checks if inserted text is normalized to NFC, see [`PieceTable::set_normalize_inserts`].
+    pub fn normalizes_inserts(&self) -> bool {
+        self.normalize
+    }
+
+    /// Checks if the whole document is in NFC.
+    ///
+    /// Most text can be checked one char at a time without allocating, and only text with
+    /// combining chars that may compose with the chars before them is normalized to be sure.
+    pub fn is_normalized(&self) -> bool {
+        let chars = || self.char_indices_from(0).map(|(_, c)| c);
+        match is_nfc_quick(chars()) {
+            IsNormalized::Yes => true,
+            IsNormalized::No => false,
+            IsNormalized::Maybe => chars().nfc().eq(chars()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::edit_builder::EditBuilder;
+
+    use super::*;
+
+    #[test]
+    fn every_kind_of_insert_is_normalized() {
+        let mut piece_table = PieceTable::new("");
+        piece_table.set_normalize_inserts(true);
+
+        // The ohm sign, whose NFC is the Greek capital omega
+        piece_table.insert_char(0, '\u{2126}');
+        piece_table.insert("A\u{30A}", 2);
+        piece_table.try_insert("\u{1100}\u{1161}", 0).unwrap();
+        // The angstrom sign, whose NFC is "Å"
+        let mut builder = EditBuilder::new(&piece_table);
+        builder.replace(5..7, "\u{212B}");
+        builder.apply(&mut piece_table).unwrap();
+
+        assert_eq!("\u{AC00}\u{3A9}\u{C5}", piece_table.to_string());
+        assert_eq!(Ok(()), piece_table.check_invariants());
+        assert!(piece_table.is_normalized());
+    }
+
+    #[test]
+    fn original_and_loaded_text_are_kept() {
+        let mut piece_table = PieceTable::new("e\u{301}");
+        assert!(!piece_table.is_normalized());
+        piece_table.set_normalize_inserts(true);
+        piece_table.insert("e\u{301}", 0);
+        assert_eq!("\u{E9}e\u{301}", piece_table.to_string());
+
+        let mut piece_table = PieceTable::new_streaming();
+        piece_table.set_normalize_inserts(true);
+        piece_table.feed_original_chunk("A\u{30A}");
+        assert_eq!("A\u{30A}", piece_table.to_string());
+    }
+}
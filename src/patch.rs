@@ -0,0 +1,272 @@
+//! Saving a document as a binary delta against the file it was opened from.
+//!
+//! The pieces already record which parts of the document are still the original text, so a
+//! patch only has to hold the text inserted since, plus the ranges of the original file to copy
+//! around it. Saving a small edit to a huge file then writes a few bytes instead of the whole
+//! file, e.g. for autosave over slow storage.
+//!
+//! A patch is little endian, and made of:
+//!
+//! - the magic bytes `PTPATCH1`,
+//! - the length of the original file as a `u64`,
+//! - the number of operations as a `u64`,
+//! - the operations, each one a tag byte followed by its arguments:
+//!   - `0`, copy: the start and length of a range of the original file, as `u64`s,
+//!   - `1`, insert: the length of the text as a `u64`, followed by the text itself.
+
+use std::io::{self, Read, Write};
+use std::ops::Range;
+
+use crate::{Node, NodeKind, PieceTable};
+
+/// The bytes every patch starts with.
+const MAGIC: &[u8; 8] = b"PTPATCH1";
+
+const COPY: u8 = 0;
+const INSERT: u8 = 1;
+
+/// An operation of a patch.
+enum Op<'s> {
+    /// Copy a range of the original file.
+    Copy(Range<usize>),
+    /// Insert text, stored in the patch in one or more parts.
+    Insert(Vec<&'s str>),
+}
+
+impl<'ptable> PieceTable<'ptable> {
+    /// Writes the document to `writer` as a patch against the text it was opened from, see the
+    /// [module documentation](crate::patch) for the format.
+    ///
+    /// Pieces of the original text become ranges to copy, and only text inserted since is
+    /// written out, so the patch is about as large as [`PieceTable::changed_ranges`]. For a table
+    /// created with [`PieceTable::new_streaming`], the original is the text loaded so far. A
+    /// table created from a `String` has no original, so its patch holds all of its text.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let file = "a large file\n".repeat(1000);
+    /// let mut pt = PieceTable::new(&file);
+    /// pt.insert("an edit to ", 0);
+    ///
+    /// let mut patch = Vec::new();
+    /// pt.save_patch_to(&mut patch).unwrap();
+    /// assert!(patch.len() < 100);
+    ///
+    /// let restored = PieceTable::apply_saved_patch(&file, patch.as_slice()).unwrap();
+    /// assert_eq!(restored.to_string(), pt.to_string());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `writer`.
+    pub fn save_patch_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let loaded = self.stream.loaded_len();
+        let original_len = if loaded > 0 {
+            loaded
+        } else {
+            self.original.len()
+        };
+
+        let mut ops: Vec<Op> = Vec::new();
+        for node in &self.nodes {
+            let copied = match node.kind {
+                NodeKind::Original => Some(node.range.clone()),
+                NodeKind::Added => self.stream.file_range(&node.range),
+                NodeKind::Inline(_) | NodeKind::Repeat(_) => None,
+            };
+            match (copied, ops.last_mut()) {
+                (Some(range), Some(Op::Copy(last))) if last.end == range.start => {
+                    last.end = range.end;
+                }
+                (Some(range), _) => ops.push(Op::Copy(range)),
+                (None, Some(Op::Insert(parts))) => {
+                    parts.extend(node.chunks(self.original, &self.added));
+                }
+                (None, _) => ops.push(Op::Insert(
+                    node.chunks(self.original, &self.added).collect(),
+                )),
+            }
+        }
+
+        writer.write_all(MAGIC)?;
+        write_u64(&mut writer, original_len)?;
+        write_u64(&mut writer, ops.len())?;
+        for op in ops {
+            match op {
+                Op::Copy(range) => {
+                    writer.write_all(&[COPY])?;
+                    write_u64(&mut writer, range.start)?;
+                    write_u64(&mut writer, range.len())?;
+                }
+                Op::Insert(parts) => {
+                    writer.write_all(&[INSERT])?;
+                    write_u64(&mut writer, parts.iter().map(|part| part.len()).sum())?;
+                    for part in parts {
+                        writer.write_all(part.as_bytes())?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates a table holding the document saved with [`PieceTable::save_patch_to`], from the
+    /// `original` text the patch was saved against and the patch read from `reader`.
+    ///
+    /// Copied ranges refer to `original` without copying it, so only the inserted text is kept
+    /// in memory, and it counts as changed, e.g. for [`PieceTable::changed_ranges`].
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `reader`, and an error of kind [`io::ErrorKind::InvalidData`] if the
+    /// patch is malformed, was saved against an original of another length, or copies a range
+    /// which isn't in `original` or doesn't start and end on char boundaries.
+    pub fn apply_saved_patch<R: Read>(original: &'ptable str, mut reader: R) -> io::Result<Self> {
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a patch"));
+        }
+        let original_len = read_u64(&mut reader)?;
+        if original_len != original.len() {
+            return Err(invalid(format!(
+                "the patch was saved against an original of length {original_len}, not {}",
+                original.len()
+            )));
+        }
+
+        let mut table = PieceTable::new(original);
+        table.nodes.clear();
+        let ops = read_u64(&mut reader)?;
+        for _ in 0..ops {
+            let mut tag = [0];
+            reader.read_exact(&mut tag)?;
+            match tag[0] {
+                COPY => {
+                    let start = read_u64(&mut reader)?;
+                    let len = read_u64(&mut reader)?;
+                    let range = start..start.saturating_add(len);
+                    let Some(text) = original.get(range.clone()) else {
+                        return Err(invalid(format!(
+                            "invalid copy of {range:?} from an original of length {}",
+                            original.len()
+                        )));
+                    };
+                    if !text.is_empty() {
+                        table
+                            .nodes
+                            .push_back(Node::new(NodeKind::Original, range, text));
+                    }
+                }
+                INSERT => {
+                    let len = read_u64(&mut reader)?;
+                    let mut bytes = Vec::new();
+                    (&mut reader).take(len as u64).read_to_end(&mut bytes)?;
+                    if bytes.len() != len {
+                        return Err(io::ErrorKind::UnexpectedEof.into());
+                    }
+                    let text = String::from_utf8(bytes).map_err(invalid)?;
+                    if !text.is_empty() {
+                        let range = table.added.push_str(&text);
+                        table
+                            .nodes
+                            .push_back(Node::new(NodeKind::Added, range, &text));
+                    }
+                }
+                tag => return Err(invalid(format!("unknown operation {tag}"))),
+            }
+        }
+
+        table.len = table.nodes.iter().map(|node| node.range.len()).sum();
+        table.newlines = table.nodes.iter().map(|node| node.newlines).sum();
+        table.chars = table.nodes.iter().map(|node| node.chars).sum();
+        table.pieces_changed();
+        Ok(table)
+    }
+}
+
+fn write_u64<W: Write>(writer: &mut W, value: usize) -> io::Result<()> {
+    writer.write_all(&(value as u64).to_le_bytes())
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<usize> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    usize::try_from(u64::from_le_bytes(bytes)).map_err(invalid)
+}
+
+fn invalid<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(original: &str, piece_table: &PieceTable) -> Vec<u8> {
+        let mut patch = Vec::new();
+        piece_table.save_patch_to(&mut patch).unwrap();
+        let restored = PieceTable::apply_saved_patch(original, patch.as_slice()).unwrap();
+
+        assert_eq!(piece_table.to_string(), restored.to_string());
+        assert_eq!(Ok(()), restored.check_invariants());
+        assert_eq!(piece_table.changed_ranges(), restored.changed_ranges());
+        patch
+    }
+
+    #[test]
+    fn only_changes_are_written() {
+        let original = "fn main() {\n    println!(\"hi\");\n}\n";
+        let mut piece_table = PieceTable::new(original);
+        piece_table.insert_char(0, 'p');
+        piece_table.insert_char(1, 'u');
+        piece_table.insert("b ", 2);
+        piece_table.delete(18..22);
+
+        let patch = round_trip(original, &piece_table);
+        // The header, an insert of "pub " and two copies around the deletion
+        assert_eq!(24 + (9 + 4) + 2 * 17, patch.len());
+    }
+
+    #[test]
+    fn moved_and_streamed_text() {
+        let original = "one two three";
+        let mut piece_table = PieceTable::new(original);
+        let two = piece_table.slice(4..8).to_string();
+        piece_table.delete(4..8);
+        piece_table.insert(&two, 0);
+        round_trip(original, &piece_table);
+
+        let mut piece_table = PieceTable::new_streaming();
+        piece_table.feed_original_chunk("one ");
+        piece_table.insert("zero ", 0);
+        piece_table.feed_original_chunk("two");
+        piece_table.finish_loading();
+        round_trip("one two", &piece_table);
+    }
+
+    #[test]
+    fn rejects_invalid_patches() {
+        let mut piece_table = PieceTable::new("héllo");
+        piece_table.insert("!", 3);
+        let mut patch = Vec::new();
+        piece_table.save_patch_to(&mut patch).unwrap();
+
+        let err = |original, patch: &[u8]| {
+            PieceTable::apply_saved_patch(original, patch)
+                .map(|_| ())
+                .unwrap_err()
+                .kind()
+        };
+        assert_eq!(io::ErrorKind::InvalidData, err("hello", &patch));
+        assert_eq!(
+            io::ErrorKind::UnexpectedEof,
+            err("héllo", &patch[..patch.len() - 1])
+        );
+        // The first copy, of "hé", now ends in the middle of "é"
+        patch[33] = 2;
+        assert_eq!(io::ErrorKind::InvalidData, err("héllo", &patch));
+    }
+}
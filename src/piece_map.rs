@@ -0,0 +1,211 @@
+//! Printing the pieces of a table, for debugging.
+//!
+//! How a document is split into pieces decides how fast it is to read and edit, and a wrong split
+//! is the usual cause of wrong text, but the pieces are private. [`PieceTable::debug_pieces`]
+//! lays them out as a table which can be printed or compared in a test, and
+//! [`PieceTable::export_dot`] as a graph which can be drawn with Graphviz.
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use crate::{Node, NodeKind, PieceTable};
+
+/// How many chars of each piece are shown.
+const PREVIEW_CHARS: usize = 16;
+
+impl PieceTable<'_> {
+    /// Returns a table of the pieces of the document, one row per piece, with its index, kind,
+    /// the range of its buffer it refers to, the range of the document it covers, and the start
+    /// of its text. Columns are aligned, and the text is escaped, so each piece takes a single
+    /// line.
+    ///
+    /// Inline and repeat pieces, whose text isn't stored in a buffer, have no buffer range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello world");
+    /// pt.insert("big\n", 6);
+    /// assert_eq!(
+    ///     pt.debug_pieces(),
+    ///     "\
+    /// idx  kind      buffer  document  text
+    /// 0    original  0..6    0..6      \"hello \"
+    /// 1    added     0..4    6..10     \"big\\n\"
+    /// 2    original  6..11   10..15    \"world\"
+    /// "
+    /// );
+    /// ```
+    pub fn debug_pieces(&self) -> String {
+        let mut rows = vec![[
+            "idx".to_string(),
+            "kind".to_string(),
+            "buffer".to_string(),
+            "document".to_string(),
+            "text".to_string(),
+        ]];
+        rows.extend(self.piece_rows());
+
+        let mut widths = [0; 4];
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let mut out = String::new();
+        for row in &rows {
+            for (width, cell) in widths.iter().zip(row) {
+                write!(out, "{cell:<0$}  ", width).unwrap();
+            }
+            out.push_str(&row[4]);
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Writes the pieces of the document to `writer` as a Graphviz graph, in the DOT language.
+    ///
+    /// Each piece is a box labelled like a row of [`PieceTable::debug_pieces`], linked to the
+    /// next piece of the document, and to the buffer its text is stored in by a dashed edge.
+    /// Render it with e.g. `dot -Tsvg`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello world");
+    /// pt.insert("big ", 6);
+    ///
+    /// let mut out = Vec::new();
+    /// pt.export_dot(&mut out).unwrap();
+    /// let dot = String::from_utf8(out).unwrap();
+    /// assert!(dot.starts_with("digraph pieces {"));
+    /// assert!(dot.contains("piece0 -> piece1;"));
+    /// assert!(dot.contains("piece1 -> added [style=dashed];"));
+    /// ```
+    pub fn export_dot<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "digraph pieces {{")?;
+        writeln!(writer, "    rankdir=LR;")?;
+        writeln!(writer, "    node [shape=box, fontname=monospace];")?;
+        writeln!(writer, "    original [shape=cylinder];")?;
+        writeln!(writer, "    added [shape=cylinder];")?;
+        for [idx, kind, buffer, document, text] in self.piece_rows() {
+            let label = [
+                format!("{idx}: {kind}"),
+                format!("buffer {buffer}"),
+                format!("document {document}"),
+                text,
+            ]
+            .map(|line| dot_escape(&line))
+            .join("\\n");
+            writeln!(writer, "    piece{idx} [label=\"{label}\"];")?;
+            if let "original" | "added" = kind.as_str() {
+                writeln!(writer, "    piece{idx} -> {kind} [style=dashed];")?;
+            }
+        }
+        for idx in 1..self.nodes.len() {
+            writeln!(writer, "    piece{} -> piece{idx};", idx - 1)?;
+        }
+        writeln!(writer, "}}")
+    }
+
+    /// Returns the index, kind, buffer range, document range and preview of every piece, see
+    /// [`PieceTable::debug_pieces`].
+    fn piece_rows(&self) -> Vec<[String; 5]> {
+        let mut start = 0;
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(idx, node)| {
+                let end = start + node.range.len();
+                let (kind, buffer) = match node.kind {
+                    NodeKind::Original => ("original", format!("{:?}", node.range)),
+                    NodeKind::Added => ("added", format!("{:?}", node.range)),
+                    NodeKind::Inline(_) => ("inline", "-".to_string()),
+                    NodeKind::Repeat(_) => ("repeat", "-".to_string()),
+                };
+                let row = [
+                    idx.to_string(),
+                    kind.to_string(),
+                    buffer,
+                    format!("{start}..{end}"),
+                    self.preview(node),
+                ];
+                start = end;
+                row
+            })
+            .collect()
+    }
+
+    /// Returns the first chars of the text of `node`, escaped and quoted, with an ellipsis if
+    /// the text is longer.
+    fn preview(&self, node: &Node) -> String {
+        let mut chars = node.chunks(self.original, &self.added).flat_map(str::chars);
+        let preview: String = chars.by_ref().take(PREVIEW_CHARS).collect();
+        let ellipsis = if chars.next().is_some() { "…" } else { "" };
+        format!("{preview:?}{ellipsis}")
+    }
+}
+
+/// Escapes `text` for a quoted DOT string.
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_kind_of_piece() {
+        let mut piece_table = PieceTable::new("a long original text");
+        piece_table.insert_char(1, 'é');
+        piece_table.insert_repeated(0, '-', 300);
+        piece_table.delete(310..315);
+
+        assert_eq!(
+            "\
+idx  kind      buffer  document  text
+0    repeat    -       0..300    \"----------------\"…
+1    original  0..1    300..301  \"a\"
+2    inline    -       301..303  \"é\"
+3    original  1..8    303..310  \" long o\"
+4    original  13..20  310..317  \"al text\"
+",
+            piece_table.debug_pieces()
+        );
+    }
+
+    #[test]
+    fn dot_labels_are_escaped() {
+        let mut piece_table = PieceTable::new("say \"hi\"");
+        piece_table.insert_char(8, '\n');
+
+        let mut out = Vec::new();
+        piece_table.export_dot(&mut out).unwrap();
+        assert_eq!(
+            r#"digraph pieces {
+    rankdir=LR;
+    node [shape=box, fontname=monospace];
+    original [shape=cylinder];
+    added [shape=cylinder];
+    piece0 [label="0: original\nbuffer 0..8\ndocument 0..8\n\"say \\\"hi\\\"\""];
+    piece0 -> original [style=dashed];
+    piece1 [label="1: inline\nbuffer -\ndocument 8..9\n\"\\n\""];
+    piece0 -> piece1;
+}
+"#,
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn empty_table_has_only_a_header() {
+        let piece_table = PieceTable::new("");
+        assert_eq!(
+            "idx  kind  buffer  document  text\n",
+            piece_table.debug_pieces()
+        );
+    }
+}
@@ -0,0 +1,298 @@
+//! Addressing text by line and column, with columns counted in different units.
+
+#[cfg(feature = "unicode-width")]
+use std::ops::Range;
+
+#[cfg(feature = "unicode-width")]
+use unicode_width::UnicodeWidthChar;
+
+use crate::PieceTable;
+
+/// A position in a document, as a (0-based) line and a (0-based) column within that line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Point {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// What a column counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnUnit {
+    /// UTF-8 bytes.
+    Bytes,
+    /// Unicode scalar values, i.e. `char`s.
+    Chars,
+    /// UTF-16 code units, as used by the Language Server Protocol.
+    Utf16,
+    /// Terminal display cells. A tab advances to the next multiple of `tab_width`, wide chars
+    /// (e.g. most CJK characters) take two cells, and zero-width and control chars take none.
+    ///
+    /// Requires the `unicode-width` feature, which is enabled by default.
+    #[cfg(feature = "unicode-width")]
+    Cells { tab_width: usize },
+}
+
+impl Point {
+    pub fn new(line: usize, column: usize) -> Self {
+        Point { line, column }
+    }
+}
+
+impl ColumnUnit {
+    /// Returns the column right after `c`, if `c` starts at `column`.
+    pub(crate) fn advance(self, column: usize, c: char) -> usize {
+        match self {
+            ColumnUnit::Bytes => column + c.len_utf8(),
+            ColumnUnit::Chars => column + 1,
+            ColumnUnit::Utf16 => column + c.len_utf16(),
+            #[cfg(feature = "unicode-width")]
+            ColumnUnit::Cells { tab_width } if c == '\t' => match tab_width {
+                0 => column,
+                _ => (column / tab_width + 1) * tab_width,
+            },
+            #[cfg(feature = "unicode-width")]
+            ColumnUnit::Cells { .. } => column + c.width().unwrap_or(0),
+        }
+    }
+}
+
+impl PieceTable<'_> {
+    /// Converts a point to a byte offset.
+    ///
+    /// A column past the end of its line refers to the end of the line, and a line past the end
+    /// of the document refers to the end of the document. A column which falls inside a char
+    /// (e.g. inside a tab, or in the middle of a multi-byte char when counting bytes) refers to
+    /// the start of that char.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// # use piece_table::point::{ColumnUnit, Point};
+    /// let pt = PieceTable::new("a\tb\n日本");
+    /// assert_eq!(pt.point_to_offset(Point::new(0, 2), ColumnUnit::Chars), 2);
+    /// assert_eq!(pt.point_to_offset(Point::new(0, 9), ColumnUnit::Chars), 3);
+    /// assert_eq!(pt.point_to_offset(Point::new(1, 1), ColumnUnit::Chars), 7);
+    /// assert_eq!(pt.point_to_offset(Point::new(1, 3), ColumnUnit::Bytes), 7);
+    /// ```
+    pub fn point_to_offset(&self, point: Point, unit: ColumnUnit) -> usize {
+        match self.line_start(point.line) {
+            Some(line_start) => self.seek_column(line_start, point.column, unit).0,
+            None => self.len,
+        }
+    }
+
+    /// Returns the offset of `column` in the line starting at `line_start`, along with the
+    /// column actually found there, which is smaller if the line is too short or the column
+    /// falls inside a char.
+    pub(crate) fn seek_column(
+        &self,
+        line_start: usize,
+        column: usize,
+        unit: ColumnUnit,
+    ) -> (usize, usize) {
+        let mut offset = line_start;
+        let mut current = 0;
+        for chunk in self.chunks_from(line_start) {
+            for c in chunk.chars() {
+                let next = unit.advance(current, c);
+                if c == '\n' || next > column {
+                    return (offset, current);
+                }
+                current = next;
+                offset += c.len_utf8();
+            }
+        }
+        (offset, current)
+    }
+
+    /// Converts a byte offset to a point.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// # use piece_table::point::{ColumnUnit, Point};
+    /// let pt = PieceTable::new("a\tb\n日本");
+    /// assert_eq!(pt.offset_to_point(2, ColumnUnit::Chars), Point::new(0, 2));
+    /// assert_eq!(pt.offset_to_point(10, ColumnUnit::Chars), Point::new(1, 2));
+    /// assert_eq!(pt.offset_to_point(10, ColumnUnit::Bytes), Point::new(1, 6));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is out of bounds or not on a char boundary.
+    pub fn offset_to_point(&self, offset: usize, unit: ColumnUnit) -> Point {
+        if let Err(err) = self.check_offset(offset) {
+            panic!("invalid offset: {err}");
+        }
+
+        let line = self.line_of(offset);
+        let line_start = self.line_start(line).unwrap();
+        let mut remaining = offset - line_start;
+        let mut column = 0;
+        'chunks: for chunk in self.chunks_from(line_start) {
+            for c in chunk.chars() {
+                if remaining == 0 {
+                    break 'chunks;
+                }
+                column = unit.advance(column, c);
+                remaining -= c.len_utf8();
+            }
+        }
+        Point::new(line, column)
+    }
+
+    /// Returns how many terminal cells the text in `range` takes, see [`ColumnUnit::Cells`].
+    ///
+    /// Tab stops are counted from the start of the line `range` starts on, so a tab takes the
+    /// cells it would take on screen. If `range` spans several lines, the widths of its parts of
+    /// each line are added up, with newlines taking no cells.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let pt = PieceTable::new("ab\t日本\n");
+    /// assert_eq!(pt.display_width(0..9, 4), 8);
+    /// // The tab only reaches from column 2 to the tab stop at 4
+    /// assert_eq!(pt.display_width(2..3, 4), 2);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds, or doesn't start and end on char boundaries.
+    #[cfg(feature = "unicode-width")]
+    pub fn display_width(&self, range: Range<usize>, tab_width: usize) -> usize {
+        if let Err(err) = self.check_range(&range) {
+            panic!("invalid display_width: {err}");
+        }
+        let unit = ColumnUnit::Cells { tab_width };
+        let mut column = self.offset_to_point(range.start, unit).column;
+        let mut width = 0;
+        for (offset, c) in self.char_indices_from(range.start) {
+            if offset >= range.end {
+                break;
+            }
+            if c == '\n' {
+                column = 0;
+                continue;
+            }
+            let next = unit.advance(column, c);
+            width += next - column;
+            column = next;
+        }
+        width
+    }
+
+    /// Returns the offset shown at display column `column` of `line` in a terminal, with tabs
+    /// `tab_width` cells wide, see [`PieceTable::point_to_offset`].
+    ///
+    /// A column inside a wide char or a tab refers to the start of that char, and a column past
+    /// the end of the line to the end of the line.
+    #[cfg(feature = "unicode-width")]
+    pub fn offset_at_display_column(&self, line: usize, column: usize, tab_width: usize) -> usize {
+        self.point_to_offset(Point::new(line, column), ColumnUnit::Cells { tab_width })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "unicode-width")]
+    const CELLS: ColumnUnit = ColumnUnit::Cells { tab_width: 4 };
+
+    #[test]
+    #[cfg(feature = "unicode-width")]
+    fn tabs_advance_to_tab_stops() {
+        let piece_table = PieceTable::new("ab\tc\t\td");
+
+        assert_eq!(Point::new(0, 4), piece_table.offset_to_point(3, CELLS));
+        assert_eq!(Point::new(0, 12), piece_table.offset_to_point(6, CELLS));
+        assert_eq!(2, piece_table.point_to_offset(Point::new(0, 3), CELLS));
+        assert_eq!(4, piece_table.point_to_offset(Point::new(0, 7), CELLS));
+        assert_eq!(6, piece_table.point_to_offset(Point::new(0, 12), CELLS));
+    }
+
+    #[test]
+    #[cfg(feature = "unicode-width")]
+    fn wide_and_zero_width_chars() {
+        let piece_table = PieceTable::new("日e\u{301}x");
+
+        assert_eq!(Point::new(0, 2), piece_table.offset_to_point(3, CELLS));
+        assert_eq!(Point::new(0, 3), piece_table.offset_to_point(6, CELLS));
+        assert_eq!(0, piece_table.point_to_offset(Point::new(0, 1), CELLS));
+        assert_eq!(6, piece_table.point_to_offset(Point::new(0, 3), CELLS));
+    }
+
+    #[test]
+    fn clamps_past_line_and_document_end() {
+        let mut piece_table = PieceTable::new("one\ntwo");
+        piece_table.insert("\nthree", 7);
+
+        assert_eq!(
+            3,
+            piece_table.point_to_offset(Point::new(0, 9), ColumnUnit::Chars)
+        );
+        assert_eq!(
+            13,
+            piece_table.point_to_offset(Point::new(2, 9), ColumnUnit::Chars)
+        );
+        assert_eq!(
+            13,
+            piece_table.point_to_offset(Point::new(7, 0), ColumnUnit::Chars)
+        );
+    }
+
+    #[test]
+    fn round_trips_in_every_unit() {
+        let mut piece_table = PieceTable::new("h\téllo\nwörld\n");
+        piece_table.insert("\t日本\n", 4);
+
+        let text = piece_table.to_string();
+        #[allow(unused_mut)]
+        let mut units = vec![ColumnUnit::Bytes, ColumnUnit::Chars, ColumnUnit::Utf16];
+        #[cfg(feature = "unicode-width")]
+        units.push(CELLS);
+        for unit in units {
+            for (offset, _) in text.char_indices().chain([(text.len(), ' ')]) {
+                let point = piece_table.offset_to_point(offset, unit);
+                assert_eq!(offset, piece_table.point_to_offset(point, unit), "{unit:?}");
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "unicode-width")]
+    fn display_width_across_pieces_and_lines() {
+        let mut piece_table = PieceTable::new("x\t日\n\ty");
+        piece_table.insert("e\u{301}", 1);
+        assert_eq!("xe\u{301}\t日\n\ty", piece_table.to_string());
+
+        assert_eq!(6, piece_table.display_width(0..9, 4));
+        // The tab on the second line starts at column 0 again
+        assert_eq!(5, piece_table.display_width(8..11, 4));
+        assert_eq!(11, piece_table.display_width(0..11, 4));
+        assert_eq!(0, piece_table.display_width(2..4, 4));
+    }
+
+    #[test]
+    #[cfg(feature = "unicode-width")]
+    #[should_panic(expected = "invalid display_width: offset 2 is not on a char boundary")]
+    fn display_width_inside_char() {
+        let piece_table = PieceTable::new("日本");
+        piece_table.display_width(0..2, 4);
+    }
+
+    #[test]
+    #[cfg(feature = "unicode-width")]
+    fn offset_at_display_column() {
+        let piece_table = PieceTable::new("日本\n\tx");
+
+        assert_eq!(3, piece_table.offset_at_display_column(0, 3, 8));
+        assert_eq!(7, piece_table.offset_at_display_column(1, 7, 8));
+        assert_eq!(8, piece_table.offset_at_display_column(1, 8, 8));
+        assert_eq!(9, piece_table.offset_at_display_column(1, 20, 8));
+    }
+}
@@ -0,0 +1,230 @@
+//! Positions in a document in whichever unit the caller works in.
+//!
+//! Editors, language servers and scripting bindings each count positions differently. Instead
+//! of a variant of every method for every unit, the methods here take any [`TextPosition`],
+//! which converts itself to and from a byte offset.
+
+use std::ops::Range;
+
+use crate::point::{ColumnUnit, Point};
+use crate::{PTableSlice, PieceTable};
+
+/// A position in a document, which can be converted to and from a byte offset.
+pub trait TextPosition: Sized {
+    /// Returns the byte offset of this position in `table`.
+    ///
+    /// # Panics
+    ///
+    /// May panic if the position is out of bounds, see the documentation of each implementation.
+    fn to_byte(&self, table: &PieceTable) -> usize;
+
+    /// Returns the position of byte `offset` in `table`, which must be on a char boundary.
+    fn from_byte(table: &PieceTable, offset: usize) -> Self;
+}
+
+/// A byte offset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteOffset(pub usize);
+
+/// The index of a char, see [`PieceTable::char_to_byte`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CharOffset(pub usize);
+
+/// An offset in UTF-16 code units, as used by JavaScript and Java strings.
+///
+/// An offset between the two halves of a surrogate pair refers to the start of its char.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Utf16Offset(pub usize);
+
+/// A (0-based) line and a (0-based) column within that line, counted in chars.
+///
+/// A column past the end of its line refers to the end of the line, and a line past the end of
+/// the document refers to the end of the document, see [`PieceTable::point_to_offset`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineCol {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl TextPosition for ByteOffset {
+    fn to_byte(&self, _: &PieceTable) -> usize {
+        self.0
+    }
+
+    fn from_byte(_: &PieceTable, offset: usize) -> Self {
+        ByteOffset(offset)
+    }
+}
+
+impl TextPosition for CharOffset {
+    /// Panics if the index is out of bounds.
+    fn to_byte(&self, table: &PieceTable) -> usize {
+        table.char_to_byte(self.0)
+    }
+
+    fn from_byte(table: &PieceTable, offset: usize) -> Self {
+        CharOffset(table.byte_to_char(offset))
+    }
+}
+
+impl TextPosition for Utf16Offset {
+    /// Panics if the offset is out of bounds.
+    fn to_byte(&self, table: &PieceTable) -> usize {
+        let mut units = 0;
+        for (offset, c) in table.char_indices_from(0) {
+            units += c.len_utf16();
+            if units > self.0 {
+                return offset;
+            }
+        }
+        assert!(
+            units == self.0,
+            "invalid UTF-16 offset: {} is out of bounds of {units}",
+            self.0
+        );
+        table.len()
+    }
+
+    fn from_byte(table: &PieceTable, offset: usize) -> Self {
+        let units = table
+            .char_indices_from(0)
+            .take_while(|&(start, _)| start < offset)
+            .map(|(_, c)| c.len_utf16())
+            .sum();
+        Utf16Offset(units)
+    }
+}
+
+impl TextPosition for LineCol {
+    fn to_byte(&self, table: &PieceTable) -> usize {
+        table.point_to_offset(Point::new(self.line, self.column), ColumnUnit::Chars)
+    }
+
+    fn from_byte(table: &PieceTable, offset: usize) -> Self {
+        let Point { line, column } = table.offset_to_point(offset, ColumnUnit::Chars);
+        LineCol { line, column }
+    }
+}
+
+impl<'ptable> PieceTable<'ptable> {
+    /// Converts `position` to another kind of position.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// use piece_table::position::{CharOffset, LineCol, Utf16Offset};
+    ///
+    /// let pt = PieceTable::new("🦀\nhé");
+    /// let end: LineCol = pt.convert_position(Utf16Offset(5));
+    /// assert_eq!(end, LineCol { line: 1, column: 2 });
+    /// assert_eq!(pt.convert_position::<_, CharOffset>(end), CharOffset(4));
+    /// ```
+    pub fn convert_position<P: TextPosition, Q: TextPosition>(&self, position: P) -> Q {
+        Q::from_byte(self, position.to_byte(self))
+    }
+
+    /// Inserts `data` at `position`, see [`PieceTable::insert`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// use piece_table::position::{LineCol, Utf16Offset};
+    ///
+    /// let mut pt = PieceTable::new("🦀\nhé");
+    /// pt.insert_at(Utf16Offset(2), "!");
+    /// pt.insert_at(LineCol { line: 1, column: 1 }, "-");
+    /// assert_eq!(pt.to_string(), "🦀!\nh-é");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`PieceTable::insert`], and if `position` can't be converted.
+    pub fn insert_at<P: TextPosition>(&mut self, position: P, data: &str) {
+        let offset = position.to_byte(self);
+        self.insert(data, offset);
+    }
+
+    /// Deletes the text between two positions, see [`PieceTable::delete`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// use piece_table::position::CharOffset;
+    ///
+    /// let mut pt = PieceTable::new("héllo");
+    /// pt.delete_at(CharOffset(1)..CharOffset(3));
+    /// assert_eq!(pt.to_string(), "hlo");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`PieceTable::delete`], and if the positions can't be
+    /// converted.
+    pub fn delete_at<P: TextPosition>(&mut self, range: Range<P>) {
+        let range = self.byte_range(range);
+        self.delete(range);
+    }
+
+    /// Returns a slice of the text between two positions, see [`PieceTable::slice`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the positions can't be converted.
+    pub fn slice_at<P: TextPosition>(&self, range: Range<P>) -> PTableSlice<'ptable> {
+        self.slice(self.byte_range(range))
+    }
+
+    fn byte_range<P: TextPosition>(&self, range: Range<P>) -> Range<usize> {
+        range.start.to_byte(self)..range.end.to_byte(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_every_position() {
+        let mut piece_table = PieceTable::new("a🦀\nβ\n");
+        piece_table.insert("c\u{301}日", 5);
+
+        let text = piece_table.to_string();
+        for offset in (0..=text.len()).filter(|&offset| text.is_char_boundary(offset)) {
+            let chars: CharOffset = piece_table.convert_position(ByteOffset(offset));
+            let utf16: Utf16Offset = piece_table.convert_position(chars);
+            let line_col: LineCol = piece_table.convert_position(utf16);
+            let back: ByteOffset = piece_table.convert_position(line_col);
+
+            assert_eq!(chars.0, text[..offset].chars().count());
+            assert_eq!(utf16.0, text[..offset].encode_utf16().count());
+            assert_eq!(ByteOffset(offset), back);
+        }
+    }
+
+    #[test]
+    fn utf16_inside_surrogate_pair() {
+        let piece_table = PieceTable::new("🦀x");
+
+        assert_eq!(0, Utf16Offset(1).to_byte(&piece_table));
+        assert_eq!(4, Utf16Offset(2).to_byte(&piece_table));
+        assert_eq!(5, Utf16Offset(3).to_byte(&piece_table));
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn utf16_out_of_bounds() {
+        Utf16Offset(4).to_byte(&PieceTable::new("🦀x"));
+    }
+
+    #[test]
+    fn slice_between_line_cols() {
+        let piece_table = PieceTable::new("one\ntwo\nthree");
+        let start = LineCol { line: 0, column: 2 };
+        let end = LineCol { line: 2, column: 1 };
+
+        assert_eq!("e\ntwo\nt", piece_table.slice_at(start..end).to_string());
+    }
+}
@@ -0,0 +1,117 @@
+//! Cached prefix sums of piece lengths, for finding pieces by offset with a binary search.
+//!
+//! The sums are built by the second lookup after an edit and dropped by the next edit, so a run
+//! of queries between two edits pays for one pass over the pieces and then searches in
+//! O(log n), while edits never build them. Waiting for the second lookup means alternating
+//! edits and single lookups, as when typing, don't build sums which are only used once.
+
+use std::collections::VecDeque;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::Node;
+
+/// Below this many pieces a linear scan is as fast as a binary search, so no sums are built.
+const MIN_PIECES: usize = 32;
+
+/// The number of lookups since the last edit which scan linearly before the sums are built.
+const LINEAR_LOOKUPS: usize = 1;
+
+#[derive(Debug, Default)]
+pub(crate) struct PrefixSums {
+    /// The offset where each piece ends
+    ends: OnceLock<Vec<usize>>,
+    /// The number of lookups since the sums were last dropped
+    lookups: AtomicUsize,
+}
+
+impl Clone for PrefixSums {
+    fn clone(&self) -> Self {
+        PrefixSums {
+            ends: self.ends.clone(),
+            lookups: AtomicUsize::new(self.lookups.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl PrefixSums {
+    /// Drops the sums, which must be done whenever a piece is added, removed or resized.
+    pub(crate) fn invalidate(&mut self) {
+        self.ends.take();
+        *self.lookups.get_mut() = 0;
+    }
+
+    /// Returns the sums if they are up to date.
+    pub(crate) fn get(&self) -> Option<&[usize]> {
+        self.ends.get().map(Vec::as_slice)
+    }
+
+    /// Returns the sums, building them from `nodes` if they aren't up to date, or `None` if
+    /// there are too few pieces, or too few lookups since the last edit, to bother.
+    pub(crate) fn get_or_build(&self, nodes: &VecDeque<Node>) -> Option<&[usize]> {
+        if let Some(ends) = self.get() {
+            return Some(ends);
+        }
+        if nodes.len() < MIN_PIECES || self.lookups.fetch_add(1, Ordering::Relaxed) < LINEAR_LOOKUPS
+        {
+            return None;
+        }
+        let ends = self.ends.get_or_init(|| {
+            nodes
+                .iter()
+                .scan(0, |end, node| {
+                    *end += node.range.len();
+                    Some(*end)
+                })
+                .collect()
+        });
+        Some(ends)
+    }
+}
+
+/// Returns the index and start of the piece containing the byte at `offset`, given the offsets
+/// where the pieces end.
+pub(crate) fn find(ends: &[usize], offset: usize) -> Option<(usize, usize)> {
+    let idx = ends.partition_point(|&end| end <= offset);
+    if idx == ends.len() {
+        return None;
+    }
+    let start = idx.checked_sub(1).map_or(0, |prev| ends[prev]);
+    Some((idx, start))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PieceTable;
+
+    use super::*;
+
+    #[test]
+    fn find_skips_empty_pieces() {
+        let ends = [2, 2, 5];
+
+        assert_eq!(Some((0, 0)), find(&ends, 0));
+        assert_eq!(Some((2, 2)), find(&ends, 2));
+        assert_eq!(Some((2, 2)), find(&ends, 4));
+        assert_eq!(None, find(&ends, 5));
+    }
+
+    #[test]
+    fn lookups_match_after_edits() {
+        let mut piece_table = PieceTable::new("0123456789");
+        for i in 0..100 {
+            piece_table.insert("ab", (i * 7) % piece_table.len());
+            let expected = piece_table.to_string();
+            piece_table.byte(0);
+            let bytes: Vec<_> = (0..piece_table.len())
+                .map(|at| piece_table.byte(at).unwrap())
+                .collect();
+
+            assert_eq!(expected.as_bytes(), bytes);
+            assert_eq!(
+                piece_table.nodes.len() >= MIN_PIECES,
+                piece_table.prefix_sums.get().is_some()
+            );
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Python bindings, for driving a `PieceTable` from scripts and tooling.
+//!
+//! Enabled by the `python` feature. All offsets are in chars, like the indices of Python
+//! strings, and are converted to byte offsets by walking the text. To build an extension module
+//! (e.g. with maturin), also enable pyo3's `extension-module` feature.
+
+use pyo3::exceptions::{PyIndexError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PySlice, PySliceMethods};
+
+use crate::{EditError, PieceTable};
+
+/// A `PieceTable` which owns its text, exported to Python as `PieceTable`.
+#[pyclass(name = "PieceTable")]
+pub struct PyPieceTable {
+    table: PieceTable<'static>,
+}
+
+impl From<EditError> for PyErr {
+    fn from(err: EditError) -> PyErr {
+        match err {
+            EditError::OutOfBounds { .. } => PyIndexError::new_err(err.to_string()),
+            _ => PyValueError::new_err(err.to_string()),
+        }
+    }
+}
+
+#[pymethods]
+impl PyPieceTable {
+    /// Creates a table containing `text`.
+    #[new]
+    #[pyo3(signature = (text = String::new()))]
+    pub fn new(text: String) -> PyPieceTable {
+        PyPieceTable {
+            table: PieceTable::from(text),
+        }
+    }
+
+    /// Inserts `text` at `offset`.
+    pub fn insert(&mut self, offset: usize, text: &str) -> PyResult<()> {
+        let offset = self.to_byte_offset(offset)?;
+        self.table.try_insert(text, offset)?;
+        Ok(())
+    }
+
+    /// Deletes the text between `start` and `end`.
+    pub fn delete(&mut self, start: usize, end: usize) -> PyResult<()> {
+        let range = self.to_byte_offset(start)?..self.to_byte_offset(end)?;
+        self.table.try_delete(range)?;
+        Ok(())
+    }
+
+    /// Returns the number of lines, see [`PieceTable::len_lines`].
+    pub fn line_count(&self) -> usize {
+        self.table.len_lines()
+    }
+
+    /// The length of the text in chars.
+    pub fn __len__(&self) -> usize {
+        self.table
+            .chunks_from(0)
+            .map(|chunk| chunk.chars().count())
+            .sum()
+    }
+
+    pub fn __str__(&self) -> String {
+        self.table.as_string()
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!("PieceTable({:?})", self.table.as_string())
+    }
+
+    /// Returns the char at an index, or the text in a slice, with the same semantics as indexing
+    /// a Python string (including negative indices and steps).
+    pub fn __getitem__(&self, key: &Bound<'_, PyAny>) -> PyResult<String> {
+        let len = self.__len__();
+        if let Ok(slice) = key.downcast::<PySlice>() {
+            let indices = slice.indices(len as isize)?;
+            if indices.slicelength == 0 {
+                return Ok(String::new());
+            } else if indices.step == 1 {
+                let start = indices.start as usize;
+                let range = self.to_byte_offset(start)?
+                    ..self.to_byte_offset(start + indices.slicelength)?;
+                return Ok(String::from(&self.table.slice(range)));
+            }
+
+            let chars: Vec<char> = self.table.chunks_from(0).flat_map(str::chars).collect();
+            return Ok((0..indices.slicelength)
+                .map(|i| chars[(indices.start + i as isize * indices.step) as usize])
+                .collect());
+        }
+
+        let index: isize = key.extract()?;
+        let index = if index < 0 {
+            index + len as isize
+        } else {
+            index
+        };
+        if index < 0 || index as usize >= len {
+            return Err(PyIndexError::new_err("index out of range"));
+        }
+        let offset = self.to_byte_offset(index as usize)?;
+        Ok(self
+            .table
+            .char(offset)
+            .map(String::from)
+            .unwrap_or_default())
+    }
+}
+
+impl PyPieceTable {
+    /// Converts an offset in chars to a byte offset.
+    fn to_byte_offset(&self, offset: usize) -> Result<usize, EditError> {
+        let len = self.table.len_chars();
+        if offset > len {
+            return Err(EditError::OutOfBounds { offset, len });
+        }
+        Ok(self.table.char_to_byte(offset))
+    }
+}
+
+/// The `piece_table` Python module.
+#[pymodule]
+fn piece_table(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyPieceTable>()?;
+    Ok(())
+}
@@ -0,0 +1,428 @@
+//! Tracking many byte ranges of a document across edits, e.g. for multi-cursor selections.
+
+use std::ops::Range;
+
+use crate::PieceTable;
+
+/// Which side of an insertion an endpoint ends up on when text is inserted exactly at it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bias {
+    /// The endpoint stays before the inserted text.
+    Left,
+    /// The endpoint moves after the inserted text.
+    Right,
+}
+
+/// Identifies a range in a [`RangeSet`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RangeId(usize);
+
+#[derive(Debug, Clone)]
+struct Entry {
+    id: RangeId,
+    range: Range<usize>,
+    start_bias: Bias,
+    end_bias: Bias,
+}
+
+/// A set of (possibly overlapping) byte ranges of a document, adjusted on every edit.
+///
+/// After each edit to the document, [`RangeSet::on_insert`] or [`RangeSet::on_delete`] must be
+/// called. Each endpoint has a [`Bias`] which decides where it goes when text is inserted
+/// exactly at it, so e.g. a selection can either grow or not when typing at its end.
+///
+/// The ranges are kept sorted by their start, along with an implicit interval tree, which
+/// answers "which ranges contain this offset" by only visiting the subtrees which can contain
+/// a match.
+///
+/// # Examples
+///
+/// ```
+/// # use piece_table::range_set::{Bias, RangeSet};
+/// let mut selections = RangeSet::new();
+/// let first = selections.add(0..5, Bias::Left, Bias::Right);
+/// let second = selections.add(3..8, Bias::Left, Bias::Left);
+///
+/// // Typing at offset 5 grows the first selection, but doesn't move the second one
+/// selections.on_insert(5, 2);
+///
+/// assert_eq!(selections.get(first), Some(0..7));
+/// assert_eq!(selections.get(second), Some(3..10));
+/// assert_eq!(selections.containing(4), vec![first, second]);
+/// assert_eq!(selections.containing(8), vec![second]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RangeSet {
+    /// Sorted by start offset
+    entries: Vec<Entry>,
+    /// A segment tree over `entries`, with the maximum end of each subtree. Node 1 is the root,
+    /// and the children of node `i` are `2 * i` and `2 * i + 1`.
+    max_end: Vec<usize>,
+    next_id: usize,
+}
+
+impl RangeSet {
+    /// Creates an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of ranges.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Checks if there are no ranges.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds a range with the given bias for each endpoint, returning its id.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range.start > range.end`.
+    pub fn add(&mut self, range: Range<usize>, start_bias: Bias, end_bias: Bias) -> RangeId {
+        assert!(
+            range.start <= range.end,
+            "invalid range: range start {} is greater than range end {}",
+            range.start,
+            range.end
+        );
+
+        let id = RangeId(self.next_id);
+        self.next_id += 1;
+        let idx = self
+            .entries
+            .partition_point(|e| e.range.start <= range.start);
+        self.entries.insert(
+            idx,
+            Entry {
+                id,
+                range,
+                start_bias,
+                end_bias,
+            },
+        );
+        self.rebuild();
+        id
+    }
+
+    /// Removes a range, returning where it was, or `None` if there is no such range.
+    pub fn remove(&mut self, id: RangeId) -> Option<Range<usize>> {
+        let idx = self.entries.iter().position(|e| e.id == id)?;
+        let entry = self.entries.remove(idx);
+        self.rebuild();
+        Some(entry.range)
+    }
+
+    /// Returns where a range currently is, or `None` if there is no such range.
+    pub fn get(&self, id: RangeId) -> Option<Range<usize>> {
+        self.entries
+            .iter()
+            .find(|e| e.id == id)
+            .map(|e| e.range.clone())
+    }
+
+    /// Returns an iterator over all ranges, ordered by their start.
+    pub fn iter(&self) -> impl Iterator<Item = (RangeId, Range<usize>)> {
+        self.entries.iter().map(|e| (e.id, e.range.clone()))
+    }
+
+    /// Returns the ranges containing `offset`, ordered by their start.
+    ///
+    /// A range contains the offsets in `start..end`, and an empty range contains just its
+    /// start, like a cursor.
+    pub fn containing(&self, offset: usize) -> Vec<RangeId> {
+        let mut found = Vec::new();
+        let candidates = self.entries.partition_point(|e| e.range.start <= offset);
+        if candidates > 0 {
+            self.stab(1, 0..self.leaves(), candidates, offset, &mut found);
+        }
+        found
+    }
+
+    /// Updates the ranges after `len` bytes were inserted at `offset`.
+    ///
+    /// If an empty range's start moves after the inserted text but its end doesn't, the range
+    /// stays empty and ends up after the inserted text.
+    pub fn on_insert(&mut self, offset: usize, len: usize) {
+        if len == 0 {
+            return;
+        }
+
+        let map = |pos: usize, bias: Bias| {
+            if pos > offset || (pos == offset && bias == Bias::Right) {
+                pos + len
+            } else {
+                pos
+            }
+        };
+        for entry in &mut self.entries {
+            let start = map(entry.range.start, entry.start_bias);
+            let end = map(entry.range.end, entry.end_bias).max(start);
+            entry.range = start..end;
+        }
+        self.entries.sort_by_key(|e| e.range.start);
+        self.rebuild();
+    }
+
+    /// Updates the ranges after `range` was deleted.
+    ///
+    /// Ranges inside the deleted text become empty ranges at its start.
+    pub fn on_delete(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+
+        let map = |pos: usize| {
+            if pos <= range.start {
+                pos
+            } else if pos >= range.end {
+                pos - range.len()
+            } else {
+                range.start
+            }
+        };
+        for entry in &mut self.entries {
+            entry.range = map(entry.range.start)..map(entry.range.end);
+        }
+        self.rebuild();
+    }
+
+    /// The number of leaves in the segment tree
+    fn leaves(&self) -> usize {
+        self.entries.len().next_power_of_two()
+    }
+
+    /// Recomputes the segment tree from `entries`.
+    fn rebuild(&mut self) {
+        let leaves = self.leaves();
+        self.max_end.clear();
+        self.max_end.resize(2 * leaves, 0);
+        for (i, entry) in self.entries.iter().enumerate() {
+            self.max_end[leaves + i] = entry.range.end;
+        }
+        for i in (1..leaves).rev() {
+            self.max_end[i] = self.max_end[2 * i].max(self.max_end[2 * i + 1]);
+        }
+    }
+
+    /// Collects the ranges containing `offset` among the first `candidates` entries, in the
+    /// subtree rooted at `node`, which covers the entries in `span`.
+    fn stab(
+        &self,
+        node: usize,
+        span: Range<usize>,
+        candidates: usize,
+        offset: usize,
+        found: &mut Vec<RangeId>,
+    ) {
+        // Every candidate starts at or before `offset`, so an end before it rules out the
+        // whole subtree
+        if span.start >= candidates || self.max_end[node] < offset {
+            return;
+        }
+
+        if span.len() == 1 {
+            let range = &self.entries[span.start].range;
+            if range.end > offset || range.start == offset {
+                found.push(self.entries[span.start].id);
+            }
+            return;
+        }
+
+        let mid = span.start + span.len() / 2;
+        self.stab(2 * node, span.start..mid, candidates, offset, found);
+        self.stab(2 * node + 1, mid..span.end, candidates, offset, found);
+    }
+}
+
+/// How a cursor was moved by [`PieceTable::replace_range_tracked`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorMove {
+    /// The cursor was before the edit, and didn't move.
+    Unchanged,
+    /// The cursor was after the edit, and moved by this many bytes.
+    Shifted(isize),
+    /// The cursor was inside the replaced text, and moved to the start or end of the new text.
+    Replaced,
+}
+
+impl PieceTable<'_> {
+    /// Replaces the text in `range` with `text`, and moves each offset in `cursors` to where the
+    /// same position is after the edit, returning how each one moved.
+    ///
+    /// Cursors before `range` don't move, and cursors after it are shifted. Cursors inside the
+    /// replaced text go to the start of the new text with [`Bias::Left`], and to its end with
+    /// [`Bias::Right`]. A cursor at `range.start` counts as inside, so with [`Bias::Left`] it
+    /// doesn't move, and when `range` is empty (i.e. for an insertion) `bias` decides whether it
+    /// ends up before or after the inserted text.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// # use piece_table::range_set::{Bias, CursorMove};
+    /// let mut pt = PieceTable::new("let x = 1;");
+    /// let mut cursors = [0, 4, 5, 10];
+    ///
+    /// let moves = pt.replace_range_tracked(4..5, "value", &mut cursors, Bias::Right);
+    ///
+    /// assert_eq!(pt.to_string(), "let value = 1;");
+    /// assert_eq!(cursors, [0, 9, 9, 14]);
+    /// assert_eq!(
+    ///     moves,
+    ///     vec![
+    ///         CursorMove::Unchanged,
+    ///         CursorMove::Replaced,
+    ///         CursorMove::Shifted(4),
+    ///         CursorMove::Shifted(4)
+    ///     ]
+    /// );
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`PieceTable::delete`] and [`PieceTable::insert`].
+    pub fn replace_range_tracked(
+        &mut self,
+        range: Range<usize>,
+        text: &str,
+        cursors: &mut [usize],
+        bias: Bias,
+    ) -> Vec<CursorMove> {
+        self.delete(range.clone());
+        self.insert(text, range.start);
+
+        let delta = text.len() as isize - range.len() as isize;
+        cursors
+            .iter_mut()
+            .map(|cursor| {
+                if *cursor < range.start || (*cursor == range.start && bias == Bias::Left) {
+                    CursorMove::Unchanged
+                } else if *cursor >= range.end {
+                    *cursor = cursor.wrapping_add_signed(delta);
+                    CursorMove::Shifted(delta)
+                } else {
+                    *cursor = match bias {
+                        Bias::Left => range.start,
+                        Bias::Right => range.start + text.len(),
+                    };
+                    CursorMove::Replaced
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_respects_bias() {
+        let mut set = RangeSet::new();
+        let left = set.add(2..4, Bias::Left, Bias::Left);
+        let right = set.add(2..4, Bias::Right, Bias::Right);
+        let grow = set.add(2..4, Bias::Left, Bias::Right);
+
+        set.on_insert(2, 1);
+        set.on_insert(5, 1);
+
+        assert_eq!(Some(2..5), set.get(left));
+        assert_eq!(Some(3..6), set.get(right));
+        assert_eq!(Some(2..6), set.get(grow));
+    }
+
+    #[test]
+    fn empty_range_with_crossed_bias() {
+        let mut set = RangeSet::new();
+        let cursor = set.add(3..3, Bias::Right, Bias::Left);
+
+        set.on_insert(3, 2);
+
+        assert_eq!(Some(5..5), set.get(cursor));
+    }
+
+    #[test]
+    fn delete_clips_ranges() {
+        let mut set = RangeSet::new();
+        let before = set.add(0..3, Bias::Left, Bias::Left);
+        let inside = set.add(4..5, Bias::Left, Bias::Left);
+        let after = set.add(5..9, Bias::Left, Bias::Left);
+
+        set.on_delete(2..6);
+
+        assert_eq!(Some(0..2), set.get(before));
+        assert_eq!(Some(2..2), set.get(inside));
+        assert_eq!(Some(2..5), set.get(after));
+    }
+
+    #[test]
+    fn remove() {
+        let mut set = RangeSet::new();
+        let a = set.add(0..3, Bias::Left, Bias::Left);
+        let b = set.add(1..2, Bias::Left, Bias::Left);
+
+        assert_eq!(Some(0..3), set.remove(a));
+        assert_eq!(None, set.remove(a));
+        assert_eq!(vec![b], set.containing(1));
+        assert_eq!(1, set.len());
+    }
+
+    #[test]
+    fn replace_tracked_with_left_bias() {
+        let mut piece_table = PieceTable::new("abcdef");
+        let mut cursors = [1, 2, 3, 4, 6];
+
+        let moves = piece_table.replace_range_tracked(2..4, "X", &mut cursors, Bias::Left);
+
+        assert_eq!("abXef", piece_table.to_string());
+        assert_eq!([1, 2, 2, 3, 5], cursors);
+        assert_eq!(
+            vec![
+                CursorMove::Unchanged,
+                CursorMove::Unchanged,
+                CursorMove::Replaced,
+                CursorMove::Shifted(-1),
+                CursorMove::Shifted(-1),
+            ],
+            moves
+        );
+    }
+
+    #[test]
+    fn insert_tracked_uses_bias_at_insertion_point() {
+        let mut left = PieceTable::new("ab");
+        let mut right = left.clone();
+        let (mut left_cursors, mut right_cursors) = ([1], [1]);
+
+        left.replace_range_tracked(1..1, "xyz", &mut left_cursors, Bias::Left);
+        right.replace_range_tracked(1..1, "xyz", &mut right_cursors, Bias::Right);
+
+        assert_eq!([1], left_cursors);
+        assert_eq!([4], right_cursors);
+    }
+
+    #[test]
+    fn containing_matches_linear_scan() {
+        let mut set = RangeSet::new();
+        for i in 0..50 {
+            let start = (i * 7) % 40;
+            let len = (i * 3) % 11;
+            set.add(start..start + len, Bias::Left, Bias::Right);
+        }
+        set.on_insert(10, 3);
+        set.on_delete(20..25);
+
+        for offset in 0..60 {
+            let expected: Vec<_> = set
+                .iter()
+                .filter(|(_, r)| r.contains(&offset) || (r.is_empty() && r.start == offset))
+                .map(|(id, _)| id)
+                .collect();
+            assert_eq!(expected, set.containing(offset), "offset {offset}");
+        }
+    }
+}
@@ -0,0 +1,360 @@
+//! Read-only handles to a `PieceTable` which can be shared with other threads.
+
+use std::fmt;
+use std::ops::Range;
+use std::sync::{Arc, RwLock};
+
+use crate::added::AddedBuffer;
+use crate::{Node, NodeKind, PieceTable, inline_text, repeat_chunks};
+
+/// A handle for reading a [`PieceTable`] from other threads, created by [`PieceTable::reader`].
+///
+/// After every edit the `PieceTable` publishes an immutable [`Snapshot`] of its state, which
+/// replaces the previous one atomically. Readers only ever see complete snapshots, so a
+/// background task (e.g. search indexing or spellchecking) can take a snapshot and work on a
+/// consistent state for as long as it needs to, while the table keeps being edited.
+///
+/// # Examples
+///
+/// ```
+/// # use piece_table::PieceTable;
+/// let mut pt = PieceTable::new("hello");
+/// let reader = pt.reader();
+///
+/// let handle = std::thread::spawn(move || reader.snapshot().to_string());
+/// let seen = handle.join().unwrap();
+/// pt.insert(" world", 5);
+///
+/// assert_eq!(seen, "hello");
+/// ```
+#[derive(Debug, Clone)]
+pub struct PieceTableReader<'a> {
+    current: Arc<RwLock<Arc<Snapshot<'a>>>>,
+}
+
+impl<'a> PieceTableReader<'a> {
+    /// Returns the most recently published state of the `PieceTable`.
+    ///
+    /// The snapshot isn't affected by later edits.
+    pub fn snapshot(&self) -> Arc<Snapshot<'a>> {
+        Arc::clone(&self.current.read().unwrap_or_else(|err| err.into_inner()))
+    }
+}
+
+/// An immutable copy of the state of a `PieceTable` at some point in time.
+///
+/// Taking a snapshot doesn't copy the text: the original text is shared with the table, and the
+/// "added" buffer is shared in chunks, each of which is copied once, when it's first published.
+#[derive(Debug)]
+pub struct Snapshot<'a> {
+    original: &'a str,
+    chunks: Vec<Chunk>,
+    nodes: Vec<Node>,
+    len: usize,
+    newlines: usize,
+}
+
+/// A part of the "added" buffer, starting at byte `start`.
+#[derive(Debug, Clone)]
+struct Chunk {
+    start: usize,
+    text: Arc<str>,
+}
+
+impl<'a> Snapshot<'a> {
+    /// Returns the length of the text in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the text is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of lines, see [`PieceTable::len_lines`].
+    pub fn len_lines(&self) -> usize {
+        self.newlines + 1
+    }
+
+    /// Returns an iterator over the text of every piece, in order.
+    ///
+    /// A piece of "added" text which was extended over several edits may be split across
+    /// chunks, in which case it's returned in parts.
+    pub fn chunks(&self) -> impl Iterator<Item = &str> {
+        self.nodes.iter().flat_map(|node| {
+            let range = node.range.clone();
+            let (original, chunks) = match node.kind {
+                NodeKind::Original => (Some(&self.original[range.clone()]), &self.chunks[..0]),
+                NodeKind::Inline(ref bytes) => {
+                    (Some(inline_text(bytes, range.clone())), &self.chunks[..0])
+                }
+                NodeKind::Added => {
+                    // Chunks are only ever cut between edits, so they start on char boundaries
+                    let first = self
+                        .chunks
+                        .partition_point(|chunk| chunk.start <= range.start)
+                        - 1;
+                    let last = self.chunks.partition_point(|chunk| chunk.start < range.end);
+                    (None, &self.chunks[first..last.max(first + 1)])
+                }
+                NodeKind::Repeat(_) => (None, &self.chunks[..0]),
+            };
+            let repeat = match node.kind {
+                NodeKind::Repeat(byte) => Some(repeat_chunks(byte, range.len())),
+                _ => None,
+            };
+            let added = chunks.iter().map(move |chunk| {
+                let start = range.start.max(chunk.start) - chunk.start;
+                let end = range.end.min(chunk.start + chunk.text.len()) - chunk.start;
+                &chunk.text[start..end]
+            });
+            original
+                .into_iter()
+                .chain(repeat.into_iter().flatten())
+                .chain(added)
+        })
+    }
+
+    /// Returns the text as a `String`.
+    pub fn as_string(&self) -> String {
+        let mut string = String::with_capacity(self.len);
+        self.chunks().for_each(|chunk| string.push_str(chunk));
+        string
+    }
+}
+
+impl fmt::Display for Snapshot<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.chunks().try_for_each(|chunk| f.write_str(chunk))
+    }
+}
+
+/// The writing side of the readers of a `PieceTable`.
+///
+/// This is inert until [`PieceTable::reader`] is first called, and goes back to being inert once
+/// every reader is dropped, so tables without readers don't pay for publishing snapshots. Cloning
+/// a table doesn't clone its readers.
+#[derive(Debug, Default)]
+pub(crate) struct Publisher<'a> {
+    state: Option<PublisherState<'a>>,
+}
+
+#[derive(Debug)]
+struct PublisherState<'a> {
+    current: Arc<RwLock<Arc<Snapshot<'a>>>>,
+    /// The chunks of the "added" buffer published so far
+    chunks: Vec<Chunk>,
+}
+
+impl Clone for Publisher<'_> {
+    fn clone(&self) -> Self {
+        Publisher::default()
+    }
+}
+
+impl<'a> PieceTable<'a> {
+    /// Returns a handle for reading this table from other threads.
+    ///
+    /// See [`PieceTableReader`] for details. Every edit made while any reader is alive copies
+    /// the list of pieces and the newly added text into a new snapshot.
+    pub fn reader(&mut self) -> PieceTableReader<'a> {
+        if let Some(state) = &self.publisher.state {
+            return PieceTableReader {
+                current: Arc::clone(&state.current),
+            };
+        }
+
+        let mut state = PublisherState {
+            current: Arc::new(RwLock::new(Arc::new(Snapshot {
+                original: self.original,
+                chunks: Vec::new(),
+                nodes: Vec::new(),
+                len: 0,
+                newlines: 0,
+            }))),
+            chunks: Vec::new(),
+        };
+        let reader = PieceTableReader {
+            current: Arc::clone(&state.current),
+        };
+        state.publish(self);
+        self.publisher.state = Some(state);
+        reader
+    }
+
+    /// Publishes the current state to the readers, if there are any, and starts a new
+    /// generation. This is called at the end of every edit.
+    pub(crate) fn publish(&mut self) {
+        self.generation = crate::cursor::next_generation();
+        let Some(mut state) = self.publisher.state.take() else {
+            return;
+        };
+        if Arc::strong_count(&state.current) > 1 {
+            state.publish(self);
+            self.publisher.state = Some(state);
+        }
+    }
+}
+
+impl Publisher<'_> {
+    /// Forgets the chunks of the "added" buffer published so far, after the buffer was replaced,
+    /// so the next snapshot copies it from the start.
+    pub(crate) fn reset_chunks(&mut self) {
+        if let Some(state) = &mut self.state {
+            state.chunks.clear();
+        }
+    }
+}
+
+impl<'a> PublisherState<'a> {
+    fn publish(&mut self, table: &PieceTable<'a>) {
+        let published = self.chunks.last().map_or(0, |c| c.start + c.text.len());
+        if table.added.len() > published {
+            self.chunks.push(Chunk {
+                start: published,
+                text: shared_text(&table.added, published..table.added.len()),
+            });
+            self.merge_chunks(&table.added);
+        }
+
+        let snapshot = Arc::new(Snapshot {
+            original: table.original,
+            chunks: self.chunks.clone(),
+            nodes: table.nodes.iter().cloned().collect(),
+            len: table.len,
+            newlines: table.newlines,
+        });
+        *self.current.write().unwrap_or_else(|err| err.into_inner()) = snapshot;
+    }
+
+    /// Merges the last chunks while they are less than twice as long as the one before them.
+    ///
+    /// This keeps the number of chunks logarithmic in the size of the buffer, while copying each
+    /// byte a logarithmic number of times.
+    fn merge_chunks(&mut self, added: &AddedBuffer) {
+        while let [.., prev, last] = self.chunks.as_slice()
+            && prev.text.len() < 2 * last.text.len()
+        {
+            let start = prev.start;
+            let end = last.start + last.text.len();
+            self.chunks.truncate(self.chunks.len() - 2);
+            self.chunks.push(Chunk {
+                start,
+                text: shared_text(added, start..end),
+            });
+        }
+    }
+}
+
+/// Copies `range` of the "added" buffer into an `Arc<str>`.
+fn shared_text(added: &AddedBuffer, range: Range<usize>) -> Arc<str> {
+    if let Some(text) = added.get(range.clone()) {
+        return Arc::from(text);
+    }
+    let mut text = String::with_capacity(range.len());
+    added.copy_range(range, &mut text);
+    Arc::from(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn reader_is_send_and_sync() {
+        assert_send_sync::<PieceTableReader<'static>>();
+        assert_send_sync::<Arc<Snapshot<'static>>>();
+    }
+
+    #[test]
+    fn snapshot_is_not_affected_by_later_edits() {
+        let mut piece_table = PieceTable::new("hello");
+        let reader = piece_table.reader();
+
+        let before = reader.snapshot();
+        piece_table.insert(" world\n", 5);
+        piece_table.delete(0..1);
+        let after = reader.snapshot();
+
+        assert_eq!("hello", before.to_string());
+        assert_eq!("ello world\n", after.to_string());
+        assert_eq!(11, after.len());
+        assert_eq!(2, after.len_lines());
+    }
+
+    #[test]
+    fn many_edits_keep_few_chunks() {
+        let mut piece_table = PieceTable::new("");
+        let reader = piece_table.reader();
+
+        let mut expected = String::new();
+        for i in 0..1000 {
+            let c = char::from(b'a' + (i % 26) as u8);
+            piece_table.insert_char(i / 2, c);
+            expected.insert(i / 2, c);
+        }
+        let snapshot = reader.snapshot();
+
+        assert_eq!(expected, snapshot.as_string());
+        assert!(snapshot.chunks.len() <= 20);
+    }
+
+    #[test]
+    fn coalesced_piece_spanning_chunks() {
+        let mut piece_table = PieceTable::new("<>");
+        let reader = piece_table.reader();
+
+        let text = "typing one char at a time";
+        for (i, c) in text.chars().enumerate() {
+            piece_table.insert_char(1 + i, c);
+        }
+        let snapshot = reader.snapshot();
+
+        assert_eq!(3, piece_table.nodes.len());
+        assert!(snapshot.chunks.len() > 1);
+        assert_eq!(format!("<{text}>"), snapshot.as_string());
+    }
+
+    #[test]
+    fn publishing_stops_when_readers_are_dropped() {
+        let mut piece_table = PieceTable::new("abc");
+        let reader = piece_table.reader();
+        drop(reader);
+
+        piece_table.insert("d", 3);
+
+        assert!(piece_table.publisher.state.is_none());
+        assert_eq!("abcd", piece_table.reader().snapshot().to_string());
+    }
+
+    #[test]
+    fn clones_have_no_readers() {
+        let mut piece_table = PieceTable::new("abc");
+        let reader = piece_table.reader();
+
+        let mut clone = piece_table.clone();
+        clone.insert("x", 0);
+
+        assert_eq!("abc", reader.snapshot().to_string());
+    }
+
+    #[test]
+    fn read_from_another_thread() {
+        let mut piece_table = PieceTable::new("0");
+        let reader = piece_table.reader();
+
+        let handle = std::thread::spawn(move || {
+            (0..100)
+                .map(|_| reader.snapshot())
+                .all(|snapshot| snapshot.as_string().len() == snapshot.len())
+        });
+        for i in 1..100 {
+            piece_table.insert(&i.to_string(), 0);
+        }
+
+        assert!(handle.join().unwrap());
+    }
+}
@@ -0,0 +1,516 @@
+//! Showing what changed between two states of a table, as colored terminal output, HTML, or the
+//! lines which changed.
+//!
+//! Text is never removed from the buffers, so the pieces of both states tell where each byte came
+//! from. Bytes which both states refer to are unchanged, bytes which only the newer state refers
+//! to were inserted, and bytes which only the older state refers to were removed, without
+//! comparing any text. Text which was moved shows up as removed and inserted.
+
+use std::ops::Range;
+
+use crate::added::AddedBuffer;
+use crate::{Node, NodeKind, PTableSlice, PieceTable};
+
+/// How a span of text changed between two states.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// The text is in both states.
+    Kept,
+    /// The text is only in the newer state.
+    Inserted,
+    /// The text is only in the older state.
+    Removed,
+}
+
+/// Lines of the newer of two states which replace lines of the older one, see
+/// [`PTableSlice::changed_lines`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineRange {
+    /// The replaced lines of the older state.
+    pub old: Range<usize>,
+    /// The lines of the newer state which replace them.
+    pub new: Range<usize>,
+}
+
+/// The output format of [`render`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// ANSI escape codes for terminals: inserted text is green, removed text is red and struck
+    /// through.
+    Ansi,
+    /// HTML, with inserted text in `<ins>` and removed text in `<del>` elements. All text is
+    /// escaped, and nothing else is added, so the output can be put inside e.g. a `<pre>`.
+    Html,
+}
+
+/// Renders `changes`, as returned by [`PieceTable::changes_since`] or
+/// [`PTableSlice::changes_to`], in `format`.
+///
+/// # Examples
+///
+/// ```
+/// # use piece_table::PieceTable;
+/// use piece_table::render::{Format, render};
+///
+/// let mut pt = PieceTable::new("a < b");
+/// pt.checkpoint("saved");
+/// pt.delete(2..3);
+/// pt.insert(">=", 2);
+///
+/// let changes = pt.changes_since("saved").unwrap();
+/// assert_eq!(render(&changes, Format::Html), "a <del>&lt;</del><ins>&gt;=</ins> b");
+/// ```
+pub fn render(changes: &[(Change, String)], format: Format) -> String {
+    let mut out = String::new();
+    for (change, text) in changes {
+        match format {
+            Format::Ansi => {
+                let style = match change {
+                    Change::Kept => None,
+                    Change::Inserted => Some("\x1b[32m"),
+                    Change::Removed => Some("\x1b[31;9m"),
+                };
+                out.push_str(style.unwrap_or_default());
+                out.push_str(text);
+                if style.is_some() {
+                    out.push_str("\x1b[0m");
+                }
+            }
+            Format::Html => {
+                let tag = match change {
+                    Change::Kept => None,
+                    Change::Inserted => Some("ins"),
+                    Change::Removed => Some("del"),
+                };
+                if let Some(tag) = tag {
+                    out.push_str(&format!("<{tag}>"));
+                }
+                push_escaped(&mut out, text);
+                if let Some(tag) = tag {
+                    out.push_str(&format!("</{tag}>"));
+                }
+            }
+        }
+    }
+    out
+}
+
+fn push_escaped(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+impl PieceTable<'_> {
+    /// Returns the changes between the state saved under `name` and the current one, as spans
+    /// of text in document order, or `None` if there is no such checkpoint.
+    ///
+    /// Removed text is placed where it used to be, and consecutive spans always differ in how
+    /// they changed. See [`render`] for turning the changes into something to look at.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// use piece_table::render::Change;
+    ///
+    /// let mut pt = PieceTable::new("hello world");
+    /// pt.checkpoint("saved");
+    /// pt.insert("brave new ", 6);
+    /// pt.delete(0..1);
+    ///
+    /// assert_eq!(
+    ///     pt.changes_since("saved").unwrap(),
+    ///     vec![
+    ///         (Change::Removed, "h".to_string()),
+    ///         (Change::Kept, "ello ".to_string()),
+    ///         (Change::Inserted, "brave new ".to_string()),
+    ///         (Change::Kept, "world".to_string()),
+    ///     ]
+    /// );
+    /// ```
+    pub fn changes_since(&self, name: &str) -> Option<Vec<(Change, String)>> {
+        let checkpoint = self.find_checkpoint(name)?;
+        Some(changes(
+            Side::new(checkpoint.nodes.iter(), self.original, &self.added),
+            Side::new(self.nodes.iter(), self.original, &self.added),
+        ))
+    }
+}
+
+impl PTableSlice<'_> {
+    /// Returns the changes between this slice and `newer`, as spans of text in document order,
+    /// see [`PieceTable::changes_since`].
+    ///
+    /// Both slices must have been created from the same table, and `newer` is usually created
+    /// after this one. The changes of slices of different tables are meaningless.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// use piece_table::render::{Format, render};
+    ///
+    /// let mut pt = PieceTable::new("let x = 1;");
+    /// let before = pt.create_slice();
+    /// pt.insert("mut ", 4);
+    /// let after = pt.create_slice();
+    ///
+    /// let changes = before.changes_to(&after);
+    /// assert_eq!(render(&changes, Format::Ansi), "let \x1b[32mmut \x1b[0mx = 1;");
+    /// ```
+    pub fn changes_to(&self, newer: &PTableSlice) -> Vec<(Change, String)> {
+        changes(
+            Side::new(self.nodes.iter(), self.original, &self.added),
+            Side::new(newer.nodes.iter(), newer.original, &newer.added),
+        )
+    }
+
+    /// Returns the lines which differ between this slice and `newer`, in document order, e.g.
+    /// for redrawing only those after a batch of edits. Both slices must have been created from
+    /// the same table, see [`PTableSlice::changes_to`].
+    ///
+    /// Each [`LineRange`] holds the lines of this slice which were replaced, and the lines of
+    /// `newer` replacing them, either of which may be empty. Lines following them are
+    /// unchanged, though they may have moved up or down. A line is changed if any of its text
+    /// was, so e.g. inserting a line before another one also reports the other one as changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// use piece_table::render::LineRange;
+    ///
+    /// let mut pt = PieceTable::new("one\ntwo\nthree\nfour\n");
+    /// let saved = pt.create_slice();
+    /// pt.insert("2\n", 8);
+    /// pt.delete(0..1);
+    /// let current = pt.create_slice();
+    ///
+    /// let changed: Vec<_> = saved.changed_lines(&current).collect();
+    /// assert_eq!(
+    ///     changed,
+    ///     vec![
+    ///         LineRange { old: 0..1, new: 0..1 },
+    ///         LineRange { old: 2..3, new: 2..4 },
+    ///     ]
+    /// );
+    /// ```
+    pub fn changed_lines(&self, newer: &PTableSlice) -> impl Iterator<Item = LineRange> {
+        let mut lines: Vec<LineRange> = Vec::new();
+        let (mut old_line, mut new_line) = (0, 0);
+        for (change, text) in self.changes_to(newer) {
+            let newlines = text.bytes().filter(|&b| b == b'\n').count();
+            let touched = match change {
+                Change::Kept => {
+                    old_line += newlines;
+                    new_line += newlines;
+                    continue;
+                }
+                Change::Removed => LineRange {
+                    old: old_line..old_line + newlines + 1,
+                    new: new_line..new_line + 1,
+                },
+                Change::Inserted => LineRange {
+                    old: old_line..old_line + 1,
+                    new: new_line..new_line + newlines + 1,
+                },
+            };
+            match change {
+                Change::Removed => old_line += newlines,
+                _ => new_line += newlines,
+            }
+
+            match lines.last_mut() {
+                // A change on a line which an earlier change already touched
+                Some(last) if touched.new.start < last.new.end => {
+                    last.old.end = last.old.end.max(touched.old.end);
+                    last.new.end = last.new.end.max(touched.new.end);
+                }
+                _ => lines.push(touched),
+            }
+        }
+        lines.into_iter()
+    }
+}
+
+/// The pieces of one state, along with the buffers they refer to.
+struct Side<'s> {
+    nodes: Vec<&'s Node>,
+    original: &'s str,
+    added: &'s AddedBuffer<'s>,
+}
+
+impl<'s> Side<'s> {
+    fn new(
+        nodes: impl Iterator<Item = &'s Node>,
+        original: &'s str,
+        added: &'s AddedBuffer,
+    ) -> Self {
+        Side {
+            nodes: nodes.collect(),
+            original,
+            added,
+        }
+    }
+
+    /// Returns where the pieces of `kind` start and end in their buffer, sorted, along with the
+    /// sorted, merged ranges of the buffer which they refer to.
+    fn referenced(&self, kind: NodeKind) -> (Vec<usize>, Vec<Range<usize>>) {
+        let mut ranges: Vec<_> = self
+            .nodes
+            .iter()
+            .filter(|node| node.kind == kind && !node.range.is_empty())
+            .map(|node| node.range.clone())
+            .collect();
+        ranges.sort_by_key(|range| range.start);
+
+        let mut cuts: Vec<_> = ranges
+            .iter()
+            .flat_map(|range| [range.start, range.end])
+            .collect();
+        cuts.sort_unstable();
+        cuts.dedup();
+
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if last.end >= range.start => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        (cuts, merged)
+    }
+
+    /// Splits the text of every piece where a piece of `other` starts or ends, into segments
+    /// which `other` either refers to as a whole or not at all.
+    ///
+    /// Splitting at every piece boundary rather than only where the referenced ranges start and
+    /// end keeps text which was moved as a separate segment.
+    fn segments(&self, other: &Side) -> Vec<Segment<'s>> {
+        let other_original = other.referenced(NodeKind::Original);
+        let other_added = other.referenced(NodeKind::Added);
+
+        let mut segments = Vec::new();
+        for &node in &self.nodes {
+            let (cuts, referenced) = match node.kind {
+                NodeKind::Original => &other_original,
+                NodeKind::Added => &other_added,
+                NodeKind::Inline(_) | NodeKind::Repeat(_) => {
+                    let shared = other.nodes.contains(&node);
+                    for text in node.chunks(self.original, self.added) {
+                        segments.push(Segment {
+                            origin: Origin::Inline(node),
+                            text,
+                            shared,
+                        });
+                    }
+                    continue;
+                }
+            };
+            let text = node.text(self.original, self.added);
+
+            let added = node.kind == NodeKind::Added;
+            let first = cuts.partition_point(|&cut| cut <= node.range.start);
+            let inner = cuts[first..]
+                .iter()
+                .copied()
+                .take_while(|&cut| cut < node.range.end);
+            let mut start = node.range.start;
+            for end in inner.chain([node.range.end]) {
+                let idx = referenced.partition_point(|range| range.end <= start);
+                segments.push(Segment {
+                    origin: Origin::Buffer { added, start },
+                    text: &text[start - node.range.start..end - node.range.start],
+                    shared: referenced
+                        .get(idx)
+                        .is_some_and(|range| range.start <= start),
+                });
+                start = end;
+            }
+        }
+        segments
+    }
+}
+
+/// Where the text of a [`Segment`] is stored.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Origin<'s> {
+    Buffer {
+        added: bool,
+        start: usize,
+    },
+    /// A piece whose text isn't in a buffer, i.e. an inline or repeat piece
+    Inline(&'s Node),
+}
+
+/// A part of a piece which the other state either does or doesn't refer to as a whole.
+#[derive(Debug)]
+struct Segment<'s> {
+    origin: Origin<'s>,
+    text: &'s str,
+    shared: bool,
+}
+
+impl Segment<'_> {
+    /// Returns the length of the text which this segment, starting `offset` bytes in, has in
+    /// common with `other`, starting `other_offset` bytes in.
+    fn common_len(&self, offset: usize, other: &Segment, other_offset: usize) -> usize {
+        let len = (self.text.len() - offset).min(other.text.len() - other_offset);
+        match (self.origin, other.origin) {
+            (
+                Origin::Buffer { added, start },
+                Origin::Buffer {
+                    added: other_added,
+                    start: other_start,
+                },
+            ) if added == other_added && start + offset == other_start + other_offset => len,
+            (Origin::Inline(node), Origin::Inline(other_node))
+                if node == other_node && offset == 0 && other_offset == 0 =>
+            {
+                len
+            }
+            _ => 0,
+        }
+    }
+}
+
+/// Returns the changes between `old` and `new`, see [`PieceTable::changes_since`].
+fn changes(old: Side, new: Side) -> Vec<(Change, String)> {
+    let old_segments = old.segments(&new);
+    let new_segments = new.segments(&old);
+
+    let mut changes: Vec<(Change, String)> = Vec::new();
+    let mut push = |change: Change, text: &str| match changes.last_mut() {
+        Some((last, last_text)) if *last == change => last_text.push_str(text),
+        _ => changes.push((change, text.to_string())),
+    };
+
+    let (mut i, mut old_offset) = (0, 0);
+    let (mut j, mut new_offset) = (0, 0);
+    loop {
+        let (removed, inserted) = match (old_segments.get(i), new_segments.get(j)) {
+            (None, None) => break,
+            (Some(o), Some(n)) if o.shared && n.shared => {
+                let common = o.common_len(old_offset, n, new_offset);
+                if common > 0 {
+                    push(Change::Kept, &n.text[new_offset..new_offset + common]);
+                    old_offset += common;
+                    new_offset += common;
+                    if old_offset == o.text.len() {
+                        (i, old_offset) = (i + 1, 0);
+                    }
+                    if new_offset == n.text.len() {
+                        (j, new_offset) = (j + 1, 0);
+                    }
+                    continue;
+                }
+                // Moved text, which is shown as removed here and inserted where it ends up
+                (true, false)
+            }
+            (Some(o), _) if !o.shared => (true, false),
+            (_, Some(n)) if !n.shared => (false, true),
+            (Some(_), None) => (true, false),
+            (None, Some(_)) => (false, true),
+            (Some(_), Some(_)) => unreachable!("both segments are shared or one isn't"),
+        };
+
+        if removed {
+            push(Change::Removed, &old_segments[i].text[old_offset..]);
+            (i, old_offset) = (i + 1, 0);
+        } else if inserted {
+            push(Change::Inserted, &new_segments[j].text[new_offset..]);
+            (j, new_offset) = (j + 1, 0);
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changed(changes: &[(Change, String)]) -> String {
+        render(changes, Format::Html)
+    }
+
+    #[test]
+    fn edits_across_pieces() {
+        let mut piece_table = PieceTable::new("one two three");
+        piece_table.insert("zero ", 0);
+        piece_table.checkpoint("saved");
+        piece_table.delete(2..9);
+        piece_table.insert_char(2, '!');
+        piece_table.insert(" four", piece_table.len());
+
+        assert_eq!(
+            "ze<del>ro one </del><ins>!</ins>two three<ins> four</ins>",
+            changed(&piece_table.changes_since("saved").unwrap())
+        );
+    }
+
+    #[test]
+    fn moved_text_is_removed_and_inserted() {
+        let mut piece_table = PieceTable::new("a\nb\nc\n");
+        piece_table.checkpoint("saved");
+        piece_table.move_range(0..2, 6);
+
+        assert_eq!(
+            "<del>a\n</del>b\nc\n<ins>a\n</ins>",
+            changed(&piece_table.changes_since("saved").unwrap())
+        );
+    }
+
+    #[test]
+    fn duplicated_text_is_inserted_once() {
+        let mut piece_table = PieceTable::new("ab");
+        let before = piece_table.create_slice();
+        piece_table.duplicate_range(0..2, 2);
+        let after = piece_table.create_slice();
+
+        let changes = before.changes_to(&after);
+        assert_eq!("ab<ins>ab</ins>", changed(&changes));
+        assert_eq!(
+            vec![(Change::Kept, "abab".to_string())],
+            after.changes_to(&after)
+        );
+    }
+
+    #[test]
+    fn changed_lines_of_edits() {
+        let mut piece_table = PieceTable::new("a\nb\nc\nd");
+        let before = piece_table.create_slice();
+        // Joins the first two lines, and replaces the last one with two lines
+        piece_table.delete(1..2);
+        piece_table.insert("x\ny", 5);
+        piece_table.delete(8..9);
+        let after = piece_table.create_slice();
+
+        assert_eq!("ab\nc\nx\ny", piece_table.to_string());
+        assert_eq!(
+            vec![
+                LineRange {
+                    old: 0..2,
+                    new: 0..1
+                },
+                LineRange {
+                    old: 3..4,
+                    new: 2..4
+                },
+            ],
+            before.changed_lines(&after).collect::<Vec<_>>()
+        );
+        assert_eq!(0, after.changed_lines(&after).count());
+    }
+
+    #[test]
+    fn unknown_checkpoint() {
+        assert_eq!(None, PieceTable::new("").changes_since("nope"));
+    }
+}
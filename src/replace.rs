@@ -0,0 +1,143 @@
+//! Replacing every occurrence of a word, whatever its case, keeping the case of each one.
+//!
+//! Renaming `user` to `account` in a document which also says `User` and `USER` should give
+//! `Account` and `ACCOUNT` there. This finds the occurrences in a single pass over the text and
+//! replaces them all as one batch.
+
+use std::ops::Range;
+
+use crate::PieceTable;
+
+impl PieceTable<'_> {
+    /// Replaces every non-overlapping occurrence of `needle`, from the first to the last and
+    /// ignoring case, with `replacement` cased like the occurrence. Returns how many were
+    /// replaced.
+    ///
+    /// Chars are compared by their lowercase forms, one char of the text for each char of
+    /// `needle`. Occurrences with no lowercase letters get `replacement` in uppercase, those
+    /// with no uppercase letters get it in lowercase, and those starting with an uppercase
+    /// letter followed by lowercase ones get it with its first letter in uppercase. Others get
+    /// `replacement` as it is. An empty `needle` replaces nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("user User USER uSeR");
+    /// assert_eq!(pt.replace_all_preserve_case("user", "account"), 4);
+    /// assert_eq!(pt.to_string(), "account Account ACCOUNT account");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the replacements would exceed the table's limits.
+    pub fn replace_all_preserve_case(&mut self, needle: &str, replacement: &str) -> usize {
+        let matches = self.find_matches_ignoring_case(needle);
+        let count = matches.len();
+        let edits = matches
+            .into_iter()
+            .map(|range| {
+                let text = match_case(&self.slice(range.clone()).to_string(), replacement);
+                (range, text)
+            })
+            .collect();
+        self.replace_all(edits, "replace_all_preserve_case");
+        count
+    }
+
+    /// Returns the ranges of the non-overlapping occurrences of `needle`, ignoring case, found
+    /// with Knuth-Morris-Pratt over chars so the text is read once.
+    fn find_matches_ignoring_case(&self, needle: &str) -> Vec<Range<usize>> {
+        let needle: Vec<char> = needle.chars().collect();
+        if needle.is_empty() {
+            return Vec::new();
+        }
+        // `fallback[i]` is the length of the longest proper border of `needle[..=i]`
+        let mut fallback = vec![0; needle.len()];
+        let mut border = 0;
+        for i in 1..needle.len() {
+            while border > 0 && !same_letter(needle[i], needle[border]) {
+                border = fallback[border - 1];
+            }
+            if same_letter(needle[i], needle[border]) {
+                border += 1;
+            }
+            fallback[i] = border;
+        }
+
+        let mut matches = Vec::new();
+        // The offsets of the chars matched so far, to find where a match started
+        let mut starts = Vec::with_capacity(needle.len());
+        for (offset, c) in self.char_indices_from(0) {
+            let mut matched = starts.len();
+            while matched > 0 && !same_letter(c, needle[matched]) {
+                matched = fallback[matched - 1];
+            }
+            starts.drain(..starts.len() - matched);
+            if same_letter(c, needle[matched]) {
+                starts.push(offset);
+            }
+            if starts.len() == needle.len() {
+                matches.push(starts[0]..offset + c.len_utf8());
+                starts.clear();
+            }
+        }
+        matches
+    }
+}
+
+/// Checks if `a` and `b` are the same letter, ignoring case.
+fn same_letter(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+/// Returns `replacement` cased like `found`, see [`PieceTable::replace_all_preserve_case`].
+fn match_case(found: &str, replacement: &str) -> String {
+    let mut cased = found
+        .chars()
+        .filter(|c| c.is_lowercase() || c.is_uppercase());
+    let has_upper = found.chars().any(char::is_uppercase);
+    let has_lower = found.chars().any(char::is_lowercase);
+    if has_upper && !has_lower {
+        replacement.to_uppercase()
+    } else if has_lower && !has_upper {
+        replacement.to_lowercase()
+    } else if cased.next().is_some_and(char::is_uppercase) && cased.all(char::is_lowercase) {
+        let mut chars = replacement.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => String::new(),
+        }
+    } else {
+        replacement.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_across_pieces_and_cases() {
+        let mut piece_table = PieceTable::new("Ääb äÄäb");
+        piece_table.split_at_offset(2);
+        piece_table.insert("B", 4);
+        assert_eq!("ÄäBb äÄäb", piece_table.to_string());
+
+        // Every "ä" starts a match, which only goes on if a "b" follows it
+        assert_eq!(2, piece_table.replace_all_preserve_case("äB", "xy"));
+        assert_eq!("Äxyb äÄxy", piece_table.to_string());
+        assert_eq!(0, piece_table.replace_all_preserve_case("", "z"));
+        assert_eq!(Ok(()), piece_table.check_invariants());
+    }
+
+    #[test]
+    fn replacement_follows_each_case() {
+        assert_eq!("NEW_NAME", match_case("OLD_NAME", "new_name"));
+        assert_eq!("new_name", match_case("old_name", "New_Name"));
+        assert_eq!("Élan", match_case("Name", "élan"));
+        assert_eq!("newName", match_case("oLdName", "newName"));
+        assert_eq!("", match_case("Old", ""));
+        assert_eq!("12", match_case("34", "12"));
+    }
+}
@@ -0,0 +1,230 @@
+//! Scanning the text char by char from an offset, e.g. for bracket matching.
+//!
+//! The text is read straight from the pieces, and scanning stops as soon as a match is found, so
+//! only the text between the start and the match is ever looked at.
+
+use crate::PieceTable;
+
+/// The direction in which [`PieceTable::scan_until`] moves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Towards the end of the document, starting with the char at the offset.
+    Forward,
+    /// Towards the start of the document, starting with the char before the offset.
+    Backward,
+}
+
+/// Opening and closing brackets matched by [`PieceTable::find_matching_bracket`].
+const BRACKETS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+impl PieceTable<'_> {
+    /// Returns the offset of the first char for which `pred` returns `true`, scanning from
+    /// `offset` in `direction`, or `None` if there's no such char.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// use piece_table::scan::Direction;
+    ///
+    /// let mut pt = PieceTable::new("let x = 1;");
+    /// pt.insert("mut ", 4);
+    ///
+    /// let is_space = |c: char| c.is_whitespace();
+    /// assert_eq!(pt.scan_until(5, is_space, Direction::Forward), Some(7));
+    /// assert_eq!(pt.scan_until(5, is_space, Direction::Backward), Some(3));
+    /// assert_eq!(pt.scan_until(3, |c| c == ';', Direction::Backward), None);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is out of bounds.
+    pub fn scan_until<F>(&self, offset: usize, mut pred: F, direction: Direction) -> Option<usize>
+    where
+        F: FnMut(char) -> bool,
+    {
+        assert!(
+            offset <= self.len,
+            "invalid scan_until: offset {offset} is out of bounds of document of length {}",
+            self.len
+        );
+
+        match direction {
+            Direction::Forward => self
+                .char_indices_from(offset)
+                .find(|&(_, c)| pred(c))
+                .map(|(offset, _)| offset),
+            Direction::Backward => self
+                .char_indices_rev_from(offset)
+                .find(|&(_, c)| pred(c))
+                .map(|(offset, _)| offset),
+        }
+    }
+
+    /// Returns the offset of the bracket matching the one at `offset`, or `None` if there's no
+    /// bracket at `offset` or it isn't matched.
+    ///
+    /// Parentheses, square brackets and curly braces are matched, skipping over nested pairs of
+    /// the same kind. Other kinds of brackets, as well as strings and comments, are not taken
+    /// into account.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let pt = PieceTable::new("f(a, (b), c[0])");
+    /// assert_eq!(pt.find_matching_bracket(1), Some(14));
+    /// assert_eq!(pt.find_matching_bracket(14), Some(1));
+    /// assert_eq!(pt.find_matching_bracket(11), Some(13));
+    /// assert_eq!(pt.find_matching_bracket(0), None);
+    /// ```
+    pub fn find_matching_bracket(&self, offset: usize) -> Option<usize> {
+        let bracket = self.char(offset)?;
+        let (open, close, direction) = BRACKETS.iter().find_map(|&(open, close)| {
+            if bracket == open {
+                Some((open, close, Direction::Forward))
+            } else if bracket == close {
+                Some((open, close, Direction::Backward))
+            } else {
+                None
+            }
+        })?;
+
+        let mut depth = 0usize;
+        let start = match direction {
+            Direction::Forward => offset + bracket.len_utf8(),
+            Direction::Backward => offset,
+        };
+        self.scan_until(
+            start,
+            |c| {
+                if c == bracket {
+                    depth += 1;
+                } else if c == open || c == close {
+                    if depth == 0 {
+                        return true;
+                    }
+                    depth -= 1;
+                }
+                false
+            },
+            direction,
+        )
+    }
+
+    /// Returns the chars from `offset` to the end, along with their offsets.
+    pub(crate) fn char_indices_from(&self, offset: usize) -> impl Iterator<Item = (usize, char)> {
+        let mut start = offset;
+        self.chunks_from(offset).flat_map(move |chunk| {
+            let chunk_start = start;
+            start += chunk.len();
+            chunk
+                .char_indices()
+                .map(move |(idx, c)| (chunk_start + idx, c))
+        })
+    }
+
+    /// Returns the chars before `offset`, from the last one to the first, along with their
+    /// offsets.
+    fn char_indices_rev_from(&self, offset: usize) -> impl Iterator<Item = (usize, char)> {
+        let (idx, node_start) = offset
+            .checked_sub(1)
+            .and_then(|last| self.find_node(last))
+            .unwrap_or_default();
+        let first = self.nodes.get(idx).into_iter().flat_map(move |node| {
+            node.chunks_in(0..offset - node_start, self.original, &self.added)
+                .rev()
+        });
+        let rest = self
+            .nodes
+            .range(..idx)
+            .rev()
+            .flat_map(|node| node.chunks(self.original, &self.added).rev());
+
+        first
+            .chain(rest)
+            .scan(offset, |end, chunk| {
+                *end -= chunk.len();
+                Some((*end, chunk))
+            })
+            .flat_map(|(start, chunk)| {
+                chunk
+                    .char_indices()
+                    .rev()
+                    .map(move |(idx, c)| (start + idx, c))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan_across_pieces() {
+        let mut piece_table = PieceTable::new("aé\nb");
+        piece_table.insert("c€d", 3);
+
+        let found: Vec<_> = (0..=piece_table.len())
+            .filter(|&offset| piece_table.is_char_boundary(offset))
+            .map(|offset| {
+                (
+                    piece_table.scan_until(offset, |c| c == '\n', Direction::Forward),
+                    piece_table.scan_until(offset, |c| c == '\n', Direction::Backward),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (Some(8), None),
+                (Some(8), None),
+                (Some(8), None),
+                (Some(8), None),
+                (Some(8), None),
+                (Some(8), None),
+                (None, Some(8)),
+                (None, Some(8)),
+            ],
+            found
+        );
+    }
+
+    #[test]
+    fn scan_stops_early() {
+        let piece_table = PieceTable::new("abcdef");
+        let mut visited = 0;
+        let found = piece_table.scan_until(
+            4,
+            |_| {
+                visited += 1;
+                true
+            },
+            Direction::Backward,
+        );
+
+        assert_eq!(Some(3), found);
+        assert_eq!(1, visited);
+    }
+
+    #[test]
+    fn matching_brackets_across_pieces() {
+        let mut piece_table = PieceTable::new("fn f() {}");
+        piece_table.insert("\n    if x { [y] }\n", 8);
+
+        assert_eq!(Some(26), piece_table.find_matching_bracket(7));
+        assert_eq!(Some(7), piece_table.find_matching_bracket(26));
+        assert_eq!(Some(24), piece_table.find_matching_bracket(18));
+        assert_eq!(Some(20), piece_table.find_matching_bracket(22));
+        assert_eq!(Some(4), piece_table.find_matching_bracket(5));
+        assert_eq!(None, piece_table.find_matching_bracket(10));
+    }
+
+    #[test]
+    fn unmatched_bracket() {
+        let piece_table = PieceTable::new("(a (b)");
+
+        assert_eq!(None, piece_table.find_matching_bracket(0));
+        assert_eq!(Some(5), piece_table.find_matching_bracket(3));
+        assert_eq!(None, piece_table.find_matching_bracket(6));
+    }
+}
@@ -0,0 +1,301 @@
+//! A trigram index for fast repeated searches, kept up to date across edits.
+
+use std::fmt;
+use std::ops::Range;
+
+use fxhash::FxHashMap;
+
+use crate::PieceTable;
+use crate::interface::EditableText;
+use crate::range_set::Bias;
+
+/// A trigram index of a document.
+///
+/// For every sequence of 3 bytes in the document, the index stores the sorted offsets where it
+/// occurs. A needle of at least 3 bytes occurs at an offset exactly when each of its trigrams
+/// occurs at the corresponding offset, so searching only looks at the offsets of the needle's
+/// rarest trigram, and never at the text itself.
+///
+/// The index is updated incrementally: after each edit it must be notified with
+/// [`SearchIndex::insert`] or [`SearchIndex::delete`], which only re-read the text around the
+/// edit. [`IndexedPieceTable`] does this automatically.
+///
+/// Offsets are stored as they are in the document, so every edit still shifts all the offsets
+/// after it, which takes time proportional to the size of the index, i.e. to the length of the
+/// document. Each keystroke is slower the larger the document is, see the `indexed_edit`
+/// benchmark.
+#[derive(Debug, Clone, Default)]
+pub struct SearchIndex {
+    trigrams: FxHashMap<[u8; 3], Vec<usize>>,
+    len: usize,
+}
+
+impl SearchIndex {
+    /// Builds the index for the current contents of `doc`.
+    pub fn new(doc: &PieceTable) -> Self {
+        let mut index = SearchIndex::default();
+        index.insert(doc, 0, doc.len());
+        index
+    }
+
+    /// Updates the index after `len` bytes were inserted into `doc` at `offset`.
+    ///
+    /// `doc` must be the document after the insertion. Takes time proportional to `len` plus
+    /// the size of the index.
+    pub fn insert(&mut self, doc: &PieceTable, offset: usize, len: usize) {
+        self.edit(doc, offset, 0, len);
+    }
+
+    /// Updates the index after `range` was deleted from `doc`.
+    ///
+    /// `doc` must be the document after the deletion. Takes time proportional to the size of
+    /// the index.
+    pub fn delete(&mut self, doc: &PieceTable, range: Range<usize>) {
+        self.edit(doc, range.start, range.len(), 0);
+    }
+
+    /// Returns the byte offsets of all non-overlapping occurrences of `needle`, in order.
+    ///
+    /// This matches the results of [`str::match_indices`] on the document's text. Needles
+    /// shorter than 3 bytes can't be looked up in the index, and are searched for in `doc`
+    /// directly.
+    pub fn find_all(&self, doc: &PieceTable, needle: &str) -> Vec<usize> {
+        let needle = needle.as_bytes();
+        if needle.len() < 3 {
+            let text = doc.as_string();
+            return text
+                .match_indices(std::str::from_utf8(needle).unwrap())
+                .map(|(offset, _)| offset)
+                .collect();
+        }
+
+        let mut lists = Vec::with_capacity(needle.len() - 2);
+        for (i, trigram) in needle.windows(3).enumerate() {
+            match self.trigrams.get(trigram) {
+                Some(offsets) => lists.push((i, offsets)),
+                None => return Vec::new(),
+            }
+        }
+        let &(rarest, candidates) = lists
+            .iter()
+            .min_by_key(|(_, offsets)| offsets.len())
+            .unwrap();
+
+        let mut found = Vec::new();
+        let mut next_allowed = 0;
+        for &candidate in candidates {
+            let Some(start) = candidate.checked_sub(rarest) else {
+                continue;
+            };
+            if start >= next_allowed
+                && lists
+                    .iter()
+                    .all(|(i, offsets)| offsets.binary_search(&(start + i)).is_ok())
+            {
+                found.push(start);
+                next_allowed = start + needle.len();
+            }
+        }
+        found
+    }
+
+    /// Replaces `removed` bytes at `offset` with `inserted` bytes, `doc` being the document
+    /// after the edit.
+    fn edit(&mut self, doc: &PieceTable, offset: usize, removed: usize, inserted: usize) {
+        // Trigrams starting up to 2 bytes before the edit overlap it, so they're removed and
+        // re-read along with the ones in the inserted text
+        let start = offset.saturating_sub(2);
+        let end = offset + removed;
+        self.trigrams.retain(|_, offsets| {
+            let first = offsets.partition_point(|&o| o < start);
+            let last = offsets.partition_point(|&o| o < end);
+            offsets.drain(first..last);
+            for o in &mut offsets[first..] {
+                *o = *o - removed + inserted;
+            }
+            !offsets.is_empty()
+        });
+        self.len = self.len - removed + inserted;
+        debug_assert_eq!(self.len, doc.len());
+
+        // Trigrams may start or end inside a multi-byte char, so the text around them is read
+        // from the nearest char boundaries
+        let new_end = offset + inserted;
+        let read_start = doc.clamp_to_char_boundary(start, Bias::Left);
+        let read_end = doc.clamp_to_char_boundary(new_end + 2, Bias::Right);
+        let text = String::from(&doc.slice(read_start..read_end));
+        for (i, trigram) in text.as_bytes().windows(3).enumerate() {
+            let pos = read_start + i;
+            if pos < start || pos >= new_end {
+                continue;
+            }
+            let offsets = self
+                .trigrams
+                .entry(trigram.try_into().unwrap())
+                .or_default();
+            let idx = offsets.partition_point(|&o| o < pos);
+            offsets.insert(idx, pos);
+        }
+    }
+}
+
+/// A `PieceTable` together with a [`SearchIndex`] which is updated on every edit.
+///
+/// # Examples
+///
+/// ```
+/// # use piece_table::interface::EditableText;
+/// # use piece_table::search::IndexedPieceTable;
+/// let mut doc = IndexedPieceTable::new("let a = b; let c = d;");
+/// doc.insert("let e = f; ", 0);
+/// assert_eq!(doc.find_all("let"), vec![0, 11, 22]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct IndexedPieceTable<'a> {
+    table: PieceTable<'a>,
+    index: SearchIndex,
+}
+
+impl<'a> IndexedPieceTable<'a> {
+    /// Builds the index for `table` and attaches it.
+    pub fn wrap(table: PieceTable<'a>) -> Self {
+        let index = SearchIndex::new(&table);
+        IndexedPieceTable { table, index }
+    }
+
+    /// Returns the wrapped table.
+    pub fn table(&self) -> &PieceTable<'a> {
+        &self.table
+    }
+
+    /// Returns the index of the wrapped table.
+    pub fn index(&self) -> &SearchIndex {
+        &self.index
+    }
+
+    /// Returns the byte offsets of all non-overlapping occurrences of `needle`, see
+    /// [`SearchIndex::find_all`].
+    pub fn find_all(&self, needle: &str) -> Vec<usize> {
+        self.index.find_all(&self.table, needle)
+    }
+
+    /// Detaches the index, returning the wrapped table.
+    pub fn into_inner(self) -> PieceTable<'a> {
+        self.table
+    }
+}
+
+impl<'a> EditableText<'a> for IndexedPieceTable<'a> {
+    fn new(string: &'a str) -> Self {
+        IndexedPieceTable::wrap(PieceTable::new(string))
+    }
+
+    fn insert(&mut self, data: &str, offset: usize) {
+        self.table.insert(data, offset);
+        self.index.insert(&self.table, offset, data.len());
+    }
+
+    fn delete(&mut self, range: Range<usize>) {
+        self.table.delete(range.clone());
+        self.index.delete(&self.table, range);
+    }
+}
+
+impl fmt::Display for IndexedPieceTable<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.table.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workload::{Profile, generate};
+
+    fn expected(text: &str, needle: &str) -> Vec<usize> {
+        text.match_indices(needle)
+            .map(|(offset, _)| offset)
+            .collect()
+    }
+
+    #[test]
+    fn find_in_original() {
+        let doc = IndexedPieceTable::new("abcabcab");
+
+        assert_eq!(vec![0, 3], doc.find_all("abc"));
+        assert_eq!(vec![1], doc.find_all("bcab"));
+        assert_eq!(vec![0, 3, 6], doc.find_all("ab"));
+        assert_eq!(Vec::<usize>::new(), doc.find_all("abd"));
+    }
+
+    #[test]
+    fn overlapping_occurrences_are_skipped() {
+        let doc = IndexedPieceTable::new("aaaaaaa");
+
+        assert_eq!(vec![0, 3], doc.find_all("aaa"));
+        assert_eq!(vec![0], doc.find_all("aaaa"));
+    }
+
+    #[test]
+    fn insert_updates_boundary_trigrams() {
+        let mut doc = IndexedPieceTable::new("hello world");
+
+        doc.insert("XX", 5);
+
+        assert_eq!(vec![0], doc.find_all("helloXX"));
+        assert_eq!(Vec::<usize>::new(), doc.find_all("lo w"));
+        assert_eq!(vec![6], doc.find_all("X w"));
+        assert_eq!(vec![8], doc.find_all("world"));
+    }
+
+    #[test]
+    fn delete_updates_boundary_trigrams() {
+        let mut doc = IndexedPieceTable::new("one two three");
+
+        doc.delete(3..8);
+
+        assert_eq!("onethree", doc.to_string());
+        assert_eq!(vec![0], doc.find_all("onet"));
+        assert_eq!(Vec::<usize>::new(), doc.find_all("two"));
+        assert_eq!(vec![3], doc.find_all("three"));
+    }
+
+    #[test]
+    fn delete_everything() {
+        let mut doc = IndexedPieceTable::new("abcdef");
+
+        doc.delete(0..6);
+        doc.insert("xyz", 0);
+
+        assert_eq!(vec![0], doc.find_all("xyz"));
+        assert_eq!(Vec::<usize>::new(), doc.find_all("abc"));
+    }
+
+    #[test]
+    fn multibyte_chars_around_edits() {
+        let mut doc = IndexedPieceTable::new("aé€b");
+
+        doc.insert("ü", 3);
+        doc.delete(5..8);
+
+        assert_eq!("aéüb", doc.to_string());
+        assert_eq!(vec![1], doc.find_all("éü"));
+        assert_eq!(vec![3], doc.find_all("üb"));
+        assert_eq!(SearchIndex::new(doc.table()).trigrams, doc.index().trigrams);
+    }
+
+    #[test]
+    fn matches_match_indices_after_random_edits() {
+        let initial = "the piece table stores the text in pieces; the table is fast\n";
+        for seed in 0..8 {
+            let mut doc = IndexedPieceTable::new(initial);
+            generate(seed, Profile::FindReplace, initial, 200).replay(&mut doc);
+
+            let text = doc.to_string();
+            for needle in ["the", "table", "e t", "piece", "in", "\n"] {
+                assert_eq!(expected(&text, needle), doc.find_all(needle), "{needle:?}");
+            }
+            assert_eq!(SearchIndex::new(doc.table()).trigrams, doc.index().trigrams);
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Finding out whether two tables share their text, without reading it.
+//!
+//! Clones and slices of a table refer to the same original text and the same segments of the
+//! "added" buffer, which are never written to once shared. So two pieces whose text is at the
+//! same address hold the same bytes, and comparing addresses tells whether a snapshot still
+//! matches the document, or how much of it is left, in time proportional to the number of
+//! pieces.
+
+use crate::{NodeKind, PieceTable};
+
+/// A run of text of a document, in storage order.
+#[derive(Debug, PartialEq, Eq)]
+enum Run<'s> {
+    /// Bytes in the original text or the "added" buffer, by address.
+    Stored { start: usize, len: usize },
+    /// Bytes stored in a piece itself, which are copied along with it.
+    Inline(&'s str),
+    /// A char repeated `len` times, which isn't stored anywhere.
+    Repeat { byte: u8, len: usize },
+}
+
+impl PieceTable<'_> {
+    /// Checks if both tables refer to the same stored text, in the same order.
+    ///
+    /// This holds for a clone of a table until either of them is edited, and a table whose
+    /// edits were undone by hand usually no longer holds it. If it holds the documents are
+    /// equal, but equal documents built from separate copies of their text don't share any of
+    /// it, so this doesn't hold for them. How the text is split into pieces doesn't matter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("hello");
+    /// pt.insert(" world", 5);
+    /// let saved = pt.clone();
+    /// assert!(pt.structurally_equal(&saved));
+    ///
+    /// pt.insert("!", 11);
+    /// assert!(!pt.structurally_equal(&saved));
+    /// pt.delete(11..12);
+    /// assert!(pt.structurally_equal(&saved));
+    ///
+    /// assert!(!pt.structurally_equal(&PieceTable::new("hello world")));
+    /// ```
+    pub fn structurally_equal(&self, other: &PieceTable) -> bool {
+        self.len == other.len && self.runs().eq(other.runs())
+    }
+
+    /// Returns how many bytes of this document are stored text which `other` also refers to.
+    ///
+    /// This is the part of a snapshot which survived the edits made since, e.g. for deciding
+    /// whether re-rendering or re-saving it in full is worth it. Text stored in the pieces
+    /// themselves, i.e. single chars typed between pieces, and runs of a repeated char, which
+    /// aren't stored at all, aren't counted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("one two three");
+    /// let saved = pt.clone();
+    /// pt.delete(4..8);
+    /// pt.insert("2 ", 4);
+    /// assert_eq!(pt.shared_bytes_with(&saved), 9);
+    /// ```
+    pub fn shared_bytes_with(&self, other: &PieceTable) -> usize {
+        let mut theirs: Vec<(usize, usize)> = Vec::new();
+        let mut stored: Vec<_> = other.stored_spans().collect();
+        stored.sort_unstable();
+        for (start, end) in stored {
+            match theirs.last_mut() {
+                Some(last) if last.1 >= start => last.1 = last.1.max(end),
+                _ => theirs.push((start, end)),
+            }
+        }
+
+        self.stored_spans()
+            .map(|(start, end)| {
+                // The spans of `other` which may overlap this one, from the last one starting
+                // before it
+                let first = theirs.partition_point(|&(_, their_end)| their_end <= start);
+                theirs[first..]
+                    .iter()
+                    .take_while(|&&(their_start, _)| their_start < end)
+                    .map(|&(their_start, their_end)| end.min(their_end) - start.max(their_start))
+                    .sum::<usize>()
+            })
+            .sum()
+    }
+
+    /// Returns the address ranges of the stored text of the pieces, skipping inline pieces.
+    fn stored_spans(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.runs().filter_map(|run| match run {
+            Run::Stored { start, len } => Some((start, start + len)),
+            Run::Inline(_) | Run::Repeat { .. } => None,
+        })
+    }
+
+    /// Returns the runs of text of the document, merging pieces stored next to each other.
+    fn runs(&self) -> impl Iterator<Item = Run<'_>> {
+        let mut runs =
+            self.nodes
+                .iter()
+                .filter(|node| !node.range.is_empty())
+                .map(|node| match node.kind {
+                    NodeKind::Inline(_) => Run::Inline(node.text(self.original, &self.added)),
+                    NodeKind::Original | NodeKind::Added => {
+                        let text = node.text(self.original, &self.added);
+                        Run::Stored {
+                            start: text.as_ptr() as usize,
+                            len: text.len(),
+                        }
+                    }
+                    NodeKind::Repeat(byte) => Run::Repeat {
+                        byte,
+                        len: node.range.len(),
+                    },
+                });
+        let mut pending = runs.next();
+        std::iter::from_fn(move || {
+            let mut run = pending.take()?;
+            for next in runs.by_ref() {
+                match (&mut run, &next) {
+                    (
+                        Run::Stored { start, len },
+                        Run::Stored {
+                            start: next_start,
+                            len: next_len,
+                        },
+                    ) if *start + *len == *next_start => {
+                        *len += next_len;
+                    }
+                    (
+                        Run::Repeat { byte, len },
+                        Run::Repeat {
+                            byte: next_byte,
+                            len: next_len,
+                        },
+                    ) if byte == next_byte => {
+                        *len += next_len;
+                    }
+                    _ => {
+                        pending = Some(next);
+                        break;
+                    }
+                }
+            }
+            Some(run)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splitting_pieces_keeps_structure() {
+        let mut piece_table = PieceTable::new("abcdef");
+        piece_table.insert("123", 3);
+        let saved = piece_table.clone();
+        piece_table.split_at_offset(1);
+        piece_table.split_at_offset(4);
+
+        assert!(piece_table.structurally_equal(&saved));
+        assert!(saved.structurally_equal(&piece_table));
+        assert_eq!(9, piece_table.shared_bytes_with(&saved));
+    }
+
+    #[test]
+    fn inline_pieces_compare_by_content() {
+        let mut piece_table = PieceTable::new("ab");
+        piece_table.insert_char(1, 'x');
+        let saved = piece_table.clone();
+        assert!(piece_table.structurally_equal(&saved));
+        assert_eq!(2, piece_table.shared_bytes_with(&saved));
+
+        piece_table.delete(1..2);
+        piece_table.insert_char(1, 'y');
+        assert!(!piece_table.structurally_equal(&saved));
+    }
+
+    #[test]
+    fn shared_bytes_after_edits_on_both_sides() {
+        let mut piece_table = PieceTable::new("0123456789");
+        let mut other = piece_table.clone();
+        piece_table.delete(2..5);
+        other.delete(7..10);
+        other.insert("abc", 0);
+
+        // Both keep "01" and "56"
+        assert_eq!(4, piece_table.shared_bytes_with(&other));
+        assert_eq!(4, other.shared_bytes_with(&piece_table));
+        let copy = "0123456789".to_string();
+        assert_eq!(0, piece_table.shared_bytes_with(&PieceTable::new(&copy)));
+    }
+}
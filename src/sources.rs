@@ -0,0 +1,220 @@
+//! Read-only texts attached to a table, whose parts can be inserted without copying them.
+//!
+//! Besides the text a table is created with, any number of texts which outlive it can be
+//! attached as sources, e.g. the other files of a project, or the chunks of a file read one at a
+//! time. Attaching a source doesn't copy it, and inserting a range of a source refers to the
+//! text where it is, so the same text can be transcluded any number of times.
+//!
+//! The original text is source 0, and the attached ones are borrowed segments of the "added"
+//! buffer, which are never written to.
+
+use std::ops::Range;
+
+use crate::error::EditError;
+use crate::{Node, NodeKind, PieceTable, VALIDATE};
+
+/// Identifies a source of a table, see [`PieceTable::attach_source`].
+///
+/// Sources are numbered in the order they're attached, starting from 1, since the text the
+/// table was created with is [`SourceId::ORIGINAL`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SourceId(usize);
+
+impl SourceId {
+    /// The text the table was created with.
+    pub const ORIGINAL: SourceId = SourceId(0);
+
+    /// Returns the number of the source.
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+impl<'ptable> PieceTable<'ptable> {
+    /// Creates a new `PieceTable` containing the concatenation of `sources`, none of which is
+    /// copied.
+    ///
+    /// The first source is the original text, and the others are attached in order, so the
+    /// source at index `i` gets the [`SourceId`] with index `i`. Unlike
+    /// [`PieceTable::from_chunks`], the text is referenced where it is, so the sources must
+    /// outlive the table.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let pt = PieceTable::from_sources(["mod a;\n", "mod b;\n", "mod c;\n"]);
+    /// assert_eq!(pt.to_string(), "mod a;\nmod b;\nmod c;\n");
+    /// assert_eq!(pt.source_ids().count(), 3);
+    /// ```
+    pub fn from_sources<I: IntoIterator<Item = &'ptable str>>(sources: I) -> Self {
+        let mut sources = sources.into_iter();
+        let mut table = PieceTable::new(sources.next().unwrap_or(""));
+        for source in sources {
+            let id = table.attach_source(source);
+            table.insert_from_source(id, 0..source.len(), table.len());
+        }
+        table
+    }
+
+    /// Attaches `text` as a source of the table without copying it, and returns its ID.
+    ///
+    /// The text isn't part of the document until some of it is inserted with
+    /// [`PieceTable::insert_from_source`].
+    pub fn attach_source(&mut self, text: &'ptable str) -> SourceId {
+        let range = self.added.push_borrowed(text);
+        self.sources.push((range.start, text));
+        SourceId(self.sources.len())
+    }
+
+    /// Returns the text of source `id`, or `None` if the table has no such source.
+    pub fn source(&self, id: SourceId) -> Option<&'ptable str> {
+        match id.0 {
+            0 => Some(self.original),
+            idx => self.sources.get(idx - 1).map(|&(_, text)| text),
+        }
+    }
+
+    /// Returns the IDs of the sources of the table, starting with [`SourceId::ORIGINAL`].
+    pub fn source_ids(&self) -> impl Iterator<Item = SourceId> {
+        (0..=self.sources.len()).map(SourceId)
+    }
+
+    /// Inserts the text in `range` of source `id` at byte `offset` of the document, without
+    /// copying it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let header = "// Copyright (c) Example\n// SPDX-License-Identifier: MIT\n";
+    /// let mut pt = PieceTable::new("fn main() {}\n");
+    /// let license = pt.attach_source(header);
+    /// pt.insert_from_source(license, 25..57, 0);
+    /// assert_eq!(pt.to_string(), "// SPDX-License-Identifier: MIT\nfn main() {}\n");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the table has no source `id`, or if `range` isn't a valid range of it. In debug
+    /// builds, or when the `strict` feature is enabled, also panics if `offset` is out of bounds
+    /// or not on a char boundary.
+    pub fn insert_from_source(&mut self, id: SourceId, range: Range<usize>, offset: usize) {
+        let Some(source) = self.source(id) else {
+            panic!(
+                "invalid insert_from_source: the table has no source {}",
+                id.0
+            );
+        };
+        if let Err(err) = check_source_range(source, &range) {
+            panic!("invalid insert_from_source: {err}");
+        }
+        if VALIDATE && let Err(err) = self.check_offset(offset) {
+            panic!("invalid insert_from_source: {err}");
+        }
+        if let Err(err) = self.check_growth(range.len(), 0, 1 + self.splits_at(offset)) {
+            panic!("invalid insert_from_source: {err}");
+        }
+        if range.is_empty() {
+            return;
+        }
+
+        let text = &source[range.clone()];
+        let node = match id.0 {
+            0 => Node::new(NodeKind::Original, range, text),
+            idx => {
+                let (start, _) = self.sources[idx - 1];
+                Node::new(
+                    NodeKind::Added,
+                    start + range.start..start + range.end,
+                    text,
+                )
+            }
+        };
+        let insert_idx = self.split_at_offset(offset);
+        self.splice_node(insert_idx, offset, node);
+        self.publish();
+    }
+}
+
+/// Checks that `range` is a valid range of `source`.
+fn check_source_range(source: &str, range: &Range<usize>) -> Result<(), EditError> {
+    if range.start > range.end {
+        return Err(EditError::InvalidRange {
+            start: range.start,
+            end: range.end,
+        });
+    }
+    for offset in [range.start, range.end] {
+        if offset > source.len() {
+            return Err(EditError::OutOfBounds {
+                offset,
+                len: source.len(),
+            });
+        }
+        if !source.is_char_boundary(offset) {
+            return Err(EditError::NotCharBoundary { offset });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transcluded_ranges_refer_to_the_source() {
+        let readme = String::from("# Title\nbody\n");
+        let mut piece_table = PieceTable::new("a\nb\n");
+        let id = piece_table.attach_source(&readme);
+        assert_eq!(Some(readme.as_str()), piece_table.source(id));
+        assert_eq!(None, piece_table.source(SourceId(2)));
+
+        piece_table.insert_from_source(id, 0..8, 2);
+        piece_table.insert_from_source(id, 8..13, 10);
+        piece_table.insert_from_source(SourceId::ORIGINAL, 0..2, 0);
+        assert_eq!("a\na\n# Title\nbody\nb\n", piece_table.to_string());
+        assert_eq!(6, piece_table.len_lines());
+        assert_eq!(Ok(()), piece_table.check_invariants());
+        // The two consecutive ranges of the source are merged into one piece
+        assert_eq!(4, piece_table.pieces().count());
+        assert_eq!(0, piece_table.added.owned_len());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid insert_from_source: offset 1 is not on a char boundary")]
+    fn source_range_inside_a_char_panics() {
+        let mut piece_table = PieceTable::new("");
+        let id = piece_table.attach_source("é");
+        piece_table.insert_from_source(id, 0..2, 0);
+        piece_table.insert_from_source(id, 1..2, 0);
+    }
+
+    #[test]
+    fn split_off_keeps_the_sources() {
+        let mut piece_table = PieceTable::from_sources(["a\n", "b\n", "c\n"]);
+        let mut rest = piece_table.split_off(2);
+        assert_eq!(Some("c\n"), rest.source(SourceId(2)));
+
+        rest.insert_from_source(SourceId(2), 0..2, 4);
+        assert_eq!("b\nc\nc\n", rest.to_string());
+        assert_eq!(Ok(()), rest.check_invariants());
+    }
+
+    #[test]
+    fn append_table_keeps_the_sources() {
+        let mut piece_table = PieceTable::from_sources(["a\n", "b\n"]);
+        let mut other = PieceTable::new("x\n");
+        other.insert("y\n", 2);
+        let id = other.attach_source("z\n");
+
+        piece_table.append_table(other);
+        assert_eq!(Some("z\n"), piece_table.source(SourceId(2)));
+        assert_eq!(1, id.index());
+
+        piece_table.insert_from_source(SourceId(2), 0..2, 0);
+        assert_eq!("z\na\nb\nx\ny\n", piece_table.to_string());
+        assert_eq!(Ok(()), piece_table.check_invariants());
+    }
+}
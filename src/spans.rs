@@ -0,0 +1,278 @@
+//! Values attached to ranges of a document, kept in place across edits.
+
+use std::ops::Range;
+
+/// What happens to a span when text is inserted strictly inside it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InsertMode {
+    /// The span grows to cover the inserted text, e.g. for folding regions.
+    #[default]
+    Expand,
+    /// The span is split in two around the inserted text, e.g. for syntax highlighting, where
+    /// the new text isn't highlighted until it's re-parsed.
+    Split,
+}
+
+/// An overlay of (possibly overlapping) byte ranges of a document, each with a value.
+///
+/// This is meant for highlights, diagnostics and folding regions. After each edit to the
+/// document, [`Spans::on_insert`] or [`Spans::on_delete`] must be called, which shift the spans
+/// after the edit, grow or split the spans around it and clip or remove the spans it deleted.
+///
+/// Text inserted at the start of a span is placed before it, and text inserted at its end is
+/// placed after it, so only insertions strictly inside a span affect its length.
+///
+/// # Examples
+///
+/// ```
+/// # use piece_table::PieceTable;
+/// # use piece_table::spans::{InsertMode, Spans};
+/// let mut pt = PieceTable::new("let x = 1;");
+/// let mut highlights = Spans::new(InsertMode::Split);
+/// highlights.add(0..3, "keyword");
+/// highlights.add(8..9, "number");
+///
+/// pt.insert("mut ", 4);
+/// highlights.on_insert(4, 4);
+///
+/// let spans: Vec<_> = highlights.iter().collect();
+/// assert_eq!(spans, vec![(0..3, &"keyword"), (12..13, &"number")]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Spans<T> {
+    /// Sorted by start offset
+    spans: Vec<(Range<usize>, T)>,
+    mode: InsertMode,
+}
+
+impl<T> Spans<T> {
+    /// Creates an empty overlay, with the given behavior for insertions inside spans.
+    pub fn new(mode: InsertMode) -> Self {
+        Spans {
+            spans: Vec::new(),
+            mode,
+        }
+    }
+
+    /// Returns the number of spans.
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    /// Checks if there are no spans.
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// Adds a span covering `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range.start > range.end`.
+    pub fn add(&mut self, range: Range<usize>, value: T) {
+        assert!(
+            range.start <= range.end,
+            "invalid span: range start {} is greater than range end {}",
+            range.start,
+            range.end
+        );
+        let idx = self.spans.partition_point(|(r, _)| r.start <= range.start);
+        self.spans.insert(idx, (range, value));
+    }
+
+    /// Removes every span.
+    pub fn clear(&mut self) {
+        self.spans.clear();
+    }
+
+    /// Removes every span for which `f` returns `false`.
+    pub fn retain(&mut self, mut f: impl FnMut(&Range<usize>, &T) -> bool) {
+        self.spans.retain(|(range, value)| f(range, value));
+    }
+
+    /// Returns an iterator over all spans, ordered by their start.
+    pub fn iter(&self) -> impl Iterator<Item = (Range<usize>, &T)> {
+        self.spans
+            .iter()
+            .map(|(range, value)| (range.clone(), value))
+    }
+
+    /// Returns an iterator over the spans containing the byte at `offset`.
+    pub fn at(&self, offset: usize) -> impl Iterator<Item = (Range<usize>, &T)> {
+        self.overlapping(offset..offset + 1)
+    }
+
+    /// Returns an iterator over the spans which share at least one byte with `range`.
+    pub fn overlapping(&self, range: Range<usize>) -> impl Iterator<Item = (Range<usize>, &T)> {
+        let end = self.spans.partition_point(|(r, _)| r.start < range.end);
+        self.spans[..end]
+            .iter()
+            .filter(move |(r, _)| r.end > range.start && !r.is_empty())
+            .map(|(range, value)| (range.clone(), value))
+    }
+
+    /// Updates the spans after `len` bytes were inserted at `offset`.
+    pub fn on_insert(&mut self, offset: usize, len: usize)
+    where
+        T: Clone,
+    {
+        if len == 0 {
+            return;
+        }
+
+        let mut split_off = Vec::new();
+        for (range, value) in &mut self.spans {
+            if range.start >= offset && range.end > offset {
+                range.start += len;
+                range.end += len;
+            } else if range.end > offset {
+                match self.mode {
+                    InsertMode::Expand => range.end += len,
+                    InsertMode::Split => {
+                        split_off.push((offset + len..range.end + len, value.clone()));
+                        range.end = offset;
+                    }
+                }
+            }
+        }
+        // Empty spans at `offset` stay before the inserted text while the other spans starting
+        // there move after it, which is the only way the order can change
+        self.spans.sort_by_key(|(range, _)| range.start);
+
+        for (range, value) in split_off {
+            self.add(range, value);
+        }
+    }
+
+    /// Updates the spans after `range` was deleted.
+    ///
+    /// Spans which are entirely inside `range` are removed, and spans which partially overlap
+    /// it are clipped.
+    pub fn on_delete(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+
+        let map = |offset: usize| {
+            if offset <= range.start {
+                offset
+            } else if offset >= range.end {
+                offset - range.len()
+            } else {
+                range.start
+            }
+        };
+        self.spans.retain_mut(|(span, _)| {
+            let inside = if span.start == span.end {
+                range.start < span.start && span.start < range.end
+            } else {
+                range.start <= span.start && span.end <= range.end
+            };
+            *span = map(span.start)..map(span.end);
+            !inside
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect(spans: &Spans<char>) -> Vec<(Range<usize>, char)> {
+        spans.iter().map(|(range, &c)| (range, c)).collect()
+    }
+
+    #[test]
+    fn insert_before_at_and_after_spans() {
+        let mut spans = Spans::new(InsertMode::Expand);
+        spans.add(2..4, 'a');
+        spans.add(6..8, 'b');
+
+        spans.on_insert(2, 1);
+        spans.on_insert(5, 1);
+        spans.on_insert(0, 2);
+
+        assert_eq!(vec![(5..7, 'a'), (10..12, 'b')], collect(&spans));
+    }
+
+    #[test]
+    fn insert_inside_expands() {
+        let mut spans = Spans::new(InsertMode::Expand);
+        spans.add(0..10, 'f');
+        spans.add(2..4, 'g');
+
+        spans.on_insert(3, 5);
+
+        assert_eq!(vec![(0..15, 'f'), (2..9, 'g')], collect(&spans));
+    }
+
+    #[test]
+    fn insert_inside_splits() {
+        let mut spans = Spans::new(InsertMode::Split);
+        spans.add(0..10, 'f');
+        spans.add(2..4, 'g');
+
+        spans.on_insert(3, 5);
+
+        assert_eq!(
+            vec![(0..3, 'f'), (2..3, 'g'), (8..15, 'f'), (8..9, 'g')],
+            collect(&spans)
+        );
+    }
+
+    #[test]
+    fn delete_clips_and_removes() {
+        let mut spans = Spans::new(InsertMode::Expand);
+        spans.add(0..3, 'a');
+        spans.add(3..5, 'b');
+        spans.add(4..8, 'c');
+        spans.add(9..10, 'd');
+
+        spans.on_delete(2..6);
+
+        assert_eq!(vec![(0..2, 'a'), (2..4, 'c'), (5..6, 'd')], collect(&spans));
+    }
+
+    #[test]
+    fn empty_spans() {
+        let mut spans = Spans::new(InsertMode::Expand);
+        spans.add(2..2, 'a');
+        spans.add(5..5, 'b');
+
+        spans.on_insert(2, 3);
+        spans.on_delete(4..9);
+
+        assert_eq!(vec![(2..2, 'a')], collect(&spans));
+        assert_eq!(0, spans.at(2).count());
+    }
+
+    #[test]
+    fn insert_keeps_empty_and_non_empty_spans_sorted() {
+        let mut spans = Spans::new(InsertMode::Expand);
+        spans.add(2..4, 'x');
+        spans.add(2..2, 'y');
+        spans.add(2..6, 'z');
+
+        spans.on_insert(2, 3);
+
+        assert_eq!(vec![(2..2, 'y'), (5..7, 'x'), (5..9, 'z')], collect(&spans));
+        let at: Vec<_> = spans.at(5).map(|(_, &c)| c).collect();
+        assert_eq!(vec!['x', 'z'], at);
+        spans.add(3..4, 'w');
+        assert_eq!(Some((3..4, &'w')), spans.iter().nth(1));
+    }
+
+    #[test]
+    fn queries() {
+        let mut spans = Spans::new(InsertMode::Expand);
+        spans.add(0..10, 'f');
+        spans.add(2..4, 'g');
+        spans.add(6..8, 'h');
+
+        let at: Vec<_> = spans.at(3).map(|(_, &c)| c).collect();
+        let overlapping: Vec<_> = spans.overlapping(4..7).map(|(_, &c)| c).collect();
+
+        assert_eq!(vec!['f', 'g'], at);
+        assert_eq!(vec!['f', 'h'], overlapping);
+    }
+}
@@ -0,0 +1,227 @@
+//! Editing a document while it's still being loaded.
+//!
+//! Opening a huge file shouldn't have to wait for all of it to be read. A streaming table starts
+//! out empty and is fed the file chunk by chunk, e.g. from a channel filled by a background
+//! thread, while the part loaded so far can already be displayed and edited.
+
+use std::ops::Range;
+
+use crate::PieceTable;
+use crate::newlines::count_newlines;
+
+/// The loading state of a `PieceTable`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Stream {
+    /// Where the next chunk goes, or `None` if the table isn't loading
+    point: Option<usize>,
+    /// The ranges of the "added" buffer holding loaded text, sorted and with adjacent ranges
+    /// merged
+    loaded: Vec<Range<usize>>,
+}
+
+impl Stream {
+    /// Checks if byte `offset` of the "added" buffer holds loaded text.
+    pub(crate) fn is_loaded(&self, offset: usize) -> bool {
+        let idx = self.loaded.partition_point(|range| range.end <= offset);
+        self.loaded
+            .get(idx)
+            .is_some_and(|range| range.start <= offset)
+    }
+
+    /// Returns where the loaded text in `range` of the "added" buffer is in the file being
+    /// loaded, or `None` if not all of it is loaded text from a single chunk run.
+    pub(crate) fn file_range(&self, range: &Range<usize>) -> Option<Range<usize>> {
+        let idx = self
+            .loaded
+            .partition_point(|loaded| loaded.end <= range.start);
+        let loaded = self.loaded.get(idx)?;
+        if loaded.start > range.start || loaded.end < range.end {
+            return None;
+        }
+        let before: usize = self.loaded[..idx].iter().map(Range::len).sum();
+        let start = before + range.start - loaded.start;
+        Some(start..start + range.len())
+    }
+
+    /// Returns the length of the text loaded so far.
+    pub(crate) fn loaded_len(&self) -> usize {
+        self.loaded.iter().map(Range::len).sum()
+    }
+
+    /// Updates the load point after `len` bytes were inserted at `offset`.
+    ///
+    /// Text inserted right at the load point goes before it, so it stays before the rest of the
+    /// document when that's loaded.
+    pub(crate) fn on_insert(&mut self, offset: usize, len: usize) {
+        if let Some(point) = &mut self.point
+            && offset <= *point
+        {
+            *point += len;
+        }
+    }
+
+    /// Updates the load point after `range` was deleted.
+    pub(crate) fn on_delete(&mut self, range: Range<usize>) {
+        if let Some(point) = &mut self.point {
+            if *point >= range.end {
+                *point -= range.len();
+            } else if *point > range.start {
+                *point = range.start;
+            }
+        }
+    }
+
+    /// Moves the load point back to `len` if it's past it, after the document was replaced or
+    /// truncated.
+    pub(crate) fn clamp(&mut self, len: usize) {
+        if let Some(point) = &mut self.point {
+            *point = (*point).min(len);
+        }
+    }
+}
+
+impl PieceTable<'static> {
+    /// Creates an empty table which is loaded with [`PieceTable::feed_original_chunk`].
+    ///
+    /// The table can be edited at any time while loading. Text inserted at the end of the part
+    /// loaded so far stays before the parts loaded later, as if the whole document had been
+    /// there from the start.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new_streaming();
+    /// pt.feed_original_chunk("fn main() {\n");
+    /// pt.insert("// entry point\n", 0);
+    /// pt.insert("    ", 27);
+    /// pt.feed_original_chunk("todo!()\n}\n");
+    /// pt.finish_loading();
+    ///
+    /// assert_eq!(pt.to_string(), "// entry point\nfn main() {\n    todo!()\n}\n");
+    /// assert_eq!(pt.changed_ranges(), vec![0..15, 27..31]);
+    /// ```
+    pub fn new_streaming() -> Self {
+        let mut table = PieceTable::from(String::new());
+        table.stream.point = Some(0);
+        table
+    }
+}
+
+impl PieceTable<'_> {
+    /// Appends the next chunk of the document being loaded.
+    ///
+    /// The chunk is copied into the "added" buffer, but still counts as original text, e.g. for
+    /// [`PieceTable::changed_ranges`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the table isn't loading, i.e. it wasn't created with
+    /// [`PieceTable::new_streaming`] or [`PieceTable::finish_loading`] was already called, or
+    /// if the chunk would exceed one of the table's [`Limits`](crate::limits::Limits).
+    pub fn feed_original_chunk(&mut self, chunk: &str) {
+        let Some(point) = self.stream.point else {
+            panic!("feed_original_chunk called on a table which isn't loading");
+        };
+        if chunk.is_empty() {
+            return;
+        }
+        if let Err(err) = self.check_insert_limits(point, chunk.len()) {
+            panic!("invalid feed_original_chunk: {err}");
+        }
+
+        let start = self.added.len();
+        match self.stream.loaded.last_mut() {
+            Some(last) if last.end == start => last.end += chunk.len(),
+            _ => self.stream.loaded.push(start..start + chunk.len()),
+        }
+        // Loaded text is kept as it is, even if inserted text is normalized
+        self.insert_added(point, chunk, count_newlines(chunk));
+    }
+
+    /// Marks the document as fully loaded, after which chunks can't be fed anymore.
+    pub fn finish_loading(&mut self) {
+        self.stream.point = None;
+    }
+
+    /// Checks if the table is still being loaded.
+    pub fn is_loading(&self) -> bool {
+        self.stream.point.is_some()
+    }
+
+    /// Returns the offset where the next loaded chunk will go, or `None` if the table isn't
+    /// loading.
+    pub fn load_point(&self) -> Option<usize> {
+        self.stream.point
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cursor::PieceSource;
+
+    use super::*;
+
+    #[test]
+    fn edits_interleaved_with_loading() {
+        let mut piece_table = PieceTable::new_streaming();
+        piece_table.feed_original_chunk("one\ntwo\n");
+        piece_table.delete(3..5);
+        assert_eq!(Some(6), piece_table.load_point());
+
+        piece_table.feed_original_chunk("three\n");
+        piece_table.insert("!", 6);
+        piece_table.delete(10..13);
+        assert_eq!(Some(10), piece_table.load_point());
+
+        piece_table.feed_original_chunk("four");
+        piece_table.finish_loading();
+
+        assert_eq!("onewo\n!thrfour", piece_table.to_string());
+        assert_eq!(2, piece_table.len_lines());
+        assert_eq!(vec![6..7], piece_table.changed_ranges());
+        assert_eq!(Ok(()), piece_table.check_invariants());
+    }
+
+    #[test]
+    fn loaded_text_is_never_merged_with_typed_text() {
+        let mut piece_table = PieceTable::new_streaming();
+        piece_table.feed_original_chunk("abc");
+        piece_table.insert("x", 3);
+        piece_table.feed_original_chunk("def");
+        piece_table.insert("y", 7);
+
+        let sources: Vec<_> = piece_table
+            .pieces()
+            .map(|(source, text, ..)| (source, text))
+            .collect();
+        assert_eq!(
+            vec![
+                (PieceSource::Original, "abc"),
+                (PieceSource::Added, "x"),
+                (PieceSource::Original, "def"),
+                (PieceSource::Added, "y"),
+            ],
+            sources
+        );
+    }
+
+    #[test]
+    fn revert_clamps_load_point() {
+        let mut piece_table = PieceTable::new_streaming();
+        piece_table.checkpoint("empty");
+        piece_table.feed_original_chunk("abc");
+        piece_table.revert_to("empty");
+        piece_table.feed_original_chunk("def");
+
+        assert_eq!("def", piece_table.to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "isn't loading")]
+    fn feeding_after_finish_panics() {
+        let mut piece_table = PieceTable::new_streaming();
+        piece_table.finish_loading();
+        piece_table.feed_original_chunk("abc");
+    }
+}
@@ -0,0 +1,176 @@
+//! Converting between tabs and spaces.
+//!
+//! Both conversions scan the text once and replace only the runs of whitespace which change, so
+//! pieces holding no tabs (or no indentation to collapse) are left as they are. Expanding tabs
+//! measures the text before them in display cells, so it requires the `unicode-width` feature.
+
+use std::ops::Range;
+
+use crate::PieceTable;
+use crate::edit_builder::EditBuilder;
+#[cfg(feature = "unicode-width")]
+use crate::point::ColumnUnit;
+
+impl PieceTable<'_> {
+    /// Replaces every tab with the spaces up to the next multiple of `width` display cells, as
+    /// a terminal would show it.
+    ///
+    /// Columns are counted in display cells from the start of each line, see
+    /// [`ColumnUnit::Cells`], so a wide char before a tab takes two cells. A `width` of 0
+    /// removes tabs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("a\tb\n\t\tc");
+    /// pt.expand_tabs(4);
+    /// assert_eq!(pt.to_string(), "a   b\n        c");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the replacements would exceed the table's limits.
+    #[cfg(feature = "unicode-width")]
+    pub fn expand_tabs(&mut self, width: usize) {
+        let unit = ColumnUnit::Cells { tab_width: width };
+        let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+        let mut column = 0;
+        for (offset, c) in self.char_indices_from(0) {
+            let next = unit.advance(column, c);
+            if c == '\t' {
+                let spaces = " ".repeat(next - column);
+                match edits.last_mut() {
+                    Some((range, text)) if range.end == offset => {
+                        range.end += 1;
+                        text.push_str(&spaces);
+                    }
+                    _ => edits.push((offset..offset + 1, spaces)),
+                }
+            }
+            column = if c == '\n' { 0 } else { next };
+        }
+        self.replace_all(edits, "expand_tabs");
+    }
+
+    /// Rewrites the indentation of every line with as many tabs as fit in it, followed by
+    /// spaces, where a tab is `width` display cells wide.
+    ///
+    /// Only the spaces and tabs at the start of each line are changed, since spaces elsewhere,
+    /// e.g. in string literals or aligned comments, often mean something. Lines whose
+    /// indentation is already in this form aren't touched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let mut pt = PieceTable::new("      a  b\n \tc\n");
+    /// pt.collapse_spaces_to_tabs(4);
+    /// assert_eq!(pt.to_string(), "\t  a  b\n\tc\n");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is 0, or if the replacements would exceed the table's limits.
+    pub fn collapse_spaces_to_tabs(&mut self, width: usize) {
+        assert!(width > 0, "invalid collapse_spaces_to_tabs: tab width is 0");
+        let mut edits = Vec::new();
+        let mut indent = String::new();
+        let mut start = 0;
+        let mut column = 0;
+        let mut in_indent = true;
+        let mut collapse = |indent: &mut String, start: usize, column: usize| {
+            let collapsed = "\t".repeat(column / width) + &" ".repeat(column % width);
+            if *indent != collapsed {
+                edits.push((start..start + indent.len(), collapsed));
+            }
+            indent.clear();
+        };
+        for (offset, c) in self.char_indices_from(0) {
+            if in_indent && (c == ' ' || c == '\t') {
+                indent.push(c);
+                column = match c {
+                    '\t' => (column / width + 1) * width,
+                    _ => column + 1,
+                };
+                continue;
+            }
+            if in_indent {
+                collapse(&mut indent, start, column);
+                in_indent = false;
+            }
+            if c == '\n' {
+                start = offset + 1;
+                column = 0;
+                in_indent = true;
+            }
+        }
+        if in_indent {
+            collapse(&mut indent, start, column);
+        }
+        self.replace_all(edits, "collapse_spaces_to_tabs");
+    }
+
+    /// Applies the sorted, non-overlapping `edits` as a single batch.
+    pub(crate) fn replace_all(&mut self, edits: Vec<(Range<usize>, String)>, op: &str) {
+        if edits.is_empty() {
+            return;
+        }
+        let mut builder = EditBuilder::new(self);
+        for (range, text) in edits {
+            builder.replace(range, text);
+        }
+        if let Err(err) = builder.apply(self) {
+            panic!("invalid {op}: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "unicode-width")]
+    fn expand_after_wide_chars() {
+        let mut piece_table = PieceTable::new("日\tx\n\t");
+        piece_table.insert("ab\t", 4);
+        piece_table.expand_tabs(4);
+
+        assert_eq!("日  ab  x\n    ", piece_table.to_string());
+        assert_eq!(Ok(()), piece_table.check_invariants());
+    }
+
+    #[test]
+    #[cfg(feature = "unicode-width")]
+    fn untouched_pieces_are_kept() {
+        let mut piece_table = PieceTable::new("\tfn main() {}\n");
+        piece_table.insert("// no tabs here\n", 0);
+        let pieces = piece_table.nodes.len();
+
+        piece_table.collapse_spaces_to_tabs(4);
+        assert_eq!(pieces, piece_table.nodes.len());
+        piece_table.expand_tabs(2);
+        assert_eq!("// no tabs here\n  fn main() {}\n", piece_table.to_string());
+    }
+
+    #[test]
+    #[cfg(feature = "unicode-width")]
+    fn collapse_round_trips_expanded_indentation() {
+        let text = "\t\tif x {\n\t\t\ty  z\n   \n\t \t";
+        let mut piece_table = PieceTable::new(text);
+        piece_table.expand_tabs(4);
+        piece_table.collapse_spaces_to_tabs(4);
+
+        assert_eq!("\t\tif x {\n\t\t\ty  z\n   \n\t\t", piece_table.to_string());
+        assert_eq!(Ok(()), piece_table.check_invariants());
+    }
+    #[test]
+    fn collapse_counts_tab_stops() {
+        let mut piece_table = PieceTable::new("    a\n  \tb\n\t  c");
+        piece_table.insert("     ", 0);
+        piece_table.collapse_spaces_to_tabs(4);
+
+        assert_eq!("\t\t a\n\tb\n\t  c", piece_table.to_string());
+    }
+}
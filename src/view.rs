@@ -0,0 +1,232 @@
+//! Read-only views of a document with some of its text hidden.
+//!
+//! Renderers often skip parts of a document, e.g. folded regions or the unchanged context of a
+//! diff, but still want to treat the text they show as one contiguous string. A
+//! [`FilteredView`] holds the visible text as a [`PTableSlice`], and maps offsets between the
+//! view and the document.
+
+use std::fmt::{self, Display};
+use std::ops::Range;
+
+use crate::{PTableSlice, PieceTable};
+
+/// A snapshot of a document with some ranges hidden, see [`PieceTable::view_excluding`].
+#[derive(Debug)]
+pub struct FilteredView<'ptable> {
+    visible: PTableSlice<'ptable>,
+    /// The hidden ranges of the document, sorted, non-empty and with adjacent ranges merged
+    hidden: Vec<Range<usize>>,
+    doc_len: usize,
+}
+
+impl<'ptable> PieceTable<'ptable> {
+    /// Returns a view of the text with `ranges` hidden, e.g. an array or a `Vec` of ranges, or a
+    /// single range wrapped in `Some`.
+    ///
+    /// The ranges may be in any order, and may overlap. Like a [`PTableSlice`], the view isn't
+    /// affected by later edits to the table.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::PieceTable;
+    /// let pt = PieceTable::new("fn f() {\n    body();\n}\n");
+    /// let view = pt.view_excluding(Some(9..21));
+    ///
+    /// assert_eq!(view.to_string(), "fn f() {\n}\n");
+    /// assert_eq!(view.view_to_doc(9), 21);
+    /// assert_eq!(view.doc_to_view(14), None);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if a range is out of bounds, or doesn't start and end on char boundaries.
+    pub fn view_excluding<I>(&self, ranges: I) -> FilteredView<'ptable>
+    where
+        I: IntoIterator<Item = Range<usize>>,
+    {
+        let mut sorted: Vec<Range<usize>> = ranges.into_iter().collect();
+        for range in &sorted {
+            if let Err(err) = self.check_range(range) {
+                panic!("invalid view_excluding: {err}");
+            }
+        }
+        sorted.sort_by_key(|range| range.start);
+
+        let mut hidden: Vec<Range<usize>> = Vec::new();
+        for range in sorted.into_iter().filter(|range| !range.is_empty()) {
+            match hidden.last_mut() {
+                Some(last) if last.end >= range.start => last.end = last.end.max(range.end),
+                _ => hidden.push(range),
+            }
+        }
+
+        let mut visible = self.slice(0..0);
+        let mut start = 0;
+        for gap in hidden
+            .iter()
+            .cloned()
+            .chain(std::iter::once(self.len..self.len))
+        {
+            if start < gap.start {
+                visible.nodes.extend(self.slice(start..gap.start).nodes);
+            }
+            start = gap.end;
+        }
+
+        FilteredView {
+            visible,
+            hidden,
+            doc_len: self.len,
+        }
+    }
+}
+
+impl<'ptable> FilteredView<'ptable> {
+    /// Returns the visible text.
+    pub fn as_slice(&self) -> &PTableSlice<'ptable> {
+        &self.visible
+    }
+
+    /// Returns the length of the visible text, in bytes.
+    pub fn len(&self) -> usize {
+        self.doc_len - self.hidden.iter().map(Range::len).sum::<usize>()
+    }
+
+    /// Checks if all of the text is hidden.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the hidden ranges of the document, sorted and with overlapping and adjacent
+    /// ranges merged.
+    pub fn hidden(&self) -> &[Range<usize>] {
+        &self.hidden
+    }
+
+    /// Returns the offset in the view of `offset` in the document, or `None` if it's strictly
+    /// inside a hidden range.
+    ///
+    /// The offsets at either end of a hidden range both map to the place where it was cut out.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is out of bounds of the document.
+    pub fn doc_to_view(&self, offset: usize) -> Option<usize> {
+        assert!(
+            offset <= self.doc_len,
+            "invalid doc_to_view: offset {offset} is out of bounds of document of length {}",
+            self.doc_len
+        );
+        let mut hidden = 0;
+        for range in &self.hidden {
+            if range.start >= offset {
+                break;
+            }
+            if range.end > offset {
+                return None;
+            }
+            hidden += range.len();
+        }
+        Some(offset - hidden)
+    }
+
+    /// Returns the offset in the document of `offset` in the view.
+    ///
+    /// Where text was cut out, this is the offset after the hidden range, i.e. of the next
+    /// visible byte.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is out of bounds of the view.
+    pub fn view_to_doc(&self, offset: usize) -> usize {
+        assert!(
+            offset <= self.len(),
+            "invalid view_to_doc: offset {offset} is out of bounds of view of length {}",
+            self.len()
+        );
+        let mut doc_offset = offset;
+        for range in &self.hidden {
+            if range.start > doc_offset {
+                break;
+            }
+            doc_offset += range.len();
+        }
+        doc_offset
+    }
+
+    /// Returns the range of the document shown by `range` of the view, see
+    /// [`FilteredView::view_to_doc`].
+    ///
+    /// If the range spans a place where text was cut out, the hidden text is part of the
+    /// returned range.
+    pub fn view_range_to_doc(&self, range: Range<usize>) -> Range<usize> {
+        let start = self.view_to_doc(range.start);
+        let end = if range.is_empty() {
+            start
+        } else {
+            // The end of the last visible byte, rather than the start of the next one
+            self.view_to_doc(range.end - 1) + 1
+        };
+        start..end
+    }
+}
+
+impl Display for FilteredView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.visible.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlapping_and_unsorted_ranges() {
+        let mut piece_table = PieceTable::new("0123456789");
+        piece_table.insert("ab", 5);
+        let view = piece_table.view_excluding([8..10, 1..3, 2..4, 4..4, 11..12]);
+
+        assert_eq!("04ab58", view.to_string());
+        assert_eq!(&[1..4, 8..10, 11..12], view.hidden());
+        assert_eq!(6, view.len());
+        assert_eq!(Some(3), view.doc_to_view(6));
+        assert_eq!(Some(1), view.doc_to_view(1));
+        assert_eq!(None, view.doc_to_view(9));
+        assert_eq!(Some(5), view.doc_to_view(10));
+    }
+
+    #[test]
+    fn offsets_round_trip() {
+        let piece_table = PieceTable::new("héllo wörld, goodbye");
+        let view = piece_table.view_excluding([0..1, 7..14, 19..21]);
+        let text = piece_table.to_string();
+        let visible = view.to_string();
+
+        for offset in 0..=view.len() {
+            let doc_offset = view.view_to_doc(offset);
+            assert_eq!(Some(offset), view.doc_to_view(doc_offset));
+            if offset < view.len() {
+                assert_eq!(visible.as_bytes()[offset], text.as_bytes()[doc_offset]);
+            }
+        }
+        assert_eq!(1..15, view.view_range_to_doc(0..7));
+    }
+
+    #[test]
+    fn everything_hidden() {
+        let piece_table = PieceTable::new("abc");
+        let view = piece_table.view_excluding(Some(0..3));
+
+        assert!(view.is_empty());
+        assert_eq!("", view.to_string());
+        assert_eq!(3, view.view_to_doc(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "char boundary")]
+    fn range_inside_char() {
+        PieceTable::new("é").view_excluding(Some(0..1));
+    }
+}
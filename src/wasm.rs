@@ -0,0 +1,157 @@
+//! JavaScript bindings, for backing browser-based editors with a `PieceTable`.
+//!
+//! Enabled by the `wasm` feature. All offsets are in UTF-16 code units, like the indices of
+//! JavaScript strings, and are converted to byte offsets by walking the text.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{EditError, PieceTable};
+
+/// A `PieceTable` which owns its text, exported to JavaScript as `PieceTable`.
+#[wasm_bindgen(js_name = PieceTable)]
+pub struct JsPieceTable {
+    table: PieceTable<'static>,
+}
+
+#[wasm_bindgen(js_class = PieceTable)]
+impl JsPieceTable {
+    /// Creates a table containing `text`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(text: String) -> JsPieceTable {
+        JsPieceTable {
+            table: PieceTable::from(text),
+        }
+    }
+
+    /// The length of the text in UTF-16 code units.
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> usize {
+        self.table
+            .chunks_from(0)
+            .map(|chunk| chunk.encode_utf16().count())
+            .sum()
+    }
+
+    /// The number of lines, see [`PieceTable::len_lines`].
+    #[wasm_bindgen(getter, js_name = lineCount)]
+    pub fn line_count(&self) -> usize {
+        self.table.len_lines()
+    }
+
+    /// Inserts `text` at `offset`.
+    pub fn insert(&mut self, offset: usize, text: &str) -> Result<(), JsError> {
+        Ok(self.try_insert(offset, text)?)
+    }
+
+    /// Deletes the text between `start` and `end`.
+    pub fn delete(&mut self, start: usize, end: usize) -> Result<(), JsError> {
+        Ok(self.try_delete(start, end)?)
+    }
+
+    /// Returns the text between `start` and `end`.
+    pub fn slice(&self, start: usize, end: usize) -> Result<String, JsError> {
+        Ok(self.try_slice(start, end)?)
+    }
+
+    /// Returns the text of line `index` (0-based) without its trailing newline, or `undefined`
+    /// if there are fewer lines.
+    pub fn line(&self, index: usize) -> Option<String> {
+        let start = self.table.line_start(index)?;
+        let end = match self.table.line_start(index + 1) {
+            Some(next) => next - 1,
+            None => self.table.len(),
+        };
+        Some(String::from(&self.table.slice(start..end)))
+    }
+
+    /// Returns the whole text.
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_js_string(&self) -> String {
+        self.table.as_string()
+    }
+
+    // Errors are only converted to `JsError` at the boundary, since creating one calls into
+    // JavaScript, so the conversions can be tested without a JavaScript host
+
+    fn try_insert(&mut self, offset: usize, text: &str) -> Result<(), EditError> {
+        let offset = self.to_byte_offset(offset)?;
+        self.table.try_insert(text, offset)
+    }
+
+    fn try_delete(&mut self, start: usize, end: usize) -> Result<(), EditError> {
+        let range = self.to_byte_offset(start)?..self.to_byte_offset(end)?;
+        self.table.try_delete(range)
+    }
+
+    fn try_slice(&self, start: usize, end: usize) -> Result<String, EditError> {
+        let range = self.to_byte_offset(start)?..self.to_byte_offset(end)?;
+        self.table.check_range(&range)?;
+        Ok(String::from(&self.table.slice(range)))
+    }
+
+    /// Converts an offset in UTF-16 code units to a byte offset.
+    fn to_byte_offset(&self, offset: usize) -> Result<usize, EditError> {
+        let mut units = 0;
+        let mut bytes = 0;
+        for chunk in self.table.chunks_from(0) {
+            for c in chunk.chars() {
+                if units == offset {
+                    return Ok(bytes);
+                } else if units + c.len_utf16() > offset {
+                    return Err(EditError::NotCharBoundary { offset });
+                }
+                units += c.len_utf16();
+                bytes += c.len_utf8();
+            }
+        }
+
+        if units == offset {
+            Ok(bytes)
+        } else {
+            Err(EditError::OutOfBounds { offset, len: units })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets_are_utf16_code_units() {
+        let mut table = JsPieceTable::new("a😀b\nc".to_string());
+        assert_eq!(6, table.length());
+        assert_eq!(2, table.line_count());
+
+        table.insert(3, "é").unwrap();
+        table.insert(0, "x").unwrap();
+        assert_eq!("xa😀éb\nc", table.to_js_string());
+        assert_eq!("😀é", table.slice(2, 5).unwrap());
+
+        table.delete(2, 4).unwrap();
+        assert_eq!("xaéb\nc", table.to_js_string());
+        assert_eq!(Some("xaéb".to_string()), table.line(0));
+        assert_eq!(Some("c".to_string()), table.line(1));
+        assert_eq!(None, table.line(2));
+    }
+
+    #[test]
+    fn invalid_offsets_are_errors() {
+        let mut table = JsPieceTable::new("a😀b".to_string());
+
+        assert_eq!(
+            Err(EditError::NotCharBoundary { offset: 2 }),
+            table.try_insert(2, "x")
+        );
+        assert_eq!(
+            Err(EditError::OutOfBounds { offset: 5, len: 4 }),
+            table.try_delete(0, 5)
+        );
+        assert_eq!(
+            Err(EditError::NotCharBoundary { offset: 2 }),
+            table.try_slice(2, 4)
+        );
+        assert!(table.try_slice(3, 1).is_err());
+        assert_eq!("a😀b", table.to_js_string());
+    }
+}
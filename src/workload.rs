@@ -0,0 +1,783 @@
+//! Recording, generating and replaying edit workloads.
+//!
+//! A workload is a sequence of edits stored in a simple line-based text format, one edit per
+//! line:
+//!
+//! ```text
+//! INSERT <offset> <text>
+//! DELETE <offset> <len>
+//! ```
+//!
+//! Offsets and lengths are in bytes, and apply to the document as it is after all previous edits.
+//! In `<text>`, backslashes, newlines, carriage returns and tabs are escaped as `\\`, `\n`, `\r`
+//! and `\t`. The fields may also be separated by commas instead of spaces, which is what
+//! `snooper.lua` writes when recording edits made in neovim.
+//!
+//! Workloads can be parsed from that format, recorded from any [`EditableText`] with a
+//! [`Recorder`], or generated with [`generate`], and then replayed against any `EditableText`. A
+//! [`Timeline`] keeps snapshots of a replay, to look at the document at any point of it.
+
+use std::fmt;
+use std::ops::Range;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::interface::EditableText;
+use crate::{EditError, PieceTable};
+
+/// A single edit in a [`Workload`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edit {
+    Insert { offset: usize, text: String },
+    Delete { offset: usize, len: usize },
+}
+
+/// A sequence of edits which can be replayed against any [`EditableText`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Workload {
+    pub edits: Vec<Edit>,
+}
+
+/// An error encountered while parsing a workload, with the (1-based) line it happened on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// An edit of a workload which can't be applied to the document as it is by then, see
+/// [`Workload::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidEdit {
+    /// The (0-based) index of the edit.
+    pub index: usize,
+    pub error: EditError,
+}
+
+impl fmt::Display for InvalidEdit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "edit {}: {}", self.index, self.error)
+    }
+}
+
+impl std::error::Error for InvalidEdit {}
+
+/// The kinds of synthetic workloads [`generate`] can produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// Mostly single characters typed one after the other, with the odd backspace and cursor jump.
+    Typing,
+    /// Mostly backspaces and deletions of short selections.
+    Deleting,
+    /// Replaces occurrences of words in the document with other words, front to back.
+    FindReplace,
+    /// Pastes large blocks of text at random positions, with some deletions of large selections.
+    PasteHeavy,
+}
+
+impl Profile {
+    /// Every profile, e.g. for running a benchmark or a test with each of them.
+    pub const ALL: [Profile; 4] = [
+        Profile::Typing,
+        Profile::Deleting,
+        Profile::FindReplace,
+        Profile::PasteHeavy,
+    ];
+
+    /// Returns the name of the profile in snake case, e.g. for labelling benchmarks.
+    pub fn name(self) -> &'static str {
+        match self {
+            Profile::Typing => "typing",
+            Profile::Deleting => "deleting",
+            Profile::FindReplace => "find_replace",
+            Profile::PasteHeavy => "paste_heavy",
+        }
+    }
+}
+
+impl Edit {
+    /// Applies this edit to `doc`.
+    pub fn apply<'a, T: EditableText<'a>>(&self, doc: &mut T) {
+        match self {
+            Edit::Insert { offset, text } => doc.insert(text, *offset),
+            Edit::Delete { offset, len } => doc.delete(*offset..offset + len),
+        }
+    }
+}
+
+impl Workload {
+    /// Parses a workload from the text format described in the [module docs](self).
+    ///
+    /// Empty lines are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::workload::{Edit, Workload};
+    /// let workload = Workload::parse("INSERT 0 hello\\nworld\nDELETE 2 3").unwrap();
+    /// assert_eq!(
+    ///     workload.edits,
+    ///     vec![
+    ///         Edit::Insert { offset: 0, text: "hello\nworld".to_string() },
+    ///         Edit::Delete { offset: 2, len: 3 },
+    ///     ]
+    /// );
+    /// ```
+    pub fn parse(s: &str) -> Result<Workload, ParseError> {
+        let mut edits = Vec::new();
+
+        for (idx, line) in s.lines().enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: String| ParseError {
+                line: idx + 1,
+                message,
+            };
+
+            let (kind, rest) = line
+                .split_once([' ', ','])
+                .ok_or_else(|| error(format!("expected an edit, found `{line}`")))?;
+            let separator = line.as_bytes()[kind.len()] as char;
+            let (offset, arg) = rest.split_once(separator).unwrap_or((rest, ""));
+            let offset = offset
+                .parse()
+                .map_err(|_| error(format!("invalid offset `{offset}`")))?;
+
+            let edit = match kind {
+                "INSERT" => Edit::Insert {
+                    offset,
+                    text: unescape(arg).map_err(error)?,
+                },
+                "DELETE" => Edit::Delete {
+                    offset,
+                    len: arg
+                        .parse()
+                        .map_err(|_| error(format!("invalid length `{arg}`")))?,
+                },
+                _ => return Err(error(format!("unknown edit `{kind}`"))),
+            };
+            edits.push(edit);
+        }
+
+        Ok(Workload { edits })
+    }
+
+    /// Replays every edit against `doc`, in order.
+    pub fn replay<'a, T: EditableText<'a>>(&self, doc: &mut T) {
+        for edit in &self.edits {
+            edit.apply(doc);
+        }
+    }
+
+    /// Checks that every edit can be applied to `initial`, i.e. that its offsets are in bounds
+    /// and on char boundaries of the document as it is after the edits before it, and returns
+    /// the first one which can't.
+    ///
+    /// Replaying a workload which isn't valid panics, or worse, depending on the backend.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::EditError;
+    /// # use piece_table::workload::Workload;
+    /// let workload = Workload::parse("INSERT 0 é\nDELETE 0 1").unwrap();
+    /// let invalid = workload.validate("").unwrap_err();
+    /// assert_eq!(invalid.index, 1);
+    /// assert_eq!(invalid.error, EditError::NotCharBoundary { offset: 1 });
+    /// ```
+    pub fn validate(&self, initial: &str) -> Result<(), InvalidEdit> {
+        let mut doc = PieceTable::new(initial);
+        for (index, edit) in self.edits.iter().enumerate() {
+            let result = match edit {
+                Edit::Insert { offset, text } => doc.try_insert(text, *offset),
+                Edit::Delete { offset, len } => {
+                    doc.try_delete(*offset..offset.saturating_add(*len))
+                }
+            };
+            result.map_err(|error| InvalidEdit { index, error })?;
+        }
+        Ok(())
+    }
+
+    /// Returns the document `initial` becomes after the first `count` edits, so `0` gives the
+    /// initial document and `self.edits.len()` the final one.
+    ///
+    /// This replays the edits every time. To look at many points of a long workload, e.g. for a
+    /// history scrubber, use a [`Timeline`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use piece_table::workload::Workload;
+    /// let workload = Workload::parse("INSERT 5  world\nDELETE 0 1").unwrap();
+    /// assert_eq!(workload.state_at("hello", 1).to_string(), "hello world");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `count` is greater than the number of edits.
+    pub fn state_at(&self, initial: &str, count: usize) -> PieceTable<'static> {
+        assert!(
+            count <= self.edits.len(),
+            "invalid state_at: {count} edits asked for, but there are {}",
+            self.edits.len()
+        );
+        let mut doc = PieceTable::from(initial.to_string());
+        for edit in &self.edits[..count] {
+            edit.apply(&mut doc);
+        }
+        doc
+    }
+}
+
+impl fmt::Display for Workload {
+    /// Writes the workload in the text format described in the [module docs](self).
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for edit in &self.edits {
+            match edit {
+                Edit::Insert { offset, text } => writeln!(f, "INSERT {offset} {}", escape(text))?,
+                Edit::Delete { offset, len } => writeln!(f, "DELETE {offset} {len}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> Result<String, String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => unescaped.push('\\'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('t') => unescaped.push('\t'),
+            Some(c) => return Err(format!("unknown escape sequence `\\{c}`")),
+            None => return Err("unterminated escape sequence".to_string()),
+        }
+    }
+    Ok(unescaped)
+}
+
+/// Wraps an [`EditableText`], recording every edit made through it into a [`Workload`].
+///
+/// # Examples
+///
+/// ```
+/// # use piece_table::PieceTable;
+/// # use piece_table::interface::EditableText;
+/// # use piece_table::workload::Recorder;
+/// let mut recorder = Recorder::<PieceTable>::new("hello");
+/// recorder.insert(" world", 5);
+/// recorder.delete(0..1);
+///
+/// let (doc, workload) = recorder.finish();
+/// assert_eq!(doc.to_string(), "ello world");
+/// assert_eq!(workload.to_string(), "INSERT 5  world\nDELETE 0 1\n");
+/// ```
+pub struct Recorder<T> {
+    doc: T,
+    workload: Workload,
+}
+
+impl<T> Recorder<T> {
+    /// Starts recording edits made to `doc`.
+    pub fn wrap(doc: T) -> Self {
+        Recorder {
+            doc,
+            workload: Workload::default(),
+        }
+    }
+
+    /// Returns the wrapped document.
+    pub fn doc(&self) -> &T {
+        &self.doc
+    }
+
+    /// Returns the edits recorded so far.
+    pub fn workload(&self) -> &Workload {
+        &self.workload
+    }
+
+    /// Stops recording, returning the wrapped document and the recorded workload.
+    pub fn finish(self) -> (T, Workload) {
+        (self.doc, self.workload)
+    }
+}
+
+impl<'a, T: EditableText<'a>> EditableText<'a> for Recorder<T> {
+    fn new(string: &'a str) -> Self {
+        Recorder::wrap(T::new(string))
+    }
+
+    fn insert(&mut self, data: &str, offset: usize) {
+        self.doc.insert(data, offset);
+        self.workload.edits.push(Edit::Insert {
+            offset,
+            text: data.to_string(),
+        });
+    }
+
+    fn delete(&mut self, range: Range<usize>) {
+        self.workload.edits.push(Edit::Delete {
+            offset: range.start,
+            len: range.len(),
+        });
+        self.doc.delete(range);
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Recorder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.doc.fmt(f)
+    }
+}
+
+/// The size of the added buffer's segments while building a [`Timeline`]. Each snapshot shares
+/// the current segment, so the next edit starts a new one, and the default size would leave most
+/// of each segment unused.
+const TIMELINE_SEGMENT_SIZE: usize = 4 * 1024;
+
+/// A workload replayed once, with snapshots of the document taken along the way, so the
+/// document at any point of it can be rebuilt quickly.
+///
+/// A snapshot is a clone of the table, which copies its list of pieces but shares its text.
+/// Rebuilding the document after some edit clones the last snapshot before it and replays the
+/// edits in between, so it takes at most `interval` edits.
+///
+/// # Examples
+///
+/// ```
+/// # use piece_table::workload::{Profile, Timeline, generate};
+/// let workload = generate(1, Profile::Typing, "", 1000);
+/// let timeline = Timeline::new("", workload.clone(), 64);
+///
+/// assert_eq!(timeline.len(), 1000);
+/// assert_eq!(
+///     timeline.state_at(500).to_string(),
+///     workload.state_at("", 500).to_string()
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    workload: Workload,
+    interval: usize,
+    /// The document after every multiple of `interval` edits
+    snapshots: Vec<PieceTable<'static>>,
+}
+
+impl Timeline {
+    /// Replays `workload` against `initial`, taking a snapshot every `interval` edits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is 0.
+    pub fn new(initial: &str, workload: Workload, interval: usize) -> Self {
+        assert!(
+            interval > 0,
+            "invalid Timeline::new: interval must not be 0"
+        );
+        let mut doc = PieceTable::from(initial.to_string());
+        doc.set_added_segment_size(TIMELINE_SEGMENT_SIZE);
+        let mut snapshots = vec![doc.clone()];
+        for (idx, edit) in workload.edits.iter().enumerate() {
+            edit.apply(&mut doc);
+            if (idx + 1) % interval == 0 {
+                snapshots.push(doc.clone());
+            }
+        }
+        Timeline {
+            workload,
+            interval,
+            snapshots,
+        }
+    }
+
+    /// Returns the number of edits.
+    pub fn len(&self) -> usize {
+        self.workload.edits.len()
+    }
+
+    /// Checks if there are no edits.
+    pub fn is_empty(&self) -> bool {
+        self.workload.edits.is_empty()
+    }
+
+    /// Returns the replayed workload.
+    pub fn workload(&self) -> &Workload {
+        &self.workload
+    }
+
+    /// Returns the document after the first `count` edits, see [`Workload::state_at`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `count` is greater than the number of edits.
+    pub fn state_at(&self, count: usize) -> PieceTable<'static> {
+        assert!(
+            count <= self.len(),
+            "invalid state_at: {count} edits asked for, but there are {}",
+            self.len()
+        );
+        let snapshot = count / self.interval;
+        let mut doc = self.snapshots[snapshot].clone();
+        for edit in &self.workload.edits[snapshot * self.interval..count] {
+            edit.apply(&mut doc);
+        }
+        doc
+    }
+}
+
+const WORDS: &[&str] = &[
+    "the", "piece", "table", "fn", "let", "mut", "self", "offset", "node", "range", "insert",
+    "delete", "buffer", "text", "len",
+];
+
+/// Generates a reproducible workload of `edits` edits of the given `profile`, to be applied to
+/// a document whose initial content is `initial`.
+///
+/// The same `seed`, `profile` and `initial` always produce the same workload, on any machine, so
+/// a seed is enough to reproduce a failure found with a generated workload, e.g. by the property
+/// tests, or a regression in a benchmark. Every generated edit is valid: offsets are in bounds
+/// and on char boundaries of the document at that point.
+///
+/// # Examples
+///
+/// ```
+/// # use piece_table::PieceTable;
+/// # use piece_table::workload::{Profile, generate};
+/// let workload = generate(42, Profile::Typing, "hello world", 100);
+/// assert_eq!(workload, generate(42, Profile::Typing, "hello world", 100));
+///
+/// let mut pt = PieceTable::new("hello world");
+/// workload.replay(&mut pt);
+/// ```
+pub fn generate(seed: u64, profile: Profile, initial: &str, edits: usize) -> Workload {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut model = initial.to_string();
+    let mut cursor = snap(&model, rng.random_range(0..=model.len()));
+    let mut workload = Workload::default();
+
+    while workload.edits.len() < edits {
+        let edit = match profile {
+            Profile::Typing => {
+                let roll = rng.random_range(0..100);
+                if roll < 5 {
+                    cursor = snap(&model, rng.random_range(0..=model.len()));
+                    continue;
+                } else if roll < 15 && cursor > 0 {
+                    backspace(&model, cursor)
+                } else {
+                    let c = match rng.random_range(0..10) {
+                        0 => ' ',
+                        1 if roll % 3 == 0 => '\n',
+                        _ => rng.random_range(b'a'..=b'z') as char,
+                    };
+                    Edit::Insert {
+                        offset: cursor,
+                        text: c.to_string(),
+                    }
+                }
+            }
+            Profile::Deleting => {
+                if model.is_empty() {
+                    Edit::Insert {
+                        offset: 0,
+                        text: random_words(&mut rng, 8),
+                    }
+                } else if rng.random_range(0..4) == 0 {
+                    let start = snap(&model, rng.random_range(0..model.len()));
+                    let end = snap(&model, (start + rng.random_range(1..32)).min(model.len()));
+                    Edit::Delete {
+                        offset: start,
+                        len: end - start,
+                    }
+                } else {
+                    if cursor == 0 {
+                        cursor = model.len();
+                    }
+                    backspace(&model, cursor)
+                }
+            }
+            Profile::FindReplace => {
+                let needle = WORDS[rng.random_range(0..WORDS.len())];
+                match model[cursor..].find(needle) {
+                    Some(pos) => {
+                        let offset = cursor + pos;
+                        let replacement = WORDS[rng.random_range(0..WORDS.len())];
+                        apply(
+                            &mut model,
+                            &mut cursor,
+                            Edit::Delete {
+                                offset,
+                                len: needle.len(),
+                            },
+                            &mut workload,
+                        );
+                        Edit::Insert {
+                            offset,
+                            text: replacement.to_string(),
+                        }
+                    }
+                    None if cursor == 0 => Edit::Insert {
+                        offset: 0,
+                        text: random_words(&mut rng, 16),
+                    },
+                    None => {
+                        cursor = 0;
+                        continue;
+                    }
+                }
+            }
+            Profile::PasteHeavy => {
+                let offset = snap(&model, rng.random_range(0..=model.len()));
+                if rng.random_range(0..4) == 0 && !model.is_empty() {
+                    let end = snap(
+                        &model,
+                        (offset + rng.random_range(64..1024)).min(model.len()),
+                    );
+                    Edit::Delete {
+                        offset,
+                        len: end - offset,
+                    }
+                } else {
+                    let words = rng.random_range(16..256);
+                    Edit::Insert {
+                        offset,
+                        text: random_words(&mut rng, words),
+                    }
+                }
+            }
+        };
+
+        apply(&mut model, &mut cursor, edit, &mut workload);
+    }
+
+    workload.edits.truncate(edits);
+    workload
+}
+
+/// Applies `edit` to `model`, moving the cursor to the end of the edit, and records it.
+fn apply(model: &mut String, cursor: &mut usize, edit: Edit, workload: &mut Workload) {
+    match &edit {
+        Edit::Insert { offset, text } => {
+            model.insert_str(*offset, text);
+            *cursor = offset + text.len();
+        }
+        Edit::Delete { offset, len } => {
+            model.replace_range(*offset..offset + len, "");
+            *cursor = *offset;
+        }
+    }
+    workload.edits.push(edit);
+}
+
+/// Deletes the character before `cursor`
+fn backspace(model: &str, cursor: usize) -> Edit {
+    let len = model[..cursor]
+        .chars()
+        .next_back()
+        .map_or(0, |c| c.len_utf8());
+    Edit::Delete {
+        offset: cursor - len,
+        len,
+    }
+}
+
+fn snap(model: &str, mut offset: usize) -> usize {
+    while !model.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+fn random_words(rng: &mut StdRng, count: usize) -> String {
+    let mut text = String::new();
+    for i in 0..count {
+        text.push_str(WORDS[rng.random_range(0..WORDS.len())]);
+        text.push(if i % 12 == 11 { '\n' } else { ' ' });
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::baseline::Baseline;
+
+    #[test]
+    fn parse_shipped_workload() {
+        let workload = Workload::parse(include_str!("../workloads/mostly_inserts.txt")).unwrap();
+
+        assert_eq!(1000, workload.edits.len());
+        assert_eq!(
+            Edit::Insert {
+                offset: 0,
+                text: "a".to_string()
+            },
+            workload.edits[0]
+        );
+    }
+
+    #[test]
+    fn parse_snooper_format() {
+        let workload = Workload::parse("INSERT,3,a, b\nDELETE,1,2").unwrap();
+
+        assert_eq!(
+            vec![
+                Edit::Insert {
+                    offset: 3,
+                    text: "a, b".to_string()
+                },
+                Edit::Delete { offset: 1, len: 2 }
+            ],
+            workload.edits
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(1, Workload::parse("MOVE 1 2").unwrap_err().line);
+        assert_eq!(
+            2,
+            Workload::parse("DELETE 0 1\nDELETE x 1").unwrap_err().line
+        );
+        assert_eq!(1, Workload::parse("DELETE 0 -1").unwrap_err().line);
+        assert_eq!(1, Workload::parse("INSERT 0 a\\b").unwrap_err().line);
+    }
+
+    #[test]
+    fn validate_tracks_the_document() {
+        let workload = Workload::parse("DELETE 0 2\nINSERT 3 x\nDELETE 1 3").unwrap();
+
+        assert_eq!(Ok(()), workload.validate("abcde"));
+        assert_eq!(
+            Err(InvalidEdit {
+                index: 1,
+                error: EditError::OutOfBounds { offset: 3, len: 2 }
+            }),
+            workload.validate("abcd")
+        );
+        let overflowing = Workload::parse(&format!("DELETE 1 {}", usize::MAX)).unwrap();
+        assert!(overflowing.validate("abc").is_err());
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let workload = Workload {
+            edits: vec![
+                Edit::Insert {
+                    offset: 0,
+                    text: "a\\b\n\tc\r\n ".to_string(),
+                },
+                Edit::Delete { offset: 4, len: 2 },
+            ],
+        };
+
+        assert_eq!(workload, Workload::parse(&workload.to_string()).unwrap());
+    }
+
+    #[test]
+    fn recorded_workload_replays_identically() {
+        let mut recorder = Recorder::<Baseline>::new("hello world");
+        recorder.insert("big ", 6);
+        recorder.delete(0..1);
+        recorder.insert("\n", 0);
+
+        let (doc, workload) = recorder.finish();
+        let mut table = PieceTable::new("hello world");
+        workload.replay(&mut table);
+
+        assert_eq!(doc.to_string(), table.to_string());
+    }
+
+    #[test]
+    fn generated_workloads_are_deterministic_and_valid() {
+        let initial = "héllo wörld, the piece table\n".repeat(4);
+        for profile in Profile::ALL {
+            let workload = generate(7, profile, &initial, 200);
+            assert_eq!(200, workload.edits.len());
+            assert_eq!(workload, generate(7, profile, &initial, 200));
+
+            let mut baseline = Baseline::new(&initial);
+            let mut table = PieceTable::new(&initial);
+            workload.replay(&mut baseline);
+            workload.replay(&mut table);
+            assert_eq!(baseline.to_string(), table.to_string());
+        }
+    }
+
+    #[test]
+    fn generated_workloads_are_pinned() {
+        // Changing what a seed generates breaks reproducing failures and comparing benchmarks
+        // across versions, so it should only ever happen on purpose
+        let initial = "fn main() {}\n";
+        assert_eq!(
+            "INSERT 11 r\nINSERT 12 f\nINSERT 13 g\nINSERT 14 u\n",
+            generate(1, Profile::Typing, initial, 4).to_string()
+        );
+        assert_eq!(
+            "DELETE 10 1\nDELETE 9 1\nDELETE 8 1\nDELETE 4 6\n",
+            generate(1, Profile::Deleting, initial, 4).to_string()
+        );
+    }
+
+    #[test]
+    fn state_at_matches_replay_prefix() {
+        let initial = "fn main() {}\n";
+        let workload = generate(3, Profile::FindReplace, initial, 40);
+        let timeline = Timeline::new(initial, workload.clone(), 7);
+
+        for count in 0..=workload.edits.len() {
+            let mut baseline = Baseline::new(initial);
+            for edit in &workload.edits[..count] {
+                edit.apply(&mut baseline);
+            }
+            assert_eq!(
+                baseline.to_string(),
+                workload.state_at(initial, count).to_string()
+            );
+            assert_eq!(baseline.to_string(), timeline.state_at(count).to_string());
+        }
+    }
+
+    #[test]
+    fn snapshots_are_not_affected_by_later_edits() {
+        let workload = Workload::parse("INSERT 0 a\nINSERT 1 b\nDELETE 0 1").unwrap();
+        let timeline = Timeline::new("", workload, 1);
+
+        let mut doc = timeline.state_at(1);
+        doc.insert("zzz", 0);
+        assert_eq!("a", timeline.state_at(1).to_string());
+        assert_eq!("b", timeline.state_at(3).to_string());
+        assert_eq!(Ok(()), timeline.state_at(2).check_invariants());
+    }
+}
@@ -0,0 +1,201 @@
+//! Soft-wrapping lines into rows of a fixed width, as shown in a terminal.
+//!
+//! Finding where a long line wraps means measuring every char of it, which is too slow to redo
+//! for every line on every frame. A [`WrapLayout`] remembers the breaks of each line it has
+//! measured, and is told which lines an edit touched, so only those are measured again.
+
+use std::ops::Range;
+
+use crate::PieceTable;
+use crate::point::ColumnUnit;
+
+/// The rows each line of a document wraps into, measured in terminal cells, see
+/// [`ColumnUnit::Cells`].
+///
+/// Lines are broken between chars, as soon as the next char doesn't fit. Each row starts at
+/// column 0, so a tab at the start of a row takes a whole tab stop, and a char wider than the
+/// wrap width gets a row of its own. The newline ending a line doesn't take any cells.
+///
+/// The layout doesn't hold on to the document, so after every edit it must be told which lines
+/// changed, with [`WrapLayout::inserted`] and [`WrapLayout::deleted`], or lines measured before
+/// the edit may be wrong.
+///
+/// # Examples
+///
+/// ```
+/// # use piece_table::PieceTable;
+/// use piece_table::wrap::WrapLayout;
+///
+/// let mut pt = PieceTable::new("hello world\nok");
+/// let mut layout = WrapLayout::new(4, 4);
+/// assert_eq!(layout.line_breaks(&pt, 0), vec![4, 8]);
+/// assert_eq!(layout.row_count(&pt, 1), 1);
+///
+/// pt.insert("a\n", 12);
+/// layout.inserted(&pt, 12, "a\n");
+/// assert_eq!(layout.line_breaks(&pt, 0), vec![4, 8]);
+/// assert_eq!(layout.row_count(&pt, 2), 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct WrapLayout {
+    width: usize,
+    tab_width: usize,
+    /// The breaks of the lines measured so far, relative to the start of their line, so they
+    /// stay valid when the lines before them change length
+    lines: Vec<Option<Vec<usize>>>,
+}
+
+impl WrapLayout {
+    /// Creates a layout wrapping lines at `width` cells, with tabs `tab_width` cells wide.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is 0.
+    pub fn new(width: usize, tab_width: usize) -> Self {
+        assert!(width > 0, "invalid WrapLayout::new: width must not be 0");
+        WrapLayout {
+            width,
+            tab_width,
+            lines: Vec::new(),
+        }
+    }
+
+    /// Returns the width lines are wrapped at, in cells.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the offsets where the rows of `line` after the first one start, or no offsets if
+    /// the line fits in a single row. Lines past the end of the document have no breaks.
+    pub fn line_breaks(&mut self, doc: &PieceTable, line: usize) -> Vec<usize> {
+        let Some(line_start) = doc.line_start(line) else {
+            return Vec::new();
+        };
+        self.relative_breaks(doc, line, line_start)
+            .iter()
+            .map(|&offset| line_start + offset)
+            .collect()
+    }
+
+    /// Returns the number of rows `line` takes.
+    pub fn row_count(&mut self, doc: &PieceTable, line: usize) -> usize {
+        match doc.line_start(line) {
+            Some(line_start) => self.relative_breaks(doc, line, line_start).len() + 1,
+            None => 0,
+        }
+    }
+
+    /// Returns the offsets where rows start inside `lines`, i.e. the breaks of each of them, in
+    /// order.
+    pub fn breaks_in_lines(&mut self, doc: &PieceTable, lines: Range<usize>) -> Vec<usize> {
+        lines.flat_map(|line| self.line_breaks(doc, line)).collect()
+    }
+
+    /// Updates the layout after `text` was inserted at `offset` of `doc`, which is the document
+    /// after the insertion.
+    pub fn inserted(&mut self, doc: &PieceTable, offset: usize, text: &str) {
+        let line = doc.line_of(offset);
+        let newlines = text.bytes().filter(|&b| b == b'\n').count();
+        self.invalidate_lines(line..line + 1, newlines + 1);
+    }
+
+    /// Updates the layout after text holding `newlines` newlines was deleted at `offset` of
+    /// `doc`, which is the document after the deletion.
+    pub fn deleted(&mut self, doc: &PieceTable, offset: usize, newlines: usize) {
+        let line = doc.line_of(offset);
+        self.invalidate_lines(line..line + newlines + 1, 1);
+    }
+
+    /// Forgets the breaks of `lines`, which were replaced by `count` lines whose breaks aren't
+    /// known yet, keeping those of the lines after them.
+    pub fn invalidate_lines(&mut self, lines: Range<usize>, count: usize) {
+        if lines.start >= self.lines.len() {
+            return;
+        }
+        let end = lines.end.min(self.lines.len());
+        self.lines
+            .splice(lines.start..end, std::iter::repeat_n(None, count));
+    }
+
+    /// Forgets the breaks of every line, e.g. after the document was replaced.
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    /// Returns the breaks of `line`, which starts at `line_start`, measuring it if needed.
+    fn relative_breaks(&mut self, doc: &PieceTable, line: usize, line_start: usize) -> &[usize] {
+        if self.lines.len() <= line {
+            self.lines.resize(line + 1, None);
+        }
+        if self.lines[line].is_none() {
+            self.lines[line] = Some(self.measure(doc, line_start));
+        }
+        self.lines[line].as_deref().unwrap()
+    }
+
+    /// Returns the breaks of the line starting at `line_start`, relative to it.
+    fn measure(&self, doc: &PieceTable, line_start: usize) -> Vec<usize> {
+        let unit = ColumnUnit::Cells {
+            tab_width: self.tab_width,
+        };
+        let mut breaks = Vec::new();
+        let mut row_start = line_start;
+        let mut column = 0;
+        for (offset, c) in doc.char_indices_from(line_start) {
+            if c == '\n' {
+                break;
+            }
+            let mut next = unit.advance(column, c);
+            if next > self.width && offset > row_start {
+                breaks.push(offset - line_start);
+                row_start = offset;
+                next = unit.advance(0, c);
+            }
+            column = next;
+        }
+        breaks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tabs_and_wide_chars() {
+        let piece_table = PieceTable::new("ab\t日本語x\n\tyz");
+        let mut layout = WrapLayout::new(5, 4);
+
+        // "ab\t" fills 4 cells, "日" doesn't fit after it, and "語" doesn't fit after "日本"
+        assert_eq!(vec![3, 9], layout.line_breaks(&piece_table, 0));
+        assert_eq!(vec![16], layout.line_breaks(&piece_table, 1));
+        assert_eq!(vec![3, 9, 16], layout.breaks_in_lines(&piece_table, 0..5));
+
+        let mut narrow = WrapLayout::new(1, 4);
+        // A tab gets a row of its own, since it never fits after another char
+        assert_eq!(vec![1, 2, 3, 6, 9, 12], narrow.line_breaks(&piece_table, 0));
+    }
+
+    #[test]
+    fn edits_only_invalidate_touched_lines() {
+        let mut piece_table = PieceTable::new("aaaaaa\nbb\ncccccc");
+        let mut layout = WrapLayout::new(4, 4);
+        for line in 0..3 {
+            layout.row_count(&piece_table, line);
+        }
+
+        piece_table.insert("bbbb\nx", 8);
+        layout.inserted(&piece_table, 8, "bbbb\nx");
+        assert_eq!(vec![None, None], layout.lines[1..3]);
+        assert_eq!(vec![11], layout.line_breaks(&piece_table, 1));
+        assert_eq!(vec![20], layout.line_breaks(&piece_table, 3));
+
+        piece_table.delete(5..13);
+        layout.deleted(&piece_table, 5, 2);
+        assert_eq!("aaaaaxb\ncccccc", piece_table.to_string());
+        assert_eq!(vec![None, Some(vec![4])], layout.lines);
+        assert_eq!(vec![4], layout.line_breaks(&piece_table, 0));
+        assert_eq!(vec![12], layout.line_breaks(&piece_table, 1));
+        assert_eq!(0, layout.row_count(&piece_table, 2));
+    }
+}